@@ -0,0 +1,3 @@
+-- Delay before a learning card rated Again is shown again in the session
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS again_delay_minutes INT NOT NULL DEFAULT 1;
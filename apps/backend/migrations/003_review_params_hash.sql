@@ -0,0 +1,3 @@
+-- Fingerprint of the algorithm parameters that scheduled each review
+ALTER TABLE reviews
+    ADD COLUMN IF NOT EXISTS params_hash TEXT;
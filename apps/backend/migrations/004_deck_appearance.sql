@@ -0,0 +1,4 @@
+-- Cosmetic deck color and icon, synced with the other deck settings
+ALTER TABLE deck_settings
+    ADD COLUMN IF NOT EXISTS color TEXT,
+    ADD COLUMN IF NOT EXISTS icon TEXT;
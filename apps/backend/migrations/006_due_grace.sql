@@ -0,0 +1,3 @@
+-- Hours past now within which due cards are still shown in the study queue
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS due_grace_hours INT NOT NULL DEFAULT 0;
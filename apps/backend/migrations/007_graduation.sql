@@ -0,0 +1,7 @@
+-- Consecutive passing ratings a learning card needs before it graduates
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS graduation_requirement INT NOT NULL DEFAULT 1;
+
+-- Progress towards that requirement, per card
+ALTER TABLE card_states
+    ADD COLUMN IF NOT EXISTS learning_reps INT NOT NULL DEFAULT 0;
@@ -0,0 +1,23 @@
+-- Reviews moved out of the history by a deck progress reset, kept for reference
+CREATE TABLE IF NOT EXISTS archived_reviews (
+    id UUID PRIMARY KEY,
+    card_id BIGINT REFERENCES cards(id),
+    device_id UUID REFERENCES devices(id),
+    reviewed_at TIMESTAMPTZ NOT NULL,
+    rating INT NOT NULL,
+    rating_scale TEXT NOT NULL,
+    answer_mode TEXT NOT NULL,
+    typed_answer TEXT,
+    was_correct BOOLEAN,
+    time_taken_ms INT,
+    interval_before REAL,
+    interval_after REAL,
+    ease_before REAL,
+    ease_after REAL,
+    algorithm TEXT NOT NULL,
+    params_hash TEXT,
+    created_at TIMESTAMPTZ,
+    archived_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_archived_reviews_card ON archived_reviews(card_id);
@@ -0,0 +1,3 @@
+-- Token that splits file names into deck paths (e.g. '::'); NULL uses directories
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS deck_path_separator TEXT;
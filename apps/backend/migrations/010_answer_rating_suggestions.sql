@@ -0,0 +1,4 @@
+-- Similarity cut-offs for the rating suggested after a typed answer
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS suggest_easy_threshold DOUBLE PRECISION NOT NULL DEFAULT 1.0,
+    ADD COLUMN IF NOT EXISTS suggest_hard_threshold DOUBLE PRECISION NOT NULL DEFAULT 0.6;
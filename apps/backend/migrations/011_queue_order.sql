@@ -0,0 +1,3 @@
+-- Whether study sessions show new cards before, after, or among due reviews
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS queue_order TEXT NOT NULL DEFAULT 'new_first';
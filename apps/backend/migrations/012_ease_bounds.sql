@@ -0,0 +1,4 @@
+-- Floor and cap for SM-2 ease
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS min_ease DOUBLE PRECISION NOT NULL DEFAULT 1.3,
+    ADD COLUMN IF NOT EXISTS max_ease DOUBLE PRECISION NOT NULL DEFAULT 5.0;
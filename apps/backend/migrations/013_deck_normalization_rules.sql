@@ -0,0 +1,3 @@
+-- Per-deck answer normalization rules, as a comma-separated spec
+ALTER TABLE deck_settings
+    ADD COLUMN IF NOT EXISTS normalization_rules TEXT;
@@ -0,0 +1,3 @@
+-- How syncs settle cards studied on more than one device
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS sync_conflict_policy TEXT NOT NULL DEFAULT 'manual';
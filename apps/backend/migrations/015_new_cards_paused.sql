@@ -0,0 +1,3 @@
+-- Stops new cards in every deck while reviews continue
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS new_cards_paused BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Which side of each card study sessions show as the prompt
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS study_direction TEXT NOT NULL DEFAULT 'forward';
@@ -0,0 +1,3 @@
+-- Default cap on each study session: none, cards:<n> or minutes:<n>
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS session_limit TEXT NOT NULL DEFAULT 'none';
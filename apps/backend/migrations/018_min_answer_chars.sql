@@ -0,0 +1,3 @@
+-- Typed answers shorter than this many non-whitespace characters aren't graded
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS min_answer_chars INT NOT NULL DEFAULT 0;
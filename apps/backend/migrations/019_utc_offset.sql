@@ -0,0 +1,4 @@
+-- The device's offset from UTC, reported on sync; study days start at the
+-- daily reset hour in the device's timezone
+ALTER TABLE global_settings
+    ADD COLUMN IF NOT EXISTS utc_offset_minutes INT NOT NULL DEFAULT 0;
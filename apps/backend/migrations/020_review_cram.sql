@@ -0,0 +1,5 @@
+-- Cram reviews left the schedule alone, so limits and retention skip them
+ALTER TABLE reviews
+    ADD COLUMN IF NOT EXISTS cram BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE archived_reviews
+    ADD COLUMN IF NOT EXISTS cram BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- Per-deck FSRS retention target; NULL keeps the default
+ALTER TABLE deck_settings
+    ADD COLUMN IF NOT EXISTS request_retention DOUBLE PRECISION;
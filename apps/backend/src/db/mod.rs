@@ -1,12 +1,40 @@
 //! PostgreSQL database operations
 
+use std::collections::{HashMap, HashSet};
+
 use chrono::{DateTime, NaiveDate, Utc};
-use sqlx::{postgres::PgPoolOptions, PgPool, Row};
+use sqlx::{
+    postgres::{PgPoolOptions, PgRow},
+    PgPool, Row,
+};
 use uuid::Uuid;
 
 use crate::error::{ApiError, Result};
 use crate::models::*;
 
+/// Minimum seconds between `last_seen_at` writes for the same device.
+pub const LAST_SEEN_THROTTLE_SECS: i64 = 60;
+
+/// Default and maximum number of orphaned cards returned per page.
+pub const ORPHAN_PAGE_SIZE: i64 = 100;
+
+/// Default and maximum number of cards returned per deck browsing page.
+pub const DECK_CARDS_PAGE_SIZE: i64 = 100;
+
+/// Largest page of the review log
+pub const REVIEWS_PAGE_SIZE: i64 = 100;
+
+/// Reviews with a recorded response time a card needs before it is ranked
+/// among the slowest cards.
+pub const SLOW_CARD_MIN_REVIEWS: i64 = 3;
+
+/// Default and maximum number of slowest cards returned.
+pub const SLOW_CARDS_LIMIT: i64 = 20;
+
+/// Default and maximum number of cards in a difficult cards session, see
+/// [`flashcard_core::types::DIFFICULT_CARDS_LIMIT`].
+pub const DIFFICULT_CARDS_LIMIT: i64 = flashcard_core::types::DIFFICULT_CARDS_LIMIT as i64;
+
 /// Database wrapper with connection pool
 #[derive(Clone)]
 pub struct Database {
@@ -85,20 +113,27 @@ impl Database {
         Ok(device)
     }
 
-    /// Update device last_seen_at timestamp
-    pub async fn update_last_seen(&self, device_id: Uuid) -> Result<()> {
-        sqlx::query(
+    /// Update device last_seen_at timestamp.
+    ///
+    /// Skips the write when the stored value is newer than
+    /// `LAST_SEEN_THROTTLE_SECS`, so bursts of requests touch the row once.
+    /// Returns whether a write happened.
+    pub async fn update_last_seen(&self, device_id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
             r#"
             UPDATE devices
             SET last_seen_at = NOW()
             WHERE id = $1
+              AND (last_seen_at IS NULL
+                   OR last_seen_at < NOW() - make_interval(secs => $2))
             "#,
         )
         .bind(device_id)
+        .bind(LAST_SEEN_THROTTLE_SECS as f64)
         .execute(&self.pool)
         .await?;
 
-        Ok(())
+        Ok(result.rows_affected() > 0)
     }
 
     // === Card Repository ===
@@ -111,6 +146,16 @@ impl Database {
         Ok(row.get("id"))
     }
 
+    /// Whether the device has ever uploaded a card, including deleted ones
+    pub async fn device_has_cards(&self, device_id: Uuid) -> Result<bool> {
+        let exists: bool =
+            sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM cards WHERE device_id = $1)")
+                .bind(device_id)
+                .fetch_one(&self.pool)
+                .await?;
+        Ok(exists)
+    }
+
     /// Get card by ID
     pub async fn get_card(&self, card_id: i64) -> Result<Option<DbCard>> {
         let card = sqlx::query_as::<_, DbCard>(
@@ -169,6 +214,157 @@ impl Database {
         Ok(cards)
     }
 
+    /// Get a page of a deck's cards with their state summary
+    ///
+    /// Cards are ordered by ID so pages are stable between requests; cards
+    /// without a state row are reported as new. Returns the page and whether
+    /// more cards follow it.
+    pub async fn get_cards_by_deck_paginated(
+        &self,
+        device_id: Uuid,
+        deck_path: &str,
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<DbDeckCard>, bool)> {
+        let limit = limit.clamp(1, DECK_CARDS_PAGE_SIZE);
+
+        // One extra row is fetched to detect a further page
+        let mut cards = sqlx::query_as::<_, DbDeckCard>(
+            r#"
+            SELECT c.id, c.device_id, c.deck_path, c.question_text, c.answer_text,
+                   c.question_hash, c.answer_hash, c.source_file, c.created_at, c.updated_at, c.deleted_at,
+                   COALESCE(cs.status, 'new') as status, cs.due_date
+            FROM cards c
+            LEFT JOIN card_states cs ON c.id = cs.card_id AND cs.device_id = $1
+            WHERE c.device_id = $1 AND c.deck_path = $2 AND c.deleted_at IS NULL
+            ORDER BY c.id
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(device_id)
+        .bind(deck_path)
+        .bind(limit + 1)
+        .bind(offset.max(0))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let has_more = cards.len() as i64 > limit;
+        cards.truncate(limit as usize);
+
+        Ok((cards, has_more))
+    }
+
+    /// Cards in a deck ranked by average response time, slowest first
+    pub async fn get_slowest_cards(
+        &self,
+        device_id: Uuid,
+        deck_path: &str,
+        limit: i64,
+    ) -> Result<Vec<SlowCard>> {
+        let cards = sqlx::query_as::<_, SlowCard>(
+            r#"
+            SELECT c.id as card_id, c.deck_path, c.question_text as question,
+                   AVG(r.time_taken_ms)::FLOAT8 as average_time_ms,
+                   COUNT(r.time_taken_ms) as review_count
+            FROM reviews r
+            JOIN cards c ON r.card_id = c.id
+            WHERE r.device_id = $1 AND c.deck_path = $2 AND c.deleted_at IS NULL
+              AND r.time_taken_ms IS NOT NULL
+            GROUP BY c.id
+            HAVING COUNT(r.time_taken_ms) >= $3
+            ORDER BY average_time_ms DESC, c.id
+            LIMIT $4
+            "#,
+        )
+        .bind(device_id)
+        .bind(deck_path)
+        .bind(SLOW_CARD_MIN_REVIEWS)
+        .bind(limit.clamp(1, SLOW_CARDS_LIMIT))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(cards)
+    }
+
+    /// Reviews in a deck per rating as `[again, hard, good, easy]`. Ratings
+    /// given on the 2-point scale count as Again or Good.
+    pub async fn get_rating_distribution(
+        &self,
+        device_id: Uuid,
+        deck_path: &str,
+    ) -> Result<[usize; 4]> {
+        let rows: Vec<(i32, String, i64)> = sqlx::query_as(
+            r#"
+            SELECT r.rating, r.rating_scale, COUNT(*)
+            FROM reviews r
+            JOIN cards c ON r.card_id = c.id
+            WHERE r.device_id = $1 AND c.deck_path = $2 AND c.deleted_at IS NULL
+              AND NOT r.cram
+            GROUP BY r.rating, r.rating_scale
+            "#,
+        )
+        .bind(device_id)
+        .bind(deck_path)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut distribution = [0; 4];
+        for (rating, rating_scale, count) in rows {
+            let rating = rating_scale
+                .parse::<flashcard_core::RatingScale>()
+                .ok()
+                .zip(u8::try_from(rating).ok())
+                .and_then(|(scale, value)| scale.rating(value));
+            if let Some(rating) = rating {
+                distribution[rating.to_value() as usize - 1] += count as usize;
+            }
+        }
+
+        Ok(distribution)
+    }
+
+    /// Live cards with a tag across all of a device's decks
+    pub async fn get_cards_by_tag(&self, device_id: Uuid, tag: &str) -> Result<Vec<DbCard>> {
+        let cards = sqlx::query_as::<_, DbCard>(
+            r#"
+            SELECT c.id, c.device_id, c.deck_path, c.question_text, c.answer_text,
+                   c.question_hash, c.answer_hash, c.source_file, c.created_at, c.updated_at, c.deleted_at
+            FROM cards c
+            JOIN card_tags t ON c.id = t.card_id
+            WHERE c.device_id = $1 AND t.tag = $2 AND c.deleted_at IS NULL
+            ORDER BY c.deck_path, c.id
+            "#,
+        )
+        .bind(device_id)
+        .bind(tag)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(cards)
+    }
+
+    /// Replace a card's tags
+    pub async fn set_card_tags(&self, card_id: i64, tags: &[String]) -> Result<()> {
+        sqlx::query("DELETE FROM card_tags WHERE card_id = $1")
+            .bind(card_id)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO card_tags (card_id, tag)
+            SELECT $1, UNNEST($2::TEXT[])
+            ON CONFLICT DO NOTHING
+            "#,
+        )
+        .bind(card_id)
+        .bind(tags)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     /// Upsert a card (insert or update)
     pub async fn upsert_card(&self, card: &DbCard) -> Result<()> {
         sqlx::query(
@@ -219,25 +415,58 @@ impl Database {
         Ok(result.rows_affected() as usize)
     }
 
-    /// Get orphaned cards (cards in DB not in the provided list)
+    /// Get a page of orphaned cards (cards in DB not in the provided list)
+    ///
+    /// Orphans are ordered by ID so pages are stable between requests. Returns
+    /// the page and whether more orphans follow it.
     pub async fn get_orphaned_cards(
         &self,
         device_id: Uuid,
         current_card_ids: &[i64],
-    ) -> Result<Vec<OrphanedCard>> {
-        let orphans = sqlx::query_as::<_, OrphanedCard>(
+        limit: i64,
+        offset: i64,
+    ) -> Result<(Vec<OrphanedCard>, bool)> {
+        let limit = limit.clamp(1, ORPHAN_PAGE_SIZE);
+
+        // `<> ALL('{}')` is true, so an empty `current_card_ids` marks every
+        // card as orphaned. One extra row is fetched to detect a further page.
+        let mut orphans = sqlx::query_as::<_, OrphanedCard>(
             r#"
             SELECT id, LEFT(question_text, 50) as question_preview
             FROM cards
-            WHERE device_id = $1 AND deleted_at IS NULL AND id != ALL($2)
+            WHERE device_id = $1 AND deleted_at IS NULL AND id <> ALL($2::BIGINT[])
+            ORDER BY id
+            LIMIT $3 OFFSET $4
             "#,
         )
         .bind(device_id)
         .bind(current_card_ids)
+        .bind(limit + 1)
+        .bind(offset.max(0))
         .fetch_all(&self.pool)
         .await?;
 
-        Ok(orphans)
+        let has_more = orphans.len() as i64 > limit;
+        orphans.truncate(limit as usize);
+
+        Ok((orphans, has_more))
+    }
+
+    /// IDs from `card_ids` that belong to the device and are not deleted
+    pub async fn get_live_card_ids(&self, device_id: Uuid, card_ids: &[i64]) -> Result<HashSet<i64>> {
+        let ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT id
+            FROM cards
+            WHERE device_id = $1 AND deleted_at IS NULL AND id = ANY($2)
+            "#,
+        )
+        .bind(device_id)
+        .bind(card_ids)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids.into_iter().collect())
     }
 
     // === Card State Repository ===
@@ -248,7 +477,7 @@ impl Database {
             r#"
             SELECT id, card_id, device_id, status, interval_days, ease_factor,
                    due_date, stability, difficulty, lapses, reviews_count,
-                   created_at, updated_at
+                   learning_reps, created_at, updated_at
             FROM card_states
             WHERE card_id = $1 AND device_id = $2
             "#,
@@ -271,8 +500,9 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO card_states (card_id, device_id, status, interval_days, ease_factor,
-                                    due_date, stability, difficulty, lapses, reviews_count)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                                    due_date, stability, difficulty, lapses, reviews_count,
+                                    learning_reps)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             ON CONFLICT (card_id, device_id) DO UPDATE SET
                 status = EXCLUDED.status,
                 interval_days = EXCLUDED.interval_days,
@@ -282,6 +512,7 @@ impl Database {
                 difficulty = EXCLUDED.difficulty,
                 lapses = EXCLUDED.lapses,
                 reviews_count = EXCLUDED.reviews_count,
+                learning_reps = EXCLUDED.learning_reps,
                 updated_at = NOW()
             "#,
         )
@@ -295,18 +526,52 @@ impl Database {
         .bind(state.difficulty)
         .bind(state.lapses)
         .bind(state.reviews_count)
+        .bind(state.learning_reps)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    /// Studied cards ranked hardest first, whether due or not: most lapses,
+    /// then lowest stability (or interval, for SM-2 cards), then lowest ease.
+    ///
+    /// Suspension is local to each desktop, so there are no suspended cards
+    /// to leave out here.
+    pub async fn get_difficult_cards(
+        &self,
+        device_id: Uuid,
+        deck_path: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<DbCard>> {
+        let cards = sqlx::query_as::<_, DbCard>(
+            r#"
+            SELECT c.id, c.device_id, c.deck_path, c.question_text, c.answer_text,
+                   c.question_hash, c.answer_hash, c.source_file, c.created_at, c.updated_at, c.deleted_at
+            FROM cards c
+            JOIN card_states cs ON c.id = cs.card_id AND cs.device_id = $1
+            WHERE c.device_id = $1 AND c.deleted_at IS NULL
+              AND ($2::TEXT IS NULL OR c.deck_path = $2)
+              AND cs.status IN ('review', 'learning', 'relearning')
+            ORDER BY cs.lapses DESC, COALESCE(cs.stability, cs.interval_days), cs.ease_factor, c.id
+            LIMIT $3
+            "#,
+        )
+        .bind(device_id)
+        .bind(deck_path)
+        .bind(limit.clamp(1, DIFFICULT_CARDS_LIMIT))
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(cards)
+    }
+
     /// Get new cards for study
     pub async fn get_new_cards(
         &self,
         device_id: Uuid,
         deck_path: Option<&str>,
-        limit: i32,
+        limit: i64,
     ) -> Result<Vec<DbCard>> {
         let cards = match deck_path {
             Some(path) => {
@@ -351,15 +616,14 @@ impl Database {
         Ok(cards)
     }
 
-    /// Get due cards for review
+    /// Get due cards for review (due on or before `today`)
     pub async fn get_due_cards(
         &self,
         device_id: Uuid,
         deck_path: Option<&str>,
-        limit: i32,
+        limit: i64,
+        today: NaiveDate,
     ) -> Result<Vec<DbCard>> {
-        let today = Utc::now().date_naive();
-
         let cards = match deck_path {
             Some(path) => {
                 sqlx::query_as::<_, DbCard>(
@@ -409,14 +673,58 @@ impl Database {
 
     // === Review Repository ===
 
+    /// Count cards studied in a deck since the start of the study day.
+    ///
+    /// Cards with no review before `since` count as new cards introduced today;
+    /// every review of an already-seen card counts against the review limit.
+    /// Cram reviews count toward neither.
+    pub async fn count_studied_since(
+        &self,
+        device_id: Uuid,
+        deck_path: &str,
+        since: DateTime<Utc>,
+    ) -> Result<StudiedCounts> {
+        let row = sqlx::query(
+            r#"
+            WITH today AS (
+                SELECT r.card_id,
+                       EXISTS (
+                           SELECT 1 FROM reviews p
+                           WHERE p.card_id = r.card_id AND p.device_id = r.device_id
+                             AND p.reviewed_at < $3 AND NOT p.cram
+                       ) AS seen_before
+                FROM reviews r
+                JOIN cards c ON r.card_id = c.id
+                WHERE r.device_id = $1 AND c.deck_path = $2 AND r.reviewed_at >= $3
+                  AND NOT r.cram
+            )
+            SELECT
+                COUNT(DISTINCT card_id) FILTER (WHERE NOT seen_before) AS new_cards,
+                COUNT(*) FILTER (WHERE seen_before) AS reviews
+            FROM today
+            "#,
+        )
+        .bind(device_id)
+        .bind(deck_path)
+        .bind(since)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(StudiedCounts {
+            new_cards: row.get("new_cards"),
+            reviews: row.get("reviews"),
+        })
+    }
+
     /// Insert a review record
     pub async fn insert_review(&self, review: &DbReview) -> Result<()> {
         sqlx::query(
             r#"
             INSERT INTO reviews (id, card_id, device_id, reviewed_at, rating, rating_scale,
                                 answer_mode, typed_answer, was_correct, time_taken_ms,
-                                interval_before, interval_after, ease_before, ease_after, algorithm)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+                                interval_before, interval_after, ease_before, ease_after, algorithm,
+                                params_hash, cram)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17)
             "#,
         )
         .bind(review.id)
@@ -434,6 +742,8 @@ impl Database {
         .bind(review.ease_before)
         .bind(review.ease_after)
         .bind(&review.algorithm)
+        .bind(&review.params_hash)
+        .bind(review.cram)
         .execute(&self.pool)
         .await?;
 
@@ -463,7 +773,7 @@ impl Database {
                     SELECT id, card_id, device_id, reviewed_at, rating, rating_scale,
                            answer_mode, typed_answer, was_correct, time_taken_ms,
                            interval_before, interval_after, ease_before, ease_after,
-                           algorithm, created_at
+                           algorithm, params_hash, cram, created_at
                     FROM reviews
                     WHERE device_id = $1 AND created_at > $2
                     ORDER BY reviewed_at
@@ -480,7 +790,7 @@ impl Database {
                     SELECT id, card_id, device_id, reviewed_at, rating, rating_scale,
                            answer_mode, typed_answer, was_correct, time_taken_ms,
                            interval_before, interval_after, ease_before, ease_after,
-                           algorithm, created_at
+                           algorithm, params_hash, cram, created_at
                     FROM reviews
                     WHERE device_id = $1
                     ORDER BY reviewed_at
@@ -495,6 +805,108 @@ impl Database {
         Ok(reviews)
     }
 
+    /// Get a page of reviews matching `filters`, newest first
+    ///
+    /// Returns the page and whether more reviews follow it.
+    pub async fn query_reviews(
+        &self,
+        device_id: Uuid,
+        filters: &ReviewFilters,
+    ) -> Result<(Vec<DbReview>, bool)> {
+        let limit = filters.limit.clamp(1, REVIEWS_PAGE_SIZE);
+
+        // One extra row is fetched to detect a further page
+        let mut reviews = sqlx::query_as::<_, DbReview>(
+            r#"
+            SELECT id, card_id, device_id, reviewed_at, rating, rating_scale,
+                   answer_mode, typed_answer, was_correct, time_taken_ms,
+                   interval_before, interval_after, ease_before, ease_after,
+                   algorithm, params_hash, cram, created_at
+            FROM reviews
+            WHERE device_id = $1
+              AND ($2::BIGINT IS NULL OR card_id = $2)
+              AND ($3::TIMESTAMPTZ IS NULL OR reviewed_at >= $3)
+              AND ($4::TIMESTAMPTZ IS NULL OR reviewed_at < $4)
+            ORDER BY reviewed_at DESC, id
+            LIMIT $5 OFFSET $6
+            "#,
+        )
+        .bind(device_id)
+        .bind(filters.card_id)
+        .bind(filters.from)
+        .bind(filters.to)
+        .bind(limit + 1)
+        .bind(filters.offset.max(0))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let has_more = reviews.len() as i64 > limit;
+        reviews.truncate(limit as usize);
+
+        Ok((reviews, has_more))
+    }
+
+    /// Set every card in a deck back to New, clearing its learning state.
+    ///
+    /// With `archive_reviews`, the deck's reviews move to `archived_reviews`
+    /// so they no longer count towards stats. Returns the number of card
+    /// states reset and reviews archived.
+    pub async fn reset_deck_progress(
+        &self,
+        device_id: Uuid,
+        deck_path: &str,
+        archive_reviews: bool,
+    ) -> Result<(usize, usize)> {
+        let mut tx = self.pool.begin().await?;
+
+        let reset = sqlx::query(
+            r#"
+            UPDATE card_states
+            SET status = 'new', interval_days = 0, ease_factor = 2.5, due_date = NULL,
+                stability = NULL, difficulty = NULL, lapses = 0, reviews_count = 0,
+                learning_reps = 0, updated_at = NOW()
+            WHERE device_id = $1
+              AND card_id IN (SELECT id FROM cards WHERE device_id = $1 AND deck_path = $2)
+            "#,
+        )
+        .bind(device_id)
+        .bind(deck_path)
+        .execute(&mut *tx)
+        .await?;
+
+        let archived = if archive_reviews {
+            sqlx::query(
+                r#"
+                WITH moved AS (
+                    DELETE FROM reviews
+                    WHERE device_id = $1
+                      AND card_id IN (SELECT id FROM cards WHERE device_id = $1 AND deck_path = $2)
+                    RETURNING *
+                )
+                INSERT INTO archived_reviews (id, card_id, device_id, reviewed_at, rating,
+                                              rating_scale, answer_mode, typed_answer, was_correct,
+                                              time_taken_ms, interval_before, interval_after,
+                                              ease_before, ease_after, algorithm, params_hash,
+                                              cram, created_at)
+                SELECT id, card_id, device_id, reviewed_at, rating, rating_scale, answer_mode,
+                       typed_answer, was_correct, time_taken_ms, interval_before, interval_after,
+                       ease_before, ease_after, algorithm, params_hash, cram, created_at
+                FROM moved
+                "#,
+            )
+            .bind(device_id)
+            .bind(deck_path)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected() as usize
+        } else {
+            0
+        };
+
+        tx.commit().await?;
+        Ok((reset.rows_affected() as usize, archived))
+    }
+
     // === Settings Repository ===
 
     /// Get global settings for a device
@@ -502,7 +914,11 @@ impl Database {
         let settings = sqlx::query_as::<_, DbGlobalSettings>(
             r#"
             SELECT device_id, algorithm, rating_scale, matching_mode, fuzzy_threshold,
-                   new_cards_per_day, reviews_per_day, daily_reset_hour, created_at, updated_at
+                   new_cards_per_day, reviews_per_day, daily_reset_hour, again_delay_minutes,
+                   due_grace_hours, graduation_requirement, deck_path_separator,
+                   suggest_easy_threshold, suggest_hard_threshold, queue_order, min_ease,
+                   max_ease, sync_conflict_policy, new_cards_paused, study_direction, session_limit,
+                   min_answer_chars, utc_offset_minutes, created_at, updated_at
             FROM global_settings
             WHERE device_id = $1
             "#,
@@ -520,8 +936,14 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO global_settings (device_id, algorithm, rating_scale, matching_mode,
-                                        fuzzy_threshold, new_cards_per_day, reviews_per_day, daily_reset_hour)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                                        fuzzy_threshold, new_cards_per_day, reviews_per_day, daily_reset_hour,
+                                        again_delay_minutes, due_grace_hours, graduation_requirement,
+                                        deck_path_separator, suggest_easy_threshold, suggest_hard_threshold,
+                                        queue_order, min_ease, max_ease, sync_conflict_policy,
+                                        new_cards_paused, study_direction, session_limit,
+                                        min_answer_chars, utc_offset_minutes)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17,
+                    $18, $19, $20, $21, $22, $23)
             ON CONFLICT (device_id) DO UPDATE SET
                 algorithm = EXCLUDED.algorithm,
                 rating_scale = EXCLUDED.rating_scale,
@@ -530,6 +952,21 @@ impl Database {
                 new_cards_per_day = EXCLUDED.new_cards_per_day,
                 reviews_per_day = EXCLUDED.reviews_per_day,
                 daily_reset_hour = EXCLUDED.daily_reset_hour,
+                again_delay_minutes = EXCLUDED.again_delay_minutes,
+                due_grace_hours = EXCLUDED.due_grace_hours,
+                graduation_requirement = EXCLUDED.graduation_requirement,
+                deck_path_separator = EXCLUDED.deck_path_separator,
+                suggest_easy_threshold = EXCLUDED.suggest_easy_threshold,
+                suggest_hard_threshold = EXCLUDED.suggest_hard_threshold,
+                queue_order = EXCLUDED.queue_order,
+                min_ease = EXCLUDED.min_ease,
+                max_ease = EXCLUDED.max_ease,
+                sync_conflict_policy = EXCLUDED.sync_conflict_policy,
+                new_cards_paused = EXCLUDED.new_cards_paused,
+                study_direction = EXCLUDED.study_direction,
+                session_limit = EXCLUDED.session_limit,
+                min_answer_chars = EXCLUDED.min_answer_chars,
+                utc_offset_minutes = EXCLUDED.utc_offset_minutes,
                 updated_at = NOW()
             "#,
         )
@@ -541,6 +978,21 @@ impl Database {
         .bind(settings.new_cards_per_day)
         .bind(settings.reviews_per_day)
         .bind(settings.daily_reset_hour)
+        .bind(settings.again_delay_minutes)
+        .bind(settings.due_grace_hours)
+        .bind(settings.graduation_requirement)
+        .bind(&settings.deck_path_separator)
+        .bind(settings.suggest_easy_threshold)
+        .bind(settings.suggest_hard_threshold)
+        .bind(&settings.queue_order)
+        .bind(settings.min_ease)
+        .bind(settings.max_ease)
+        .bind(&settings.sync_conflict_policy)
+        .bind(settings.new_cards_paused)
+        .bind(&settings.study_direction)
+        .bind(&settings.session_limit)
+        .bind(settings.min_answer_chars)
+        .bind(settings.utc_offset_minutes)
         .execute(&self.pool)
         .await?;
 
@@ -552,7 +1004,8 @@ impl Database {
         let settings = sqlx::query_as::<_, DbDeckSettings>(
             r#"
             SELECT id, device_id, deck_path, algorithm, rating_scale, matching_mode,
-                   fuzzy_threshold, new_cards_per_day, reviews_per_day, created_at, updated_at
+                   fuzzy_threshold, new_cards_per_day, reviews_per_day, color, icon,
+                   normalization_rules, request_retention, created_at, updated_at
             FROM deck_settings
             WHERE device_id = $1 AND deck_path = $2
             "#,
@@ -570,8 +1023,9 @@ impl Database {
         sqlx::query(
             r#"
             INSERT INTO deck_settings (device_id, deck_path, algorithm, rating_scale, matching_mode,
-                                      fuzzy_threshold, new_cards_per_day, reviews_per_day)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                                      fuzzy_threshold, new_cards_per_day, reviews_per_day,
+                                      color, icon, normalization_rules, request_retention)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
             ON CONFLICT (device_id, deck_path) DO UPDATE SET
                 algorithm = EXCLUDED.algorithm,
                 rating_scale = EXCLUDED.rating_scale,
@@ -579,6 +1033,10 @@ impl Database {
                 fuzzy_threshold = EXCLUDED.fuzzy_threshold,
                 new_cards_per_day = EXCLUDED.new_cards_per_day,
                 reviews_per_day = EXCLUDED.reviews_per_day,
+                color = EXCLUDED.color,
+                icon = EXCLUDED.icon,
+                normalization_rules = EXCLUDED.normalization_rules,
+                request_retention = EXCLUDED.request_retention,
                 updated_at = NOW()
             "#,
         )
@@ -590,20 +1048,27 @@ impl Database {
         .bind(settings.fuzzy_threshold)
         .bind(settings.new_cards_per_day)
         .bind(settings.reviews_per_day)
+        .bind(&settings.color)
+        .bind(&settings.icon)
+        .bind(&settings.normalization_rules)
+        .bind(settings.request_retention)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    /// Get all deck settings for a device
+    /// Get all deck settings for a device, shortest path first so ancestors
+    /// come before their descendants, as [`DeckSettings::cascade`] expects
     pub async fn get_all_deck_settings(&self, device_id: Uuid) -> Result<Vec<DbDeckSettings>> {
         let settings = sqlx::query_as::<_, DbDeckSettings>(
             r#"
             SELECT id, device_id, deck_path, algorithm, rating_scale, matching_mode,
-                   fuzzy_threshold, new_cards_per_day, reviews_per_day, created_at, updated_at
+                   fuzzy_threshold, new_cards_per_day, reviews_per_day, color, icon,
+                   normalization_rules, request_retention, created_at, updated_at
             FROM deck_settings
             WHERE device_id = $1
+            ORDER BY LENGTH(deck_path), deck_path
             "#,
         )
         .bind(device_id)
@@ -629,7 +1094,8 @@ impl Database {
         Ok(result.rows_affected() > 0)
     }
 
-    /// Get effective settings (global merged with deck overrides)
+    /// Get effective settings (global merged with the overrides of the deck
+    /// and its ancestors)
     pub async fn get_effective_settings(
         &self,
         device_id: Uuid,
@@ -638,7 +1104,15 @@ impl Database {
         let global = self.get_global_settings(device_id).await?;
 
         let deck = match deck_path {
-            Some(path) => self.get_deck_settings(device_id, path).await?,
+            Some(path) => {
+                let all: Vec<DeckSettings> = self
+                    .get_all_deck_settings(device_id)
+                    .await?
+                    .iter()
+                    .map(DbDeckSettings::to_api_settings)
+                    .collect();
+                DeckSettings::cascade(&all, path)
+            }
             None => None,
         };
 
@@ -647,8 +1121,36 @@ impl Database {
 
     // === Deck Repository ===
 
+    /// Latest change to any of a device's cards or card states, including
+    /// deletions. `None` when the device has no cards.
+    pub async fn get_last_card_change(&self, device_id: Uuid) -> Result<Option<DateTime<Utc>>> {
+        let last_change: Option<DateTime<Utc>> = sqlx::query_scalar(
+            r#"
+            SELECT GREATEST(
+                (SELECT MAX(GREATEST(updated_at, deleted_at)) FROM cards WHERE device_id = $1),
+                (SELECT MAX(updated_at) FROM card_states WHERE device_id = $1)
+            )
+            "#,
+        )
+        .bind(device_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(last_change)
+    }
+
     /// Get all decks for a device
-    pub async fn get_all_decks(&self, device_id: Uuid) -> Result<Vec<DeckInfo>> {
+    ///
+    /// Due counts use the current study day so they honor `daily_reset_hour`
+    /// in the device's timezone.
+    pub async fn get_all_decks(
+        &self,
+        device_id: Uuid,
+        daily_reset_hour: i32,
+        utc_offset_minutes: i32,
+    ) -> Result<Vec<DeckInfo>> {
+        let today =
+            crate::services::study::study_day(Utc::now(), daily_reset_hour, utc_offset_minutes);
         let decks = sqlx::query_as::<_, DeckInfo>(
             r#"
             SELECT
@@ -657,7 +1159,7 @@ impl Database {
                 COUNT(c.id)::INT as card_count,
                 COUNT(CASE WHEN cs.status IS NULL OR cs.status = 'new' THEN 1 END)::INT as new_count,
                 COUNT(CASE WHEN cs.status IN ('review', 'learning', 'relearning')
-                           AND cs.due_date <= CURRENT_DATE THEN 1 END)::INT as due_count
+                           AND cs.due_date <= $2 THEN 1 END)::INT as due_count
             FROM cards c
             LEFT JOIN card_states cs ON c.id = cs.card_id AND cs.device_id = $1
             WHERE c.device_id = $1 AND c.deleted_at IS NULL
@@ -666,14 +1168,73 @@ impl Database {
             "#,
         )
         .bind(device_id)
+        .bind(today)
         .fetch_all(&self.pool)
         .await?;
 
         Ok(decks)
     }
 
+    /// Get per-deck new/due/total counts with grand totals.
+    ///
+    /// Due counts use the current study day so they honor `daily_reset_hour`
+    /// in the device's timezone.
+    pub async fn get_deck_summaries(
+        &self,
+        device_id: Uuid,
+        daily_reset_hour: i32,
+        utc_offset_minutes: i32,
+    ) -> Result<DeckSummaries> {
+        let today =
+            crate::services::study::study_day(Utc::now(), daily_reset_hour, utc_offset_minutes);
+        let rows = sqlx::query(
+            r#"
+            SELECT
+                c.deck_path as path,
+                COUNT(c.id)::INT as total,
+                COUNT(CASE WHEN cs.status IS NULL OR cs.status = 'new' THEN 1 END)::INT as new_count,
+                COUNT(CASE WHEN cs.status IN ('review', 'learning', 'relearning')
+                           AND cs.due_date <= $2 THEN 1 END)::INT as due_count
+            FROM cards c
+            LEFT JOIN card_states cs ON c.id = cs.card_id AND cs.device_id = $1
+            WHERE c.device_id = $1 AND c.deleted_at IS NULL
+            GROUP BY c.deck_path
+            ORDER BY c.deck_path
+            "#,
+        )
+        .bind(device_id)
+        .bind(today)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let decks = rows
+            .iter()
+            .map(|row| DeckSummary {
+                path: row.get("path"),
+                new_count: row.get::<i32, _>("new_count") as usize,
+                due_count: row.get::<i32, _>("due_count") as usize,
+                total: row.get::<i32, _>("total") as usize,
+            })
+            .collect();
+
+        Ok(DeckSummaries::from_decks(decks))
+    }
+
     /// Get deck statistics
-    pub async fn get_deck_stats(&self, device_id: Uuid, deck_path: &str) -> Result<DeckStatsResponse> {
+    ///
+    /// Reviews today count from the start of the study day.
+    pub async fn get_deck_stats(
+        &self,
+        device_id: Uuid,
+        deck_path: &str,
+        daily_reset_hour: i32,
+        utc_offset_minutes: i32,
+    ) -> Result<DeckStatsResponse> {
+        let day_start = crate::services::study::study_day_start(
+            Utc::now(),
+            daily_reset_hour,
+            utc_offset_minutes,
+        );
         let row = sqlx::query(
             r#"
             SELECT
@@ -699,11 +1260,12 @@ impl Database {
             FROM reviews r
             JOIN cards c ON r.card_id = c.id
             WHERE r.device_id = $1 AND c.deck_path = $2
-              AND r.reviewed_at >= CURRENT_DATE
+              AND r.reviewed_at >= $3
             "#,
         )
         .bind(device_id)
         .bind(deck_path)
+        .bind(day_start)
         .fetch_one(&self.pool)
         .await?;
 
@@ -713,12 +1275,13 @@ impl Database {
             SELECT AVG(CASE WHEN rating >= 3 THEN 1.0 ELSE 0.0 END)::FLOAT8
             FROM reviews r
             JOIN cards c ON r.card_id = c.id
-            WHERE r.device_id = $1 AND c.deck_path = $2
-              AND r.reviewed_at >= CURRENT_DATE - INTERVAL '30 days'
+            WHERE r.device_id = $1 AND c.deck_path = $2 AND NOT r.cram
+              AND r.reviewed_at >= $3 - INTERVAL '30 days'
             "#,
         )
         .bind(device_id)
         .bind(deck_path)
+        .bind(day_start)
         .fetch_one(&self.pool)
         .await?;
 
@@ -734,6 +1297,114 @@ impl Database {
         })
     }
 
+    /// Get deck statistics for every deck at once, with totals over all decks.
+    ///
+    /// Each figure matches [`Self::get_deck_stats`] for the same deck; the
+    /// totals row comes from the same grouped queries, so averages and
+    /// retention are over all cards and reviews rather than over decks.
+    pub async fn get_stats_overview(
+        &self,
+        device_id: Uuid,
+        daily_reset_hour: i32,
+        utc_offset_minutes: i32,
+    ) -> Result<StatsOverviewResponse> {
+        let day_start = crate::services::study::study_day_start(
+            Utc::now(),
+            daily_reset_hour,
+            utc_offset_minutes,
+        );
+        let card_rows = sqlx::query(
+            r#"
+            SELECT
+                c.deck_path,
+                COUNT(c.id)::INT as total_cards,
+                COUNT(CASE WHEN cs.status IS NULL OR cs.status = 'new' THEN 1 END)::INT as new_cards,
+                COUNT(CASE WHEN cs.status = 'learning' THEN 1 END)::INT as learning_cards,
+                COUNT(CASE WHEN cs.status = 'review' THEN 1 END)::INT as review_cards,
+                COALESCE(AVG(cs.ease_factor), 2.5)::FLOAT8 as average_ease,
+                COALESCE(AVG(cs.interval_days), 0)::FLOAT8 as average_interval
+            FROM cards c
+            LEFT JOIN card_states cs ON c.id = cs.card_id AND cs.device_id = $1
+            WHERE c.device_id = $1 AND c.deleted_at IS NULL
+            GROUP BY GROUPING SETS ((c.deck_path), ())
+            ORDER BY c.deck_path
+            "#,
+        )
+        .bind(device_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Every review since the retention window, which also covers today
+        let review_rows = sqlx::query(
+            r#"
+            SELECT
+                c.deck_path,
+                COUNT(CASE WHEN r.reviewed_at >= $2 THEN 1 END) as reviews_today,
+                AVG(CASE WHEN r.rating >= 3 AND NOT r.cram THEN 1.0
+                         WHEN NOT r.cram THEN 0.0 END)::FLOAT8 as retention
+            FROM reviews r
+            JOIN cards c ON r.card_id = c.id
+            WHERE r.device_id = $1
+              AND r.reviewed_at >= $2 - INTERVAL '30 days'
+            GROUP BY GROUPING SETS ((c.deck_path), ())
+            "#,
+        )
+        .bind(device_id)
+        .bind(day_start)
+        .fetch_all(&self.pool)
+        .await?;
+
+        // Keyed by deck path; `None` is the totals row
+        let reviews: HashMap<Option<String>, (i64, Option<f64>)> = review_rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get("deck_path"),
+                    (row.get("reviews_today"), row.get("retention")),
+                )
+            })
+            .collect();
+
+        let stats_for = |row: &PgRow, path: &Option<String>| {
+            let (reviews_today, retention) = reviews.get(path).copied().unwrap_or((0, None));
+            DeckStatsResponse {
+                total_cards: row.get::<i32, _>("total_cards") as usize,
+                new_cards: row.get::<i32, _>("new_cards") as usize,
+                learning_cards: row.get::<i32, _>("learning_cards") as usize,
+                review_cards: row.get::<i32, _>("review_cards") as usize,
+                average_ease: row.get("average_ease"),
+                average_interval: row.get("average_interval"),
+                retention_rate: retention.unwrap_or(0.0),
+                reviews_today: reviews_today as usize,
+            }
+        };
+
+        let mut totals = None;
+        let mut decks = Vec::new();
+        for row in &card_rows {
+            let path: Option<String> = row.get("deck_path");
+            let stats = stats_for(row, &path);
+            match path {
+                Some(path) => decks.push(DeckStatsEntry { path, stats }),
+                None => totals = Some(stats),
+            }
+        }
+
+        Ok(StatsOverviewResponse {
+            totals: totals.unwrap_or(DeckStatsResponse {
+                total_cards: 0,
+                new_cards: 0,
+                learning_cards: 0,
+                review_cards: 0,
+                average_ease: 2.5,
+                average_interval: 0.0,
+                retention_rate: 0.0,
+                reviews_today: 0,
+            }),
+            decks,
+        })
+    }
+
     /// Get cards updated since a timestamp (for sync)
     pub async fn get_cards_since(
         &self,
@@ -787,7 +1458,7 @@ impl Database {
                     r#"
                     SELECT id, card_id, device_id, status, interval_days, ease_factor,
                            due_date, stability, difficulty, lapses, reviews_count,
-                           created_at, updated_at
+                           learning_reps, created_at, updated_at
                     FROM card_states
                     WHERE device_id = $1 AND updated_at > $2
                     ORDER BY card_id
@@ -803,7 +1474,7 @@ impl Database {
                     r#"
                     SELECT id, card_id, device_id, status, interval_days, ease_factor,
                            due_date, stability, difficulty, lapses, reviews_count,
-                           created_at, updated_at
+                           learning_reps, created_at, updated_at
                     FROM card_states
                     WHERE device_id = $1
                     ORDER BY card_id
@@ -865,6 +1536,27 @@ impl Database {
         Ok(files)
     }
 
+    /// Count a device's tracked MD files, live cards and reviews
+    pub async fn get_collection_counts(&self, device_id: Uuid) -> Result<(i64, i64, i64)> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM md_files WHERE device_id = $1) as file_count,
+                (SELECT COUNT(*) FROM cards WHERE device_id = $1 AND deleted_at IS NULL) as card_count,
+                (SELECT COUNT(*) FROM reviews WHERE device_id = $1) as review_count
+            "#,
+        )
+        .bind(device_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((
+            row.get("file_count"),
+            row.get("card_count"),
+            row.get("review_count"),
+        ))
+    }
+
     /// Get MD file by path
     pub async fn get_md_file(&self, device_id: Uuid, file_path: &str) -> Result<Option<MdFile>> {
         let file = sqlx::query_as::<_, MdFile>(
@@ -8,6 +8,8 @@ use axum::{
 use serde::Serialize;
 use thiserror::Error;
 
+use crate::models::DuplicateIdConflict;
+
 /// API error types
 #[derive(Debug, Error)]
 pub enum ApiError {
@@ -29,15 +31,38 @@ pub enum ApiError {
     #[error("Bad request: {0}")]
     BadRequest(String),
 
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    #[error("Duplicate card IDs: {}", format_conflicts(.0))]
+    DuplicateIds(Vec<DuplicateIdConflict>),
+
     #[error("Internal error: {0}")]
     Internal(String),
 }
 
+fn format_conflicts(conflicts: &[DuplicateIdConflict]) -> String {
+    conflicts
+        .iter()
+        .map(|c| {
+            let locations: Vec<String> = c
+                .locations
+                .iter()
+                .map(|l| format!("{}:{}", l.path, l.line))
+                .collect();
+            format!("{} ({})", c.id, locations.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
 /// Error response body
 #[derive(Serialize)]
 struct ErrorResponse {
     error: String,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    conflicts: Option<Vec<DuplicateIdConflict>>,
 }
 
 impl IntoResponse for ApiError {
@@ -47,14 +72,23 @@ impl IntoResponse for ApiError {
             ApiError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
             ApiError::BadRequest(_) => (StatusCode::BAD_REQUEST, "bad_request"),
             ApiError::Parse(_) => (StatusCode::BAD_REQUEST, "parse_error"),
+            ApiError::PayloadTooLarge(_) => (StatusCode::PAYLOAD_TOO_LARGE, "payload_too_large"),
+            ApiError::DuplicateIds(_) => (StatusCode::CONFLICT, "duplicate_ids"),
             ApiError::Database(_) => (StatusCode::INTERNAL_SERVER_ERROR, "database_error"),
             ApiError::Migration(_) => (StatusCode::INTERNAL_SERVER_ERROR, "migration_error"),
             ApiError::Internal(_) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
         };
 
+        let message = self.to_string();
+        let conflicts = match self {
+            ApiError::DuplicateIds(conflicts) => Some(conflicts),
+            _ => None,
+        };
+
         let body = Json(ErrorResponse {
             error: error_type.to_string(),
-            message: self.to_string(),
+            message,
+            conflicts,
         });
 
         (status, body).into_response()
@@ -96,6 +130,13 @@ mod tests {
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[test]
+    fn test_payload_too_large_status() {
+        let error = ApiError::PayloadTooLarge("file too big".to_string());
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
     #[test]
     fn test_internal_error_status() {
         let error = ApiError::Internal("unexpected error".to_string());
@@ -110,6 +151,31 @@ mod tests {
         assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 
+    #[test]
+    fn test_duplicate_ids_status() {
+        let error = ApiError::DuplicateIds(vec![]);
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_error_display_duplicate_ids() {
+        let error = ApiError::DuplicateIds(vec![DuplicateIdConflict {
+            id: 5,
+            locations: vec![
+                crate::models::CardLocation {
+                    path: "a.md".to_string(),
+                    line: 1,
+                },
+                crate::models::CardLocation {
+                    path: "b.md".to_string(),
+                    line: 4,
+                },
+            ],
+        }]);
+        assert_eq!(error.to_string(), "Duplicate card IDs: 5 (a.md:1, b.md:4)");
+    }
+
     #[test]
     fn test_error_display_unauthorized() {
         let error = ApiError::Unauthorized("invalid token".to_string());
@@ -7,22 +7,99 @@ pub mod services;
 use std::sync::Arc;
 
 use axum::{
+    extract::DefaultBodyLimit,
+    http::{header, HeaderValue, Method},
     middleware,
     routing::{delete, get, post, put},
     Router,
 };
-use tower_http::cors::CorsLayer;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use tower_http::limit::RequestBodyLimitLayer;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use crate::db::Database;
-use crate::services::storage::StorageService;
+use crate::services::auth_cache::AuthCache;
+use crate::services::storage::{ObjectStore, S3Store};
 
 /// Shared application state
 #[derive(Clone)]
 pub struct AppState {
     pub db: Arc<Database>,
-    pub storage: Arc<StorageService>,
+    pub storage: Arc<dyn ObjectStore>,
+    pub auth_cache: Arc<AuthCache>,
+}
+
+/// Default maximum request body size (25 MB).
+pub const DEFAULT_MAX_BODY_BYTES: usize = 25 * 1024 * 1024;
+
+/// Request body limit from `MAX_BODY_BYTES`, falling back to the default.
+pub fn max_body_bytes() -> usize {
+    std::env::var("MAX_BODY_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_BODY_BYTES)
+}
+
+/// Reject request bodies larger than `max_bytes` with 413 before they are parsed.
+///
+/// Replaces axum's built-in 2 MB extractor limit so large-but-valid uploads
+/// are governed by a single configurable limit.
+pub fn with_body_limit<S>(router: Router<S>, max_bytes: usize) -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+{
+    router
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_bytes))
+}
+
+/// CORS origins from `ALLOWED_ORIGINS` (comma-separated), if set.
+pub fn allowed_origins() -> Option<Vec<String>> {
+    std::env::var("ALLOWED_ORIGINS")
+        .ok()
+        .map(|v| parse_origins(&v))
+}
+
+/// Split a comma-separated origin list, dropping blanks.
+pub fn parse_origins(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|origin| !origin.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Build the CORS layer from the configured origins.
+///
+/// Configured origins get a strict allow-list limited to the methods and
+/// headers the API uses. Without configuration, debug builds stay permissive
+/// for local development and release builds allow no cross-origin requests.
+pub fn cors_layer(origins: Option<&[String]>) -> CorsLayer {
+    let Some(origins) = origins else {
+        if cfg!(debug_assertions) {
+            return CorsLayer::permissive();
+        }
+        tracing::warn!("ALLOWED_ORIGINS is not set; cross-origin requests are disabled");
+        return CorsLayer::new();
+    };
+
+    let origins: Vec<HeaderValue> = origins
+        .iter()
+        .filter_map(|origin| match HeaderValue::from_str(origin) {
+            Ok(value) => Some(value),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid CORS origin: {}", origin);
+                None
+            }
+        })
+        .collect();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
 }
 
 pub async fn run() -> anyhow::Result<()> {
@@ -46,11 +123,12 @@ pub async fn run() -> anyhow::Result<()> {
     db.run_migrations().await?;
 
     tracing::info!("Initializing S3 storage...");
-    let storage = StorageService::new().await?;
+    let storage = S3Store::new().await?;
 
     let state = AppState {
         db: Arc::new(db),
         storage: Arc::new(storage),
+        auth_cache: Arc::new(AuthCache::default()),
     };
 
     // Build router with protected routes
@@ -59,20 +137,42 @@ pub async fn run() -> anyhow::Result<()> {
         .route("/api/device/status", get(routes::device::status))
         // Study routes
         .route("/api/study/queue", get(routes::study::queue))
+        .route("/api/study/difficult", get(routes::study::difficult))
         .route("/api/study/review", post(routes::study::review))
+        // Review log routes
+        .route("/api/reviews", get(routes::reviews::list))
         // Settings routes
         .route("/api/settings", get(routes::settings::get_all))
+        .route("/api/settings/effective", get(routes::settings::get_effective))
         .route("/api/settings/global", put(routes::settings::update_global))
         .route("/api/settings/deck/{path}", put(routes::settings::update_deck))
         .route("/api/settings/deck/{path}", delete(routes::settings::delete_deck))
         // Deck routes
         .route("/api/decks", get(routes::decks::list))
+        .route("/api/decks/summaries", get(routes::decks::summaries))
         .route("/api/decks/{path}/stats", get(routes::decks::stats))
+        .route("/api/decks/{path}/cards", get(routes::decks::cards))
+        .route("/api/decks/{path}/slowest-cards", get(routes::decks::slowest_cards))
+        .route(
+            "/api/decks/{path}/rating-distribution",
+            get(routes::decks::rating_distribution),
+        )
+        .route("/api/decks/{path}/reset-progress", post(routes::decks::reset_progress))
+        .route("/api/stats/overview", get(routes::decks::stats_overview))
+        .route("/api/stats/storage", get(routes::decks::storage_stats))
+        // Card routes
+        .route("/api/cards", get(routes::cards::list))
+        .route("/api/cards/{id}", get(routes::cards::get))
+        .route("/api/cards/{id}/states", get(routes::cards::states))
         // Sync routes
         .route("/api/sync/pull", post(routes::sync::pull))
         .route("/api/sync/push-reviews", post(routes::sync::push_reviews))
+        .route("/api/sync/orphans", post(routes::sync::orphans))
         .route("/api/sync/confirm-delete", post(routes::sync::confirm_delete))
         .route("/api/sync/upload", post(routes::sync::upload))
+        .route("/api/sync/files/{path}", get(routes::sync::download))
+        // Maintenance routes
+        .route("/api/maintenance/reconcile", post(routes::maintenance::reconcile))
         .layer(middleware::from_fn_with_state(
             state.clone(),
             routes::auth::auth_middleware,
@@ -82,8 +182,9 @@ pub async fn run() -> anyhow::Result<()> {
     let app = Router::new()
         .route("/health", get(health_check))
         .route("/api/device/register", post(routes::device::register))
-        .merge(protected_routes)
-        .layer(CorsLayer::permissive())
+        .merge(protected_routes);
+    let app = with_body_limit(app, max_body_bytes())
+        .layer(cors_layer(allowed_origins().as_deref()))
         .layer(TraceLayer::new_for_http())
         .with_state(state);
 
@@ -102,3 +203,111 @@ pub async fn run() -> anyhow::Result<()> {
 async fn health_check() -> &'static str {
     "OK"
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use axum::Json;
+    use axum_test::TestServer;
+
+    use crate::models::{SyncFile, SyncUploadRequest};
+
+    async fn count_files(Json(payload): Json<SyncUploadRequest>) -> String {
+        payload.files.len().to_string()
+    }
+
+    fn upload(content: String) -> SyncUploadRequest {
+        SyncUploadRequest {
+            files: vec![SyncFile {
+                path: "big.md".to_string(),
+                hash: String::new(),
+                content,
+            }],
+            claim_existing_ids: false,
+            claimed_ids: Vec::new(),
+        }
+    }
+
+    fn server(max_bytes: usize) -> TestServer {
+        let app = with_body_limit(Router::new().route("/upload", post(count_files)), max_bytes);
+        TestServer::new(app).unwrap()
+    }
+
+    fn cors_server(origins: &[&str]) -> TestServer {
+        let origins: Vec<String> = origins.iter().map(|o| o.to_string()).collect();
+        let app = Router::new()
+            .route("/health", get(health_check))
+            .layer(cors_layer(Some(&origins)));
+        TestServer::new(app).unwrap()
+    }
+
+    #[test]
+    fn test_parse_origins() {
+        assert_eq!(
+            parse_origins(" https://a.example ,https://b.example,, "),
+            vec!["https://a.example", "https://b.example"]
+        );
+        assert!(parse_origins("").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cors_allows_listed_origin() {
+        let server = cors_server(&["https://app.example"]);
+        let response = server
+            .get("/health")
+            .add_header(header::ORIGIN, HeaderValue::from_static("https://app.example"))
+            .await;
+
+        assert_eq!(
+            response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some(&HeaderValue::from_static("https://app.example"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cors_rejects_unlisted_origin() {
+        let server = cors_server(&["https://app.example"]);
+        let response = server
+            .get("/health")
+            .add_header(header::ORIGIN, HeaderValue::from_static("https://evil.example"))
+            .await;
+        assert!(response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+
+        // Preflight from the unlisted origin is not granted either
+        let preflight = server
+            .method(Method::OPTIONS, "/health")
+            .add_header(header::ORIGIN, HeaderValue::from_static("https://evil.example"))
+            .add_header(
+                header::ACCESS_CONTROL_REQUEST_METHOD,
+                HeaderValue::from_static("POST"),
+            )
+            .await;
+        assert!(preflight
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_over_limit_body_rejected_before_parsing() {
+        let server = server(1024);
+        let response = server.post("/upload").json(&upload("x".repeat(2048))).await;
+        assert_eq!(response.status_code(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_body_limit_replaces_default_extractor_limit() {
+        // Larger than axum's 2 MB default but within the configured limit
+        let server = server(4 * 1024 * 1024);
+        let response = server
+            .post("/upload")
+            .json(&upload("x".repeat(3 * 1024 * 1024)))
+            .await;
+        assert_eq!(response.status_code(), StatusCode::OK);
+        assert_eq!(response.text(), "1");
+    }
+}
@@ -8,8 +8,9 @@ use uuid::Uuid;
 
 // Re-export shared types from flashcard-core
 pub use flashcard_core::types::{
-    Algorithm, AnswerMode, Card, CardState, CardStatus, DeckSettings, GlobalSettings,
-    MatchingMode, Rating, RatingScale, RawCard,
+    Algorithm, AnswerMode, Card, CardState, CardStatus, ConflictPolicy, DeckSettings,
+    DeckSummaries, DeckSummary, GlobalSettings, Limit, MatchingMode, NormRule, QueueOrder,
+    Rating, RatingScale, RawCard, ReviewSubmission, SessionLimit, StudyDirection,
 };
 
 // === Database Entity Types ===
@@ -50,6 +51,7 @@ impl DbCard {
             answer: self.answer_text.clone(),
             source_file: self.source_file.clone(),
             deleted_at: self.deleted_at,
+            updated_at: Some(self.updated_at),
         }
     }
 }
@@ -68,6 +70,7 @@ pub struct DbCardState {
     pub difficulty: Option<f64>,
     pub lapses: i32,
     pub reviews_count: i32,
+    pub learning_reps: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -96,6 +99,7 @@ impl DbCardState {
             difficulty: state.difficulty,
             lapses: state.lapses as i32,
             reviews_count: state.reviews_count as i32,
+            learning_reps: state.learning_reps as i32,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -104,12 +108,7 @@ impl DbCardState {
     /// Convert to flashcard-core CardState
     pub fn to_core_state(&self) -> CardState {
         CardState {
-            status: match self.status.as_str() {
-                "learning" => CardStatus::Learning,
-                "review" => CardStatus::Review,
-                "relearning" => CardStatus::Relearning,
-                _ => CardStatus::New,
-            },
+            status: parse_status(&self.status),
             interval_days: self.interval_days,
             ease_factor: self.ease_factor,
             stability: self.stability,
@@ -117,6 +116,17 @@ impl DbCardState {
             lapses: self.lapses as u32,
             reviews_count: self.reviews_count as u32,
             due_date: self.due_date.map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc()),
+            learning_reps: self.learning_reps.max(0) as u32,
+        }
+    }
+
+    /// Convert to a per-device state with its timestamps
+    pub fn to_device_state(&self) -> DeviceCardState {
+        DeviceCardState {
+            device_id: self.device_id,
+            state: self.to_core_state(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
         }
     }
 
@@ -125,6 +135,37 @@ impl DbCardState {
         CardStateWithId {
             card_id: self.card_id,
             state: self.to_core_state(),
+            updated_at: Some(self.updated_at),
+        }
+    }
+}
+
+/// Parse a stored status, treating unknown values as new
+fn parse_status(status: &str) -> CardStatus {
+    match status {
+        "learning" => CardStatus::Learning,
+        "review" => CardStatus::Review,
+        "relearning" => CardStatus::Relearning,
+        _ => CardStatus::New,
+    }
+}
+
+/// Card joined with its state summary for deck browsing
+#[derive(Debug, Clone, FromRow)]
+pub struct DbDeckCard {
+    #[sqlx(flatten)]
+    pub card: DbCard,
+    pub status: String,
+    pub due_date: Option<NaiveDate>,
+}
+
+impl DbDeckCard {
+    /// Convert to API deck card type
+    pub fn to_api_card(&self) -> DeckCard {
+        DeckCard {
+            card: self.card.to_api_card(),
+            status: parse_status(&self.status),
+            due_date: self.due_date,
         }
     }
 }
@@ -135,6 +176,27 @@ pub struct CardStateWithId {
     pub card_id: i64,
     #[serde(flatten)]
     pub state: CardState,
+    /// When the state last changed, for last-write-wins conflict resolution
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A card's state as one device holds it, for comparing devices
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCardState {
+    pub device_id: Uuid,
+    #[serde(flatten)]
+    pub state: CardState,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CardStatesResponse {
+    pub card_id: i64,
+    /// One entry per device that has studied the card. Devices are not
+    /// linked into accounts, so this holds at most the requesting device.
+    pub states: Vec<DeviceCardState>,
 }
 
 impl Default for DbCardState {
@@ -151,6 +213,7 @@ impl Default for DbCardState {
             difficulty: None,
             lapses: 0,
             reviews_count: 0,
+            learning_reps: 0,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -175,6 +238,9 @@ pub struct DbReview {
     pub ease_before: Option<f64>,
     pub ease_after: Option<f64>,
     pub algorithm: String,
+    pub params_hash: Option<String>,
+    /// A cram review, which left the card's schedule alone
+    pub cram: bool,
     pub created_at: DateTime<Utc>,
 }
 
@@ -189,6 +255,22 @@ pub struct DbGlobalSettings {
     pub new_cards_per_day: i32,
     pub reviews_per_day: i32,
     pub daily_reset_hour: i32,
+    pub again_delay_minutes: i32,
+    pub due_grace_hours: i32,
+    pub graduation_requirement: i32,
+    pub deck_path_separator: Option<String>,
+    pub suggest_easy_threshold: f64,
+    pub suggest_hard_threshold: f64,
+    pub queue_order: String,
+    pub min_ease: f64,
+    pub max_ease: f64,
+    pub sync_conflict_policy: String,
+    pub new_cards_paused: bool,
+    pub study_direction: String,
+    pub session_limit: String,
+    pub min_answer_chars: i32,
+    /// The device's offset from UTC when it last synced, for its study days
+    pub utc_offset_minutes: i32,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -205,6 +287,22 @@ impl DbGlobalSettings {
             new_cards_per_day: 20,
             reviews_per_day: 200,
             daily_reset_hour: 0,
+            again_delay_minutes: flashcard_core::algorithm::DEFAULT_AGAIN_DELAY_MINUTES as i32,
+            due_grace_hours: 0,
+            graduation_requirement: flashcard_core::algorithm::DEFAULT_GRADUATION_REQUIREMENT
+                as i32,
+            deck_path_separator: None,
+            suggest_easy_threshold: flashcard_core::matching::DEFAULT_SUGGEST_EASY_THRESHOLD,
+            suggest_hard_threshold: flashcard_core::matching::DEFAULT_SUGGEST_HARD_THRESHOLD,
+            queue_order: QueueOrder::default().as_str().to_string(),
+            min_ease: flashcard_core::algorithm::DEFAULT_MIN_EASE,
+            max_ease: flashcard_core::algorithm::DEFAULT_MAX_EASE,
+            sync_conflict_policy: ConflictPolicy::default().as_str().to_string(),
+            new_cards_paused: false,
+            study_direction: StudyDirection::default().as_str().to_string(),
+            session_limit: SessionLimit::default().to_string(),
+            min_answer_chars: 0,
+            utc_offset_minutes: 0,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         }
@@ -221,16 +319,37 @@ impl DbGlobalSettings {
             matching_mode: match self.matching_mode.as_str() {
                 "exact" => MatchingMode::Exact,
                 "case_insensitive" => MatchingMode::CaseInsensitive,
+                "contains" => MatchingMode::Contains,
                 _ => MatchingMode::Fuzzy,
             },
             fuzzy_threshold: self.fuzzy_threshold,
-            new_cards_per_day: self.new_cards_per_day as u32,
-            reviews_per_day: self.reviews_per_day as u32,
+            new_cards_per_day: Limit::from_db(self.new_cards_per_day.into()),
+            reviews_per_day: Limit::from_db(self.reviews_per_day.into()),
             daily_reset_hour: self.daily_reset_hour as u32,
+            again_delay_minutes: self.again_delay_minutes as u32,
+            due_grace_hours: self.due_grace_hours as u32,
+            graduation_requirement: self.graduation_requirement.max(1) as u32,
+            deck_path_separator: self.deck_path_separator.clone(),
+            suggest_easy_threshold: self.suggest_easy_threshold,
+            suggest_hard_threshold: self.suggest_hard_threshold,
+            queue_order: self.queue_order.parse().unwrap_or_default(),
+            min_ease: self.min_ease,
+            max_ease: self.max_ease,
+            sync_conflict_policy: self.sync_conflict_policy.parse().unwrap_or_default(),
+            new_cards_paused: self.new_cards_paused,
+            study_direction: self.study_direction.parse().unwrap_or_default(),
+            session_limit: self.session_limit.parse().unwrap_or_default(),
+            min_answer_chars: self.min_answer_chars.max(0) as u32,
         }
     }
 }
 
+/// Column value for a daily limit; unlimited is stored as a negative number,
+/// see [`Limit::to_db`]
+pub fn limit_to_db(limit: Limit) -> i32 {
+    i32::try_from(limit.to_db()).unwrap_or(i32::MAX)
+}
+
 /// Deck settings in PostgreSQL
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct DbDeckSettings {
@@ -243,6 +362,11 @@ pub struct DbDeckSettings {
     pub fuzzy_threshold: Option<f64>,
     pub new_cards_per_day: Option<i32>,
     pub reviews_per_day: Option<i32>,
+    pub color: Option<String>,
+    pub icon: Option<String>,
+    /// Comma-separated [`NormRule`] spec
+    pub normalization_rules: Option<String>,
+    pub request_retention: Option<f64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -260,13 +384,28 @@ impl DbDeckSettings {
             matching_mode: self.matching_mode.as_ref().map(|m| match m.as_str() {
                 "exact" => MatchingMode::Exact,
                 "case_insensitive" => MatchingMode::CaseInsensitive,
+                "contains" => MatchingMode::Contains,
                 _ => MatchingMode::Fuzzy,
             }),
             fuzzy_threshold: self.fuzzy_threshold,
-            new_cards_per_day: self.new_cards_per_day.map(|n| n as u32),
-            reviews_per_day: self.reviews_per_day.map(|n| n as u32),
+            new_cards_per_day: self.new_cards_per_day.map(|n| Limit::from_db(n.into())),
+            reviews_per_day: self.reviews_per_day.map(|n| Limit::from_db(n.into())),
+            request_retention: self.request_retention,
+            color: self.color.clone(),
+            icon: self.icon.clone(),
+            // Archiving is local to each device
+            archived: false,
+            normalization_rules: self.normalization_rules(),
         }
     }
+
+    /// Parsed normalization rules; a spec that can't be read applies none
+    pub fn normalization_rules(&self) -> Vec<NormRule> {
+        self.normalization_rules
+            .as_deref()
+            .and_then(|spec| NormRule::parse_spec(spec).ok())
+            .unwrap_or_default()
+    }
 }
 
 /// Orphaned card info
@@ -306,32 +445,85 @@ pub struct EffectiveSettings {
     pub rating_scale: String,
     pub matching_mode: String,
     pub fuzzy_threshold: f64,
-    pub new_cards_per_day: i32,
-    pub reviews_per_day: i32,
+    pub new_cards_per_day: Limit,
+    pub reviews_per_day: Limit,
     pub daily_reset_hour: i32,
+    /// The device's offset from UTC, which `daily_reset_hour` is relative to
+    pub utc_offset_minutes: i32,
+    pub again_delay_minutes: i32,
+    pub due_grace_hours: i32,
+    pub graduation_requirement: i32,
+    pub min_ease: f64,
+    pub max_ease: f64,
+    /// FSRS retention target; only decks set one
+    pub request_retention: f64,
+    /// Ratings to offer for `rating_scale`, in button order
+    pub rating_buttons: Vec<Rating>,
+    /// The deck's answer normalization rules; there are no global ones
+    pub normalization_rules: Vec<NormRule>,
 }
 
 impl EffectiveSettings {
     /// Merge global settings with optional deck settings
-    pub fn merge(global: &DbGlobalSettings, deck: Option<&DbDeckSettings>) -> Self {
+    pub fn merge(global: &DbGlobalSettings, deck: Option<&DeckSettings>) -> Self {
+        let rating_scale = deck
+            .and_then(|d| d.rating_scale)
+            .map(|scale| scale.as_str().to_string())
+            .unwrap_or_else(|| global.rating_scale.clone());
+        let rating_buttons = rating_scale
+            .parse::<RatingScale>()
+            .unwrap_or_default()
+            .ratings()
+            .to_vec();
+
         match deck {
             Some(d) => Self {
-                algorithm: d.algorithm.clone().unwrap_or_else(|| global.algorithm.clone()),
-                rating_scale: d.rating_scale.clone().unwrap_or_else(|| global.rating_scale.clone()),
-                matching_mode: d.matching_mode.clone().unwrap_or_else(|| global.matching_mode.clone()),
+                algorithm: d
+                    .algorithm
+                    .map(|a| a.as_str().to_string())
+                    .unwrap_or_else(|| global.algorithm.clone()),
+                rating_scale,
+                matching_mode: d
+                    .matching_mode
+                    .map(|m| m.as_str().to_string())
+                    .unwrap_or_else(|| global.matching_mode.clone()),
                 fuzzy_threshold: d.fuzzy_threshold.unwrap_or(global.fuzzy_threshold),
-                new_cards_per_day: d.new_cards_per_day.unwrap_or(global.new_cards_per_day),
-                reviews_per_day: d.reviews_per_day.unwrap_or(global.reviews_per_day),
+                new_cards_per_day: d
+                    .new_cards_per_day
+                    .unwrap_or_else(|| Limit::from_db(global.new_cards_per_day.into())),
+                reviews_per_day: d
+                    .reviews_per_day
+                    .unwrap_or_else(|| Limit::from_db(global.reviews_per_day.into())),
                 daily_reset_hour: global.daily_reset_hour,
+                utc_offset_minutes: global.utc_offset_minutes,
+                again_delay_minutes: global.again_delay_minutes,
+                due_grace_hours: global.due_grace_hours,
+                graduation_requirement: global.graduation_requirement,
+                min_ease: global.min_ease,
+                max_ease: global.max_ease,
+                request_retention: d
+                    .request_retention
+                    .unwrap_or(flashcard_core::algorithm::DEFAULT_REQUEST_RETENTION),
+                rating_buttons,
+                normalization_rules: d.normalization_rules.clone(),
             },
             None => Self {
                 algorithm: global.algorithm.clone(),
-                rating_scale: global.rating_scale.clone(),
+                rating_scale,
                 matching_mode: global.matching_mode.clone(),
                 fuzzy_threshold: global.fuzzy_threshold,
-                new_cards_per_day: global.new_cards_per_day,
-                reviews_per_day: global.reviews_per_day,
+                new_cards_per_day: Limit::from_db(global.new_cards_per_day.into()),
+                reviews_per_day: Limit::from_db(global.reviews_per_day.into()),
                 daily_reset_hour: global.daily_reset_hour,
+                utc_offset_minutes: global.utc_offset_minutes,
+                again_delay_minutes: global.again_delay_minutes,
+                due_grace_hours: global.due_grace_hours,
+                graduation_requirement: global.graduation_requirement,
+                min_ease: global.min_ease,
+                max_ease: global.max_ease,
+                request_retention: flashcard_core::algorithm::DEFAULT_REQUEST_RETENTION,
+                rating_buttons,
+                normalization_rules: Vec::new(),
             },
         }
     }
@@ -360,6 +552,13 @@ pub struct DeviceStatusResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SyncUploadRequest {
     pub files: Vec<SyncFile>,
+    /// Report which ID each card in `claimed_ids` was assigned, so the client
+    /// can re-key its local data. Only honored on the device's first upload.
+    #[serde(default)]
+    pub claim_existing_ids: bool,
+    /// Client-side IDs for cards without an ID line, by file path and line.
+    #[serde(default)]
+    pub claimed_ids: Vec<NewIdAssignment>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -374,6 +573,10 @@ pub struct SyncUploadResponse {
     pub updated_files: Vec<UpdatedFile>,
     pub new_ids: Vec<NewIdAssignment>,
     pub orphaned_cards: Vec<OrphanedCard>,
+    /// More orphans exist beyond the first page; fetch them from `/api/sync/orphans`.
+    pub has_more_orphans: bool,
+    /// Claimed local IDs with the IDs their cards were assigned.
+    pub claimed_ids: Vec<ClaimedId>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -389,6 +592,47 @@ pub struct NewIdAssignment {
     pub id: i64,
 }
 
+/// A client's local card ID and the ID its card was assigned on upload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClaimedId {
+    pub local_id: i64,
+    pub id: i64,
+}
+
+/// A card ID declared more than once in an upload batch
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateIdConflict {
+    pub id: i64,
+    pub locations: Vec<CardLocation>,
+}
+
+/// Position of a card within an uploaded file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardLocation {
+    pub path: String,
+    pub line: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrphansRequest {
+    /// Every card ID still present in the client's files.
+    pub current_card_ids: Vec<i64>,
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "default_orphan_limit")]
+    pub limit: i64,
+}
+
+fn default_orphan_limit() -> i64 {
+    crate::db::ORPHAN_PAGE_SIZE
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrphansResponse {
+    pub orphaned_cards: Vec<OrphanedCard>,
+    pub has_more: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ConfirmDeleteRequest {
     pub card_ids: Vec<i64>,
@@ -402,6 +646,9 @@ pub struct ConfirmDeleteResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SyncPullRequest {
     pub last_sync_at: Option<DateTime<Utc>>,
+    /// The device's current offset from UTC, kept for its study days
+    #[serde(default)]
+    pub utc_offset_minutes: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -423,25 +670,26 @@ pub struct PushReviewsRequest {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct ReviewSubmission {
-    pub card_id: i64,
-    pub reviewed_at: DateTime<Utc>,
-    pub rating: i32,
-    pub rating_scale: String,
-    pub answer_mode: String,
-    pub typed_answer: Option<String>,
-    pub was_correct: Option<bool>,
-    pub time_taken_ms: Option<i32>,
-    pub interval_before: f64,
-    pub interval_after: f64,
-    pub ease_before: f64,
-    pub ease_after: f64,
-    pub algorithm: String,
+pub struct PushReviewsResponse {
+    pub synced_count: usize,
+    /// Reviews that can never be stored; clients should stop retrying them
+    pub rejected: Vec<RejectedReview>,
+}
+
+/// Why a pushed review was permanently rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReviewRejectionReason {
+    /// The card does not exist for this device or was deleted
+    CardNotFound,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct PushReviewsResponse {
-    pub synced_count: usize,
+pub struct RejectedReview {
+    /// Position of the review in the pushed list
+    pub index: usize,
+    pub card_id: i64,
+    pub reason: ReviewRejectionReason,
 }
 
 // Study types
@@ -450,11 +698,25 @@ pub struct StudyQueueQuery {
     pub deck_path: Option<String>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DifficultCardsQuery {
+    pub deck_path: Option<String>,
+    #[serde(default = "default_difficult_cards_limit")]
+    pub limit: i64,
+}
+
+fn default_difficult_cards_limit() -> i64 {
+    crate::db::DIFFICULT_CARDS_LIMIT
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StudyQueueResponse {
     pub new_cards: Vec<Card>,
     pub review_cards: Vec<Card>,
     pub limits: StudyLimits,
+    /// Cards to show answer first, per the study direction.
+    #[serde(default)]
+    pub reversed_card_ids: Vec<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -463,20 +725,49 @@ pub struct StudyLimits {
     pub review_remaining: usize,
 }
 
+/// Cards already studied in the current study day
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StudiedCounts {
+    pub new_cards: i64,
+    pub reviews: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubmitReviewRequest {
     pub card_id: i64,
     pub rating: i32,
-    pub rating_scale: String,
-    pub answer_mode: String,
+    pub rating_scale: RatingScale,
+    pub answer_mode: AnswerMode,
     pub typed_answer: Option<String>,
     pub time_taken_ms: Option<i32>,
+    /// Record the review without changing the card's schedule
+    #[serde(default)]
+    pub cram: bool,
+}
+
+impl SubmitReviewRequest {
+    /// Validate the rating against the declared scale
+    pub fn to_rating(&self) -> crate::error::Result<Rating> {
+        u8::try_from(self.rating)
+            .ok()
+            .and_then(|value| self.rating_scale.rating(value))
+            .ok_or_else(|| {
+                crate::error::ApiError::BadRequest(format!(
+                    "Rating {} is out of range for the {} scale (1-{})",
+                    self.rating,
+                    self.rating_scale.as_str(),
+                    self.rating_scale.max_rating()
+                ))
+            })
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SubmitReviewResponse {
     pub next_state: CardState,
     pub next_due: DateTime<Utc>,
+    /// Show the card again later in the current session.
+    pub requeue: bool,
 }
 
 // Deck types
@@ -485,6 +776,91 @@ pub struct DeckListResponse {
     pub decks: Vec<DeckInfo>,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeckListQuery {
+    /// Answer 304 Not Modified if no card changed after this time
+    pub since: Option<DateTime<Utc>>,
+}
+
+/// Card with the state fields shown when browsing a deck
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeckCard {
+    #[serde(flatten)]
+    pub card: Card,
+    pub status: CardStatus,
+    pub due_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeckCardsQuery {
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "default_deck_cards_limit")]
+    pub limit: i64,
+}
+
+fn default_deck_cards_limit() -> i64 {
+    crate::db::DECK_CARDS_PAGE_SIZE
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeckCardsResponse {
+    pub cards: Vec<DeckCard>,
+    /// More cards follow this page
+    pub has_more: bool,
+}
+
+/// Filters for the review log; every filter is optional
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReviewFilters {
+    pub card_id: Option<i64>,
+    /// Reviews at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Reviews before this time
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub offset: i64,
+    #[serde(default = "default_reviews_limit")]
+    pub limit: i64,
+}
+
+fn default_reviews_limit() -> i64 {
+    crate::db::REVIEWS_PAGE_SIZE
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReviewsResponse {
+    /// Newest first
+    pub reviews: Vec<DbReview>,
+    /// More reviews follow this page
+    pub has_more: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CardsQuery {
+    pub tag: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SlowCardsQuery {
+    #[serde(default = "default_slow_cards_limit")]
+    pub limit: i64,
+}
+
+fn default_slow_cards_limit() -> i64 {
+    crate::db::SLOW_CARDS_LIMIT
+}
+
+/// Card ranked by average response time
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SlowCard {
+    pub card_id: i64,
+    pub deck_path: String,
+    pub question: String,
+    pub average_time_ms: f64,
+    pub review_count: i64,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DeckStatsResponse {
     pub total_cards: usize,
@@ -497,7 +873,74 @@ pub struct DeckStatsResponse {
     pub reviews_today: usize,
 }
 
+/// One deck's stats within a [`StatsOverviewResponse`]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeckStatsEntry {
+    pub path: String,
+    #[serde(flatten)]
+    pub stats: DeckStatsResponse,
+}
+
+/// Stats across all decks, with the per-deck breakdown
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatsOverviewResponse {
+    #[serde(flatten)]
+    pub totals: DeckStatsResponse,
+    pub decks: Vec<DeckStatsEntry>,
+}
+
+/// How much of a device's collection the backend stores
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageStatsResponse {
+    /// Tracked MD files
+    pub file_count: i64,
+    /// Objects stored under the device's prefix
+    pub object_count: usize,
+    pub card_count: i64,
+    pub review_count: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReconcileStorageRequest {
+    /// Delete stored objects no tracked file points at
+    #[serde(default)]
+    pub delete_orphaned_objects: bool,
+}
+
+/// Where a device's tracked files and stored objects disagree
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct StorageDrift {
+    /// Paths of tracked files whose object is missing
+    pub missing_objects: Vec<String>,
+    /// Keys of stored objects no tracked file points at
+    pub orphaned_objects: Vec<String>,
+    /// Orphaned objects deleted during the reconcile
+    pub deleted_objects: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetProgressRequest {
+    /// Must be true; resetting a deck's progress can't be undone
+    #[serde(default)]
+    pub confirm: bool,
+    /// Move the deck's reviews to the archive instead of keeping them in the history
+    #[serde(default)]
+    pub archive_reviews: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResetProgressResponse {
+    pub cards_reset: usize,
+    pub reviews_archived: usize,
+}
+
 // Settings types
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EffectiveSettingsQuery {
+    /// Deck whose overrides are applied; omitted for global values
+    pub deck: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AllSettingsResponse {
     pub global: GlobalSettings,
@@ -510,9 +953,24 @@ pub struct UpdateGlobalSettingsRequest {
     pub rating_scale: Option<String>,
     pub matching_mode: Option<String>,
     pub fuzzy_threshold: Option<f64>,
-    pub new_cards_per_day: Option<i32>,
-    pub reviews_per_day: Option<i32>,
+    pub new_cards_per_day: Option<Limit>,
+    pub reviews_per_day: Option<Limit>,
     pub daily_reset_hour: Option<i32>,
+    pub again_delay_minutes: Option<i32>,
+    pub due_grace_hours: Option<i32>,
+    pub graduation_requirement: Option<i32>,
+    /// Empty to derive decks from directories again
+    pub deck_path_separator: Option<String>,
+    pub suggest_easy_threshold: Option<f64>,
+    pub suggest_hard_threshold: Option<f64>,
+    pub queue_order: Option<QueueOrder>,
+    pub min_ease: Option<f64>,
+    pub max_ease: Option<f64>,
+    pub sync_conflict_policy: Option<ConflictPolicy>,
+    pub new_cards_paused: Option<bool>,
+    pub study_direction: Option<StudyDirection>,
+    pub session_limit: Option<SessionLimit>,
+    pub min_answer_chars: Option<i32>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -521,8 +979,16 @@ pub struct UpdateDeckSettingsRequest {
     pub rating_scale: Option<String>,
     pub matching_mode: Option<String>,
     pub fuzzy_threshold: Option<f64>,
-    pub new_cards_per_day: Option<i32>,
-    pub reviews_per_day: Option<i32>,
+    pub new_cards_per_day: Option<Limit>,
+    pub reviews_per_day: Option<Limit>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub normalization_rules: Vec<NormRule>,
+    #[serde(default)]
+    pub request_retention: Option<f64>,
 }
 
 #[cfg(test)]
@@ -591,6 +1057,7 @@ mod tests {
             lapses: 0,
             reviews_count: 0,
             due_date: None,
+            learning_reps: 0,
         };
 
         let device_id = Uuid::new_v4();
@@ -615,6 +1082,7 @@ mod tests {
             lapses: 1,
             reviews_count: 3,
             due_date: Some(Utc::now()),
+            learning_reps: 1,
         };
 
         let db_state = DbCardState::from_core_state(1, Uuid::new_v4(), &core_state);
@@ -624,6 +1092,8 @@ mod tests {
         assert_eq!(db_state.difficulty, Some(0.5));
         assert_eq!(db_state.lapses, 1);
         assert_eq!(db_state.reviews_count, 3);
+        assert_eq!(db_state.learning_reps, 1);
+        assert_eq!(db_state.to_core_state().learning_reps, 1);
     }
 
     #[test]
@@ -637,6 +1107,7 @@ mod tests {
             lapses: 2,
             reviews_count: 20,
             due_date: None,
+            learning_reps: 0,
         };
 
         let db_state = DbCardState::from_core_state(1, Uuid::new_v4(), &original);
@@ -708,6 +1179,21 @@ mod tests {
             new_cards_per_day: 30,
             reviews_per_day: 150,
             daily_reset_hour: 4,
+            again_delay_minutes: 1,
+            due_grace_hours: 0,
+            graduation_requirement: 1,
+            deck_path_separator: None,
+            suggest_easy_threshold: 1.0,
+            suggest_hard_threshold: 0.6,
+            queue_order: "interleaved".to_string(),
+            min_ease: 1.3,
+            max_ease: 5.0,
+            sync_conflict_policy: "most_progress".to_string(),
+            new_cards_paused: false,
+            study_direction: "reverse".to_string(),
+            session_limit: "cards:10".to_string(),
+            min_answer_chars: 3,
+            utc_offset_minutes: 120,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -718,7 +1204,12 @@ mod tests {
         assert_eq!(api.rating_scale, RatingScale::TwoPoint);
         assert_eq!(api.matching_mode, MatchingMode::Exact);
         assert_eq!(api.fuzzy_threshold, 0.9);
-        assert_eq!(api.new_cards_per_day, 30);
+        assert_eq!(api.new_cards_per_day, Limit::Count(30));
+        assert_eq!(api.queue_order, QueueOrder::Interleaved);
+        assert_eq!(api.sync_conflict_policy, ConflictPolicy::MostProgress);
+        assert_eq!(api.study_direction, StudyDirection::Reverse);
+        assert_eq!(api.session_limit, SessionLimit::Cards(10));
+        assert_eq!(api.min_answer_chars, 3);
     }
 
     #[test]
@@ -747,6 +1238,10 @@ mod tests {
             fuzzy_threshold: None,
             new_cards_per_day: None,
             reviews_per_day: None,
+            color: None,
+            icon: None,
+            normalization_rules: None,
+            request_retention: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -770,6 +1265,10 @@ mod tests {
             fuzzy_threshold: Some(0.95),
             new_cards_per_day: Some(50),
             reviews_per_day: Some(100),
+            color: None,
+            icon: None,
+            normalization_rules: None,
+            request_retention: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
@@ -781,6 +1280,98 @@ mod tests {
         assert_eq!(api.fuzzy_threshold, Some(0.95));
     }
 
+    #[test]
+    fn test_db_deck_settings_to_api_with_appearance() {
+        let settings = DbDeckSettings {
+            id: Uuid::new_v4(),
+            device_id: Uuid::new_v4(),
+            deck_path: "test".to_string(),
+            algorithm: None,
+            rating_scale: None,
+            matching_mode: None,
+            fuzzy_threshold: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            color: Some("#33aa77".to_string()),
+            icon: Some("🦀".to_string()),
+            normalization_rules: None,
+            request_retention: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+
+        let api = settings.to_api_settings();
+        assert_eq!(api.color.as_deref(), Some("#33aa77"));
+        assert_eq!(api.icon.as_deref(), Some("🦀"));
+
+        // Appearance alone overrides nothing
+        let global = DbGlobalSettings::default_for_device(Uuid::new_v4());
+        let merged = EffectiveSettings::merge(&global, Some(&settings.to_api_settings()));
+        let plain = EffectiveSettings::merge(&global, None);
+        assert_eq!(
+            serde_json::to_value(&merged).unwrap(),
+            serde_json::to_value(&plain).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_db_deck_settings_normalization_rules() {
+        let mut settings = DbDeckSettings {
+            id: Uuid::new_v4(),
+            device_id: Uuid::new_v4(),
+            deck_path: "french".to_string(),
+            algorithm: None,
+            rating_scale: None,
+            matching_mode: None,
+            fuzzy_threshold: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            color: None,
+            icon: None,
+            normalization_rules: Some("diacritics,numeric:0.01".to_string()),
+            request_retention: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let rules = vec![NormRule::Diacritics, NormRule::NumericTolerance(0.01)];
+
+        assert_eq!(settings.to_api_settings().normalization_rules, rules);
+        let global = DbGlobalSettings::default_for_device(Uuid::new_v4());
+        let effective = EffectiveSettings::merge(&global, Some(&settings.to_api_settings()));
+        assert_eq!(effective.normalization_rules, rules);
+
+        // An unreadable spec applies no rules
+        settings.normalization_rules = Some("subscripts".to_string());
+        assert!(settings.to_api_settings().normalization_rules.is_empty());
+    }
+
+    #[test]
+    fn test_db_deck_settings_request_retention() {
+        let settings = DbDeckSettings {
+            id: Uuid::new_v4(),
+            device_id: Uuid::new_v4(),
+            deck_path: "french".to_string(),
+            algorithm: None,
+            rating_scale: None,
+            matching_mode: None,
+            fuzzy_threshold: None,
+            new_cards_per_day: None,
+            reviews_per_day: None,
+            color: None,
+            icon: None,
+            normalization_rules: None,
+            request_retention: Some(0.85),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        };
+        let global = DbGlobalSettings::default_for_device(Uuid::new_v4());
+
+        assert_eq!(settings.to_api_settings().request_retention, Some(0.85));
+        let effective = EffectiveSettings::merge(&global, Some(&settings.to_api_settings()));
+        assert_eq!(effective.request_retention, 0.85);
+        assert_eq!(EffectiveSettings::merge(&global, None).request_retention, 0.9);
+    }
+
     // === EffectiveSettings tests ===
 
     #[test]
@@ -790,7 +1381,8 @@ mod tests {
 
         assert_eq!(effective.algorithm, global.algorithm);
         assert_eq!(effective.rating_scale, global.rating_scale);
-        assert_eq!(effective.new_cards_per_day, global.new_cards_per_day);
+        assert_eq!(effective.new_cards_per_day, Limit::Count(20));
+        assert_eq!(effective.rating_buttons, RatingScale::FourPoint.ratings());
     }
 
     #[test]
@@ -806,18 +1398,35 @@ mod tests {
             fuzzy_threshold: Some(0.99),
             new_cards_per_day: Some(5),
             reviews_per_day: None,
+            color: None,
+            icon: None,
+            normalization_rules: None,
+            request_retention: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
-        let effective = EffectiveSettings::merge(&global, Some(&deck));
+        let effective = EffectiveSettings::merge(&global, Some(&deck.to_api_settings()));
 
         assert_eq!(effective.algorithm, "fsrs");
         assert_eq!(effective.rating_scale, "4point"); // From global
         assert_eq!(effective.matching_mode, "exact");
         assert_eq!(effective.fuzzy_threshold, 0.99);
-        assert_eq!(effective.new_cards_per_day, 5);
-        assert_eq!(effective.reviews_per_day, 200); // From global
+        assert_eq!(effective.new_cards_per_day, Limit::Count(5));
+        assert_eq!(effective.reviews_per_day, Limit::Count(200)); // From global
+    }
+
+    #[test]
+    fn test_unlimited_limit_round_trips_through_columns() {
+        let mut global = DbGlobalSettings::default_for_device(Uuid::new_v4());
+        global.new_cards_per_day = limit_to_db(Limit::Unlimited);
+        global.reviews_per_day = limit_to_db(Limit::Count(0));
+
+        let api = global.to_api_settings();
+        assert_eq!(api.new_cards_per_day, Limit::Unlimited);
+        assert_eq!(api.reviews_per_day, Limit::Count(0));
+        let effective = EffectiveSettings::merge(&global, None);
+        assert_eq!(effective.new_cards_per_day, Limit::Unlimited);
     }
 
     #[test]
@@ -835,11 +1444,79 @@ mod tests {
             fuzzy_threshold: None,
             new_cards_per_day: None,
             reviews_per_day: None,
+            color: None,
+            icon: None,
+            normalization_rules: None,
+            request_retention: None,
             created_at: Utc::now(),
             updated_at: Utc::now(),
         };
 
-        let effective = EffectiveSettings::merge(&global, Some(&deck));
+        let effective = EffectiveSettings::merge(&global, Some(&deck.to_api_settings()));
         assert_eq!(effective.daily_reset_hour, 6);
     }
+
+    #[test]
+    fn test_again_delay_defaults_and_converts() {
+        let mut global = DbGlobalSettings::default_for_device(Uuid::new_v4());
+        assert_eq!(global.again_delay_minutes, 1);
+
+        global.again_delay_minutes = 10;
+        assert_eq!(global.to_api_settings().again_delay_minutes, 10);
+        assert_eq!(EffectiveSettings::merge(&global, None).again_delay_minutes, 10);
+    }
+
+    #[test]
+    fn test_due_grace_defaults_and_converts() {
+        let mut global = DbGlobalSettings::default_for_device(Uuid::new_v4());
+        assert_eq!(global.due_grace_hours, 0);
+
+        global.due_grace_hours = 8;
+        assert_eq!(global.to_api_settings().due_grace_hours, 8);
+        assert_eq!(EffectiveSettings::merge(&global, None).due_grace_hours, 8);
+    }
+
+    // === SubmitReviewRequest tests ===
+
+    fn review_request(rating: i32, rating_scale: RatingScale) -> SubmitReviewRequest {
+        SubmitReviewRequest {
+            card_id: 1,
+            rating,
+            rating_scale,
+            answer_mode: AnswerMode::Flip,
+            typed_answer: None,
+            time_taken_ms: None,
+            cram: false,
+        }
+    }
+
+    #[test]
+    fn test_submit_review_rating_in_range() {
+        let request = review_request(3, RatingScale::FourPoint);
+        assert_eq!(request.to_rating().unwrap(), Rating::Good);
+
+        let request = review_request(2, RatingScale::TwoPoint);
+        assert_eq!(request.to_rating().unwrap(), Rating::Good);
+
+        let request = review_request(1, RatingScale::TwoPoint);
+        assert_eq!(request.to_rating().unwrap(), Rating::Again);
+    }
+
+    #[test]
+    fn test_submit_review_rating_out_of_range() {
+        assert!(review_request(3, RatingScale::TwoPoint).to_rating().is_err());
+        assert!(review_request(5, RatingScale::FourPoint).to_rating().is_err());
+        assert!(review_request(0, RatingScale::FourPoint).to_rating().is_err());
+        assert!(review_request(-1, RatingScale::FourPoint).to_rating().is_err());
+    }
+
+    #[test]
+    fn test_submit_review_rejects_unknown_scale() {
+        let json = r#"{"card_id":1,"rating":3,"rating_scale":"five_point","answer_mode":"flip","typed_answer":null,"time_taken_ms":null}"#;
+        assert!(serde_json::from_str::<SubmitReviewRequest>(json).is_err());
+
+        let json = r#"{"card_id":1,"rating":3,"rating_scale":"4point","answer_mode":"typed","typed_answer":null,"time_taken_ms":null}"#;
+        let request: SubmitReviewRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(request.answer_mode, AnswerMode::Typed);
+    }
 }
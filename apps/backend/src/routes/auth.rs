@@ -43,14 +43,14 @@ pub async fn auth_middleware(
         .ok_or_else(|| ApiError::Unauthorized("Invalid Authorization format".to_string()))?
         .to_string();
 
-    // Look up device by token
+    // Look up device by token, served from the auth cache when fresh
     let device = state
-        .db
-        .get_device_by_token(&token)
+        .auth_cache
+        .get_or_fetch(&token, || state.db.get_device_by_token(&token))
         .await?
         .ok_or_else(|| ApiError::Unauthorized("Invalid device token".to_string()))?;
 
-    // Update last_seen
+    // Update last_seen (throttled in the database layer)
     state.db.update_last_seen(device.id).await?;
 
     // Store authenticated device in request extensions
@@ -0,0 +1,85 @@
+//! Card endpoints
+
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use chrono::Utc;
+use flashcard_core::algorithm::fsrs::Fsrs;
+use flashcard_core::types::{CardMeta, CardWithState};
+
+use crate::error::{ApiError, Result};
+use crate::models::*;
+use crate::routes::auth::AuthenticatedDevice;
+use crate::AppState;
+
+/// GET /api/cards?tag=...
+/// Cards with a tag across all decks
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Query(query): Query<CardsQuery>,
+) -> Result<Json<Vec<Card>>> {
+    let Some(tag) = CardMeta::normalize_tag(&query.tag) else {
+        return Ok(Json(Vec::new()));
+    };
+    let cards = state.db.get_cards_by_tag(auth.device_id, &tag).await?;
+    Ok(Json(cards.iter().map(DbCard::to_api_card).collect()))
+}
+
+/// GET /api/cards/{id}
+/// A card with its scheduling state and current retrievability
+pub async fn get(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Path(card_id): Path<i64>,
+) -> Result<Json<CardWithState>> {
+    let card = state
+        .db
+        .get_card(card_id)
+        .await?
+        .filter(|c| c.device_id == auth.device_id)
+        .ok_or_else(|| ApiError::NotFound("Card not found".to_string()))?;
+
+    let card_state = state
+        .db
+        .get_card_state(card_id, auth.device_id)
+        .await?
+        .map(|s| s.to_core_state());
+    let retrievability = card_state
+        .as_ref()
+        .and_then(|s| Fsrs::default().current_retrievability(s, Utc::now()));
+
+    Ok(Json(CardWithState {
+        card: card.to_api_card(),
+        state: card_state,
+        retrievability,
+    }))
+}
+
+/// GET /api/cards/{id}/states
+/// The card's state on each device, with change times, for tracking down
+/// devices that disagree. Without accounts only the requesting device's
+/// own state is visible.
+pub async fn states(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Path(card_id): Path<i64>,
+) -> Result<Json<CardStatesResponse>> {
+    state
+        .db
+        .get_card(card_id)
+        .await?
+        .filter(|c| c.device_id == auth.device_id)
+        .ok_or_else(|| ApiError::NotFound("Card not found".to_string()))?;
+
+    let states = state
+        .db
+        .get_card_state(card_id, auth.device_id)
+        .await?
+        .iter()
+        .map(DbCardState::to_device_state)
+        .collect();
+
+    Ok(Json(CardStatesResponse { card_id, states }))
+}
@@ -1,22 +1,59 @@
 //! Deck endpoints
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Extension, Json,
 };
+use chrono::Utc;
 
-use crate::error::Result;
+use crate::error::{ApiError, Result};
 use crate::models::*;
 use crate::routes::auth::AuthenticatedDevice;
+use crate::services::study::study_day_start;
 use crate::AppState;
 
 /// GET /api/decks
+/// With `since`, answers 304 Not Modified when the counts can't have changed:
+/// no card or card state changed after `since`, and no daily reset passed.
 pub async fn list(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthenticatedDevice>,
-) -> Result<Json<DeckListResponse>> {
-    let decks = state.db.get_all_decks(auth.device_id).await?;
-    Ok(Json(DeckListResponse { decks }))
+    Query(query): Query<DeckListQuery>,
+) -> Result<Response> {
+    let settings = state.db.get_global_settings(auth.device_id).await?;
+    if let Some(since) = query.since {
+        // Due counts also move on at the daily reset
+        let day_start = study_day_start(
+            Utc::now(),
+            settings.daily_reset_hour,
+            settings.utc_offset_minutes,
+        );
+        let last_change = state.db.get_last_card_change(auth.device_id).await?;
+        if since >= day_start && last_change.is_none_or(|changed| changed <= since) {
+            return Ok(StatusCode::NOT_MODIFIED.into_response());
+        }
+    }
+
+    let decks = state
+        .db
+        .get_all_decks(auth.device_id, settings.daily_reset_hour, settings.utc_offset_minutes)
+        .await?;
+    Ok(Json(DeckListResponse { decks }).into_response())
+}
+
+/// GET /api/decks/summaries
+pub async fn summaries(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+) -> Result<Json<DeckSummaries>> {
+    let settings = state.db.get_global_settings(auth.device_id).await?;
+    let summaries = state
+        .db
+        .get_deck_summaries(auth.device_id, settings.daily_reset_hour, settings.utc_offset_minutes)
+        .await?;
+    Ok(Json(summaries))
 }
 
 /// GET /api/decks/:path/stats
@@ -25,6 +62,124 @@ pub async fn stats(
     Extension(auth): Extension<AuthenticatedDevice>,
     Path(deck_path): Path<String>,
 ) -> Result<Json<DeckStatsResponse>> {
-    let stats = state.db.get_deck_stats(auth.device_id, &deck_path).await?;
+    let settings = state.db.get_global_settings(auth.device_id).await?;
+    let stats = state
+        .db
+        .get_deck_stats(
+            auth.device_id,
+            &deck_path,
+            settings.daily_reset_hour,
+            settings.utc_offset_minutes,
+        )
+        .await?;
     Ok(Json(stats))
 }
+
+/// GET /api/stats/overview
+/// Stats for every deck plus totals, in one response
+pub async fn stats_overview(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+) -> Result<Json<StatsOverviewResponse>> {
+    let settings = state.db.get_global_settings(auth.device_id).await?;
+    let overview = state
+        .db
+        .get_stats_overview(auth.device_id, settings.daily_reset_hour, settings.utc_offset_minutes)
+        .await?;
+    Ok(Json(overview))
+}
+
+/// GET /api/stats/storage
+/// Tracked files, stored objects, cards and reviews for the device
+pub async fn storage_stats(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+) -> Result<Json<StorageStatsResponse>> {
+    let (file_count, card_count, review_count) =
+        state.db.get_collection_counts(auth.device_id).await?;
+    let object_count = state
+        .storage
+        .list_files(&format!("{}/", auth.device_id))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))?
+        .len();
+
+    Ok(Json(StorageStatsResponse {
+        file_count,
+        object_count,
+        card_count,
+        review_count,
+    }))
+}
+
+/// GET /api/decks/:path/slowest-cards
+/// Cards that take longest to answer on average
+pub async fn slowest_cards(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Path(deck_path): Path<String>,
+    Query(query): Query<SlowCardsQuery>,
+) -> Result<Json<Vec<SlowCard>>> {
+    let cards = state
+        .db
+        .get_slowest_cards(auth.device_id, &deck_path, query.limit)
+        .await?;
+    Ok(Json(cards))
+}
+
+/// GET /api/decks/:path/rating-distribution
+/// Review counts per rating as `[again, hard, good, easy]`
+pub async fn rating_distribution(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Path(deck_path): Path<String>,
+) -> Result<Json<[usize; 4]>> {
+    let distribution = state
+        .db
+        .get_rating_distribution(auth.device_id, &deck_path)
+        .await?;
+    Ok(Json(distribution))
+}
+
+/// GET /api/decks/:path/cards
+/// Page through a deck's cards with their state summary
+pub async fn cards(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Path(deck_path): Path<String>,
+    Query(query): Query<DeckCardsQuery>,
+) -> Result<Json<DeckCardsResponse>> {
+    let (cards, has_more) = state
+        .db
+        .get_cards_by_deck_paginated(auth.device_id, &deck_path, query.limit, query.offset)
+        .await?;
+
+    Ok(Json(DeckCardsResponse {
+        cards: cards.iter().map(DbDeckCard::to_api_card).collect(),
+        has_more,
+    }))
+}
+
+/// POST /api/decks/:path/reset-progress
+/// Set every card in the deck back to New; requires `confirm: true`
+pub async fn reset_progress(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Path(deck_path): Path<String>,
+    Json(payload): Json<ResetProgressRequest>,
+) -> Result<Json<ResetProgressResponse>> {
+    if !payload.confirm {
+        return Err(ApiError::BadRequest(
+            "Resetting deck progress requires confirm: true".to_string(),
+        ));
+    }
+
+    let (cards_reset, reviews_archived) = state
+        .db
+        .reset_deck_progress(auth.device_id, &deck_path, payload.archive_reviews)
+        .await?;
+    Ok(Json(ResetProgressResponse {
+        cards_reset,
+        reviews_archived,
+    }))
+}
@@ -0,0 +1,32 @@
+//! Maintenance endpoints
+
+use axum::{extract::State, Extension, Json};
+use chrono::Utc;
+
+use crate::error::{ApiError, Result};
+use crate::models::*;
+use crate::routes::auth::AuthenticatedDevice;
+use crate::services::storage::{reconcile_storage, ORPHAN_GRACE};
+use crate::AppState;
+
+/// POST /api/maintenance/reconcile
+/// Compare the device's tracked files with its stored objects, optionally
+/// deleting objects no file points at that are older than [`ORPHAN_GRACE`]
+pub async fn reconcile(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Json(request): Json<ReconcileStorageRequest>,
+) -> Result<Json<StorageDrift>> {
+    let files = state.db.get_md_files(auth.device_id).await?;
+    let drift = reconcile_storage(
+        state.storage.as_ref(),
+        &auth.device_id.to_string(),
+        &files,
+        request.delete_orphaned_objects,
+        Utc::now() - ORPHAN_GRACE,
+    )
+    .await
+    .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+    Ok(Json(drift))
+}
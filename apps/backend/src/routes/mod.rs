@@ -1,8 +1,11 @@
 //! API route handlers
 
 pub mod auth;
+pub mod cards;
 pub mod decks;
 pub mod device;
+pub mod maintenance;
+pub mod reviews;
 pub mod settings;
 pub mod study;
 pub mod sync;
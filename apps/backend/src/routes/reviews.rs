@@ -0,0 +1,30 @@
+//! Review log endpoints
+
+use axum::{
+    extract::{Query, State},
+    Extension, Json,
+};
+
+use crate::error::{ApiError, Result};
+use crate::models::*;
+use crate::routes::auth::AuthenticatedDevice;
+use crate::AppState;
+
+/// GET /api/reviews
+/// Reviews filtered by card and time range, newest first
+pub async fn list(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Query(filters): Query<ReviewFilters>,
+) -> Result<Json<ReviewsResponse>> {
+    if let (Some(from), Some(to)) = (filters.from, filters.to) {
+        if from > to {
+            return Err(ApiError::BadRequest(
+                "`from` must not be after `to`".to_string(),
+            ));
+        }
+    }
+
+    let (reviews, has_more) = state.db.query_reviews(auth.device_id, &filters).await?;
+    Ok(Json(ReviewsResponse { reviews, has_more }))
+}
@@ -1,12 +1,12 @@
 //! Settings endpoints
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Extension, Json,
 };
 use uuid::Uuid;
 
-use crate::error::Result;
+use crate::error::{ApiError, Result};
 use crate::models::*;
 use crate::routes::auth::AuthenticatedDevice;
 use crate::AppState;
@@ -30,6 +30,20 @@ pub async fn get_all(
     }))
 }
 
+/// GET /api/settings/effective
+/// Global settings with the deck's overrides applied
+pub async fn get_effective(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Query(query): Query<EffectiveSettingsQuery>,
+) -> Result<Json<EffectiveSettings>> {
+    let settings = state
+        .db
+        .get_effective_settings(auth.device_id, query.deck.as_deref())
+        .await?;
+    Ok(Json(settings))
+}
+
 /// PUT /api/settings/global
 pub async fn update_global(
     State(state): State<AppState>,
@@ -53,14 +67,59 @@ pub async fn update_global(
         current.fuzzy_threshold = fuzzy_threshold;
     }
     if let Some(new_cards_per_day) = request.new_cards_per_day {
-        current.new_cards_per_day = new_cards_per_day;
+        current.new_cards_per_day = limit_to_db(new_cards_per_day);
     }
     if let Some(reviews_per_day) = request.reviews_per_day {
-        current.reviews_per_day = reviews_per_day;
+        current.reviews_per_day = limit_to_db(reviews_per_day);
     }
     if let Some(daily_reset_hour) = request.daily_reset_hour {
         current.daily_reset_hour = daily_reset_hour;
     }
+    if let Some(again_delay_minutes) = request.again_delay_minutes {
+        current.again_delay_minutes = again_delay_minutes.max(0);
+    }
+    if let Some(due_grace_hours) = request.due_grace_hours {
+        current.due_grace_hours = due_grace_hours.max(0);
+    }
+    if let Some(graduation_requirement) = request.graduation_requirement {
+        current.graduation_requirement = graduation_requirement.max(1);
+    }
+    if let Some(threshold) = request.suggest_easy_threshold {
+        current.suggest_easy_threshold = threshold.clamp(0.0, 1.0);
+    }
+    if let Some(threshold) = request.suggest_hard_threshold {
+        current.suggest_hard_threshold = threshold.clamp(0.0, 1.0);
+    }
+    if let Some(queue_order) = request.queue_order {
+        current.queue_order = queue_order.as_str().to_string();
+    }
+    if let Some(policy) = request.sync_conflict_policy {
+        current.sync_conflict_policy = policy.as_str().to_string();
+    }
+    if let Some(paused) = request.new_cards_paused {
+        current.new_cards_paused = paused;
+    }
+    if let Some(direction) = request.study_direction {
+        current.study_direction = direction.as_str().to_string();
+    }
+    if let Some(limit) = request.session_limit {
+        current.session_limit = limit.to_string();
+    }
+    if let Some(min_answer_chars) = request.min_answer_chars {
+        current.min_answer_chars = min_answer_chars.max(0);
+    }
+    if request.min_ease.is_some() || request.max_ease.is_some() {
+        let min_ease = request.min_ease.unwrap_or(current.min_ease);
+        let max_ease = request.max_ease.unwrap_or(current.max_ease);
+        GlobalSettings::validate_ease_bounds(min_ease, max_ease).map_err(ApiError::BadRequest)?;
+        current.min_ease = min_ease;
+        current.max_ease = max_ease;
+    }
+    // An empty separator switches back to directory-based decks
+    if let Some(deck_path_separator) = request.deck_path_separator {
+        current.deck_path_separator =
+            Some(deck_path_separator).filter(|separator| !separator.trim().is_empty());
+    }
 
     // Save
     state
@@ -78,6 +137,12 @@ pub async fn update_deck(
     Path(deck_path): Path<String>,
     Json(request): Json<UpdateDeckSettingsRequest>,
 ) -> Result<Json<DeckSettings>> {
+    DeckSettings::validate_appearance(request.color.as_deref(), request.icon.as_deref())
+        .map_err(ApiError::BadRequest)?;
+    if let Some(retention) = request.request_retention {
+        DeckSettings::validate_request_retention(retention).map_err(ApiError::BadRequest)?;
+    }
+
     // Get current settings or create new
     let mut current = state
         .db
@@ -93,6 +158,10 @@ pub async fn update_deck(
             fuzzy_threshold: None,
             new_cards_per_day: None,
             reviews_per_day: None,
+            color: None,
+            icon: None,
+            normalization_rules: None,
+            request_retention: None,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
         });
@@ -102,8 +171,13 @@ pub async fn update_deck(
     current.rating_scale = request.rating_scale;
     current.matching_mode = request.matching_mode;
     current.fuzzy_threshold = request.fuzzy_threshold;
-    current.new_cards_per_day = request.new_cards_per_day;
-    current.reviews_per_day = request.reviews_per_day;
+    current.new_cards_per_day = request.new_cards_per_day.map(limit_to_db);
+    current.reviews_per_day = request.reviews_per_day.map(limit_to_db);
+    current.color = request.color;
+    current.icon = request.icon;
+    current.normalization_rules = (!request.normalization_rules.is_empty())
+        .then(|| NormRule::format_spec(&request.normalization_rules));
+    current.request_retention = request.request_retention;
 
     // Save
     state
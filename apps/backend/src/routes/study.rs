@@ -10,66 +10,148 @@ use uuid::Uuid;
 use crate::error::{ApiError, Result};
 use crate::models::*;
 use crate::routes::auth::AuthenticatedDevice;
+use crate::services::study::{
+    due_calculator, remaining_allowance, reversed_card_ids, study_day, study_day_start,
+};
 use crate::AppState;
-use flashcard_core::algorithm::{get_algorithm, SchedulingResult};
+use flashcard_core::algorithm::{AlgorithmKind, SchedulingResult, SpacedRepetitionAlgorithm};
 
 /// GET /api/study/queue
+///
+/// Applies each deck's effective daily limits, minus what was already studied
+/// since the device's daily reset hour. No new cards are offered while they
+/// are paused in the global settings. Cards to show answer first under the
+/// study direction are listed in `reversed_card_ids`.
 pub async fn queue(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthenticatedDevice>,
     Query(query): Query<StudyQueueQuery>,
 ) -> Result<Json<StudyQueueResponse>> {
-    let settings = state
-        .db
-        .get_effective_settings(auth.device_id, query.deck_path.as_deref())
-        .await?;
+    let global = state.db.get_global_settings(auth.device_id).await?;
+    let deck_paths = match query.deck_path {
+        Some(path) => vec![path],
+        None => state
+            .db
+            .get_all_decks(auth.device_id, global.daily_reset_hour, global.utc_offset_minutes)
+            .await?
+            .into_iter()
+            .map(|d| d.path)
+            .collect(),
+    };
 
-    let new_limit = settings.new_cards_per_day;
-    let review_limit = settings.reviews_per_day;
+    let now = Utc::now();
+    let today = study_day(now, global.daily_reset_hour, global.utc_offset_minutes);
+    let mut new_cards = Vec::new();
+    let mut review_cards = Vec::new();
+    let mut new_remaining = 0;
+    let mut review_remaining = 0;
+
+    for deck_path in &deck_paths {
+        let settings = state
+            .db
+            .get_effective_settings(auth.device_id, Some(deck_path))
+            .await?;
+
+        let day_start =
+            study_day_start(now, settings.daily_reset_hour, settings.utc_offset_minutes);
+        let studied = state
+            .db
+            .count_studied_since(auth.device_id, deck_path, day_start)
+            .await?;
+
+        // Paused new cards override every deck's limit
+        let new_limit = if global.new_cards_paused {
+            Limit::Count(0)
+        } else {
+            remaining_allowance(settings.new_cards_per_day, studied.new_cards)
+        };
+        let review_limit = remaining_allowance(settings.reviews_per_day, studied.reviews);
+
+        let deck_new = state
+            .db
+            .get_new_cards(auth.device_id, Some(deck_path), new_limit.query_limit())
+            .await?;
+        let deck_due = state
+            .db
+            .get_due_cards(auth.device_id, Some(deck_path), review_limit.query_limit(), today)
+            .await?;
+
+        new_remaining += new_limit.unused(deck_new.len());
+        review_remaining += review_limit.unused(deck_due.len());
+        new_cards.extend(deck_new);
+        review_cards.extend(deck_due);
+    }
+
+    let new_cards: Vec<Card> = new_cards.into_iter().map(|c| c.to_api_card()).collect();
+    let review_cards: Vec<Card> = review_cards.into_iter().map(|c| c.to_api_card()).collect();
+    let direction: StudyDirection = global.study_direction.parse().unwrap_or_default();
+    let reversed_card_ids =
+        reversed_card_ids(new_cards.iter().chain(&review_cards), direction, today);
 
-    let new_cards = state
-        .db
-        .get_new_cards(auth.device_id, query.deck_path.as_deref(), new_limit)
-        .await?;
-    let review_cards = state
+    Ok(Json(StudyQueueResponse {
+        new_cards,
+        review_cards,
+        limits: StudyLimits {
+            new_remaining,
+            review_remaining,
+        },
+        reversed_card_ids,
+    }))
+}
+
+/// GET /api/study/difficult
+///
+/// A queue of only the hardest studied cards, due or not. They don't count
+/// against the daily limits; reviews are scheduled as usual.
+pub async fn difficult(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Query(query): Query<DifficultCardsQuery>,
+) -> Result<Json<StudyQueueResponse>> {
+    let cards = state
         .db
-        .get_due_cards(auth.device_id, query.deck_path.as_deref(), review_limit)
+        .get_difficult_cards(auth.device_id, query.deck_path.as_deref(), query.limit)
         .await?;
+    let global = state.db.get_global_settings(auth.device_id).await?;
 
-    let new_count = new_cards.len();
-    let review_count = review_cards.len();
+    let review_cards: Vec<Card> = cards.into_iter().map(|c| c.to_api_card()).collect();
+    let direction: StudyDirection = global.study_direction.parse().unwrap_or_default();
+    let reversed_card_ids = reversed_card_ids(
+        &review_cards,
+        direction,
+        study_day(Utc::now(), global.daily_reset_hour, global.utc_offset_minutes),
+    );
 
     Ok(Json(StudyQueueResponse {
-        new_cards: new_cards.into_iter().map(|c| c.to_api_card()).collect(),
-        review_cards: review_cards.into_iter().map(|c| c.to_api_card()).collect(),
+        new_cards: Vec::new(),
+        review_cards,
         limits: StudyLimits {
-            new_remaining: (new_limit as usize).saturating_sub(new_count),
-            review_remaining: (review_limit as usize).saturating_sub(review_count),
+            new_remaining: 0,
+            review_remaining: 0,
         },
+        reversed_card_ids,
     }))
 }
 
 /// POST /api/study/review
+///
+/// Schedules the review server-side and returns the persisted state.
 pub async fn review(
     State(state): State<AppState>,
     Extension(auth): Extension<AuthenticatedDevice>,
     Json(payload): Json<SubmitReviewRequest>,
 ) -> Result<Json<SubmitReviewResponse>> {
-    // Get the card
+    // Validate rating against the declared scale
+    let rating = payload.to_rating()?;
+
+    // Get the card (only the owning device may review it)
     let card = state
         .db
         .get_card(payload.card_id)
         .await?
+        .filter(|c| c.device_id == auth.device_id)
         .ok_or_else(|| ApiError::NotFound("Card not found".to_string()))?;
 
-    // Get current card state (or default if none)
-    let current_state = state
-        .db
-        .get_card_state(payload.card_id, auth.device_id)
-        .await?
-        .map(|s| s.to_core_state())
-        .unwrap_or_default();
-
     // Get effective settings for the algorithm
     let settings = state
         .db
@@ -77,40 +159,65 @@ pub async fn review(
         .await?;
 
     // Get the algorithm
-    let algorithm = get_algorithm(&settings.algorithm)
-        .ok_or_else(|| ApiError::BadRequest(format!("Unknown algorithm: {}", settings.algorithm)))?;
+    let algorithm = AlgorithmKind::from_name(&settings.algorithm)
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown algorithm: {}", settings.algorithm)))?
+        .with_graduation_requirement(settings.graduation_requirement.max(1) as u32)
+        .with_ease_bounds(settings.min_ease, settings.max_ease)
+        .with_request_retention(settings.request_retention);
 
-    // Convert rating
-    let rating = Rating::from_value(payload.rating as u8).unwrap_or(Rating::Good);
+    // Get current card state (or the algorithm's initial state if none)
+    let current_state = state
+        .db
+        .get_card_state(payload.card_id, auth.device_id)
+        .await?
+        .map(|s| s.to_core_state())
+        .unwrap_or_else(|| algorithm.initial_state());
 
     // Calculate next state
     let now = Utc::now();
-    let result: SchedulingResult = algorithm.schedule(&current_state, rating, now);
-
-    // Convert to DB state and save
-    let db_state = DbCardState::from_core_state(payload.card_id, auth.device_id, &result.new_state);
-    state
-        .db
-        .upsert_card_state(payload.card_id, auth.device_id, &db_state)
-        .await?;
-
-    // Record the review
+    let result: SchedulingResult = algorithm
+        .schedule(&current_state, rating, now)
+        .with_again_delay(rating, now, settings.again_delay_minutes.max(0) as u32);
+
+    // Convert to DB state and save. A cram review leaves the schedule
+    // alone and only reports the state it would have produced.
+    if !payload.cram {
+        let mut db_state =
+            DbCardState::from_core_state(payload.card_id, auth.device_id, &result.new_state);
+        // Due dates are stored as the study day they fall in; requeued cards
+        // stay in today's queue
+        let due_calc = due_calculator(settings.daily_reset_hour, settings.utc_offset_minutes);
+        db_state.due_date = if result.requeue {
+            Some(due_calc.study_day(now))
+        } else {
+            result.new_state.due_date.map(|due| due_calc.study_day(due))
+        };
+        state
+            .db
+            .upsert_card_state(payload.card_id, auth.device_id, &db_state)
+            .await?;
+    }
+
+    // Record the review. A cram review logs the schedule it left in place
+    let after = if payload.cram { &current_state } else { &result.new_state };
     let review = DbReview {
         id: Uuid::new_v4(),
         card_id: payload.card_id,
         device_id: auth.device_id,
         reviewed_at: now,
         rating: payload.rating,
-        rating_scale: payload.rating_scale,
-        answer_mode: payload.answer_mode,
+        rating_scale: payload.rating_scale.as_str().to_string(),
+        answer_mode: payload.answer_mode.as_str().to_string(),
         typed_answer: payload.typed_answer,
         was_correct: None,
         time_taken_ms: payload.time_taken_ms,
         interval_before: Some(current_state.interval_days),
-        interval_after: Some(result.new_state.interval_days),
+        interval_after: Some(after.interval_days),
         ease_before: Some(current_state.ease_factor),
-        ease_after: Some(result.new_state.ease_factor),
+        ease_after: Some(after.ease_factor),
         algorithm: settings.algorithm,
+        params_hash: Some(algorithm.params_hash()),
+        cram: payload.cram,
         created_at: now,
     };
     state.db.insert_review(&review).await?;
@@ -118,5 +225,6 @@ pub async fn review(
     Ok(Json(SubmitReviewResponse {
         next_state: result.new_state,
         next_due: result.next_due,
+        requeue: result.requeue,
     }))
 }
@@ -1,14 +1,24 @@
 //! Sync endpoints
 
-use axum::{extract::State, Extension, Json};
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
 use chrono::Utc;
 use uuid::Uuid;
 
-use crate::error::Result;
+use crate::db::ORPHAN_PAGE_SIZE;
+use crate::error::{ApiError, Result};
 use crate::models::*;
 use crate::routes::auth::AuthenticatedDevice;
-use crate::services::storage::StorageService;
-use crate::services::sync::{extract_deck_path, hash_content, parse_md_content, regenerate_md_with_ids};
+use crate::services::storage::{make_key, StorageError};
+use crate::services::study::MAX_UTC_OFFSET_MINUTES;
+use crate::services::sync::{
+    check_card_count, check_upload_limits, extract_deck_path, hash_content, pair_claims,
+    parse_md_content, regenerate_md_with_ids, ParsedMdFile,
+};
 use crate::AppState;
 
 /// POST /api/sync/pull
@@ -26,9 +36,18 @@ pub async fn pull(
         .db
         .get_card_states_since(auth.device_id, payload.last_sync_at)
         .await?;
-    let global = state.db.get_global_settings(auth.device_id).await?;
+    let mut global = state.db.get_global_settings(auth.device_id).await?;
     let decks = state.db.get_all_deck_settings(auth.device_id).await?;
 
+    // Keep the device's timezone current for its study days, e.g. across DST
+    if let Some(offset) = payload.utc_offset_minutes {
+        let offset = offset.clamp(-MAX_UTC_OFFSET_MINUTES, MAX_UTC_OFFSET_MINUTES);
+        if offset != global.utc_offset_minutes {
+            global.utc_offset_minutes = offset;
+            state.db.upsert_global_settings(auth.device_id, &global).await?;
+        }
+    }
+
     Ok(Json(SyncPullResponse {
         cards: cards.into_iter().map(|c| c.to_api_card()).collect(),
         card_states: card_states.into_iter().map(|s| s.to_core_state_with_id()).collect(),
@@ -46,8 +65,26 @@ pub async fn push_reviews(
     Extension(auth): Extension<AuthenticatedDevice>,
     Json(payload): Json<PushReviewsRequest>,
 ) -> Result<Json<PushReviewsResponse>> {
-    let db_reviews: Vec<DbReview> = payload
-        .reviews
+    let card_ids: Vec<i64> = payload.reviews.iter().map(|r| r.card_id).collect();
+    let live_ids = state.db.get_live_card_ids(auth.device_id, &card_ids).await?;
+
+    // Reviews for unknown cards would fail on every retry, so they are
+    // reported back instead of failing the whole push
+    let mut rejected = Vec::new();
+    let mut accepted = Vec::with_capacity(payload.reviews.len());
+    for (index, review) in payload.reviews.into_iter().enumerate() {
+        if live_ids.contains(&review.card_id) {
+            accepted.push(review);
+        } else {
+            rejected.push(RejectedReview {
+                index,
+                card_id: review.card_id,
+                reason: ReviewRejectionReason::CardNotFound,
+            });
+        }
+    }
+
+    let db_reviews: Vec<DbReview> = accepted
         .into_iter()
         .map(|r| DbReview {
             id: Uuid::new_v4(),
@@ -65,13 +102,41 @@ pub async fn push_reviews(
             ease_before: Some(r.ease_before),
             ease_after: Some(r.ease_after),
             algorithm: r.algorithm,
+            params_hash: r.params_hash,
+            cram: r.cram,
             created_at: chrono::Utc::now(),
         })
         .collect();
 
     let count = state.db.insert_reviews(&db_reviews).await?;
 
-    Ok(Json(PushReviewsResponse { synced_count: count }))
+    Ok(Json(PushReviewsResponse {
+        synced_count: count,
+        rejected,
+    }))
+}
+
+/// POST /api/sync/orphans
+/// Page through orphaned cards after an upload
+pub async fn orphans(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Json(payload): Json<OrphansRequest>,
+) -> Result<Json<OrphansResponse>> {
+    let (orphaned_cards, has_more) = state
+        .db
+        .get_orphaned_cards(
+            auth.device_id,
+            &payload.current_card_ids,
+            payload.limit,
+            payload.offset,
+        )
+        .await?;
+
+    Ok(Json(OrphansResponse {
+        orphaned_cards,
+        has_more,
+    }))
 }
 
 /// POST /api/sync/confirm-delete
@@ -85,6 +150,37 @@ pub async fn confirm_delete(
     Ok(Json(ConfirmDeleteResponse { deleted_count: count }))
 }
 
+/// GET /api/sync/files/:path
+/// Restore an uploaded MD file, verified against the hash recorded at upload
+pub async fn download(
+    State(state): State<AppState>,
+    Extension(auth): Extension<AuthenticatedDevice>,
+    Path(file_path): Path<String>,
+) -> Result<Json<SyncFile>> {
+    let file = state
+        .db
+        .get_md_file(auth.device_id, &file_path)
+        .await?
+        .ok_or_else(|| ApiError::NotFound(format!("File {} not found", file_path)))?;
+
+    let bytes = state
+        .storage
+        .download_md_file(&file)
+        .await
+        .map_err(|e| match e {
+            StorageError::NotFound(key) => ApiError::NotFound(format!("Object {} not found", key)),
+            e => ApiError::Internal(e.to_string()),
+        })?;
+    let content = String::from_utf8(bytes)
+        .map_err(|_| ApiError::Internal(format!("File {} is not valid UTF-8", file_path)))?;
+
+    Ok(Json(SyncFile {
+        path: file.file_path,
+        content,
+        hash: file.content_hash,
+    }))
+}
+
 /// POST /api/sync/upload
 /// Upload MD files, parse cards, generate IDs, and store in S3
 pub async fn upload(
@@ -96,13 +192,38 @@ pub async fn upload(
     let mut all_new_ids = Vec::new();
     let mut all_card_ids = Vec::new();
 
-    for file in &payload.files {
-        // 1. Parse MD content to extract cards
-        let parsed = parse_md_content(&file.content)?;
+    // 1. Parse every file up front so conflicts are caught before any write
+    check_upload_limits(&payload.files)?;
+    let parsed_files = payload
+        .files
+        .iter()
+        .map(|file| parse_md_content(&file.content).map(|parsed| (file, parsed)))
+        .collect::<Result<Vec<_>>>()?;
+    let card_count = parsed_files
+        .iter()
+        .map(|(_, parsed)| parsed.cards.len())
+        .sum();
+    check_card_count(card_count)?;
 
+    let conflicts = find_duplicate_ids(&parsed_files);
+    if !conflicts.is_empty() {
+        return Err(ApiError::DuplicateIds(conflicts));
+    }
+
+    // Only the device's first upload reports assigned IDs for claimed local
+    // ones, so the client can re-key its existing states and reviews
+    let claim = payload.claim_existing_ids && !state.db.device_has_cards(auth.device_id).await?;
+
+    let separator = state
+        .db
+        .get_global_settings(auth.device_id)
+        .await?
+        .deck_path_separator;
+
+    for (file, parsed) in &parsed_files {
         // 2. For each card, generate ID if needed and upsert to database
         let mut file_new_ids = Vec::new();
-        let deck_path = extract_deck_path(&file.path);
+        let deck_path = extract_deck_path(&file.path, separator.as_deref());
         let deck_path = if deck_path.is_empty() {
             file.path.trim_end_matches(".md").to_string()
         } else {
@@ -141,6 +262,7 @@ pub async fn upload(
                 deleted_at: None,
             };
             state.db.upsert_card(&db_card).await?;
+            state.db.set_card_tags(card_id, &card.tags).await?;
         }
 
         // 3. If new IDs were assigned, regenerate content
@@ -155,33 +277,71 @@ pub async fn upload(
             file.content.clone()
         };
 
-        // 4. Upload to S3
-        let s3_key = StorageService::make_key(&auth.device_id.to_string(), &file.path);
-        state
-            .storage
-            .upload_file(&s3_key, updated_content.as_bytes(), Some("text/markdown"))
-            .await
-            .map_err(|e| crate::error::ApiError::Internal(e.to_string()))?;
-
-        // 5. Update md_files tracking table
+        // 4. Skip storage writes when the stored copy is already identical
         let content_hash = hash_content(&updated_content);
-        state
+        let unchanged = state
             .db
-            .upsert_md_file(auth.device_id, &file.path, &s3_key, &content_hash)
-            .await?;
+            .get_md_file(auth.device_id, &file.path)
+            .await?
+            .is_some_and(|existing| existing.content_hash == content_hash);
+
+        if !unchanged {
+            // 5. Upload to S3
+            let s3_key = make_key(&auth.device_id.to_string(), &file.path);
+            state
+                .storage
+                .upload_file(&s3_key, updated_content.as_bytes(), Some("text/markdown"))
+                .await
+                .map_err(|e| ApiError::Internal(e.to_string()))?;
+
+            // 6. Update md_files tracking table
+            state
+                .db
+                .upsert_md_file(auth.device_id, &file.path, &s3_key, &content_hash)
+                .await?;
+        }
 
         all_new_ids.extend(file_new_ids);
     }
 
-    // 6. Detect orphaned cards (cards in DB but not in any uploaded file)
-    let orphaned_cards = state
+    // 7. Detect orphaned cards (cards in DB but not in any uploaded file)
+    let (orphaned_cards, has_more_orphans) = state
         .db
-        .get_orphaned_cards(auth.device_id, &all_card_ids)
+        .get_orphaned_cards(auth.device_id, &all_card_ids, ORPHAN_PAGE_SIZE, 0)
         .await?;
 
+    let claimed_ids = if claim {
+        pair_claims(&payload.claimed_ids, &all_new_ids)
+    } else {
+        Vec::new()
+    };
+
     Ok(Json(SyncUploadResponse {
         updated_files,
         new_ids: all_new_ids,
         orphaned_cards,
+        has_more_orphans,
+        claimed_ids,
     }))
 }
+
+/// Collect card IDs declared more than once across the uploaded files.
+fn find_duplicate_ids(files: &[(&SyncFile, ParsedMdFile)]) -> Vec<DuplicateIdConflict> {
+    let mut locations: BTreeMap<i64, Vec<CardLocation>> = BTreeMap::new();
+    for (file, parsed) in files {
+        for card in &parsed.cards {
+            if let Some(id) = card.id {
+                locations.entry(id).or_default().push(CardLocation {
+                    path: file.path.clone(),
+                    line: card.line,
+                });
+            }
+        }
+    }
+
+    locations
+        .into_iter()
+        .filter(|(_, locations)| locations.len() > 1)
+        .map(|(id, locations)| DuplicateIdConflict { id, locations })
+        .collect()
+}
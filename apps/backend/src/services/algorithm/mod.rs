@@ -3,6 +3,6 @@
 //! Re-exports from flashcard-core for backward compatibility.
 
 pub use flashcard_core::algorithm::{
-    fsrs, get_algorithm, sm2, SchedulingResult, SpacedRepetitionAlgorithm,
+    fsrs, get_algorithm, sm2, AlgorithmKind, SchedulingResult, SpacedRepetitionAlgorithm,
 };
 pub use flashcard_core::types::{CardState, CardStatus, Rating};
@@ -0,0 +1,143 @@
+//! Short-lived cache of authenticated devices keyed by token.
+//!
+//! Avoids a `devices` lookup on every authenticated request during busy
+//! syncs. Entries expire after a TTL. No route changes or removes a token,
+//! so there is nothing to invalidate: a device revoked in the database keeps
+//! authenticating until its entry expires, at most one TTL later.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::models::Device;
+
+/// Default time a cached token stays valid before re-querying the database.
+pub const DEFAULT_AUTH_CACHE_TTL: Duration = Duration::from_secs(5);
+
+/// TTL cache mapping device tokens to their device record.
+pub struct AuthCache {
+    ttl: Duration,
+    entries: Mutex<HashMap<String, (Device, Instant)>>,
+}
+
+impl Default for AuthCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_AUTH_CACHE_TTL)
+    }
+}
+
+impl AuthCache {
+    /// Create a cache whose entries expire after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Get a cached device by token if the entry has not expired.
+    pub fn get(&self, token: &str) -> Option<Device> {
+        let mut entries = self.entries.lock().expect("auth cache lock");
+        match entries.get(token) {
+            Some((device, cached_at)) if cached_at.elapsed() < self.ttl => Some(device.clone()),
+            Some(_) => {
+                entries.remove(token);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache a device under its current token.
+    pub fn insert(&self, device: Device) {
+        let mut entries = self.entries.lock().expect("auth cache lock");
+        entries.insert(device.token.clone(), (device, Instant::now()));
+    }
+
+    /// Return the cached device for `token`, or run `fetch` and cache a hit.
+    ///
+    /// Misses (`Ok(None)`) are not cached so a newly registered token works
+    /// immediately.
+    pub async fn get_or_fetch<F, Fut, E>(&self, token: &str, fetch: F) -> Result<Option<Device>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Option<Device>, E>>,
+    {
+        if let Some(device) = self.get(token) {
+            return Ok(Some(device));
+        }
+
+        let device = fetch().await?;
+        if let Some(device) = &device {
+            self.insert(device.clone());
+        }
+        Ok(device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    fn device(token: &str) -> Device {
+        Device {
+            id: Uuid::new_v4(),
+            token: token.to_string(),
+            name: None,
+            created_at: Utc::now(),
+            last_seen_at: Utc::now(),
+        }
+    }
+
+    async fn lookup(
+        cache: &AuthCache,
+        token: &str,
+        stored: &Device,
+        calls: &AtomicUsize,
+    ) -> Option<Device> {
+        cache
+            .get_or_fetch::<_, _, ()>(token, || async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok((stored.token == token).then(|| stored.clone()))
+            })
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cached_token_skips_fetch_within_ttl() {
+        let cache = AuthCache::new(Duration::from_secs(60));
+        let stored = device("token-a");
+        let calls = AtomicUsize::new(0);
+
+        assert!(lookup(&cache, "token-a", &stored, &calls).await.is_some());
+        assert!(lookup(&cache, "token-a", &stored, &calls).await.is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entry_refetches() {
+        let cache = AuthCache::new(Duration::ZERO);
+        let stored = device("token-a");
+        let calls = AtomicUsize::new(0);
+
+        lookup(&cache, "token-a", &stored, &calls).await;
+        lookup(&cache, "token-a", &stored, &calls).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_token_not_cached() {
+        let cache = AuthCache::new(Duration::from_secs(60));
+        let stored = device("token-a");
+        let calls = AtomicUsize::new(0);
+
+        assert!(lookup(&cache, "other", &stored, &calls).await.is_none());
+        assert!(lookup(&cache, "other", &stored, &calls).await.is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
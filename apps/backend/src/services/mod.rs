@@ -1,5 +1,7 @@
 //! Business logic services
 
 pub mod algorithm;
+pub mod auth_cache;
 pub mod storage;
+pub mod study;
 pub mod sync;
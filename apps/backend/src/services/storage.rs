@@ -1,12 +1,21 @@
-//! S3/R2 storage service for MD file backups.
+//! Object storage for MD file backups: S3/R2 in production, memory in tests.
+
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Mutex, MutexGuard};
 
 use aws_sdk_s3::{
     config::{Credentials, Region},
     primitives::ByteStream,
     Client, Config,
 };
+use chrono::{DateTime, Duration, Utc};
 use thiserror::Error;
 
+use crate::models::{MdFile, StorageDrift};
+use crate::services::sync::hash_bytes;
+
 #[derive(Debug, Error)]
 pub enum StorageError {
     #[error("S3 error: {0}")]
@@ -15,16 +24,92 @@ pub enum StorageError {
     Config(String),
     #[error("File not found: {0}")]
     NotFound(String),
+    #[error("Checksum mismatch for {key}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        key: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// How long an object without a tracked file is left alone by
+/// [`reconcile_storage`]. An upload stores its object before writing the
+/// file's tracking row, so a newer object may still be mid-upload.
+pub const ORPHAN_GRACE: Duration = Duration::minutes(15);
+
+/// A stored object and when it was last written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredObject {
+    pub key: String,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Future returned by [`ObjectStore`] methods.
+pub type StoreFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, StorageError>> + Send + 'a>>;
+
+/// Storage for uploaded files, keyed by [`make_key`].
+///
+/// [`S3Store`] backs the server; [`MemoryStore`] keeps objects in memory so
+/// tests run without S3.
+pub trait ObjectStore: Send + Sync {
+    /// Store `content` under `key`, replacing any existing object, and
+    /// return the key.
+    fn upload_file<'a>(
+        &'a self,
+        key: &'a str,
+        content: &'a [u8],
+        content_type: Option<&'a str>,
+    ) -> StoreFuture<'a, String>;
+
+    /// Fetch the object under `key`, or [`StorageError::NotFound`].
+    fn download_file<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Vec<u8>>;
+
+    /// Remove the object under `key`.
+    fn delete_file<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()>;
+
+    /// Objects whose keys start with `prefix` (e.g. "device_id/").
+    fn list_objects<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<StoredObject>>;
+
+    /// Keys of the objects starting with `prefix` (e.g. "device_id/").
+    fn list_files<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<String>> {
+        Box::pin(async move {
+            let objects = self.list_objects(prefix).await?;
+            Ok(objects.into_iter().map(|object| object.key).collect())
+        })
+    }
+
+    /// Whether an object is stored under `key`.
+    fn file_exists<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool>;
+
+    /// Download a tracked MD file and verify it against its recorded hash.
+    ///
+    /// Use this when restoring content: a truncated or corrupted object
+    /// fails with [`StorageError::ChecksumMismatch`] instead of being
+    /// returned as a partial file.
+    fn download_md_file<'a>(&'a self, file: &'a MdFile) -> StoreFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            let bytes = self.download_file(&file.s3_key).await?;
+            verify_checksum(&file.s3_key, &bytes, &file.content_hash)?;
+            Ok(bytes)
+        })
+    }
 }
 
-/// S3/R2 storage service for file uploads and downloads.
-pub struct StorageService {
+/// Generate the storage key for a device's file.
+///
+/// Format: `{device_id}/{file_path}`
+pub fn make_key(device_id: &str, file_path: &str) -> String {
+    format!("{}/{}", device_id, file_path.trim_start_matches('/'))
+}
+
+/// S3/R2 storage for file uploads and downloads.
+pub struct S3Store {
     client: Client,
     bucket: String,
 }
 
-impl StorageService {
-    /// Create a new storage service from environment variables.
+impl S3Store {
+    /// Create a new S3 store from environment variables.
     ///
     /// Required env vars:
     /// - S3_BUCKET: Bucket name
@@ -69,190 +154,444 @@ impl StorageService {
 
         Ok(Self { client, bucket })
     }
+}
 
-    /// Upload a file to S3.
-    ///
-    /// # Arguments
-    /// * `key` - The S3 object key (path within bucket)
-    /// * `content` - File content as bytes
-    /// * `content_type` - MIME type (e.g., "text/markdown")
-    ///
-    /// # Returns
-    /// The full S3 key of the uploaded object
-    pub async fn upload_file(
-        &self,
-        key: &str,
-        content: &[u8],
-        content_type: Option<&str>,
-    ) -> Result<String, StorageError> {
-        let body = ByteStream::from(content.to_vec());
-
-        let mut request = self
-            .client
-            .put_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .body(body);
-
-        if let Some(ct) = content_type {
-            request = request.content_type(ct);
-        }
+impl ObjectStore for S3Store {
+    fn upload_file<'a>(
+        &'a self,
+        key: &'a str,
+        content: &'a [u8],
+        content_type: Option<&'a str>,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            let body = ByteStream::from(content.to_vec());
+
+            let mut request = self
+                .client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .body(body);
+
+            if let Some(ct) = content_type {
+                request = request.content_type(ct);
+            }
 
-        request
-            .send()
-            .await
-            .map_err(|e| StorageError::S3(e.to_string()))?;
+            request
+                .send()
+                .await
+                .map_err(|e| StorageError::S3(e.to_string()))?;
 
-        tracing::info!("Uploaded file to S3: {}", key);
-        Ok(key.to_string())
+            tracing::info!("Uploaded file to S3: {}", key);
+            Ok(key.to_string())
+        })
     }
 
-    /// Download a file from S3.
-    ///
-    /// # Arguments
-    /// * `key` - The S3 object key
-    ///
-    /// # Returns
-    /// File content as bytes
-    pub async fn download_file(&self, key: &str) -> Result<Vec<u8>, StorageError> {
-        let response = self
-            .client
-            .get_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await
-            .map_err(|e| {
-                let err_str = e.to_string();
-                if err_str.contains("NoSuchKey") || err_str.contains("not found") {
-                    StorageError::NotFound(key.to_string())
-                } else {
-                    StorageError::S3(err_str)
+    fn download_file<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            let response = self
+                .client
+                .get_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .send()
+                .await
+                .map_err(|e| {
+                    let err_str = e.to_string();
+                    if err_str.contains("NoSuchKey") || err_str.contains("not found") {
+                        StorageError::NotFound(key.to_string())
+                    } else {
+                        StorageError::S3(err_str)
+                    }
+                })?;
+
+            let bytes = response
+                .body
+                .collect()
+                .await
+                .map_err(|e| StorageError::S3(e.to_string()))?
+                .into_bytes()
+                .to_vec();
+
+            Ok(bytes)
+        })
+    }
+
+    fn delete_file<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            self.client
+                .delete_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .send()
+                .await
+                .map_err(|e| StorageError::S3(e.to_string()))?;
+
+            tracing::info!("Deleted file from S3: {}", key);
+            Ok(())
+        })
+    }
+
+    fn list_objects<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<StoredObject>> {
+        Box::pin(async move {
+            let mut objects = Vec::new();
+            let mut continuation_token = None;
+
+            // Each response holds one page of at most 1,000 keys
+            loop {
+                let response = self
+                    .client
+                    .list_objects_v2()
+                    .bucket(&self.bucket)
+                    .prefix(prefix)
+                    .set_continuation_token(continuation_token)
+                    .send()
+                    .await
+                    .map_err(|e| StorageError::S3(e.to_string()))?;
+
+                objects.extend(response.contents().iter().filter_map(|obj| {
+                    // Without a timestamp the object counts as just written
+                    let last_modified = obj
+                        .last_modified()
+                        .and_then(|t| DateTime::from_timestamp(t.secs(), t.subsec_nanos()))
+                        .unwrap_or_else(Utc::now);
+                    Some(StoredObject {
+                        key: obj.key()?.to_string(),
+                        last_modified,
+                    })
+                }));
+
+                continuation_token = response.next_continuation_token().map(String::from);
+                if response.is_truncated() != Some(true) || continuation_token.is_none() {
+                    break;
                 }
-            })?;
+            }
 
-        let bytes = response
-            .body
-            .collect()
-            .await
-            .map_err(|e| StorageError::S3(e.to_string()))?
-            .into_bytes()
-            .to_vec();
+            Ok(objects)
+        })
+    }
 
-        Ok(bytes)
+    fn file_exists<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool> {
+        Box::pin(async move {
+            match self
+                .client
+                .head_object()
+                .bucket(&self.bucket)
+                .key(key)
+                .send()
+                .await
+            {
+                Ok(_) => Ok(true),
+                Err(e) => {
+                    let err_str = e.to_string();
+                    if err_str.contains("NotFound") || err_str.contains("not found") {
+                        Ok(false)
+                    } else {
+                        Err(StorageError::S3(err_str))
+                    }
+                }
+            }
+        })
     }
+}
 
-    /// Delete a file from S3.
-    ///
-    /// # Arguments
-    /// * `key` - The S3 object key to delete
-    pub async fn delete_file(&self, key: &str) -> Result<(), StorageError> {
-        self.client
-            .delete_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await
-            .map_err(|e| StorageError::S3(e.to_string()))?;
+/// Stored bytes and upload time, by key.
+type Objects = HashMap<String, (Vec<u8>, DateTime<Utc>)>;
 
-        tracing::info!("Deleted file from S3: {}", key);
-        Ok(())
+/// Objects kept in memory, for tests and running without S3.
+#[derive(Default)]
+pub struct MemoryStore {
+    objects: Mutex<Objects>,
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// List files with a given prefix.
-    ///
-    /// # Arguments
-    /// * `prefix` - The prefix to filter objects by (e.g., "device_id/")
-    ///
-    /// # Returns
-    /// List of object keys matching the prefix
-    pub async fn list_files(&self, prefix: &str) -> Result<Vec<String>, StorageError> {
-        let response = self
-            .client
-            .list_objects_v2()
-            .bucket(&self.bucket)
-            .prefix(prefix)
-            .send()
-            .await
-            .map_err(|e| StorageError::S3(e.to_string()))?;
+    fn objects(&self) -> MutexGuard<'_, Objects> {
+        self.objects.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
 
-        let keys = response
-            .contents()
-            .iter()
-            .filter_map(|obj| obj.key().map(String::from))
-            .collect();
+impl ObjectStore for MemoryStore {
+    fn upload_file<'a>(
+        &'a self,
+        key: &'a str,
+        content: &'a [u8],
+        _content_type: Option<&'a str>,
+    ) -> StoreFuture<'a, String> {
+        Box::pin(async move {
+            self.objects()
+                .insert(key.to_string(), (content.to_vec(), Utc::now()));
+            Ok(key.to_string())
+        })
+    }
 
-        Ok(keys)
+    fn download_file<'a>(&'a self, key: &'a str) -> StoreFuture<'a, Vec<u8>> {
+        Box::pin(async move {
+            self.objects()
+                .get(key)
+                .map(|(content, _)| content.clone())
+                .ok_or_else(|| StorageError::NotFound(key.to_string()))
+        })
     }
 
-    /// Check if a file exists in S3.
-    ///
-    /// # Arguments
-    /// * `key` - The S3 object key
-    ///
-    /// # Returns
-    /// true if the object exists, false otherwise
-    pub async fn file_exists(&self, key: &str) -> Result<bool, StorageError> {
-        match self
-            .client
-            .head_object()
-            .bucket(&self.bucket)
-            .key(key)
-            .send()
-            .await
-        {
-            Ok(_) => Ok(true),
-            Err(e) => {
-                let err_str = e.to_string();
-                if err_str.contains("NotFound") || err_str.contains("not found") {
-                    Ok(false)
-                } else {
-                    Err(StorageError::S3(err_str))
-                }
-            }
+    fn delete_file<'a>(&'a self, key: &'a str) -> StoreFuture<'a, ()> {
+        Box::pin(async move {
+            self.objects().remove(key);
+            Ok(())
+        })
+    }
+
+    fn list_objects<'a>(&'a self, prefix: &'a str) -> StoreFuture<'a, Vec<StoredObject>> {
+        Box::pin(async move {
+            let mut objects: Vec<StoredObject> = self
+                .objects()
+                .iter()
+                .filter(|(key, _)| key.starts_with(prefix))
+                .map(|(key, (_, last_modified))| StoredObject {
+                    key: key.clone(),
+                    last_modified: *last_modified,
+                })
+                .collect();
+            objects.sort_by(|a, b| a.key.cmp(&b.key));
+            Ok(objects)
+        })
+    }
+
+    fn file_exists<'a>(&'a self, key: &'a str) -> StoreFuture<'a, bool> {
+        Box::pin(async move { Ok(self.objects().contains_key(key)) })
+    }
+}
+
+/// Compare a device's tracked `files` with the objects under its prefix.
+///
+/// Tracked files without an object are only reported; restoring them takes
+/// another upload from the device. Objects without a tracked file are
+/// deleted when `delete_orphaned` is set, if last written before
+/// `orphaned_before`; callers pass [`ORPHAN_GRACE`] ago so uploads still
+/// writing their tracking row are kept.
+pub async fn reconcile_storage(
+    store: &dyn ObjectStore,
+    device_id: &str,
+    files: &[MdFile],
+    delete_orphaned: bool,
+    orphaned_before: DateTime<Utc>,
+) -> Result<StorageDrift, StorageError> {
+    let objects = store.list_objects(&make_key(device_id, "")).await?;
+    let stored: HashSet<&str> = objects.iter().map(|o| o.key.as_str()).collect();
+    let tracked: HashSet<&str> = files.iter().map(|f| f.s3_key.as_str()).collect();
+
+    let mut drift = StorageDrift {
+        missing_objects: files
+            .iter()
+            .filter(|f| !stored.contains(f.s3_key.as_str()))
+            .map(|f| f.file_path.clone())
+            .collect(),
+        orphaned_objects: objects
+            .iter()
+            .filter(|o| !tracked.contains(o.key.as_str()))
+            .map(|o| o.key.clone())
+            .collect(),
+        deleted_objects: 0,
+    };
+
+    if delete_orphaned {
+        let expired = objects
+            .iter()
+            .filter(|o| !tracked.contains(o.key.as_str()) && o.last_modified < orphaned_before);
+        for object in expired {
+            store.delete_file(&object.key).await?;
+            drift.deleted_objects += 1;
         }
     }
 
-    /// Generate the S3 key for a device's file.
-    ///
-    /// Format: `{device_id}/{file_path}`
-    pub fn make_key(device_id: &str, file_path: &str) -> String {
-        format!("{}/{}", device_id, file_path.trim_start_matches('/'))
+    Ok(drift)
+}
+
+/// Check downloaded bytes against the hash recorded at upload time.
+pub fn verify_checksum(key: &str, content: &[u8], expected_hash: &str) -> Result<(), StorageError> {
+    let actual = hash_bytes(content);
+    if actual == expected_hash {
+        Ok(())
+    } else {
+        Err(StorageError::ChecksumMismatch {
+            key: key.to_string(),
+            expected: expected_hash.to_string(),
+            actual,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::services::sync::hash_content;
+
+    #[test]
+    fn test_verify_checksum_accepts_intact_content() {
+        let content = "Q: What is Rust?\nA: A language";
+        let hash = hash_content(content);
+        assert!(verify_checksum("dev/deck.md", content.as_bytes(), &hash).is_ok());
+    }
+
+    #[test]
+    fn test_verify_checksum_detects_truncated_content() {
+        let content = "Q: What is Rust?\nA: A language";
+        let hash = hash_content(content);
+        let truncated = &content.as_bytes()[..10];
+
+        match verify_checksum("dev/deck.md", truncated, &hash) {
+            Err(StorageError::ChecksumMismatch {
+                key,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(key, "dev/deck.md");
+                assert_eq!(expected, hash);
+                assert_eq!(actual, hash_bytes(truncated));
+            }
+            other => panic!("expected checksum mismatch, got {:?}", other),
+        }
+    }
+
+    fn md_file(key: &str, content_hash: String) -> MdFile {
+        MdFile {
+            id: uuid::Uuid::new_v4(),
+            device_id: uuid::Uuid::new_v4(),
+            file_path: "deck.md".to_string(),
+            s3_key: key.to_string(),
+            content_hash,
+            uploaded_at: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_round_trips_files() {
+        let store = MemoryStore::new();
+        let key = make_key("dev", "deck/cards.md");
+        let content = "Q: What is Rust?\nA: A language";
+
+        let stored = store
+            .upload_file(&key, content.as_bytes(), Some("text/markdown"))
+            .await
+            .unwrap();
+        assert_eq!(stored, "dev/deck/cards.md");
+        assert!(store.file_exists(&key).await.unwrap());
+        assert_eq!(store.download_file(&key).await.unwrap(), content.as_bytes());
+
+        // Uploading again replaces the object
+        store.upload_file(&key, b"Q: New\nA: Content", None).await.unwrap();
+        assert_eq!(store.download_file(&key).await.unwrap(), b"Q: New\nA: Content");
+
+        // Checked downloads verify the recorded hash
+        let file = md_file(&key, hash_content("Q: New\nA: Content"));
+        assert!(store.download_md_file(&file).await.is_ok());
+        let stale = md_file(&key, hash_content(content));
+        assert!(matches!(
+            store.download_md_file(&stale).await,
+            Err(StorageError::ChecksumMismatch { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_lists_and_deletes_by_key() {
+        let store: Box<dyn ObjectStore> = Box::new(MemoryStore::new());
+        for key in ["dev-2/a.md", "dev/b.md", "dev/a.md"] {
+            store.upload_file(key, b"content", None).await.unwrap();
+        }
+
+        assert_eq!(store.list_files("dev/").await.unwrap(), ["dev/a.md", "dev/b.md"]);
+
+        store.delete_file("dev/a.md").await.unwrap();
+        assert!(!store.file_exists("dev/a.md").await.unwrap());
+        assert!(matches!(
+            store.download_file("dev/a.md").await,
+            Err(StorageError::NotFound(key)) if key == "dev/a.md"
+        ));
+        assert_eq!(store.list_files("dev/").await.unwrap(), ["dev/b.md"]);
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_reports_drift_both_ways() {
+        let store = MemoryStore::new();
+        let tracked = md_file("dev/kept.md", String::new());
+        let lost = MdFile {
+            file_path: "lost.md".to_string(),
+            ..md_file("dev/lost.md", String::new())
+        };
+        store.upload_file("dev/kept.md", b"kept", None).await.unwrap();
+        store.upload_file("dev/stray.md", b"stray", None).await.unwrap();
+        // Another device's objects are out of scope
+        store.upload_file("dev-2/other.md", b"other", None).await.unwrap();
+
+        let files = [tracked, lost];
+        let drift = reconcile_storage(&store, "dev", &files, false, Utc::now()).await.unwrap();
+        assert_eq!(
+            drift,
+            StorageDrift {
+                missing_objects: vec!["lost.md".to_string()],
+                orphaned_objects: vec!["dev/stray.md".to_string()],
+                deleted_objects: 0,
+            }
+        );
+        assert!(store.file_exists("dev/stray.md").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_deletes_orphaned_objects_when_asked() {
+        let store = MemoryStore::new();
+        store.upload_file("dev/kept.md", b"kept", None).await.unwrap();
+        store.upload_file("dev/stray.md", b"stray", None).await.unwrap();
+        let files = [md_file("dev/kept.md", String::new())];
+
+        // A fresh orphan may be an upload still writing its tracking row
+        let drift = reconcile_storage(&store, "dev", &files, true, Utc::now() - ORPHAN_GRACE)
+            .await
+            .unwrap();
+        assert_eq!(drift.orphaned_objects, ["dev/stray.md"]);
+        assert_eq!(drift.deleted_objects, 0);
+
+        let later = Utc::now() + Duration::seconds(1);
+        let drift = reconcile_storage(&store, "dev", &files, true, later).await.unwrap();
+        assert_eq!(drift.orphaned_objects, ["dev/stray.md"]);
+        assert_eq!(drift.deleted_objects, 1);
+        assert_eq!(store.list_files("dev/").await.unwrap(), ["dev/kept.md"]);
+
+        // Nothing left to clean up
+        let drift = reconcile_storage(&store, "dev", &files, true, later).await.unwrap();
+        assert_eq!(drift, StorageDrift::default());
+    }
 
     #[test]
     fn test_make_key_basic() {
-        let key = StorageService::make_key("device-123", "path/to/file.md");
+        let key = make_key("device-123", "path/to/file.md");
         assert_eq!(key, "device-123/path/to/file.md");
     }
 
     #[test]
     fn test_make_key_strips_leading_slash() {
-        let key = StorageService::make_key("device-123", "/path/to/file.md");
+        let key = make_key("device-123", "/path/to/file.md");
         assert_eq!(key, "device-123/path/to/file.md");
     }
 
     #[test]
     fn test_make_key_multiple_leading_slashes() {
-        let key = StorageService::make_key("device-123", "///path/file.md");
+        let key = make_key("device-123", "///path/file.md");
         assert_eq!(key, "device-123/path/file.md");
     }
 
     #[test]
     fn test_make_key_empty_path() {
-        let key = StorageService::make_key("device-123", "");
+        let key = make_key("device-123", "");
         assert_eq!(key, "device-123/");
     }
 
     #[test]
     fn test_make_key_uuid_device_id() {
         let device_id = "550e8400-e29b-41d4-a716-446655440000";
-        let key = StorageService::make_key(device_id, "deck/cards.md");
+        let key = make_key(device_id, "deck/cards.md");
         assert!(key.starts_with(device_id));
         assert!(key.ends_with("deck/cards.md"));
         assert_eq!(key, "550e8400-e29b-41d4-a716-446655440000/deck/cards.md");
@@ -260,7 +599,7 @@ mod tests {
 
     #[test]
     fn test_make_key_preserves_internal_slashes() {
-        let key = StorageService::make_key("dev", "a/b/c/d/e.md");
+        let key = make_key("dev", "a/b/c/d/e.md");
         assert_eq!(key, "dev/a/b/c/d/e.md");
     }
 }
@@ -0,0 +1,149 @@
+//! Study queue helpers shared by the study routes.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use flashcard_core::types::{Card, Limit, StudyDirection};
+use flashcard_core::DueCalculator;
+
+/// Largest UTC offset a device may report, in minutes (UTC+14:00).
+pub const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+/// Study-day calculator for a device's reset hour.
+///
+/// The reset hour is in the device's timezone, as the UTC offset it reported
+/// on its last sync; devices that never reported one use UTC.
+pub fn due_calculator(
+    daily_reset_hour: i32,
+    utc_offset_minutes: i32,
+) -> DueCalculator<FixedOffset> {
+    let offset = utc_offset_minutes.clamp(-MAX_UTC_OFFSET_MINUTES, MAX_UTC_OFFSET_MINUTES);
+    let timezone = FixedOffset::east_opt(offset * 60).expect("offset within a day");
+    DueCalculator::new(daily_reset_hour.clamp(0, 23) as u32, timezone)
+}
+
+/// Start of the current study day.
+///
+/// A study day begins at `daily_reset_hour` in the device's timezone. Before
+/// that hour, the previous calendar day is still active.
+pub fn study_day_start(
+    now: DateTime<Utc>,
+    daily_reset_hour: i32,
+    utc_offset_minutes: i32,
+) -> DateTime<Utc> {
+    let calc = due_calculator(daily_reset_hour, utc_offset_minutes);
+    calc.day_bounds(calc.study_day(now)).0
+}
+
+/// Calendar date of the current study day, used for due-date comparisons.
+///
+/// Due dates are stored per day as the study day they fall in, so a card is
+/// due when its date is on or before this one.
+pub fn study_day(
+    now: DateTime<Utc>,
+    daily_reset_hour: i32,
+    utc_offset_minutes: i32,
+) -> NaiveDate {
+    due_calculator(daily_reset_hour, utc_offset_minutes).study_day(now)
+}
+
+/// Remaining daily allowance after `studied` cards have been used.
+pub fn remaining_allowance(daily_limit: Limit, studied: i64) -> Limit {
+    daily_limit.remaining(usize::try_from(studied).unwrap_or(0))
+}
+
+/// IDs of the cards to show answer first on study day `day`.
+pub fn reversed_card_ids<'a>(
+    cards: impl IntoIterator<Item = &'a Card>,
+    direction: StudyDirection,
+    day: NaiveDate,
+) -> Vec<i64> {
+    cards
+        .into_iter()
+        .map(|card| card.id)
+        .filter(|&id| direction.reverses(id, day))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    #[test]
+    fn test_study_day_start_midnight_reset() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 15, 30, 0).unwrap();
+        let start = study_day_start(now, 0, 0);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 3, 10, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_study_day_start_before_reset_hour() {
+        // 02:00 with a 04:00 reset still belongs to the previous day
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 2, 0, 0).unwrap();
+        let start = study_day_start(now, 4, 0);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 3, 9, 4, 0, 0).unwrap());
+        assert_eq!(
+            study_day(now, 4, 0),
+            NaiveDate::from_ymd_opt(2024, 3, 9).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_study_day_start_after_reset_hour() {
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 4, 0, 0).unwrap();
+        let start = study_day_start(now, 4, 0);
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 3, 10, 4, 0, 0).unwrap());
+        assert_eq!(
+            study_day(now, 4, 0),
+            NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_study_day_follows_device_offset() {
+        // 23:30 UTC is already 04:30 the next day at UTC+5
+        let now = Utc.with_ymd_and_hms(2024, 3, 10, 23, 30, 0).unwrap();
+        assert_eq!(
+            study_day(now, 4, 5 * 60),
+            NaiveDate::from_ymd_opt(2024, 3, 11).unwrap()
+        );
+        assert_eq!(
+            study_day_start(now, 4, 5 * 60),
+            Utc.with_ymd_and_hms(2024, 3, 10, 23, 0, 0).unwrap()
+        );
+        // and still the previous evening at UTC-8
+        assert_eq!(
+            study_day(now, 4, -8 * 60),
+            NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_due_days_agree_with_due_timestamps() {
+        // The desktop compares due timestamps with the study day's end; here
+        // the due time is stored as its study day and compared by date.
+        let reset = Utc.with_ymd_and_hms(2024, 3, 10, 4, 0, 0).unwrap();
+        let calc = due_calculator(4, 0);
+        for now_offset in [-61, -1, 0, 1, 61] {
+            let now = reset + Duration::minutes(now_offset);
+            for due_offset in [-1500, -61, -1, 0, 1, 61, 1439, 1440] {
+                let due = reset + Duration::minutes(due_offset);
+                assert_eq!(
+                    calc.study_day(due) <= study_day(now, 4, 0),
+                    calc.is_due(due, now),
+                    "due {due} at {now}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_remaining_allowance() {
+        let count = Limit::Count;
+        assert_eq!(remaining_allowance(count(20), 0), count(20));
+        assert_eq!(remaining_allowance(count(20), 5), count(15));
+        assert_eq!(remaining_allowance(count(20), 20), count(0));
+        assert_eq!(remaining_allowance(count(20), 35), count(0));
+        assert_eq!(remaining_allowance(count(0), 3), count(0));
+        assert_eq!(remaining_allowance(Limit::Unlimited, 35), Limit::Unlimited);
+    }
+}
@@ -1,9 +1,22 @@
 //! Sync service for MD file processing.
 
+use std::collections::{HashMap, HashSet};
+
+use flashcard_core::types::CardMeta;
+use flashcard_core::unescape_field_prefix;
 use sha2::{Digest, Sha256};
 
 use crate::error::ApiError;
-use crate::models::NewIdAssignment;
+use crate::models::{ClaimedId, NewIdAssignment, SyncFile};
+
+/// Maximum number of files accepted in a single upload.
+pub const MAX_UPLOAD_FILES: usize = 5_000;
+
+/// Maximum size of a single uploaded MD file, in bytes.
+pub const MAX_FILE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Maximum number of cards parsed from a single upload.
+pub const MAX_UPLOAD_CARDS: usize = 100_000;
 
 /// Parsed card from MD content.
 #[derive(Debug, Clone)]
@@ -13,6 +26,8 @@ pub struct ParsedCard {
     pub answer: String,
     /// Line number where this card starts (1-indexed).
     pub line: usize,
+    /// Normalized tags from the card's `META:` line.
+    pub tags: Vec<String>,
 }
 
 /// Result of parsing an MD file.
@@ -21,6 +36,39 @@ pub struct ParsedMdFile {
     pub cards: Vec<ParsedCard>,
 }
 
+/// Reject uploads with too many files or oversized files before parsing.
+pub fn check_upload_limits(files: &[SyncFile]) -> Result<(), ApiError> {
+    if files.len() > MAX_UPLOAD_FILES {
+        return Err(ApiError::BadRequest(format!(
+            "Upload contains {} files (maximum {})",
+            files.len(),
+            MAX_UPLOAD_FILES
+        )));
+    }
+
+    if let Some(file) = files.iter().find(|f| f.content.len() > MAX_FILE_BYTES) {
+        return Err(ApiError::PayloadTooLarge(format!(
+            "File '{}' is {} bytes (maximum {})",
+            file.path,
+            file.content.len(),
+            MAX_FILE_BYTES
+        )));
+    }
+
+    Ok(())
+}
+
+/// Reject uploads whose parsed files contain more cards than allowed.
+pub fn check_card_count(count: usize) -> Result<(), ApiError> {
+    if count > MAX_UPLOAD_CARDS {
+        return Err(ApiError::BadRequest(format!(
+            "Upload contains {} cards (maximum {})",
+            count, MAX_UPLOAD_CARDS
+        )));
+    }
+    Ok(())
+}
+
 /// Parse MD content to extract flashcards.
 ///
 /// Format:
@@ -29,6 +77,9 @@ pub struct ParsedMdFile {
 /// Q: Question text
 /// A: Answer text
 /// ```
+///
+/// A field prefix escaped with a backslash (`\A: note`) is field text, as in
+/// the core parser.
 pub fn parse_md_content(content: &str) -> Result<ParsedMdFile, ApiError> {
     let mut cards = Vec::new();
     let mut current_card: Option<ParsedCardBuilder> = None;
@@ -39,8 +90,14 @@ pub fn parse_md_content(content: &str) -> Result<ParsedMdFile, ApiError> {
         let line_num = line_idx + 1;
         let trimmed = line.trim();
 
+        // Escaped prefix - append the literal text to current field
+        if let Some(text) = unescape_field_prefix(line) {
+            if current_field.is_some() {
+                append_line(&mut field_buffer, &text);
+            }
+        }
         // Check for ID: line (starts a new card)
-        if trimmed.starts_with("ID:") {
+        else if trimmed.starts_with("ID:") {
             // Flush previous card
             if let Some(builder) = current_card.take() {
                 flush_field(&mut current_field, &mut field_buffer, &mut cards, builder)?;
@@ -60,6 +117,7 @@ pub fn parse_md_content(content: &str) -> Result<ParsedMdFile, ApiError> {
                 question: None,
                 answer: None,
                 line: line_num,
+                tags: Vec::new(),
             });
             current_field = None;
             field_buffer.clear();
@@ -73,6 +131,7 @@ pub fn parse_md_content(content: &str) -> Result<ParsedMdFile, ApiError> {
                     question: None,
                     answer: None,
                     line: line_num,
+                    tags: Vec::new(),
                 });
             }
 
@@ -93,12 +152,22 @@ pub fn parse_md_content(content: &str) -> Result<ParsedMdFile, ApiError> {
             current_field = Some(Field::Answer);
             field_buffer = trimmed.strip_prefix("A:").unwrap().trim().to_string();
         }
+        // META: scheduling hints are applied by clients; only tags are kept
+        else if let Some(meta) = trimmed.strip_prefix("META:") {
+            if let Some(builder) = current_card.as_mut() {
+                flush_current_field(&current_field, &field_buffer, builder);
+                for tag in meta_tags(meta) {
+                    if !builder.tags.contains(&tag) {
+                        builder.tags.push(tag);
+                    }
+                }
+            }
+            current_field = None;
+            field_buffer.clear();
+        }
         // Regular line - append to current field
         else if current_field.is_some() {
-            if !field_buffer.is_empty() {
-                field_buffer.push('\n');
-            }
-            field_buffer.push_str(line);
+            append_line(&mut field_buffer, line);
         }
     }
 
@@ -122,6 +191,22 @@ struct ParsedCardBuilder {
     question: Option<String>,
     answer: Option<String>,
     line: usize,
+    tags: Vec<String>,
+}
+
+/// Tags listed in the `key=value` pairs of a `META:` line.
+fn meta_tags(meta: &str) -> Vec<String> {
+    meta.split_whitespace()
+        .filter_map(|pair| pair.strip_prefix("tags="))
+        .flat_map(CardMeta::parse_tags)
+        .collect()
+}
+
+fn append_line(buffer: &mut String, line: &str) {
+    if !buffer.is_empty() {
+        buffer.push('\n');
+    }
+    buffer.push_str(line);
 }
 
 fn flush_current_field(field: &Option<Field>, buffer: &str, builder: &mut ParsedCardBuilder) {
@@ -152,6 +237,7 @@ fn flush_field(
             question,
             answer,
             line: builder.line,
+            tags: builder.tags,
         });
     }
 
@@ -196,10 +282,40 @@ pub fn regenerate_md_with_ids(content: &str, new_ids: &[NewIdAssignment]) -> Str
     result
 }
 
+/// Pair each claimed local ID with the ID `assigned` to the card at its file
+/// path and line.
+///
+/// Assigned IDs always come from the server's sequence, so a client's local
+/// IDs never need to be free on the server. Claims for cards that weren't
+/// assigned an ID, and local IDs claimed more than once, are dropped.
+pub fn pair_claims(claims: &[NewIdAssignment], assigned: &[NewIdAssignment]) -> Vec<ClaimedId> {
+    let assigned: HashMap<(&str, usize), i64> = assigned
+        .iter()
+        .map(|a| ((a.path.as_str(), a.line), a.id))
+        .collect();
+    let mut used = HashSet::new();
+    claims
+        .iter()
+        .filter(|claim| used.insert(claim.id))
+        .filter_map(|claim| {
+            let id = assigned.get(&(claim.path.as_str(), claim.line))?;
+            Some(ClaimedId {
+                local_id: claim.id,
+                id: *id,
+            })
+        })
+        .collect()
+}
+
 /// Calculate SHA256 hash of content.
 pub fn hash_content(content: &str) -> String {
+    hash_bytes(content.as_bytes())
+}
+
+/// Calculate SHA256 hash of raw bytes, matching [`hash_content`] for UTF-8 text.
+pub fn hash_bytes(content: &[u8]) -> String {
     let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
+    hasher.update(content);
     format!("{:x}", hasher.finalize())
 }
 
@@ -207,8 +323,17 @@ pub fn hash_content(content: &str) -> String {
 ///
 /// E.g., "rust/ownership.md" -> "rust"
 /// E.g., "programming/rust/basics.md" -> "programming/rust"
-pub fn extract_deck_path(file_path: &str) -> String {
+///
+/// With a `separator`, a file name containing it names the deck instead:
+/// "Programming::Rust::Basics.md" with "::" -> "Programming/Rust"
+pub fn extract_deck_path(file_path: &str, separator: Option<&str>) -> String {
     let path = std::path::Path::new(file_path);
+    let from_name = separator.zip(path.file_name()).and_then(|(separator, name)| {
+        flashcard_core::deck_path_from_file_name(&name.to_string_lossy(), separator)
+    });
+    if let Some(deck_path) = from_name {
+        return deck_path;
+    }
     path.parent()
         .map(|p| p.to_string_lossy().to_string())
         .unwrap_or_default()
@@ -218,6 +343,43 @@ pub fn extract_deck_path(file_path: &str) -> String {
 mod tests {
     use super::*;
 
+    fn sync_file(path: &str, content: String) -> SyncFile {
+        SyncFile {
+            path: path.to_string(),
+            hash: hash_content(&content),
+            content,
+        }
+    }
+
+    #[test]
+    fn test_upload_limits_accept_normal_upload() {
+        let files = vec![sync_file("a.md", "Q: q\nA: a\n".to_string())];
+        assert!(check_upload_limits(&files).is_ok());
+        assert!(check_card_count(MAX_UPLOAD_CARDS).is_ok());
+    }
+
+    #[test]
+    fn test_upload_limits_reject_oversized_file() {
+        let files = vec![sync_file("big.md", "x".repeat(MAX_FILE_BYTES + 1))];
+        let err = check_upload_limits(&files).unwrap_err();
+        assert!(matches!(err, ApiError::PayloadTooLarge(_)));
+    }
+
+    #[test]
+    fn test_upload_limits_reject_too_many_files() {
+        let files: Vec<SyncFile> = (0..=MAX_UPLOAD_FILES)
+            .map(|i| sync_file(&format!("{i}.md"), String::new()))
+            .collect();
+        let err = check_upload_limits(&files).unwrap_err();
+        assert!(matches!(err, ApiError::BadRequest(_)));
+    }
+
+    #[test]
+    fn test_card_count_limit() {
+        let err = check_card_count(MAX_UPLOAD_CARDS + 1).unwrap_err();
+        assert!(matches!(err, ApiError::BadRequest(_)));
+    }
+
     #[test]
     fn test_parse_simple_card() {
         let content = r#"ID: 1
@@ -231,6 +393,16 @@ A: A systems programming language."#;
         assert_eq!(result.cards[0].answer, "A systems programming language.");
     }
 
+    #[test]
+    fn test_parse_meta_tags() {
+        let content = "ID: 1\nQ: Q1\nA: A1\nMETA: suspended=true tags=exam,#Oral\n\nID: 2\nQ: Q2\nA: A2";
+
+        let result = parse_md_content(content).unwrap();
+        assert_eq!(result.cards[0].tags, vec!["exam", "oral"]);
+        assert_eq!(result.cards[0].answer, "A1");
+        assert!(result.cards[1].tags.is_empty());
+    }
+
     #[test]
     fn test_parse_card_without_id() {
         let content = r#"Q: What is a closure?
@@ -256,6 +428,18 @@ A: 1. Each value has one owner
         assert!(result.cards[0].answer.contains("3. When owner"));
     }
 
+    #[test]
+    fn test_parse_escaped_prefixes() {
+        let content = "ID: 3\nQ: Which prefix?\nA: The answer\n\\A: note\n  \\ID: 5\n\\META: tags=x\n\nID: 4\nQ: Next\nA: Two";
+
+        let result = parse_md_content(content).unwrap();
+        assert_eq!(result.cards.len(), 2);
+        assert_eq!(result.cards[0].id, Some(3));
+        assert_eq!(result.cards[0].answer, "The answer\nA: note\n  ID: 5\nMETA: tags=x");
+        assert!(result.cards[0].tags.is_empty());
+        assert_eq!(result.cards[1].line, 8);
+    }
+
     #[test]
     fn test_regenerate_with_ids() {
         let content = "Q: What is Rust?\nA: A language.\n";
@@ -269,6 +453,27 @@ A: 1. Each value has one owner
         assert!(result.starts_with("ID: 42\n"));
     }
 
+    #[test]
+    fn test_pair_claims_with_assigned_ids() {
+        let at = |line, id| NewIdAssignment {
+            path: "test.md".to_string(),
+            line,
+            id,
+        };
+        // Line 7 already had an ID line; local ID 10 is claimed twice
+        let claims = vec![at(1, 10), at(4, 11), at(7, 12), at(10, 10)];
+        let assigned = vec![at(1, 500), at(4, 501), at(10, 502)];
+
+        let paired = pair_claims(&claims, &assigned);
+        assert_eq!(
+            paired,
+            vec![
+                ClaimedId { local_id: 10, id: 500 },
+                ClaimedId { local_id: 11, id: 501 },
+            ]
+        );
+    }
+
     #[test]
     fn test_hash_content() {
         let hash = hash_content("test content");
@@ -278,13 +483,22 @@ A: 1. Each value has one owner
 
     #[test]
     fn test_extract_deck_path() {
-        assert_eq!(extract_deck_path("rust/ownership.md"), "rust");
-        assert_eq!(extract_deck_path("prog/rust/basics.md"), "prog/rust");
-        assert_eq!(extract_deck_path("single.md"), "");
+        assert_eq!(extract_deck_path("rust/ownership.md", None), "rust");
+        assert_eq!(extract_deck_path("prog/rust/basics.md", None), "prog/rust");
+        assert_eq!(extract_deck_path("single.md", None), "");
     }
 
     // === Additional parse tests ===
 
+    #[test]
+    fn test_parse_meta_line_is_not_card_text() {
+        let content = "ID: 3\nQ: What is a slice?\nA: A view into a sequence\nMETA: suspended=true";
+
+        let result = parse_md_content(content).unwrap();
+        assert_eq!(result.cards.len(), 1);
+        assert_eq!(result.cards[0].answer, "A view into a sequence");
+    }
+
     #[test]
     fn test_parse_multiple_cards() {
         let content = r#"ID: 1
@@ -425,11 +639,23 @@ A: Answer without question"#;
 
     #[test]
     fn test_extract_deck_path_nested() {
-        assert_eq!(extract_deck_path("a/b/c/d.md"), "a/b/c");
+        assert_eq!(extract_deck_path("a/b/c/d.md", None), "a/b/c");
+    }
+
+    #[test]
+    fn test_extract_deck_path_with_separator() {
+        let separator = Some("::");
+        assert_eq!(
+            extract_deck_path("Programming::Rust::Basics.md", separator),
+            "Programming/Rust"
+        );
+        assert_eq!(extract_deck_path("notes/Rust::Basics.md", separator), "Rust");
+        assert_eq!(extract_deck_path("rust/ownership.md", separator), "rust");
+        assert_eq!(extract_deck_path("Programming::Rust::Basics.md", None), "");
     }
 
     #[test]
     fn test_extract_deck_path_with_spaces() {
-        assert_eq!(extract_deck_path("my decks/rust/basics.md"), "my decks/rust");
+        assert_eq!(extract_deck_path("my decks/rust/basics.md", None), "my decks/rust");
     }
 }
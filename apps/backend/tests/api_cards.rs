@@ -0,0 +1,202 @@
+//! Card API tests.
+//!
+//! These tests require a running PostgreSQL database and S3 storage.
+//! Set DATABASE_URL and S3_* environment variables before running.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+
+use common::fixtures;
+use common::TestContext;
+
+/// Test cards are found by tag across decks and not under other tags.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_cards_by_tag() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let rust = "Q: Rust exam question\nA: Answer\nMETA: tags=exam\n\nQ: Rust oral question\nA: Answer\nMETA: tags=oral";
+    let go = "Q: Go exam question\nA: Answer\nMETA: tags=#Exam,oral";
+    server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![
+            fixtures::sync_file("rust/tagged.md", rust),
+            fixtures::sync_file("go/tagged.md", go),
+        ]))
+        .await
+        .assert_status_ok();
+
+    let questions = |body: serde_json::Value| -> Vec<String> {
+        body.as_array()
+            .unwrap()
+            .iter()
+            .map(|card| card["question"].as_str().unwrap().to_string())
+            .collect()
+    };
+
+    let response = server
+        .get("/api/cards")
+        .add_query_param("tag", "exam")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    assert_eq!(
+        questions(response.json()),
+        vec!["Go exam question", "Rust exam question"]
+    );
+
+    let response = server
+        .get("/api/cards")
+        .add_query_param("tag", "final")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    assert!(questions(response.json()).is_empty());
+
+    // The tag is required
+    server
+        .get("/api/cards")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test a reviewed card is returned with its content and advanced state.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_card_with_state() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload_response = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "state/cards.md",
+            "Q: Stateful question\nA: Answer",
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload_response.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    // Not reviewed yet: no state
+    let response = server
+        .get(&format!("/api/cards/{}", card_id))
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["card"]["question"], "Stateful question");
+    assert!(body["state"].is_null());
+
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get(&format!("/api/cards/{}", card_id))
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["card"]["question"], "Stateful question");
+    assert_eq!(body["card"]["answer"], "Answer");
+    assert_eq!(body["state"]["reviews_count"], 1);
+    assert!(body["state"]["due_date"].is_string());
+
+    // Other devices cannot see the card
+    let (other_id, other_token) = ctx.create_test_device(None).await;
+    server
+        .get(&format!("/api/cards/{}", card_id))
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&other_token),
+        )
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+    ctx.cleanup_device(other_id).await;
+}
+
+/// Test a reviewed card's per-device state is returned with all its fields.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_card_states_for_reviewed_card() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload_response = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "state/devices.md",
+            "Q: Divergent question\nA: Answer",
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload_response.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    // Not reviewed yet: no states
+    let response = server
+        .get(&format!("/api/cards/{}/states", card_id))
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["states"].as_array().unwrap().is_empty());
+
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get(&format!("/api/cards/{}/states", card_id))
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["card_id"], card_id);
+    let states = body["states"].as_array().unwrap();
+    assert_eq!(states.len(), 1);
+    assert_eq!(states[0]["device_id"], device_id.to_string());
+    assert_eq!(states[0]["reviews_count"], 1);
+    assert!(states[0]["due_date"].is_string());
+    assert!(states[0]["ease_factor"].is_number());
+    assert!(states[0]["updated_at"].is_string());
+
+    // Other devices cannot see the card
+    let (other_id, other_token) = ctx.create_test_device(None).await;
+    server
+        .get(&format!("/api/cards/{}/states", card_id))
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&other_token),
+        )
+        .await
+        .assert_status(StatusCode::NOT_FOUND);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+    ctx.cleanup_device(other_id).await;
+}
@@ -82,6 +82,56 @@ async fn test_list_decks_after_upload() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test list decks answers Not Modified until a card changes after `since`.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_list_decks_since_unchanged_is_not_modified() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "rust/basics.md",
+            &fixtures::sample_md_content(2, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    let since = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true);
+
+    let response = server
+        .get("/api/decks")
+        .add_query_param("since", &since)
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status(StatusCode::NOT_MODIFIED);
+
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/decks")
+        .add_query_param("since", &since)
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let rust_deck = &body["decks"][0];
+    assert_eq!(rust_deck["card_count"], 2);
+    assert_eq!(rust_deck["new_count"], 1);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test deck stats.
 #[tokio::test]
 #[ignore = "requires database and storage"]
@@ -122,6 +172,432 @@ async fn test_deck_stats() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test deck summaries are sorted and totals equal per-deck sums.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_deck_summaries_totals() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let _ = server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_upload_request(vec![
+            fixtures::sync_file("rust/basics.md", &fixtures::sample_md_content(5, false)),
+            fixtures::sync_file("python/advanced.md", &fixtures::sample_md_content(3, false)),
+        ]))
+        .await;
+
+    let response = server
+        .get("/api/decks/summaries")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let decks = body["decks"].as_array().unwrap();
+
+    let paths: Vec<&str> = decks.iter().map(|d| d["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["python", "rust"]);
+
+    let sum = |key: &str| decks.iter().map(|d| d[key].as_u64().unwrap()).sum::<u64>();
+    assert_eq!(body["total_new"], sum("new_count"));
+    assert_eq!(body["total_due"], sum("due_count"));
+    assert_eq!(body["total_cards"], sum("total"));
+    assert_eq!(body["total_cards"], 8);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test stats overview totals equal the sums of the per-deck stats.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_stats_overview_matches_deck_stats() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![
+            fixtures::sync_file("rust/basics.md", &fixtures::sample_md_content(5, false)),
+            fixtures::sync_file("python/advanced.md", &fixtures::sample_md_content(3, false)),
+        ]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    for (i, rating) in [(0, 3), (1, 1)] {
+        let card_id = upload_body["new_ids"][i]["id"].as_i64().unwrap();
+        server
+            .post("/api/study/review")
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+            .json(&fixtures::submit_review_request(card_id, rating, "4point", "flip"))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get("/api/stats/overview")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+
+    response.assert_status_ok();
+    let overview: serde_json::Value = response.json();
+    let decks = overview["decks"].as_array().unwrap();
+    let paths: Vec<&str> = decks.iter().map(|d| d["path"].as_str().unwrap()).collect();
+    assert_eq!(paths, vec!["python", "rust"]);
+
+    let mut sums = std::collections::HashMap::new();
+    for deck in decks {
+        let stats: serde_json::Value = server
+            .get(&format!("/api/decks/{}/stats", deck["path"].as_str().unwrap()))
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+            .await
+            .json();
+        for (key, value) in stats.as_object().unwrap() {
+            assert_eq!(&deck[key], value, "{} {}", deck["path"], key);
+        }
+        for key in ["total_cards", "new_cards", "learning_cards", "review_cards", "reviews_today"] {
+            *sums.entry(key).or_insert(0) += stats[key].as_u64().unwrap();
+        }
+    }
+    for (key, sum) in sums {
+        assert_eq!(overview[key], sum, "{}", key);
+    }
+    assert_eq!(overview["total_cards"], 8);
+    assert_eq!(overview["reviews_today"], 2);
+    assert_eq!(overview["retention_rate"], 0.5);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test storage stats count the uploaded files, cards and reviews.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_storage_stats_match_uploads() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![
+            fixtures::sync_file("rust/basics.md", &fixtures::sample_md_content(3, false)),
+            fixtures::sync_file("python/basics.md", &fixtures::sample_md_content(2, false)),
+        ]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/stats/storage")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["file_count"], 2);
+    assert_eq!(body["object_count"], 2);
+    assert_eq!(body["card_count"], 5);
+    assert_eq!(body["review_count"], 1);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test deck cards pagination boundaries.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_deck_cards_pagination() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let _ = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![
+            fixtures::sync_file("paged/cards.md", &fixtures::sample_md_content(5, false)),
+            fixtures::sync_file("other/cards.md", &fixtures::sample_md_content(2, false)),
+        ]))
+        .await;
+
+    let page = |offset: i64, limit: i64| {
+        server
+            .get(&format!(
+                "/api/decks/paged/cards?offset={}&limit={}",
+                offset, limit
+            ))
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+    };
+
+    let first: serde_json::Value = page(0, 2).await.json();
+    let second: serde_json::Value = page(2, 2).await.json();
+    let last: serde_json::Value = page(4, 2).await.json();
+    let past_end: serde_json::Value = page(5, 2).await.json();
+
+    assert_eq!(first["has_more"], true);
+    assert_eq!(second["has_more"], true);
+    assert_eq!(last["has_more"], false);
+    assert_eq!(last["cards"].as_array().unwrap().len(), 1);
+    assert!(past_end["cards"].as_array().unwrap().is_empty());
+    assert_eq!(past_end["has_more"], false);
+
+    // Pages are ordered by ID, do not overlap, and only hold this deck
+    let ids: Vec<i64> = [&first, &second, &last]
+        .iter()
+        .flat_map(|body| body["cards"].as_array().unwrap().clone())
+        .map(|card| {
+            assert_eq!(card["deck_path"], "paged");
+            card["id"].as_i64().unwrap()
+        })
+        .collect();
+    assert_eq!(ids.len(), 5);
+    assert!(ids.windows(2).all(|w| w[0] < w[1]));
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test deck cards carry their state summary.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_deck_cards_join_states() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "joined/cards.md",
+            &fixtures::sample_md_content(2, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/decks/joined/cards")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let cards = body["cards"].as_array().unwrap();
+    assert_eq!(cards.len(), 2);
+
+    for card in cards {
+        assert!(card["question"].is_string());
+        if card["id"].as_i64().unwrap() == card_id {
+            assert_eq!(card["status"], "review");
+            assert!(card["due_date"].is_string());
+        } else {
+            assert_eq!(card["status"], "new");
+            assert!(card["due_date"].is_null());
+        }
+    }
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test slowest cards rank by average response time.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_slowest_cards_rank_by_average_time() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "timed/cards.md",
+            &fixtures::sample_md_content(3, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    let ids: Vec<i64> = upload_body["new_ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| id["id"].as_i64().unwrap())
+        .collect();
+
+    // Equal sample counts for the fast and slow card; one review for the third
+    let mut reviews = Vec::new();
+    for _ in 0..3 {
+        reviews.push((ids[0], 1_000));
+        reviews.push((ids[1], 10_000));
+    }
+    reviews.push((ids[2], 60_000));
+
+    for (card_id, time_taken_ms) in reviews {
+        let mut review = fixtures::submit_review_request(card_id, 3, "4point", "flip");
+        review["time_taken_ms"] = serde_json::json!(time_taken_ms);
+        server
+            .post("/api/study/review")
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+            .json(&review)
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get("/api/decks/timed/slowest-cards")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let cards = body.as_array().unwrap();
+    let ranked: Vec<i64> = cards
+        .iter()
+        .map(|card| card["card_id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ranked, vec![ids[1], ids[0]]);
+    assert_eq!(cards[0]["average_time_ms"], 10_000.0);
+    assert_eq!(cards[0]["review_count"], 3);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test rating distribution buckets reviews by rating.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_rating_distribution_counts_each_rating() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "graded/cards.md",
+            &fixtures::sample_md_content(1, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    // A 2-point Correct (2) counts as Good alongside the 4-point ratings
+    let reviews = [(1, "4point"), (3, "4point"), (3, "4point"), (4, "4point"), (2, "2point")];
+    for (rating, rating_scale) in reviews {
+        server
+            .post("/api/study/review")
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+            .json(&fixtures::submit_review_request(card_id, rating, rating_scale, "flip"))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get("/api/decks/graded/rating-distribution")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body, serde_json::json!([1, 0, 3, 1]));
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test resetting progress sets cards back to New and archives their reviews.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_reset_progress_returns_cards_to_new() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "resettable/cards.md",
+            &fixtures::sample_md_content(2, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    // Refused without confirmation
+    server
+        .post("/api/decks/resettable/reset-progress")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&serde_json::json!({ "confirm": false }))
+        .await
+        .assert_status(StatusCode::BAD_REQUEST);
+
+    let response = server
+        .post("/api/decks/resettable/reset-progress")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&serde_json::json!({ "confirm": true, "archive_reviews": true }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["cards_reset"], 1);
+    assert_eq!(body["reviews_archived"], 1);
+
+    let response = server
+        .get("/api/decks/resettable/cards")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+    let body: serde_json::Value = response.json();
+    for card in body["cards"].as_array().unwrap() {
+        assert_eq!(card["status"], "new");
+        assert!(card["due_date"].is_null());
+    }
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test decks endpoint requires authentication.
 #[tokio::test]
 #[ignore = "requires database"]
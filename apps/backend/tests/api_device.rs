@@ -168,3 +168,35 @@ async fn test_device_status_updates_last_seen() {
     // Cleanup
     ctx.cleanup_device(device_id).await;
 }
+
+/// Test rapid last_seen updates are throttled to a single write.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_update_last_seen_throttled() {
+    let ctx = TestContext::new().await;
+    let (device_id, _token) = ctx.create_test_device(None).await;
+
+    // Push last_seen_at outside the throttle window
+    sqlx::query("UPDATE devices SET last_seen_at = NOW() - INTERVAL '2 minutes' WHERE id = $1")
+        .bind(device_id)
+        .execute(ctx.db.pool())
+        .await
+        .unwrap();
+
+    // Two rapid updates: only the first writes
+    assert!(ctx.db.update_last_seen(device_id).await.unwrap());
+    assert!(!ctx.db.update_last_seen(device_id).await.unwrap());
+
+    // A minute later the next update writes again
+    sqlx::query(
+        "UPDATE devices SET last_seen_at = last_seen_at - INTERVAL '61 seconds' WHERE id = $1",
+    )
+    .bind(device_id)
+    .execute(ctx.db.pool())
+    .await
+    .unwrap();
+    assert!(ctx.db.update_last_seen(device_id).await.unwrap());
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
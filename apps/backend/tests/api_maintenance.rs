@@ -0,0 +1,76 @@
+//! Maintenance API tests.
+//!
+//! These tests require a running PostgreSQL database.
+//! Set DATABASE_URL environment variable before running.
+
+mod common;
+
+use axum_test::TestServer;
+use serde_json::json;
+
+use common::fixtures;
+use common::TestContext;
+
+/// Test reconcile reports tracked files without objects and objects without
+/// tracked files, and leaves fresh orphans alone even when asked to delete.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_reconcile_finds_drift_both_ways() {
+    let ctx = TestContext::new_without_storage().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![
+            fixtures::sync_file("rust/kept.md", &fixtures::sample_md_content(1, false)),
+            fixtures::sync_file("rust/stray.md", &fixtures::sample_md_content(1, false)),
+        ]))
+        .await
+        .assert_status_ok();
+
+    // A tracked file whose upload never reached storage
+    ctx.db
+        .upsert_md_file(device_id, "rust/lost.md", &format!("{}/rust/lost.md", device_id), "hash")
+        .await
+        .unwrap();
+    // An object whose tracking row is gone
+    sqlx::query("DELETE FROM md_files WHERE device_id = $1 AND file_path = 'rust/stray.md'")
+        .bind(device_id)
+        .execute(ctx.db.pool())
+        .await
+        .unwrap();
+
+    let response = server
+        .post("/api/maintenance/reconcile")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({}))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["missing_objects"], json!(["rust/lost.md"]));
+    assert_eq!(
+        body["orphaned_objects"],
+        json!([format!("{}/rust/stray.md", device_id)])
+    );
+    assert_eq!(body["deleted_objects"], 0);
+
+    let response = server
+        .post("/api/maintenance/reconcile")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&json!({ "delete_orphaned_objects": true }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    // The stray object was just uploaded, so it is within the grace window
+    assert_eq!(body["deleted_objects"], 0);
+    assert_eq!(
+        body["orphaned_objects"],
+        json!([format!("{}/rust/stray.md", device_id)])
+    );
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
@@ -0,0 +1,163 @@
+//! Review log API tests.
+//!
+//! These tests require a running PostgreSQL database and S3 storage.
+//! Set DATABASE_URL and S3_* environment variables before running.
+
+mod common;
+
+use axum::http::StatusCode;
+use axum_test::TestServer;
+use chrono::{Duration, SecondsFormat, Utc};
+
+use common::fixtures;
+use common::TestContext;
+
+/// Upload `num_cards` cards and return their IDs.
+async fn upload_cards(server: &TestServer, auth: &str, num_cards: usize) -> Vec<i64> {
+    let response = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.to_string())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "log/cards.md",
+            &fixtures::sample_md_content(num_cards, false),
+        )]))
+        .await;
+    let body: serde_json::Value = response.json();
+    body["new_ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|assignment| assignment["id"].as_i64().unwrap())
+        .collect()
+}
+
+/// Test card and date filters combine.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_reviews_filter_by_card_and_date() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let card_ids = upload_cards(&server, &auth, 2).await;
+    let now = Utc::now();
+    let review_at = |card_id: i64, days_ago: i64| {
+        let mut review = fixtures::review_submission(card_id, 3);
+        review.reviewed_at = now - Duration::days(days_ago);
+        review
+    };
+    server
+        .post("/api/sync/push-reviews")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::push_reviews_request(vec![
+            review_at(card_ids[0], 10),
+            review_at(card_ids[0], 3),
+            review_at(card_ids[0], 1),
+            review_at(card_ids[1], 2),
+        ]))
+        .await
+        .assert_status_ok();
+
+    let from = (now - Duration::days(5)).to_rfc3339_opts(SecondsFormat::Secs, true);
+    let to = now.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let response = server
+        .get(&format!(
+            "/api/reviews?card_id={}&from={}&to={}",
+            card_ids[0], from, to
+        ))
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let reviews = body["reviews"].as_array().unwrap();
+    assert_eq!(reviews.len(), 2);
+    assert!(reviews.iter().all(|r| r["card_id"] == card_ids[0]));
+    assert_eq!(body["has_more"], false);
+
+    // Newest first
+    let first: chrono::DateTime<Utc> =
+        serde_json::from_value(reviews[0]["reviewed_at"].clone()).unwrap();
+    let second: chrono::DateTime<Utc> =
+        serde_json::from_value(reviews[1]["reviewed_at"].clone()).unwrap();
+    assert!(first > second);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test the review log pages without overlap.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_reviews_pagination() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let card_ids = upload_cards(&server, &auth, 1).await;
+    let now = Utc::now();
+    let reviews = (0..5)
+        .map(|minutes_ago| {
+            let mut review = fixtures::review_submission(card_ids[0], 3);
+            review.reviewed_at = now - Duration::minutes(minutes_ago);
+            review
+        })
+        .collect();
+    server
+        .post("/api/sync/push-reviews")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::push_reviews_request(reviews))
+        .await
+        .assert_status_ok();
+
+    let page = |offset: i64, limit: i64| {
+        server
+            .get(&format!("/api/reviews?offset={}&limit={}", offset, limit))
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+    };
+
+    let first: serde_json::Value = page(0, 2).await.json();
+    let second: serde_json::Value = page(2, 2).await.json();
+    let last: serde_json::Value = page(4, 2).await.json();
+
+    assert_eq!(first["has_more"], true);
+    assert_eq!(second["has_more"], true);
+    assert_eq!(last["has_more"], false);
+    assert_eq!(last["reviews"].as_array().unwrap().len(), 1);
+
+    let ids: Vec<String> = [&first, &second, &last]
+        .iter()
+        .flat_map(|body| body["reviews"].as_array().unwrap().clone())
+        .map(|review| review["id"].as_str().unwrap().to_string())
+        .collect();
+    let unique: std::collections::HashSet<_> = ids.iter().collect();
+    assert_eq!(ids.len(), 5);
+    assert_eq!(unique.len(), 5);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test an inverted date range is rejected.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_reviews_rejects_inverted_range() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let response = server
+        .get("/api/reviews?from=2024-03-10T00:00:00Z&to=2024-03-01T00:00:00Z")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
@@ -72,6 +72,64 @@ async fn test_update_global_settings() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test an unlimited daily limit is kept apart from a limit of zero.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_update_global_settings_unlimited_limit() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let response = server
+        .put("/api/settings/global")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&serde_json::json!({
+            "new_cards_per_day": "unlimited",
+            "reviews_per_day": 0,
+        }))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["new_cards_per_day"], "unlimited");
+    assert_eq!(body["reviews_per_day"], 0);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test the queue order is checked against the known orders.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_update_global_settings_validates_queue_order() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let response = server
+        .put("/api/settings/global")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&serde_json::json!({ "queue_order": "random" }))
+        .await;
+    response.assert_status(StatusCode::UNPROCESSABLE_ENTITY);
+
+    let response = server
+        .put("/api/settings/global")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .json(&serde_json::json!({ "queue_order": "interleaved" }))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["queue_order"], "interleaved");
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test updating deck settings.
 #[tokio::test]
 #[ignore = "requires database"]
@@ -99,6 +157,131 @@ async fn test_update_deck_settings() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test effective settings apply deck overrides.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_effective_settings_reflect_deck_override() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let _ = server
+        .put("/api/settings/deck/rust%2Fbasics")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::update_deck_settings_request(Some("fsrs"), Some(10)))
+        .await;
+
+    let response = server
+        .get("/api/settings/effective?deck=rust%2Fbasics")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+
+    assert_eq!(body["algorithm"], "fsrs");
+    assert_eq!(body["new_cards_per_day"], 10);
+    // Fields without an override come from global settings
+    assert_eq!(body["rating_scale"], "4point");
+    assert_eq!(body["reviews_per_day"], 200);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test deck settings come shortest path first and effective settings
+/// inherit ancestor overrides.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_effective_settings_inherit_from_ancestor_decks() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    // Saved leaf first, so the order can't come from insertion
+    for (path, algorithm, new_cards) in [
+        ("lang%2Fes%2Fverbs", None, Some(15)),
+        ("lang%2Fes", None, Some(10)),
+        ("lang", Some("fsrs"), Some(5)),
+    ] {
+        server
+            .put(&format!("/api/settings/deck/{}", path))
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+            .json(&fixtures::update_deck_settings_request(algorithm, new_cards))
+            .await
+            .assert_status_ok();
+    }
+
+    let all = ctx.db.get_all_deck_settings(device_id).await.unwrap();
+    let paths: Vec<_> = all.iter().map(|s| s.deck_path.as_str()).collect();
+    assert_eq!(paths, ["lang", "lang/es", "lang/es/verbs"]);
+
+    let response = server
+        .get("/api/settings/effective?deck=lang%2Fes%2Fverbs")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["algorithm"], "fsrs");
+    assert_eq!(body["new_cards_per_day"], 15);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test effective settings without a deck are the global values.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_effective_settings_without_deck_are_global() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let _ = server
+        .put("/api/settings/global")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::update_global_settings_request(
+            Some("fsrs"),
+            Some(50),
+        ))
+        .await;
+    let _ = server
+        .put("/api/settings/deck/test")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::update_deck_settings_request(Some("sm2"), Some(5)))
+        .await;
+
+    let response = server
+        .get("/api/settings/effective")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+
+    assert_eq!(body["algorithm"], "fsrs");
+    assert_eq!(body["new_cards_per_day"], 50);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test deleting deck settings.
 #[tokio::test]
 #[ignore = "requires database"]
@@ -171,6 +354,71 @@ async fn test_deck_settings_show_in_all_settings() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test deck color and icon round-trip and reach the sync pull.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_deck_appearance_round_trips() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let response = server
+        .put("/api/settings/deck/my-deck")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&serde_json::json!({ "color": "#33aa77", "icon": "🦀" }))
+        .await;
+    response.assert_status_ok();
+
+    let response = server
+        .post("/api/sync/pull")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_pull_request(None))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let deck = &body["settings"]["decks"][0];
+    assert_eq!(deck["deck_path"], "my-deck");
+    assert_eq!(deck["color"], "#33aa77");
+    assert_eq!(deck["icon"], "🦀");
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test invalid deck colors and icons are rejected.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_deck_appearance_is_validated() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    for body in [
+        serde_json::json!({ "color": "red" }),
+        serde_json::json!({ "icon": "far too long for an icon" }),
+    ] {
+        let response = server
+            .put("/api/settings/deck/my-deck")
+            .add_header(
+                axum::http::header::AUTHORIZATION,
+                TestContext::auth_header_value(&token),
+            )
+            .json(&body)
+            .await;
+        response.assert_status(StatusCode::BAD_REQUEST);
+    }
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test settings endpoint requires authentication.
 #[tokio::test]
 #[ignore = "requires database"]
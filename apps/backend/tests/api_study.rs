@@ -117,6 +117,366 @@ async fn test_study_queue_respects_deck_filter() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test study queue subtracts cards studied today from the deck's daily limit.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_study_queue_applies_daily_limits() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let content = fixtures::sample_md_content(5, false);
+    let _ = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "limits/cards.md",
+            &content,
+        )]))
+        .await;
+
+    // Limit the deck to 3 new cards per day
+    server
+        .put("/api/settings/deck/limits")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::update_deck_settings_request(None, Some(3)))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/study/queue?deck_path=limits")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let new_cards = body["new_cards"].as_array().unwrap();
+    assert_eq!(new_cards.len(), 3);
+    assert_eq!(body["limits"]["new_remaining"].as_i64().unwrap(), 0);
+
+    // Study one new card
+    let card_id = new_cards[0]["id"].as_i64().unwrap();
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    // Only 2 of the 3 new-card slots are left today
+    let response = server
+        .get("/api/study/queue?deck_path=limits")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["new_cards"].as_array().unwrap().len(), 2);
+    assert_eq!(body["limits"]["new_remaining"].as_i64().unwrap(), 0);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test pausing new cards empties the new list while due cards still come.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_study_queue_paused_new_cards_keeps_reviews() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "paused/cards.md",
+            &fixtures::sample_md_content(3, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 1, "4point", "flip"))
+        .await
+        .assert_status_ok();
+
+    server
+        .put("/api/settings/global")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&serde_json::json!({ "new_cards_paused": true }))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/study/queue?deck_path=paused")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["new_cards"].as_array().unwrap().is_empty());
+    assert_eq!(body["limits"]["new_remaining"].as_i64().unwrap(), 0);
+    let review_cards = body["review_cards"].as_array().unwrap();
+    assert_eq!(review_cards.len(), 1);
+    assert_eq!(review_cards[0]["id"].as_i64().unwrap(), card_id);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test a Good review on a new card is scheduled and persisted by the server.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_submit_review_schedules_new_card() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let content = fixtures::sample_md_content(1, false);
+    let upload_response = server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "review/cards.md",
+            &content,
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload_response.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    let response = server
+        .post("/api/study/review")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["next_state"]["status"], "review");
+    assert!(body["next_state"]["interval_days"].as_f64().unwrap() > 0.0);
+    assert!(body["next_due"].is_string());
+
+    // The scheduled state is persisted
+    let stored = ctx
+        .db
+        .get_card_state(card_id, device_id)
+        .await
+        .unwrap()
+        .expect("card state should be saved");
+    assert_eq!(stored.status, "review");
+    assert!(stored.interval_days > 0.0);
+    assert_eq!(stored.reviews_count, 1);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test a cram review is logged without changing the stored schedule.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_cram_review_leaves_schedule_unchanged() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload_response = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "cram/cards.md",
+            &fixtures::sample_md_content(1, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload_response.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(card_id, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+    let before = ctx
+        .db
+        .get_card_state(card_id, device_id)
+        .await
+        .unwrap()
+        .expect("card state should be saved");
+
+    let mut cram = fixtures::submit_review_request(card_id, 1, "4point", "flip");
+    cram["cram"] = serde_json::json!(true);
+    let response = server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&cram)
+        .await;
+
+    // The response still shows where Again would have put the card
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["next_state"]["status"], "relearning");
+
+    let after = ctx
+        .db
+        .get_card_state(card_id, device_id)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(after.status, before.status);
+    assert_eq!(after.reviews_count, before.reviews_count);
+    assert_eq!(after.due_date, before.due_date);
+
+    // Both reviews are logged
+    let reviews: serde_json::Value = server
+        .get(&format!("/api/reviews?card_id={}", card_id))
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await
+        .json();
+    let reviews = reviews["reviews"].as_array().unwrap();
+    assert_eq!(reviews.len(), 2);
+    assert_eq!(reviews.iter().filter(|r| r["cram"] == true).count(), 1);
+
+    // The cram review doesn't count against today's limits
+    let since = chrono::Utc::now() - chrono::Duration::hours(1);
+    let studied = ctx
+        .db
+        .count_studied_since(device_id, "cram", since)
+        .await
+        .unwrap();
+    assert_eq!(studied.new_cards, 1);
+    assert_eq!(studied.reviews, 0);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test Again on a new card brings it back within the session, not tomorrow.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_submit_review_again_requeues_in_session() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let content = fixtures::sample_md_content(1, false);
+    let upload_response = server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "review/cards.md",
+            &content,
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload_response.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    let before = chrono::Utc::now();
+    let response = server
+        .post("/api/study/review")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::submit_review_request(card_id, 1, "4point", "flip"))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["next_state"]["status"], "learning");
+    assert_eq!(body["requeue"], true);
+
+    let next_due: chrono::DateTime<chrono::Utc> =
+        serde_json::from_value(body["next_due"].clone()).unwrap();
+    assert!(next_due <= before + chrono::Duration::minutes(5));
+
+    // Still part of today's queue
+    let queue_response = server
+        .get("/api/study/queue")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .await;
+    let queue: serde_json::Value = queue_response.json();
+    let due_ids: Vec<i64> = queue["review_cards"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["id"].as_i64().unwrap())
+        .collect();
+    assert!(due_ids.contains(&card_id));
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test the difficult cards queue ranks lapsed cards first, due or not.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_difficult_queue_ranks_lapsed_cards_first() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let upload_response = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "hard/cards.md",
+            &fixtures::sample_md_content(3, false),
+        )]))
+        .await;
+    let upload_body: serde_json::Value = upload_response.json();
+    let ids: Vec<i64> = upload_body["new_ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|id| id["id"].as_i64().unwrap())
+        .collect();
+
+    // Both graduate; only the second lapses. The third is never studied.
+    for (card_id, rating) in [(ids[0], 3), (ids[1], 3), (ids[1], 1)] {
+        server
+            .post("/api/study/review")
+            .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+            .json(&fixtures::submit_review_request(card_id, rating, "4point", "flip"))
+            .await
+            .assert_status_ok();
+    }
+
+    let response = server
+        .get("/api/study/difficult?deck_path=hard")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let ranked: Vec<i64> = body["review_cards"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|card| card["id"].as_i64().unwrap())
+        .collect();
+    assert_eq!(ranked, vec![ids[1], ids[0]]);
+    assert!(body["new_cards"].as_array().unwrap().is_empty());
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test submitting a review for non-existent card returns not found.
 #[tokio::test]
 #[ignore = "requires database"]
@@ -140,6 +500,29 @@ async fn test_submit_review_not_found() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test a rating outside the declared scale is rejected.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_submit_review_rejects_out_of_range_rating() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let response = server
+        .post("/api/study/review")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::submit_review_request(1, 3, "2point", "flip"))
+        .await;
+
+    response.assert_status(StatusCode::BAD_REQUEST);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test study endpoint requires authentication.
 #[tokio::test]
 #[ignore = "requires database"]
@@ -7,6 +7,7 @@ mod common;
 
 use axum::http::StatusCode;
 use axum_test::TestServer;
+use jirehs_flashcards_backend::models::NewIdAssignment;
 
 use common::fixtures;
 use common::TestContext;
@@ -39,6 +40,38 @@ async fn test_sync_pull_initial() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test sync pull keeps the device's UTC offset for its study days.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_sync_pull_records_utc_offset() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    server
+        .post("/api/sync/pull")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&serde_json::json!({ "last_sync_at": null, "utc_offset_minutes": -300 }))
+        .await
+        .assert_status_ok();
+    let global = ctx.db.get_global_settings(device_id).await.unwrap();
+    assert_eq!(global.utc_offset_minutes, -300);
+
+    // A pull without an offset leaves it alone
+    server
+        .post("/api/sync/pull")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .json(&fixtures::sync_pull_request(None))
+        .await
+        .assert_status_ok();
+    let global = ctx.db.get_global_settings(device_id).await.unwrap();
+    assert_eq!(global.utc_offset_minutes, -300);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test sync upload creates new cards.
 #[tokio::test]
 #[ignore = "requires database and storage"]
@@ -117,6 +150,218 @@ async fn test_sync_upload_existing_cards() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test re-uploading an unchanged file skips the storage write.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_sync_upload_unchanged_file_skips_write() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let content = fixtures::sample_md_content(2, true);
+
+    // First upload writes the file
+    let file = fixtures::sync_file("unchanged.md", &content);
+    server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_upload_request(vec![file]))
+        .await
+        .assert_status_ok();
+
+    let first = ctx
+        .db
+        .get_md_file(device_id, "unchanged.md")
+        .await
+        .unwrap()
+        .expect("md file should be tracked after first upload");
+
+    // Second upload of identical content
+    let file = fixtures::sync_file("unchanged.md", &content);
+    let response = server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_upload_request(vec![file]))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["orphaned_cards"].as_array().unwrap().len(), 0);
+
+    // Tracking row untouched means neither S3 nor md_files was rewritten
+    let second = ctx
+        .db
+        .get_md_file(device_id, "unchanged.md")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first.uploaded_at, second.uploaded_at);
+    assert_eq!(first.content_hash, second.content_hash);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test uploading two files that declare the same ID is rejected before any write.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_sync_upload_rejects_cross_file_duplicate_ids() {
+    let ctx = TestContext::new_without_storage().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let first = fixtures::sync_file("deck/first.md", "ID: 5\nQ: First?\nA: One.\n");
+    let second = fixtures::sync_file(
+        "deck/second.md",
+        "Q: Other?\nA: Two.\n\nID: 5\nQ: Second?\nA: Three.\n",
+    );
+
+    let response = server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_upload_request(vec![first, second]))
+        .await;
+
+    response.assert_status(StatusCode::CONFLICT);
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["error"], "duplicate_ids");
+
+    let conflicts = body["conflicts"].as_array().unwrap();
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0]["id"], 5);
+    let locations = conflicts[0]["locations"].as_array().unwrap();
+    assert_eq!(locations[0]["path"], "deck/first.md");
+    assert_eq!(locations[0]["line"], 1);
+    assert_eq!(locations[1]["path"], "deck/second.md");
+    assert_eq!(locations[1]["line"], 4);
+
+    // Nothing was written
+    let cards = ctx.db.get_cards_by_device(device_id, None).await.unwrap();
+    assert!(cards.is_empty());
+    assert!(ctx.db.get_md_files(device_id).await.unwrap().is_empty());
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test uploads are stored in the in-memory store without S3.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_sync_upload_stores_files_in_memory() {
+    let ctx = TestContext::new_without_storage().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![
+            fixtures::sync_file("rust/basics.md", &fixtures::sample_md_content(2, false)),
+            fixtures::sync_file("go/basics.md", &fixtures::sample_md_content(1, false)),
+        ]))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/stats/storage")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["file_count"], 2);
+    assert_eq!(body["object_count"], 2);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test a first-sync claim maps local card IDs to the IDs assigned to them.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_sync_upload_claims_local_ids() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    // Local IDs may well belong to another device's cards on the server;
+    // only the first card is claimed
+    let (other_device_id, other_token) = ctx.create_test_device(None).await;
+    let local_id = upload_new_cards(&server, &other_token, 1).await[0];
+    let claim = |path: &str, id| NewIdAssignment {
+        path: path.to_string(),
+        line: 1,
+        id,
+    };
+    let file = fixtures::sync_file("claim.md", &fixtures::sample_md_content(2, false));
+    let response = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::claim_upload_request(
+            vec![file],
+            vec![claim("claim.md", local_id)],
+        ))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let new_ids = body["new_ids"].as_array().unwrap();
+    assert_eq!(new_ids.len(), 2);
+    let assigned = new_ids[0]["id"].as_i64().unwrap();
+    assert_ne!(assigned, local_id);
+    assert_eq!(
+        body["claimed_ids"],
+        serde_json::json!([{ "local_id": local_id, "id": assigned }])
+    );
+    let updated = body["updated_files"][0]["content"].as_str().unwrap();
+    assert!(updated.starts_with(&format!("ID: {}\n", assigned)));
+
+    // Progress re-keyed to the assigned ID lands on the claimed card
+    server
+        .post("/api/study/review")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::submit_review_request(assigned, 3, "4point", "flip"))
+        .await
+        .assert_status_ok();
+    let response = server
+        .post("/api/sync/pull")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_pull_request(None))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let states = body["card_states"].as_array().unwrap();
+    assert!(states.iter().any(|s| s["card_id"].as_i64() == Some(assigned)));
+
+    // Later uploads from the same device ignore claims
+    let file = fixtures::sync_file("later.md", &fixtures::sample_md_content(1, false));
+    let response = server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .json(&fixtures::claim_upload_request(
+            vec![file],
+            vec![claim("later.md", local_id + 1)],
+        ))
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert!(body["claimed_ids"].as_array().unwrap().is_empty());
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+    ctx.cleanup_device(other_device_id).await;
+}
+
 /// Test sync upload detects orphaned cards.
 #[tokio::test]
 #[ignore = "requires database and storage"]
@@ -159,6 +404,101 @@ async fn test_sync_upload_detects_orphans() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Upload `num_cards` new cards and return their assigned IDs.
+async fn upload_new_cards(server: &TestServer, token: &str, num_cards: usize) -> Vec<i64> {
+    let content = fixtures::sample_md_content(num_cards, false);
+    let file = fixtures::sync_file("test.md", &content);
+    let response = server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(token),
+        )
+        .json(&fixtures::sync_upload_request(vec![file]))
+        .await;
+
+    let body: serde_json::Value = response.json();
+    body["new_ids"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v["id"].as_i64().unwrap())
+        .collect()
+}
+
+/// Fetch one orphan page and return its IDs and `has_more` flag.
+async fn orphan_page(
+    server: &TestServer,
+    token: &str,
+    current_card_ids: Vec<i64>,
+    offset: i64,
+    limit: i64,
+) -> (Vec<i64>, bool) {
+    let response = server
+        .post("/api/sync/orphans")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(token),
+        )
+        .json(&fixtures::orphans_request(current_card_ids, offset, limit))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    let ids = body["orphaned_cards"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|v| v["id"].as_i64().unwrap())
+        .collect();
+    (ids, body["has_more"].as_bool().unwrap())
+}
+
+/// Test that an empty current ID list treats every card as orphaned.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_sync_orphans_empty_current_ids() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let mut card_ids = upload_new_cards(&server, &token, 3).await;
+    card_ids.sort();
+
+    let (orphans, has_more) = orphan_page(&server, &token, vec![], 0, 100).await;
+    assert_eq!(orphans, card_ids);
+    assert!(!has_more);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test that orphans are ordered by ID and paginated.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_sync_orphans_ordered_and_paginated() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let mut card_ids = upload_new_cards(&server, &token, 5).await;
+    card_ids.sort();
+
+    // Keep the first card; the other four are orphans
+    let kept = vec![card_ids[0]];
+
+    let (first, has_more) = orphan_page(&server, &token, kept.clone(), 0, 3).await;
+    assert_eq!(first, card_ids[1..4]);
+    assert!(has_more);
+
+    let (second, has_more) = orphan_page(&server, &token, kept, 3, 3).await;
+    assert_eq!(second, card_ids[4..]);
+    assert!(!has_more);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test confirm delete soft-deletes cards.
 #[tokio::test]
 #[ignore = "requires database and storage"]
@@ -247,6 +587,110 @@ async fn test_push_reviews() {
     ctx.cleanup_device(device_id).await;
 }
 
+/// Test reviews for unknown cards are rejected without failing the push.
+#[tokio::test]
+#[ignore = "requires database and storage"]
+async fn test_push_reviews_rejects_unknown_cards() {
+    let ctx = TestContext::new().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+
+    let content = fixtures::sample_md_content(1, false);
+    let file = fixtures::sync_file("test.md", &content);
+    let upload_response = server
+        .post("/api/sync/upload")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::sync_upload_request(vec![file]))
+        .await;
+
+    let upload_body: serde_json::Value = upload_response.json();
+    let card_id = upload_body["new_ids"][0]["id"].as_i64().unwrap();
+
+    // The first review references a card that never existed
+    let reviews = vec![
+        fixtures::review_submission(i64::MAX, 3),
+        fixtures::review_submission(card_id, 3),
+    ];
+    let response = server
+        .post("/api/sync/push-reviews")
+        .add_header(
+            axum::http::header::AUTHORIZATION,
+            TestContext::auth_header_value(&token),
+        )
+        .json(&fixtures::push_reviews_request(reviews))
+        .await;
+
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["synced_count"].as_i64().unwrap(), 1);
+    let rejected = body["rejected"].as_array().unwrap();
+    assert_eq!(rejected.len(), 1);
+    assert_eq!(rejected[0]["index"].as_i64().unwrap(), 0);
+    assert_eq!(rejected[0]["card_id"].as_i64().unwrap(), i64::MAX);
+    assert_eq!(rejected[0]["reason"], "card_not_found");
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
+/// Test an uploaded file can be restored, and a stored object that no longer
+/// matches its recorded hash is refused.
+#[tokio::test]
+#[ignore = "requires database"]
+async fn test_download_verifies_checksum() {
+    let ctx = TestContext::new_without_storage().await;
+    let server = TestServer::new(ctx.router()).unwrap();
+    let (device_id, token) = ctx.create_test_device(None).await;
+    let auth = TestContext::auth_header_value(&token);
+
+    let content = fixtures::sample_md_content(1, false);
+    server
+        .post("/api/sync/upload")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .json(&fixtures::sync_upload_request(vec![fixtures::sync_file(
+            "rust/basics.md",
+            &content,
+        )]))
+        .await
+        .assert_status_ok();
+
+    let response = server
+        .get("/api/sync/files/rust%2Fbasics.md")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status_ok();
+    let body: serde_json::Value = response.json();
+    assert_eq!(body["path"], "rust/basics.md");
+    // Restored with the ID the upload assigned
+    let restored = body["content"].as_str().unwrap();
+    assert!(restored.starts_with("ID: "));
+    assert!(restored.contains("Q: Question 1?"));
+
+    // The object no longer matches what was recorded at upload
+    sqlx::query("UPDATE md_files SET content_hash = 'stale' WHERE device_id = $1")
+        .bind(device_id)
+        .execute(ctx.db.pool())
+        .await
+        .unwrap();
+    let response = server
+        .get("/api/sync/files/rust%2Fbasics.md")
+        .add_header(axum::http::header::AUTHORIZATION, auth.clone())
+        .await;
+    response.assert_status(StatusCode::INTERNAL_SERVER_ERROR);
+
+    let response = server
+        .get("/api/sync/files/rust%2Fmissing.md")
+        .add_header(axum::http::header::AUTHORIZATION, auth)
+        .await;
+    response.assert_status(StatusCode::NOT_FOUND);
+
+    // Cleanup
+    ctx.cleanup_device(device_id).await;
+}
+
 /// Test sync endpoints require authentication.
 #[tokio::test]
 #[ignore = "requires database"]
@@ -55,6 +55,8 @@ pub fn review_submission(card_id: i64, rating: i32) -> ReviewSubmission {
         ease_before: 2.5,
         ease_after: 2.5,
         algorithm: "sm2".to_string(),
+        params_hash: None,
+        cram: false,
     }
 }
 
@@ -71,6 +73,14 @@ pub fn sync_upload_request(files: Vec<SyncFile>) -> serde_json::Value {
     json!({ "files": files })
 }
 
+/// Create a first-sync upload request body claiming local card IDs.
+pub fn claim_upload_request(
+    files: Vec<SyncFile>,
+    claimed_ids: Vec<NewIdAssignment>,
+) -> serde_json::Value {
+    json!({ "files": files, "claim_existing_ids": true, "claimed_ids": claimed_ids })
+}
+
 /// Create a sync pull request body.
 pub fn sync_pull_request(last_sync_at: Option<chrono::DateTime<Utc>>) -> serde_json::Value {
     json!({ "last_sync_at": last_sync_at })
@@ -81,6 +91,11 @@ pub fn push_reviews_request(reviews: Vec<ReviewSubmission>) -> serde_json::Value
     json!({ "reviews": reviews })
 }
 
+/// Create an orphans page request body.
+pub fn orphans_request(current_card_ids: Vec<i64>, offset: i64, limit: i64) -> serde_json::Value {
+    json!({ "current_card_ids": current_card_ids, "offset": offset, "limit": limit })
+}
+
 /// Create a confirm delete request body.
 pub fn confirm_delete_request(card_ids: Vec<i64>) -> serde_json::Value {
     json!({ "card_ids": card_ids })
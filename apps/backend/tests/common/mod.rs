@@ -8,7 +8,8 @@
 //! # Requirements
 //! Integration tests require:
 //! - PostgreSQL database (set DATABASE_URL env var)
-//! - Optionally S3/R2 for storage tests (set S3_* env vars)
+//! - S3/R2 for tests using [`TestContext::new`] (set S3_* env vars); the
+//!   others keep files in memory
 
 pub mod fixtures;
 
@@ -24,8 +25,9 @@ use uuid::Uuid;
 use jirehs_flashcards_backend::db::Database;
 use jirehs_flashcards_backend::models::Device;
 use jirehs_flashcards_backend::routes;
-use jirehs_flashcards_backend::services::storage::StorageService;
-use jirehs_flashcards_backend::AppState;
+use jirehs_flashcards_backend::services::auth_cache::AuthCache;
+use jirehs_flashcards_backend::services::storage::{MemoryStore, ObjectStore, S3Store};
+use jirehs_flashcards_backend::{with_body_limit, AppState, DEFAULT_MAX_BODY_BYTES};
 
 /// Test context containing database connection and test server.
 ///
@@ -37,50 +39,28 @@ pub struct TestContext {
 }
 
 impl TestContext {
-    /// Create a new test context.
+    /// Create a new test context backed by S3/R2.
     ///
     /// # Panics
-    /// Panics if DATABASE_URL is not set or database connection fails.
+    /// Panics if DATABASE_URL or the S3_* env vars are not set, or the
+    /// database connection fails.
     pub async fn new() -> Self {
-        dotenvy::dotenv().ok();
-
-        let database_url =
-            std::env::var("DATABASE_URL").expect("DATABASE_URL must be set for integration tests");
-
-        let db = Database::connect(&database_url)
-            .await
-            .expect("Failed to connect to test database");
-
-        db.run_migrations()
-            .await
-            .expect("Failed to run migrations");
-
-        let db = Arc::new(db);
-
-        // For integration tests, we'll skip storage or use a mock
-        // In a real test, you'd either:
-        // 1. Use a mock storage service
-        // 2. Connect to localstack/minio
-        // 3. Use the real S3/R2 with test credentials
-        let storage = StorageService::new()
+        let storage = S3Store::new()
             .await
             .expect("Failed to initialize storage (set S3_* env vars)");
-
-        let state = AppState {
-            db: db.clone(),
-            storage: Arc::new(storage),
-        };
-
-        let app = build_test_router(state);
-
-        Self { db, app }
+        Self::with_storage(Arc::new(storage)).await
     }
 
-    /// Create a new test context with storage disabled.
+    /// Create a new test context that stores files in memory.
     ///
-    /// Use this for tests that don't need S3/R2.
-    /// Note: Sync upload tests will fail without storage.
+    /// Use this for tests that don't need S3/R2; uploads and downloads
+    /// still work, they just don't leave the process.
     pub async fn new_without_storage() -> Self {
+        Self::with_storage(Arc::new(MemoryStore::new())).await
+    }
+
+    /// Connect to the test database and build the app around `storage`.
+    async fn with_storage(storage: Arc<dyn ObjectStore>) -> Self {
         dotenvy::dotenv().ok();
 
         let database_url =
@@ -96,20 +76,10 @@ impl TestContext {
 
         let db = Arc::new(db);
 
-        // Create a dummy storage - will panic if actually used
-        // Tests using this context should not call storage methods
-        std::env::set_var("S3_BUCKET", "test-bucket");
-        std::env::set_var("S3_ACCESS_KEY", "test-key");
-        std::env::set_var("S3_SECRET_KEY", "test-secret");
-        std::env::set_var("S3_ENDPOINT", "http://localhost:9000");
-
-        let storage = StorageService::new()
-            .await
-            .expect("Failed to create storage config");
-
         let state = AppState {
             db: db.clone(),
-            storage: Arc::new(storage),
+            storage,
+            auth_cache: Arc::new(AuthCache::default()),
         };
 
         let app = build_test_router(state);
@@ -189,8 +159,11 @@ fn build_test_router(state: AppState) -> Router {
     let protected_routes = Router::new()
         .route("/api/device/status", get(routes::device::status))
         .route("/api/study/queue", get(routes::study::queue))
+        .route("/api/study/difficult", get(routes::study::difficult))
         .route("/api/study/review", post(routes::study::review))
+        .route("/api/reviews", get(routes::reviews::list))
         .route("/api/settings", get(routes::settings::get_all))
+        .route("/api/settings/effective", get(routes::settings::get_effective))
         .route("/api/settings/global", put(routes::settings::update_global))
         .route(
             "/api/settings/deck/{path}",
@@ -201,22 +174,41 @@ fn build_test_router(state: AppState) -> Router {
             delete(routes::settings::delete_deck),
         )
         .route("/api/decks", get(routes::decks::list))
+        .route("/api/decks/summaries", get(routes::decks::summaries))
         .route("/api/decks/{path}/stats", get(routes::decks::stats))
+        .route("/api/decks/{path}/cards", get(routes::decks::cards))
+        .route("/api/decks/{path}/slowest-cards", get(routes::decks::slowest_cards))
+        .route(
+            "/api/decks/{path}/rating-distribution",
+            get(routes::decks::rating_distribution),
+        )
+        .route("/api/decks/{path}/reset-progress", post(routes::decks::reset_progress))
+        .route("/api/stats/overview", get(routes::decks::stats_overview))
+        .route("/api/stats/storage", get(routes::decks::storage_stats))
+        .route("/api/cards", get(routes::cards::list))
+        .route("/api/cards/{id}", get(routes::cards::get))
+        .route("/api/cards/{id}/states", get(routes::cards::states))
         .route("/api/sync/pull", post(routes::sync::pull))
         .route("/api/sync/push-reviews", post(routes::sync::push_reviews))
+        .route("/api/sync/orphans", post(routes::sync::orphans))
         .route(
             "/api/sync/confirm-delete",
             post(routes::sync::confirm_delete),
         )
         .route("/api/sync/upload", post(routes::sync::upload))
+        .route("/api/sync/files/{path}", get(routes::sync::download))
+        .route(
+            "/api/maintenance/reconcile",
+            post(routes::maintenance::reconcile),
+        )
         .layer(middleware::from_fn_with_state(
             state.clone(),
             routes::auth::auth_middleware,
         ));
 
-    Router::new()
+    let app = Router::new()
         .route("/health", get(|| async { "OK" }))
         .route("/api/device/register", post(routes::device::register))
-        .merge(protected_routes)
-        .with_state(state)
+        .merge(protected_routes);
+    with_body_limit(app, DEFAULT_MAX_BODY_BYTES).with_state(state)
 }
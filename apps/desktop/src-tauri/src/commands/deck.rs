@@ -1,19 +1,46 @@
 //! Deck-related Tauri commands.
 
-use crate::db::{CardRepository, DeckRepository, SettingsRepository, SqliteRepository};
+use crate::db::{
+    CardRepository, DeckRepository, ProgressReset, SettingsRepository, SqliteRepository,
+    StateRepository, SyncRepository,
+};
+use crate::commands::sync::{collect_md_files, FileReadError};
+use crate::file_locks::FileLocks;
 use crate::state::AppState;
-use flashcard_core::types::Deck;
-use flashcard_core::parser;
+use crate::sync::SyncEngine;
+use crate::watcher::{deck_path_for, deck_path_from_name, innermost_root};
+use flashcard_core::types::{Deck, DeckSummaries, IdStrategy, RawCard};
+use flashcard_core::parser::{self, ParserConfig};
+use std::collections::BTreeSet;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use tauri::State;
 
 #[derive(Debug, serde::Serialize)]
 pub struct ImportResult {
     pub imported: usize,
     pub deck_path: String,
+    /// Imported questions that closely match an existing card elsewhere.
+    pub warnings: Vec<String>,
 }
 
+/// What importing a directory would bring in, without importing it.
+#[derive(Debug, serde::Serialize)]
+pub struct DirectoryScan {
+    /// Markdown files read, including ones that fail to parse.
+    pub files: usize,
+    pub total_cards: usize,
+    /// Cards that would be given an ID on import.
+    pub cards_without_ids: usize,
+    /// Files that could not be read or parsed.
+    pub parse_errors: Vec<FileReadError>,
+    /// Decks the cards would land in, as [`import_directory`] names them.
+    pub decks: Vec<String>,
+}
+
+/// Similarity at which an imported question is reported as a likely duplicate.
+const DUPLICATE_SIMILARITY_THRESHOLD: f64 = 0.9;
+
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
 pub struct CommandError {
     pub message: String,
@@ -37,45 +64,128 @@ impl From<std::io::Error> for CommandError {
     }
 }
 
-/// List all decks.
+impl From<crate::sync::SyncError> for CommandError {
+    fn from(e: crate::sync::SyncError) -> Self {
+        Self { message: e.to_string() }
+    }
+}
+
+/// List all decks, leaving out archived ones unless `include_archived` is set.
 #[tauri::command]
-pub async fn list_decks(state: State<'_, AppState>) -> Result<Vec<Deck>, CommandError> {
+pub async fn list_decks(
+    include_archived: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Deck>, CommandError> {
     let repo = state.repository.lock().expect("repository lock");
     let settings = repo.get_global_settings()?;
-    repo.get_all_decks(settings.daily_reset_hour)
+    repo.get_all_decks(settings.daily_reset_hour, include_archived.unwrap_or(false))
         .map_err(Into::into)
 }
 
+/// Get new/due counts for every deck with grand totals.
+#[tauri::command]
+pub async fn get_deck_summaries(
+    include_archived: Option<bool>,
+    state: State<'_, AppState>,
+) -> Result<DeckSummaries, CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    let settings = repo.get_global_settings()?;
+    repo.get_deck_summaries(settings.daily_reset_hour, include_archived.unwrap_or(false))
+        .map_err(Into::into)
+}
+
+/// Parse a file's cards, assigning content-hash IDs when requested.
+fn parse_for_import(
+    repo: &SqliteRepository,
+    content: &str,
+    id_strategy: IdStrategy,
+) -> Result<Vec<RawCard>, CommandError> {
+    let (mut raw_cards, warnings) = parser::parse_lenient(content, &ParserConfig::default())?;
+    for warning in &warnings {
+        eprintln!("Import warning: {}", warning);
+    }
+    for card in raw_cards.iter().filter(|c| !c.meta.unknown_keys.is_empty()) {
+        eprintln!(
+            "Ignoring unknown META keys at line {}: {}",
+            card.line_number,
+            card.meta.unknown_keys.join(", ")
+        );
+    }
+    if id_strategy == IdStrategy::ContentHash {
+        repo.assign_content_ids(&mut raw_cards)?;
+    }
+    Ok(raw_cards)
+}
+
+/// Warn about cards that look like duplicates of existing cards from other
+/// files. Call before importing so a file is not compared with itself.
+fn duplicate_warnings(
+    repo: &SqliteRepository,
+    file_path: &str,
+    raw_cards: &[RawCard],
+) -> Result<Vec<String>, CommandError> {
+    let mut warnings = Vec::new();
+    for raw in raw_cards {
+        let similar = repo.find_similar_cards(&raw.question, DUPLICATE_SIMILARITY_THRESHOLD)?;
+        if let Some((card, score)) = similar
+            .iter()
+            .find(|(card, _)| card.source_file != file_path && Some(card.id) != raw.id)
+        {
+            warnings.push(format!(
+                "{}:{}: question is {:.0}% similar to card {} in {}: {}",
+                file_path,
+                raw.line_number,
+                score * 100.0,
+                card.id,
+                card.source_file,
+                card.question
+            ));
+        }
+    }
+    Ok(warnings)
+}
+
 /// Import a markdown file as a deck.
 #[tauri::command]
 pub async fn import_file(
     file_path: String,
+    id_strategy: Option<IdStrategy>,
     state: State<'_, AppState>,
 ) -> Result<ImportResult, CommandError> {
     let path = Path::new(&file_path);
-    let content = fs::read_to_string(path)?;
-    let raw_cards = parser::parse(&content)?;
-
-    // Derive deck path from file path
-    let deck_path = path
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("default")
-        .to_string();
+    let content = state.file_locks.with_lock(path, || fs::read_to_string(path))?;
 
     let repo = state.repository.lock().expect("repository lock");
+    let separator = repo.get_global_settings()?.deck_path_separator;
+
+    // Derive deck path from file name
+    let deck_path = deck_path_from_name(path, separator.as_deref()).unwrap_or_else(|| {
+        path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("default")
+            .to_string()
+    });
+
+    let raw_cards = parse_for_import(&repo, &content, id_strategy.unwrap_or_default())?;
+    let warnings = duplicate_warnings(&repo, &file_path, &raw_cards)?;
     let ids = repo.import_cards(&deck_path, &file_path, &raw_cards)?;
 
     Ok(ImportResult {
         imported: ids.len(),
         deck_path,
+        warnings,
     })
 }
 
-/// Import all markdown files from a directory.
+/// Import all markdown files under a directory, including subdirectories.
+///
+/// Deck paths are relative to the [`deck_root`], so they match the ones the
+/// watcher gives the same files: importing `programming` under a watched
+/// `notes` puts `programming/rust/basics.md` in the deck `programming/rust`.
 #[tauri::command]
 pub async fn import_directory(
     dir_path: String,
+    id_strategy: Option<IdStrategy>,
     state: State<'_, AppState>,
 ) -> Result<ImportResult, CommandError> {
     let dir = Path::new(&dir_path);
@@ -85,26 +195,123 @@ pub async fn import_directory(
         .unwrap_or("default")
         .to_string();
 
-    let mut total_imported = 0;
+    let watched_dirs = state.watcher.lock().await.get_watched_directories();
+    let root = deck_root(dir, &watched_dirs);
     let repo = state.repository.lock().expect("repository lock");
+    let separator = repo.get_global_settings()?.deck_path_separator;
+    let mut warnings = Vec::new();
+    let total_imported = import_tree(
+        &repo,
+        &state.file_locks,
+        &root,
+        dir,
+        id_strategy.unwrap_or_default(),
+        separator.as_deref(),
+        &mut warnings,
+    )?;
+
+    Ok(ImportResult {
+        imported: total_imported,
+        deck_path,
+        warnings,
+    })
+}
+
+/// Root that deck paths under `dir` are relative to: the innermost watched
+/// directory containing it, as for files the watcher imports. Outside any
+/// watched directory it is the parent, so `dir` names the top-level deck.
+fn deck_root(dir: &Path, watched_dirs: &[String]) -> PathBuf {
+    let roots: Vec<PathBuf> = watched_dirs.iter().map(PathBuf::from).collect();
+    innermost_root(&roots, dir)
+        .or_else(|| dir.parent())
+        .unwrap_or(dir)
+        .to_path_buf()
+}
+
+/// Import the markdown files under `dir` with deck paths relative to `root`.
+///
+/// Each file is read under its lock in `file_locks`, as in [`import_file`].
+fn import_tree(
+    repo: &SqliteRepository,
+    file_locks: &FileLocks,
+    root: &Path,
+    dir: &Path,
+    id_strategy: IdStrategy,
+    separator: Option<&str>,
+    warnings: &mut Vec<String>,
+) -> Result<usize, CommandError> {
+    let mut total_imported = 0;
 
     for entry in fs::read_dir(dir)? {
         let entry = entry?;
         let path = entry.path();
 
-        if path.extension().map_or(false, |ext| ext == "md") {
-            let content = fs::read_to_string(&path)?;
-            let raw_cards = parser::parse(&content)?;
+        if path.is_dir() {
+            total_imported +=
+                import_tree(repo, file_locks, root, &path, id_strategy, separator, warnings)?;
+        } else if path.extension().map_or(false, |ext| ext == "md") {
+            let content = file_locks.with_lock(&path, || fs::read_to_string(&path))?;
+            let raw_cards = parse_for_import(repo, &content, id_strategy)?;
             let file_path = path.to_string_lossy().to_string();
+            let deck_path = deck_path_for(root, &path, separator);
+            warnings.extend(duplicate_warnings(repo, &file_path, &raw_cards)?);
             let ids = repo.import_cards(&deck_path, &file_path, &raw_cards)?;
             total_imported += ids.len();
         }
     }
 
-    Ok(ImportResult {
-        imported: total_imported,
-        deck_path,
-    })
+    Ok(total_imported)
+}
+
+/// Summarize the cards under a directory before importing it.
+#[tauri::command]
+pub async fn scan_directory(
+    dir_path: String,
+    state: State<'_, AppState>,
+) -> Result<DirectoryScan, CommandError> {
+    let dir = Path::new(&dir_path);
+    let watched_dirs = state.watcher.lock().await.get_watched_directories();
+    let root = deck_root(dir, &watched_dirs);
+    let separator = {
+        let repo = state.repository.lock().expect("repository lock");
+        repo.get_global_settings()?.deck_path_separator
+    };
+    Ok(scan_tree(&root, dir, separator.as_deref()))
+}
+
+/// Parse every markdown file under `dir` and total up the results, with deck
+/// paths relative to `root` as in [`import_directory`].
+fn scan_tree(root: &Path, dir: &Path, separator: Option<&str>) -> DirectoryScan {
+    let mut files = Vec::new();
+    let mut parse_errors = Vec::new();
+    collect_md_files(dir, dir, &mut files, &mut parse_errors);
+
+    let mut total_cards = 0;
+    let mut cards_without_ids = 0;
+    let mut decks = BTreeSet::new();
+    for (path, content) in &files {
+        match parser::parse_lenient(content, &ParserConfig::default()) {
+            Ok((cards, _)) => {
+                total_cards += cards.len();
+                cards_without_ids += cards.iter().filter(|c| c.id.is_none()).count();
+                if !cards.is_empty() {
+                    decks.insert(deck_path_for(root, &dir.join(path), separator));
+                }
+            }
+            Err(e) => parse_errors.push(FileReadError {
+                path: path.clone(),
+                error: e.to_string(),
+            }),
+        }
+    }
+
+    DirectoryScan {
+        files: files.len(),
+        total_cards,
+        cards_without_ids,
+        parse_errors,
+        decks: decks.into_iter().collect(),
+    }
 }
 
 /// Get deck details.
@@ -118,3 +325,154 @@ pub async fn get_deck(
     repo.get_deck(&deck_path, settings.daily_reset_hour)
         .map_err(Into::into)
 }
+
+/// Set every card in a deck back to New, optionally archiving its reviews.
+///
+/// Progress can't be restored afterwards, so `confirm` must be true. On a
+/// registered device given `backend_url`, the backend is reset first, so a
+/// failed request leaves local progress untouched; otherwise the next pull
+/// would bring the old progress back.
+#[tauri::command]
+pub async fn reset_deck_progress(
+    deck_path: String,
+    confirm: bool,
+    archive_reviews: Option<bool>,
+    backend_url: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<ProgressReset, CommandError> {
+    if !confirm {
+        return Err(CommandError {
+            message: "Resetting deck progress requires confirmation".to_string(),
+        });
+    }
+    let archive_reviews = archive_reviews.unwrap_or(false);
+    let device = {
+        let repo = state.repository.lock().expect("repository lock");
+        repo.get_device_token()?
+    };
+    if let (Some(backend_url), Some(device)) = (backend_url, device) {
+        SyncEngine::new(backend_url)
+            .reset_deck_progress(&device.token, &deck_path, archive_reviews)
+            .await?;
+    }
+    let repo = state.repository.lock().expect("repository lock");
+    repo.reset_deck_progress(&deck_path, archive_reviews)
+        .map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_directory_keeps_nested_decks() {
+        let base = std::env::temp_dir().join(format!("flashcards-import-{}", uuid::Uuid::new_v4()));
+        let dir = base.join("programming");
+        fs::create_dir_all(dir.join("rust")).unwrap();
+        fs::write(dir.join("overview.md"), "Q: Overview\nA: Answer\n").unwrap();
+        fs::write(dir.join("rust/basics.md"), "Q: Basics\nA: Answer\n").unwrap();
+
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let mut warnings = Vec::new();
+        let locks = FileLocks::new();
+        let imported =
+            import_tree(&repo, &locks, &base, &dir, IdStrategy::default(), None, &mut warnings)
+                .unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(imported, 2);
+        assert!(warnings.is_empty());
+        let mut decks: Vec<String> = repo
+            .get_all_decks(4, false)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.path)
+            .collect();
+        decks.sort();
+        assert_eq!(decks, vec!["programming", "programming/rust"]);
+    }
+
+    #[test]
+    fn test_deck_root_is_the_watched_directory() {
+        let watched = vec!["/notes".to_string(), "/notes/lang".to_string()];
+        let root = |dir: &str| deck_root(Path::new(dir), &watched);
+
+        // Decks match the watcher's for the same files
+        assert_eq!(root("/notes/programming/rust"), Path::new("/notes"));
+        assert_eq!(root("/notes/lang/spanish"), Path::new("/notes/lang"));
+        assert_eq!(root("/notes"), Path::new("/notes"));
+        // Outside the watched directories the directory names the deck
+        assert_eq!(root("/imports/programming"), Path::new("/imports"));
+    }
+
+    #[test]
+    fn test_scan_directory_counts_cards_and_errors() {
+        let base = std::env::temp_dir().join(format!("flashcards-scan-{}", uuid::Uuid::new_v4()));
+        let dir = base.join("programming");
+        fs::create_dir_all(dir.join("rust")).unwrap();
+        fs::write(
+            dir.join("rust/basics.md"),
+            "ID: 7\nQ: Ownership\nA: One owner\n\nQ: Borrowing\nA: References\n",
+        )
+        .unwrap();
+        fs::write(dir.join("broken.md"), "Q: No answer here\n").unwrap();
+
+        let scan = scan_tree(&base, &dir, None);
+        let watched_scan = scan_tree(&dir, &dir, None);
+        fs::remove_dir_all(&base).unwrap();
+
+        assert_eq!(scan.files, 2);
+        assert_eq!(scan.total_cards, 2);
+        assert_eq!(scan.cards_without_ids, 1);
+        assert_eq!(scan.decks, vec!["programming/rust"]);
+        // Scanning a watched directory gives the decks the watcher would
+        assert_eq!(watched_scan.decks, vec!["rust"]);
+        assert_eq!(scan.parse_errors.len(), 1);
+        assert_eq!(scan.parse_errors[0].path, "broken.md");
+        assert!(scan.parse_errors[0].error.contains("missing answer"));
+    }
+
+    #[test]
+    fn test_import_directory_with_deck_path_separator() {
+        let base = std::env::temp_dir().join(format!("flashcards-import-{}", uuid::Uuid::new_v4()));
+        let dir = base.join("notes");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Programming::Rust::Basics.md"), "Q: Basics\nA: Answer\n").unwrap();
+        fs::write(dir.join("inbox.md"), "Q: Loose\nA: Answer\n").unwrap();
+
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let mut warnings = Vec::new();
+        let locks = FileLocks::new();
+        import_tree(&repo, &locks, &base, &dir, IdStrategy::default(), Some("::"), &mut warnings)
+            .unwrap();
+        fs::remove_dir_all(&base).unwrap();
+
+        let mut decks: Vec<String> = repo
+            .get_all_decks(4, false)
+            .unwrap()
+            .into_iter()
+            .map(|d| d.path)
+            .collect();
+        decks.sort();
+        assert_eq!(decks, vec!["Programming/Rust", "notes"]);
+    }
+
+    #[test]
+    fn test_near_duplicate_question_is_flagged() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let existing = parser::parse("Q: What is the capital of France?\nA: Paris\n").unwrap();
+        repo.import_cards("geo", "geo.md", &existing).unwrap();
+
+        let incoming = parser::parse(
+            "Q: What is the captial of France?\nA: Paris\n\nQ: Name a prime number\nA: 7\n",
+        )
+        .unwrap();
+        let warnings = duplicate_warnings(&repo, "travel.md", &incoming).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("travel.md:1:"));
+        assert!(warnings[0].contains("geo.md"));
+
+        // Re-importing the same file doesn't flag its own cards
+        assert!(duplicate_warnings(&repo, "geo.md", &existing).unwrap().is_empty());
+    }
+}
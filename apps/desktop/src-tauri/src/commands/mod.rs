@@ -7,16 +7,27 @@ pub mod study;
 pub mod sync;
 pub mod watcher;
 
-pub use deck::{get_deck, import_directory, import_file, list_decks};
+pub use deck::{
+    get_deck, get_deck_summaries, import_directory, import_file, list_decks, reset_deck_progress,
+    scan_directory,
+};
 pub use settings::{
     delete_deck_settings, get_deck_settings, get_effective_settings, get_global_settings,
-    save_deck_settings, save_global_settings,
+    save_deck_settings, save_global_settings, set_deck_archived,
+};
+pub use stats::{
+    collection_stats, get_calendar_data, get_deck_stats, get_rating_distribution, get_reviews,
+    get_slowest_cards, get_study_stats, project_workload, refresh_stats_cache,
+};
+pub use study::{
+    compare_typed_answer, get_card, get_card_state, get_card_with_state, get_cards_by_tag,
+    get_difficult_cards, get_difficult_study_queue, get_study_queue, preview_review_debug,
+    set_new_priority, submit_review,
 };
-pub use stats::{get_calendar_data, get_deck_stats, get_study_stats};
-pub use study::{compare_typed_answer, get_card, get_card_state, get_study_queue, submit_review};
 pub use sync::{
-    cancel_sync, check_connectivity, confirm_orphan_deletion, get_device_status,
-    get_local_sync_state, get_sync_status, register_device, skip_orphan_deletion, start_sync,
-    SyncEngineState,
+    auto_resolve_sync_conflicts, cancel_sync, check_connectivity, confirm_orphan_deletion,
+    diagnostics, get_auto_sync, get_device_status, get_failed_reviews, get_local_sync_state,
+    get_sync_status, next_orphan_batch, register_device, resolve_sync_conflicts, set_auto_sync,
+    skip_orphan_deletion, start_sync, SyncEngineState,
 };
 pub use watcher::{get_watched_directories, start_watching, stop_watching};
@@ -22,6 +22,9 @@ pub async fn save_global_settings(
     settings: GlobalSettings,
     state: State<'_, AppState>,
 ) -> Result<(), CommandError> {
+    GlobalSettings::validate_ease_bounds(settings.min_ease, settings.max_ease)
+        .map_err(|message| CommandError { message })?;
+
     let repo = state.repository.lock().expect("repository lock");
     repo.save_global_settings(&settings).map_err(Into::into)
 }
@@ -42,6 +45,13 @@ pub async fn save_deck_settings(
     settings: DeckSettings,
     state: State<'_, AppState>,
 ) -> Result<(), CommandError> {
+    DeckSettings::validate_appearance(settings.color.as_deref(), settings.icon.as_deref())
+        .map_err(|message| CommandError { message })?;
+    if let Some(retention) = settings.request_retention {
+        DeckSettings::validate_request_retention(retention)
+            .map_err(|message| CommandError { message })?;
+    }
+
     let repo = state.repository.lock().expect("repository lock");
     repo.save_deck_settings(&settings).map_err(Into::into)
 }
@@ -56,6 +66,18 @@ pub async fn delete_deck_settings(
     repo.delete_deck_settings(&deck_path).map_err(Into::into)
 }
 
+/// Archive or restore a deck without touching its other settings.
+#[tauri::command]
+pub async fn set_deck_archived(
+    deck_path: String,
+    archived: bool,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.set_deck_archived(&deck_path, archived)
+        .map_err(Into::into)
+}
+
 /// Get effective settings for a deck (global merged with deck overrides).
 #[tauri::command]
 pub async fn get_effective_settings(
@@ -1,7 +1,12 @@
 //! Statistics Tauri commands.
 
-use crate::db::{CalendarData, DeckStats, SettingsRepository, StatsRepository, StudyStats};
+use crate::db::{
+    CalendarData, CollectionStats, DateRange, DeckStats, ReviewFilters, ReviewPage,
+    SettingsRepository, SlowCard, StatsRepository, StudyStats, SyncRepository, WorkloadProjection,
+};
 use crate::state::AppState;
+use crate::sync::SyncEngine;
+use flashcard_core::types::DeckSettings;
 use tauri::State;
 
 use super::deck::CommandError;
@@ -16,12 +21,32 @@ pub async fn get_deck_stats(
     repo.get_deck_stats(deck_path.as_deref()).map_err(Into::into)
 }
 
-/// Get overall study statistics.
+/// Get overall study statistics, optionally for an inclusive date range.
 #[tauri::command]
-pub async fn get_study_stats(state: State<'_, AppState>) -> Result<StudyStats, CommandError> {
+pub async fn get_study_stats(
+    range: Option<DateRange>,
+    state: State<'_, AppState>,
+) -> Result<StudyStats, CommandError> {
+    if let Some(range) = range {
+        if range.from > range.to {
+            return Err(CommandError {
+                message: format!("Date range starts after it ends: {} to {}", range.from, range.to),
+            });
+        }
+    }
+    let repo = state.repository.lock().expect("repository lock");
+    let settings = repo.get_global_settings()?;
+    repo.get_study_stats(settings.daily_reset_hour, range)
+        .map_err(Into::into)
+}
+
+/// Recompute today's study stats and cache them, so later reads skip the
+/// live queries until a review is added or the study day ends.
+#[tauri::command]
+pub async fn refresh_stats_cache(state: State<'_, AppState>) -> Result<StudyStats, CommandError> {
     let repo = state.repository.lock().expect("repository lock");
     let settings = repo.get_global_settings()?;
-    repo.get_study_stats(settings.daily_reset_hour)
+    repo.refresh_stats_cache(settings.daily_reset_hour)
         .map_err(Into::into)
 }
 
@@ -37,3 +62,81 @@ pub async fn get_calendar_data(
     repo.get_calendar_data(days, settings.daily_reset_hour)
         .map_err(Into::into)
 }
+
+/// Get the cards with the slowest average response time.
+#[tauri::command]
+pub async fn get_slowest_cards(
+    deck_path: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<SlowCard>, CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.get_slowest_cards(deck_path.as_deref(), limit.unwrap_or(20))
+        .map_err(Into::into)
+}
+
+/// Count reviews per rating, as `[again, hard, good, easy]`.
+#[tauri::command]
+pub async fn get_rating_distribution(
+    deck_path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<[usize; 4], CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.get_rating_distribution(deck_path.as_deref())
+        .map_err(Into::into)
+}
+
+/// Get a page of the review log, newest first.
+#[tauri::command]
+pub async fn get_reviews(
+    filters: ReviewFilters,
+    state: State<'_, AppState>,
+) -> Result<ReviewPage, CommandError> {
+    if let (Some(from), Some(to)) = (filters.from, filters.to) {
+        if from > to {
+            return Err(CommandError {
+                message: format!("Date range starts after it ends: {} to {}", from, to),
+            });
+        }
+    }
+    let repo = state.repository.lock().expect("repository lock");
+    repo.get_reviews(&filters).map_err(Into::into)
+}
+
+/// Project how the daily review load would change at a different FSRS
+/// retention, without rescheduling anything.
+#[tauri::command]
+pub async fn project_workload(
+    deck_path: Option<String>,
+    new_retention: f64,
+    state: State<'_, AppState>,
+) -> Result<WorkloadProjection, CommandError> {
+    DeckSettings::validate_request_retention(new_retention)
+        .map_err(|message| CommandError { message })?;
+    let repo = state.repository.lock().expect("repository lock");
+    repo.project_workload(deck_path.as_deref(), new_retention)
+        .map_err(Into::into)
+}
+
+/// Get collection totals and the database size.
+///
+/// With a `backend_url` and a registered device, the backend's file count
+/// is included too. An unreachable backend leaves it out rather than
+/// failing, like the sync diagnostics.
+#[tauri::command]
+pub async fn collection_stats(
+    backend_url: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<CollectionStats, CommandError> {
+    let (mut stats, device) = {
+        let repo = state.repository.lock().expect("repository lock");
+        (repo.get_collection_stats()?, repo.get_device_token()?)
+    };
+    if let (Some(backend_url), Some(device)) = (backend_url, device) {
+        stats.remote_file_count = SyncEngine::new(backend_url)
+            .remote_file_count(&device.token)
+            .await
+            .ok();
+    }
+    Ok(stats)
+}
@@ -1,13 +1,22 @@
 //! Study session Tauri commands.
 
+use crate::db::date_utils::{get_adjusted_today, get_study_day_start};
 use crate::db::{
-    CardRepository, PendingReview, SettingsRepository, StateRepository, SyncRepository,
+    CardRepository, DbError, PendingReview, SettingsRepository, SqliteRepository,
+    StateRepository, StatsRepository, SyncRepository,
 };
 use crate::state::AppState;
 use chrono::Utc;
-use flashcard_core::algorithm::{get_algorithm, SpacedRepetitionAlgorithm};
-use flashcard_core::matching::{compare_answers, word_diff, DiffSegment};
-use flashcard_core::types::{Card, CardState, Rating, StudyQueue};
+use flashcard_core::algorithm::fsrs::Fsrs;
+use flashcard_core::algorithm::{AlgorithmKind, SchedulingDebug, SpacedRepetitionAlgorithm};
+use flashcard_core::matching::{
+    answer_list_items, compare_answers_with_options, compare_list_answer, word_diff, DiffSegment,
+    MatchOptions, MatchResult,
+};
+use flashcard_core::types::{
+    AnswerMode, Card, CardState, CardWithState, GlobalSettings, Limit, QueueOrder, Rating,
+    RatingScale, SessionLimit, StudyQueue, DIFFICULT_CARDS_LIMIT,
+};
 use tauri::State;
 
 use super::deck::CommandError;
@@ -16,52 +25,220 @@ use super::deck::CommandError;
 pub struct ReviewRequest {
     pub card_id: i64,
     pub rating: u8,
-    pub rating_scale: String,
-    pub answer_mode: String,
+    pub rating_scale: RatingScale,
+    pub answer_mode: AnswerMode,
     #[serde(default)]
     pub typed_answer: Option<String>,
     #[serde(default)]
     pub time_taken_ms: Option<i64>,
+    /// Record the review without changing the card's schedule.
+    #[serde(default)]
+    pub cram: bool,
 }
 
 #[derive(Debug, serde::Serialize)]
 pub struct ReviewResponse {
     pub new_state: CardState,
     pub next_due: String,
+    /// Show the card again later in this session.
+    pub requeue: bool,
 }
 
+#[derive(Debug, serde::Serialize)]
+pub struct ReviewDebugResponse {
+    pub new_state: CardState,
+    pub next_due: String,
+    pub debug: SchedulingDebug,
+}
+
+/// Largest per-session limit override accepted by [`get_study_queue`].
+pub const MAX_LIMIT_OVERRIDE: u32 = 10_000;
+
 /// Get the study queue for a deck (or all decks).
+///
+/// Reviews already submitted today, including learning repeats, are taken
+/// off the review limit. Once it is used up no further review-stage cards are
+/// offered, but learning cards still come back until they graduate.
+///
+/// `new_cards_limit_override` and `review_limit_override` replace the daily
+/// limits for this call only (e.g. `0` new cards for a review-only cram
+/// session) and are never written to settings. While new cards are paused
+/// in the global settings no new cards are offered, even with an override.
+///
+/// Cards to show answer first under the study direction are listed in
+/// `reversed_card_ids`.
+///
+/// `session_limit` replaces the session cap from the global settings. A
+/// card cap keeps the first cards in session order; a time cap is returned
+/// for the client to enforce.
+///
+/// With a `tag`, only cards carrying it are offered, across all decks when
+/// no `deck_path` is given.
 #[tauri::command]
 pub async fn get_study_queue(
     deck_path: Option<String>,
+    tag: Option<String>,
+    new_cards_limit_override: Option<u32>,
+    review_limit_override: Option<u32>,
+    session_limit: Option<SessionLimit>,
     state: State<'_, AppState>,
 ) -> Result<StudyQueue, CommandError> {
     let repo = state.repository.lock().expect("repository lock");
+    build_study_queue(
+        &repo,
+        deck_path.as_deref(),
+        tag.as_deref(),
+        new_cards_limit_override,
+        review_limit_override,
+        session_limit,
+    )
+}
+
+fn build_study_queue(
+    repo: &SqliteRepository,
+    deck_path: Option<&str>,
+    tag: Option<&str>,
+    new_cards_limit_override: Option<u32>,
+    review_limit_override: Option<u32>,
+    session_limit: Option<SessionLimit>,
+) -> Result<StudyQueue, CommandError> {
+    for limit in [new_cards_limit_override, review_limit_override]
+        .into_iter()
+        .flatten()
+    {
+        if limit > MAX_LIMIT_OVERRIDE {
+            return Err(CommandError {
+                message: format!(
+                    "Limit override {} exceeds the maximum of {}",
+                    limit, MAX_LIMIT_OVERRIDE
+                ),
+            });
+        }
+    }
 
     // Get limits from effective settings
-    let settings = repo.get_effective_settings(deck_path.as_deref())?;
-    let new_limit = settings.new_cards_per_day as usize;
-    let review_limit = settings.reviews_per_day as usize;
+    let settings = repo.get_effective_settings(deck_path)?;
+    let global = repo.get_global_settings()?;
+    let new_limit = if global.new_cards_paused {
+        Limit::Count(0)
+    } else {
+        new_cards_limit_override.map_or(settings.new_cards_per_day, Limit::Count)
+    };
     let daily_reset_hour = settings.daily_reset_hour;
+    let review_limit = match review_limit_override {
+        Some(limit) => Limit::Count(limit),
+        None => {
+            let day_start = get_study_day_start(daily_reset_hour).to_rfc3339();
+            let reviews_done = repo.count_reviews_since(deck_path, &day_start)?;
+            settings.reviews_per_day.remaining(reviews_done)
+        }
+    };
 
-    let new_cards = repo.get_new_cards(deck_path.as_deref(), new_limit)?;
-    let review_cards = repo.get_due_cards(deck_path.as_deref(), review_limit, daily_reset_hour)?;
+    let new_cards = repo.get_new_cards(deck_path, tag, new_limit.query_limit())?;
+    let review_cards = repo.get_due_cards(
+        deck_path,
+        tag,
+        review_limit.query_limit(),
+        daily_reset_hour,
+        settings.due_grace_hours,
+    )?;
 
-    Ok(StudyQueue {
-        new_remaining: new_limit.saturating_sub(new_cards.len()),
-        review_remaining: review_limit.saturating_sub(review_cards.len()),
+    let mut queue = StudyQueue {
+        new_remaining: new_limit.unused(new_cards.len()),
+        review_remaining: review_limit.unused(review_cards.len()),
         new_cards,
         review_cards,
+        queue_order: global.queue_order,
+        reversed_card_ids: Vec::new(),
+        session_limit: session_limit.unwrap_or(global.session_limit),
+    };
+    if let Some(max) = queue.session_limit.max_cards() {
+        queue.truncate(max);
+    }
+
+    let today = get_adjusted_today(daily_reset_hour);
+    queue.reversed_card_ids = queue
+        .new_cards
+        .iter()
+        .chain(&queue.review_cards)
+        .map(|card| card.id)
+        .filter(|&id| global.study_direction.reverses(id, today))
+        .collect();
+    Ok(queue)
+}
+
+
+/// Get the hardest studied cards, most lapses first, regardless of due
+/// date. Suspended cards are left out.
+#[tauri::command]
+pub async fn get_difficult_cards(
+    deck_path: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<Card>, CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.get_difficult_cards(deck_path.as_deref(), difficult_cards_limit(limit))
+        .map_err(Into::into)
+}
+
+/// Requested difficult card count, defaulting to and capped at
+/// [`DIFFICULT_CARDS_LIMIT`].
+fn difficult_cards_limit(limit: Option<usize>) -> usize {
+    limit
+        .unwrap_or(DIFFICULT_CARDS_LIMIT)
+        .clamp(1, DIFFICULT_CARDS_LIMIT)
+}
+
+/// Get a study queue of only the hardest cards, for a targeted session.
+///
+/// Cards are offered whether due or not and don't count against the daily
+/// limits; reviews are scheduled as usual.
+#[tauri::command]
+pub async fn get_difficult_study_queue(
+    deck_path: Option<String>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<StudyQueue, CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    let review_cards =
+        repo.get_difficult_cards(deck_path.as_deref(), difficult_cards_limit(limit))?;
+    let global = repo.get_global_settings()?;
+    let today = get_adjusted_today(global.daily_reset_hour);
+    let reversed_card_ids = review_cards
+        .iter()
+        .map(|card| card.id)
+        .filter(|&id| global.study_direction.reverses(id, today))
+        .collect();
+    Ok(StudyQueue {
+        new_cards: Vec::new(),
+        review_cards,
+        new_remaining: 0,
+        review_remaining: 0,
+        queue_order: QueueOrder::default(),
+        reversed_card_ids,
+        session_limit: SessionLimit::None,
     })
 }
 
 /// Submit a review for a card.
+///
+/// A cram review is logged for stats and sync like any other, but the card's
+/// stored state is left alone; the response shows the state the rating
+/// would have produced.
 #[tauri::command]
 pub async fn submit_review(
     request: ReviewRequest,
     state: State<'_, AppState>,
 ) -> Result<ReviewResponse, CommandError> {
     let repo = state.repository.lock().expect("repository lock");
+    record_review(&repo, &request)
+}
+
+fn record_review(
+    repo: &SqliteRepository,
+    request: &ReviewRequest,
+) -> Result<ReviewResponse, CommandError> {
+    let rating = validate_rating(request.rating, request.rating_scale)?;
 
     // Get card to find deck path
     let card = repo.get_card(request.card_id)?;
@@ -77,34 +254,43 @@ pub async fn submit_review(
 
     // Get algorithm from settings
     let algorithm_name = settings.algorithm.as_str();
-    let algorithm = get_algorithm(algorithm_name).expect("algorithm should exist");
-
-    // Convert rating
-    let rating = Rating::from_value(request.rating).unwrap_or(Rating::Good);
+    let algorithm = AlgorithmKind::from(settings.algorithm)
+        .with_graduation_requirement(settings.graduation_requirement)
+        .with_ease_bounds(settings.min_ease, settings.max_ease)
+        .with_request_retention(settings.request_retention);
 
     // Calculate next state
     let now = Utc::now();
-    let result = algorithm.schedule(&card_state, rating, now);
+    let result = algorithm
+        .schedule(&card_state, rating, now)
+        .with_again_delay(rating, now, settings.again_delay_minutes);
 
-    // Save new state
-    repo.save_card_state(request.card_id, &result.new_state)?;
+    // Save new state, unless cramming
+    if !request.cram {
+        repo.save_card_state(request.card_id, &result.new_state)?;
+    }
 
-    // Save to pending_reviews for sync
+    // Save to pending_reviews for sync. A cram review logs the schedule it
+    // left in place
+    let after = if request.cram { &card_state } else { &result.new_state };
     let pending_review = PendingReview {
         id: 0, // Will be assigned by database
         card_id: request.card_id,
         reviewed_at: now.to_rfc3339(),
         rating: request.rating as i32,
-        rating_scale: request.rating_scale.clone(),
-        answer_mode: request.answer_mode.clone(),
+        rating_scale: request.rating_scale.as_str().to_string(),
+        answer_mode: request.answer_mode.as_str().to_string(),
         typed_answer: request.typed_answer.clone(),
         was_correct: None, // Could be computed if needed
         time_taken_ms: request.time_taken_ms.map(|t| t as i32),
         interval_before: card_state.interval_days,
-        interval_after: result.new_state.interval_days,
+        interval_after: after.interval_days,
         ease_before: card_state.ease_factor,
-        ease_after: result.new_state.ease_factor,
+        ease_after: after.ease_factor,
         algorithm: algorithm_name.to_string(),
+        params_hash: Some(algorithm.params_hash()),
+        status_before: Some(card_state.status.as_str().to_string()),
+        cram: request.cram,
     };
     repo.insert_pending_review(&pending_review)?;
     repo.increment_pending_changes()?;
@@ -112,6 +298,59 @@ pub async fn submit_review(
     Ok(ReviewResponse {
         new_state: result.new_state,
         next_due: result.next_due.to_rfc3339(),
+        requeue: result.requeue,
+    })
+}
+
+/// Show how a rating would schedule a card, and why, without saving anything.
+#[tauri::command]
+pub async fn preview_review_debug(
+    card_id: i64,
+    rating: u8,
+    rating_scale: RatingScale,
+    state: State<'_, AppState>,
+) -> Result<ReviewDebugResponse, CommandError> {
+    let rating = validate_rating(rating, rating_scale)?;
+
+    let repo = state.repository.lock().expect("repository lock");
+    preview_review(&repo, card_id, rating)
+}
+
+fn preview_review(
+    repo: &SqliteRepository,
+    card_id: i64,
+    rating: Rating,
+) -> Result<ReviewDebugResponse, CommandError> {
+    let card = repo.get_card(card_id)?.ok_or(DbError::CardNotFound(card_id))?;
+    let settings = repo.get_effective_settings(Some(&card.deck_path))?;
+    let card_state = repo.get_card_state(card_id)?.unwrap_or_default();
+
+    let algorithm = AlgorithmKind::from(settings.algorithm)
+        .with_graduation_requirement(settings.graduation_requirement)
+        .with_ease_bounds(settings.min_ease, settings.max_ease)
+        .with_request_retention(settings.request_retention);
+    let now = Utc::now();
+    let (result, mut debug) = algorithm.schedule_with_debug(&card_state, rating, now);
+    let result = result.with_again_delay(rating, now, settings.again_delay_minutes);
+    // A card coming back after the again delay is on a same-day step
+    debug.short_term |= result.requeue;
+
+    Ok(ReviewDebugResponse {
+        new_state: result.new_state,
+        next_due: result.next_due.to_rfc3339(),
+        debug,
+    })
+}
+
+/// Validate a rating against the declared scale.
+fn validate_rating(rating: u8, rating_scale: RatingScale) -> Result<Rating, CommandError> {
+    rating_scale.rating(rating).ok_or_else(|| CommandError {
+        message: format!(
+            "Rating {} is out of range for the {} scale (1-{})",
+            rating,
+            rating_scale.as_str(),
+            rating_scale.max_rating()
+        ),
     })
 }
 
@@ -125,6 +364,56 @@ pub async fn get_card(
     repo.get_card(card_id).map_err(Into::into)
 }
 
+/// Get a card together with its scheduling state and current retrievability.
+#[tauri::command]
+pub async fn get_card_with_state(
+    card_id: i64,
+    state: State<'_, AppState>,
+) -> Result<Option<CardWithState>, CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    load_card_with_state(&repo, card_id)
+}
+
+fn load_card_with_state(
+    repo: &SqliteRepository,
+    card_id: i64,
+) -> Result<Option<CardWithState>, CommandError> {
+    let Some(card) = repo.get_card(card_id)? else {
+        return Ok(None);
+    };
+    let state = repo.get_card_state(card_id)?;
+    let retrievability = state
+        .as_ref()
+        .and_then(|s| Fsrs::default().current_retrievability(s, Utc::now()));
+    Ok(Some(CardWithState {
+        card,
+        state,
+        retrievability,
+    }))
+}
+
+/// Get live cards with a tag across all decks.
+#[tauri::command]
+pub async fn get_cards_by_tag(
+    tag: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<Card>, CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.get_cards_by_tag(&tag).map_err(Into::into)
+}
+
+/// Move a new card up or down the new card queue. Higher priorities are
+/// introduced first; cards default to 0 and ties go by ID.
+#[tauri::command]
+pub async fn set_new_priority(
+    card_id: i64,
+    priority: i32,
+    state: State<'_, AppState>,
+) -> Result<(), CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.set_new_priority(card_id, priority).map_err(Into::into)
+}
+
 /// Get card state by ID.
 #[tauri::command]
 pub async fn get_card_state(
@@ -143,10 +432,19 @@ pub struct CompareAnswerResponse {
     pub matching_mode: String,
     pub typed_normalized: String,
     pub correct_normalized: String,
+    /// The typed answer was empty, so it was not graded.
+    pub no_answer: bool,
     pub diff: Vec<DiffSegment>,
+    /// Items of a list answer that the typed answer left out.
+    pub missing_items: Vec<String>,
+    /// Rating to preselect, so the typed flow can grade with one confirm.
+    pub suggested_rating: Rating,
 }
 
 /// Compare a typed answer to the correct answer.
+///
+/// `correct_answer` is the side the session shows as the answer, so a card
+/// reversed by the study direction is checked against its question.
 #[tauri::command]
 pub async fn compare_typed_answer(
     typed_answer: String,
@@ -155,24 +453,48 @@ pub async fn compare_typed_answer(
     state: State<'_, AppState>,
 ) -> Result<CompareAnswerResponse, CommandError> {
     let repo = state.repository.lock().expect("repository lock");
+    compare_typed(&repo, &typed_answer, &correct_answer, deck_path.as_deref())
+}
 
+fn compare_typed(
+    repo: &SqliteRepository,
+    typed_answer: &str,
+    correct_answer: &str,
+    deck_path: Option<&str>,
+) -> Result<CompareAnswerResponse, CommandError> {
     // Get effective settings for matching mode
-    let settings = repo.get_effective_settings(deck_path.as_deref())?;
+    let settings = repo.get_effective_settings(deck_path)?;
+    let global = repo.get_global_settings()?;
 
-    // Compare answers
-    let result = compare_answers(
-        &typed_answer,
-        &correct_answer,
-        settings.matching_mode,
-        settings.fuzzy_threshold,
-    );
+    // A list answer needs every item, in any order
+    let result = match answer_list_items(correct_answer) {
+        Some(items) => compare_list_answer(
+            typed_answer,
+            &items,
+            settings.matching_mode,
+            settings.fuzzy_threshold,
+        ),
+        None => compare_answers_with_options(
+            typed_answer,
+            correct_answer,
+            settings.matching_mode,
+            settings.fuzzy_threshold,
+            &MatchOptions {
+                min_answer_chars: global.min_answer_chars as usize,
+                normalization_rules: settings.normalization_rules.clone(),
+                ..Default::default()
+            },
+        ),
+    };
 
     // Generate diff for display
     let diff = word_diff(&result.typed_normalized, &result.correct_normalized);
+    let suggested_rating = suggest_rating(&result, settings.rating_scale, &global);
 
     let matching_mode_str = match settings.matching_mode {
         flashcard_core::types::MatchingMode::Exact => "exact",
         flashcard_core::types::MatchingMode::CaseInsensitive => "case_insensitive",
+        flashcard_core::types::MatchingMode::Contains => "contains",
         flashcard_core::types::MatchingMode::Fuzzy => "fuzzy",
     };
 
@@ -182,6 +504,385 @@ pub async fn compare_typed_answer(
         matching_mode: matching_mode_str.to_string(),
         typed_normalized: result.typed_normalized,
         correct_normalized: result.correct_normalized,
+        no_answer: result.no_answer,
         diff,
+        missing_items: result.missing_items,
+        suggested_rating,
     })
 }
+
+/// Rating to suggest for a typed answer.
+///
+/// A correct answer suggests Easy at or above `suggest_easy_threshold`
+/// similarity and Good below it; an incorrect one suggests Hard at or above
+/// `suggest_hard_threshold` and Again below it. The 2-point scale has only
+/// Again and Good, so it gets Good for any correct answer and Again otherwise.
+fn suggest_rating(
+    result: &MatchResult,
+    rating_scale: RatingScale,
+    settings: &GlobalSettings,
+) -> Rating {
+    let rating = if result.no_answer {
+        Rating::Again
+    } else if result.is_correct {
+        if result.similarity >= settings.suggest_easy_threshold {
+            Rating::Easy
+        } else {
+            Rating::Good
+        }
+    } else if result.similarity >= settings.suggest_hard_threshold {
+        Rating::Hard
+    } else {
+        Rating::Again
+    };
+
+    match rating_scale {
+        RatingScale::FourPoint => rating,
+        RatingScale::TwoPoint => Rating::from_2point(result.is_correct && !result.no_answer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flashcard_core::matching::compare_answers;
+    use flashcard_core::types::{CardMeta, CardStatus, RawCard, StudyDirection};
+
+    fn repo_with_new_cards(count: usize) -> SqliteRepository {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let cards: Vec<RawCard> = (0..count)
+            .map(|i| RawCard {
+                id: None,
+                question: format!("Question {}", i),
+                answer: "Answer".to_string(),
+                line_number: i + 1,
+                media: Vec::new(),
+                meta: CardMeta::default(),
+            })
+            .collect();
+        repo.import_cards("deck", "deck.md", &cards).unwrap();
+        repo
+    }
+
+    #[test]
+    fn test_zero_new_card_override_returns_no_new_cards() {
+        let repo = repo_with_new_cards(3);
+        let default_queue = || build_study_queue(&repo, None, None, None, None, None).unwrap();
+        assert_eq!(default_queue().new_cards.len(), 3);
+
+        let queue = build_study_queue(&repo, None, None, Some(0), None, None).unwrap();
+        assert!(queue.new_cards.is_empty());
+        assert_eq!(queue.new_remaining, 0);
+
+        // The override is not persisted
+        let settings = repo.get_effective_settings(None).unwrap();
+        assert_eq!(settings.new_cards_per_day, Limit::Count(20));
+        assert_eq!(default_queue().new_cards.len(), 3);
+    }
+
+    #[test]
+    fn test_unlimited_new_cards_returns_all_and_zero_returns_none() {
+        let repo = repo_with_new_cards(30);
+        let with_limit = |new_cards_per_day| {
+            let settings = GlobalSettings {
+                new_cards_per_day,
+                ..GlobalSettings::default()
+            };
+            repo.save_global_settings(&settings).unwrap();
+            build_study_queue(&repo, None, None, None, None, None).unwrap()
+        };
+
+        let queue = with_limit(Limit::Unlimited);
+        assert_eq!(queue.new_cards.len(), 30);
+        assert_eq!(queue.new_remaining, 0);
+
+        let queue = with_limit(Limit::Count(0));
+        assert!(queue.new_cards.is_empty());
+        assert_eq!(queue.new_remaining, 0);
+
+        assert_eq!(with_limit(Limit::Count(20)).new_cards.len(), 20);
+    }
+
+    #[test]
+    fn test_card_with_state_after_review() {
+        let repo = repo_with_new_cards(1);
+        let card_id = repo.get_cards_by_deck("deck").unwrap()[0].id;
+
+        let loaded = load_card_with_state(&repo, card_id).unwrap().unwrap();
+        assert_eq!(loaded.state.unwrap().reviews_count, 0);
+        assert!(loaded.retrievability.is_none());
+
+        let fsrs = Fsrs::default();
+        let result = fsrs.schedule(&fsrs.initial_state(), Rating::Good, Utc::now());
+        repo.save_card_state(card_id, &result.new_state).unwrap();
+
+        let loaded = load_card_with_state(&repo, card_id).unwrap().unwrap();
+        assert_eq!(loaded.card.question, "Question 0");
+        let state = loaded.state.unwrap();
+        assert_eq!(state.reviews_count, 1);
+        assert!(state.due_date.is_some());
+        assert!(loaded.retrievability.unwrap() > 0.9);
+
+        assert!(load_card_with_state(&repo, card_id + 1).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_cram_review_leaves_state_unchanged() {
+        let repo = repo_with_new_cards(1);
+        let card_id = repo.get_cards_by_deck("deck").unwrap()[0].id;
+        let review = |rating: u8, cram: bool| ReviewRequest {
+            card_id,
+            rating,
+            rating_scale: RatingScale::FourPoint,
+            answer_mode: AnswerMode::Flip,
+            typed_answer: None,
+            time_taken_ms: None,
+            cram,
+        };
+
+        record_review(&repo, &review(3, false)).unwrap();
+        let before = repo.get_card_state(card_id).unwrap().unwrap();
+
+        let response = record_review(&repo, &review(1, true)).unwrap();
+        assert_eq!(response.new_state.lapses, before.lapses + 1);
+
+        let after = repo.get_card_state(card_id).unwrap().unwrap();
+        assert_eq!(after.status, before.status);
+        assert_eq!(after.reviews_count, before.reviews_count);
+        assert_eq!(after.due_date, before.due_date);
+
+        // Still logged for sync, marked so limits and retention skip it
+        let pending = repo.get_pending_reviews().unwrap();
+        assert_eq!(pending.len(), 2);
+        let cram = pending.iter().find(|review| review.rating == 1).unwrap();
+        assert!(cram.cram);
+        assert_eq!(cram.interval_after, before.interval_days);
+    }
+
+    #[test]
+    fn test_preview_matches_the_review_it_would_record() {
+        let repo = repo_with_new_cards(1);
+        let card_id = repo.get_cards_by_deck("deck").unwrap()[0].id;
+
+        let preview = preview_review(&repo, card_id, Rating::Again).unwrap();
+        assert!(preview.debug.short_term);
+        let review = ReviewRequest {
+            card_id,
+            rating: 1,
+            rating_scale: RatingScale::FourPoint,
+            answer_mode: AnswerMode::Flip,
+            typed_answer: None,
+            time_taken_ms: None,
+            cram: false,
+        };
+        let response = record_review(&repo, &review).unwrap();
+        assert!(response.requeue);
+        assert_eq!(preview.new_state.status, response.new_state.status);
+
+        assert!(preview_review(&repo, card_id + 1, Rating::Good).is_err());
+    }
+
+    #[test]
+    fn test_paused_new_cards_still_returns_due_reviews() {
+        let repo = repo_with_new_cards(3);
+        let card_id = repo.get_cards_by_deck("deck").unwrap()[0].id;
+        let due = CardState {
+            status: CardStatus::Review,
+            interval_days: 1.0,
+            reviews_count: 1,
+            due_date: Some(Utc::now() - chrono::Duration::days(1)),
+            ..CardState::default()
+        };
+        repo.save_card_state(card_id, &due).unwrap();
+
+        let paused = GlobalSettings {
+            new_cards_paused: true,
+            ..GlobalSettings::default()
+        };
+        repo.save_global_settings(&paused).unwrap();
+
+        let queue = build_study_queue(&repo, None, None, Some(10), None, None).unwrap();
+        assert!(queue.new_cards.is_empty());
+        assert_eq!(queue.new_remaining, 0);
+        assert_eq!(queue.review_cards.len(), 1);
+        assert_eq!(queue.review_cards[0].id, card_id);
+
+        repo.save_global_settings(&GlobalSettings::default()).unwrap();
+        let queue = build_study_queue(&repo, None, None, None, None, None).unwrap();
+        assert_eq!(queue.new_cards.len(), 2);
+    }
+
+    #[test]
+    fn test_queue_reports_configured_order() {
+        let repo = repo_with_new_cards(1);
+        let queue = build_study_queue(&repo, None, None, None, None, None).unwrap();
+        assert_eq!(queue.queue_order, QueueOrder::NewFirst);
+
+        let settings = GlobalSettings {
+            queue_order: QueueOrder::Interleaved,
+            ..GlobalSettings::default()
+        };
+        repo.save_global_settings(&settings).unwrap();
+        let queue = build_study_queue(&repo, None, None, None, None, None).unwrap();
+        assert_eq!(queue.queue_order, QueueOrder::Interleaved);
+    }
+
+    #[test]
+    fn test_short_typed_answers_are_not_graded() {
+        let repo = repo_with_new_cards(0);
+        let settings = GlobalSettings {
+            min_answer_chars: 3,
+            ..GlobalSettings::default()
+        };
+        repo.save_global_settings(&settings).unwrap();
+
+        let short = compare_typed(&repo, "a b", "ab", None).unwrap();
+        assert!(short.no_answer);
+        assert_eq!(short.suggested_rating, Rating::Again);
+        let long_enough = compare_typed(&repo, "abc", "abc", None).unwrap();
+        assert!(!long_enough.no_answer);
+        assert!(long_enough.is_correct);
+    }
+
+    #[test]
+    fn test_reverse_direction_lists_reversed_cards() {
+        let repo = repo_with_new_cards(1);
+        let card_id = repo.get_cards_by_deck("deck").unwrap()[0].id;
+        let queue = build_study_queue(&repo, None, None, None, None, None).unwrap();
+        assert!(queue.reversed_card_ids.is_empty());
+
+        let reverse = GlobalSettings {
+            study_direction: StudyDirection::Reverse,
+            ..GlobalSettings::default()
+        };
+        repo.save_global_settings(&reverse).unwrap();
+
+        let queue = build_study_queue(&repo, None, None, None, None, None).unwrap();
+        assert_eq!(queue.reversed_card_ids, vec![card_id]);
+
+        // A reversed card is checked against the side the session shows
+        let compare = compare_typed(&repo, "Question 0", "Question 0", Some("deck")).unwrap();
+        assert!(compare.is_correct);
+    }
+
+    #[test]
+    fn test_card_session_limit_caps_queue() {
+        let repo = repo_with_new_cards(15);
+        let due = CardState {
+            status: CardStatus::Review,
+            interval_days: 1.0,
+            reviews_count: 1,
+            due_date: Some(Utc::now() - chrono::Duration::days(1)),
+            ..CardState::default()
+        };
+        for card in repo.get_cards_by_deck("deck").unwrap() {
+            repo.save_card_state(card.id, &due).unwrap();
+        }
+        let settings = GlobalSettings {
+            session_limit: SessionLimit::Cards(10),
+            ..GlobalSettings::default()
+        };
+        repo.save_global_settings(&settings).unwrap();
+
+        let queue = build_study_queue(&repo, None, None, None, None, None).unwrap();
+        assert_eq!(queue.review_cards.len(), 10);
+        assert!(queue.new_cards.is_empty());
+        assert_eq!(queue.session_limit, SessionLimit::Cards(10));
+
+        // A per-session limit replaces the stored one; time caps are left
+        // to the client
+        let three = Some(SessionLimit::Cards(3));
+        let queue = build_study_queue(&repo, None, None, None, None, three).unwrap();
+        assert_eq!(queue.review_cards.len(), 3);
+        let timed = Some(SessionLimit::Minutes(5));
+        let queue = build_study_queue(&repo, None, None, None, None, timed).unwrap();
+        assert_eq!(queue.review_cards.len(), 15);
+        assert_eq!(queue.session_limit, SessionLimit::Minutes(5));
+    }
+
+    #[test]
+    fn test_limit_overrides_are_bounded() {
+        let repo = repo_with_new_cards(0);
+        let queue = |new, review| build_study_queue(&repo, None, None, new, review, None);
+        assert!(queue(Some(MAX_LIMIT_OVERRIDE), None).is_ok());
+        assert!(queue(Some(MAX_LIMIT_OVERRIDE + 1), None).is_err());
+        assert!(queue(None, Some(MAX_LIMIT_OVERRIDE + 1)).is_err());
+    }
+
+    #[test]
+    fn test_study_queue_filters_by_tag() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let tagged = |question: &str, tags: &[&str]| RawCard {
+            id: None,
+            question: question.to_string(),
+            answer: "Answer".to_string(),
+            line_number: 1,
+            media: Vec::new(),
+            meta: CardMeta {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                ..CardMeta::default()
+            },
+        };
+        repo.import_cards("rust", "rust.md", &[tagged("Q1", &["exam"]), tagged("Q2", &[])])
+            .unwrap();
+        repo.import_cards("go", "go.md", &[tagged("Q3", &["exam"]), tagged("Q4", &["oral"])])
+            .unwrap();
+        let due = CardState {
+            status: CardStatus::Review,
+            interval_days: 1.0,
+            reviews_count: 1,
+            due_date: Some(Utc::now() - chrono::Duration::days(1)),
+            ..CardState::default()
+        };
+        repo.save_card_state(repo.get_cards_by_deck("go").unwrap()[0].id, &due)
+            .unwrap();
+
+        let questions = |deck_path: Option<&str>, tag: &str| -> Vec<String> {
+            let queue = build_study_queue(&repo, deck_path, Some(tag), None, None, None).unwrap();
+            let cards = queue.new_cards.iter().chain(&queue.review_cards);
+            cards.map(|card| card.question_text.clone()).collect()
+        };
+        assert_eq!(questions(None, "#Exam"), ["Q1", "Q3"]);
+        assert_eq!(questions(Some("rust"), "exam"), ["Q1"]);
+        assert_eq!(questions(None, "oral"), ["Q4"]);
+        assert!(questions(None, "unknown").is_empty());
+        assert!(questions(None, " # ").is_empty());
+    }
+
+    #[test]
+    fn test_difficult_cards_limit_is_clamped() {
+        assert_eq!(difficult_cards_limit(None), DIFFICULT_CARDS_LIMIT);
+        assert_eq!(difficult_cards_limit(Some(0)), 1);
+        assert_eq!(difficult_cards_limit(Some(10)), 10);
+        assert_eq!(difficult_cards_limit(Some(10_000)), DIFFICULT_CARDS_LIMIT);
+    }
+
+    #[test]
+    fn test_suggested_rating_follows_similarity() {
+        use flashcard_core::types::MatchingMode;
+
+        let settings = GlobalSettings::default();
+        let fuzzy = |typed: &str| compare_answers(typed, "ownership", MatchingMode::Fuzzy, 0.8);
+        let suggest = |typed: &str| suggest_rating(&fuzzy(typed), RatingScale::FourPoint, &settings);
+
+        // Exact, high fuzzy, borderline and failed answers
+        assert_eq!(suggest("ownership"), Rating::Easy);
+        assert_eq!(suggest("ownershp"), Rating::Good);
+        assert_eq!(suggest("ownrshp"), Rating::Hard);
+        assert_eq!(suggest("borrowing"), Rating::Again);
+        assert_eq!(suggest(""), Rating::Again);
+
+        // Thresholds come from settings
+        let lenient = GlobalSettings {
+            suggest_easy_threshold: 0.85,
+            ..GlobalSettings::default()
+        };
+        let result = fuzzy("ownershp");
+        assert_eq!(suggest_rating(&result, RatingScale::FourPoint, &lenient), Rating::Easy);
+
+        // Only Again and Good exist on the 2-point scale
+        assert_eq!(suggest_rating(&result, RatingScale::TwoPoint, &settings), Rating::Good);
+        let borderline = fuzzy("ownrshp");
+        assert_eq!(suggest_rating(&borderline, RatingScale::TwoPoint, &settings), Rating::Again);
+    }
+}
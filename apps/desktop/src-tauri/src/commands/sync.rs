@@ -2,13 +2,22 @@
 
 use std::fs;
 use std::path::Path;
-use tauri::State;
+use tauri::{AppHandle, Emitter, Manager, State};
 use tokio::sync::Mutex;
 
-use crate::db::{CardRepository, LocalDeviceInfo, LocalSyncState, StateRepository, SyncRepository};
+use crate::db::{
+    CardRepository, DbError, FailedReview, LocalDeviceInfo, LocalSyncState, SettingsRepository,
+    SqliteRepository, StateRepository, SyncRepository,
+};
 use crate::state::AppState;
-use crate::sync::{ApiDeckSettings, ApiGlobalSettings, SyncEngine, SyncStats, SyncStatus};
-use flashcard_core::types::{Card, CardState};
+use crate::sync::scheduler::{check_auto_sync, AutoSync, AutoSyncConfig};
+use crate::file_locks::FileLocks;
+use crate::sync::{
+    ClaimedId, ConflictResolution, NewIdAssignment, OrphanInfo, PulledChanges, SyncEngine,
+    SyncError, SyncStats, SyncStatus, UpdatedFile,
+};
+use chrono::{DateTime, Utc};
+use flashcard_core::types::{CardState, ConflictPolicy};
 
 /// Command error type for sync operations.
 #[derive(Debug, serde::Serialize)]
@@ -28,15 +37,38 @@ impl CommandError {
     }
 }
 
+/// A markdown file left out of a sync or scan because it could not be read
+/// or parsed.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FileReadError {
+    pub path: String,
+    pub error: String,
+}
+
+/// A snapshot of the local sync setup, for troubleshooting.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct Diagnostics {
+    pub registered: bool,
+    pub device_id: Option<String>,
+    pub backend_reachable: bool,
+    pub last_sync_at: Option<String>,
+    pub pending_reviews: usize,
+    pub pending_files: usize,
+    pub watched_directories: Vec<String>,
+    pub schema_version: i32,
+}
+
 /// Sync engine state wrapper.
 pub struct SyncEngineState {
     engine: Mutex<Option<SyncEngine>>,
+    pub auto_sync: AutoSync,
 }
 
 impl SyncEngineState {
     pub fn new() -> Self {
         Self {
             engine: Mutex::new(None),
+            auto_sync: AutoSync::default(),
         }
     }
 }
@@ -46,18 +78,13 @@ impl SyncEngineState {
 pub async fn start_sync(
     backend_url: String,
     watched_dirs: Vec<String>,
+    app: AppHandle,
     state: State<'_, AppState>,
     sync_state: State<'_, SyncEngineState>,
 ) -> Result<SyncStatus, CommandError> {
     // Collect all MD files from watched directories (sync operation, no await)
-    let mut md_files: Vec<(String, String)> = Vec::new();
-
-    for dir_path in &watched_dirs {
-        let path = Path::new(dir_path);
-        if path.is_dir() {
-            collect_md_files(path, path, &mut md_files)?;
-        }
-    }
+    let files_read_at = Utc::now();
+    let md_files = read_sync_files(&app, &watched_dirs);
 
     // Create or get sync engine - hold lock only briefly
     let engine = {
@@ -79,71 +106,108 @@ pub async fn start_sync(
     let device_info = device_token.ok_or_else(|| CommandError::new("Not authenticated - please register device first"))?;
 
     // Run sync with the cloned engine (no MutexGuard held)
-    match engine.sync(&device_info.token, md_files, || {
+    let result = run_sync(&engine, &device_info.token, &watched_dirs, md_files, files_read_at, &state);
+    match result.await {
+        Ok(_stats) => {
+            let files = engine.take_files_to_write().await;
+            write_updated_files(&watched_dirs, &files, &state.file_locks)?;
+            Ok(engine.status().await)
+        }
+        Err(e) => {
+            Ok(SyncStatus::Failed {
+                error: e.to_string(),
+            })
+        }
+    }
+}
+
+/// Run a full sync, reading and writing local data through `state`.
+///
+/// `files_read_at` is when `md_files` were read from `watched_dirs`; cards
+/// edited before then are no longer dirty once the upload succeeds. The first
+/// sync claims the local IDs of cards without an ID line, so they are re-keyed
+/// to their assigned IDs rather than imported again.
+async fn run_sync(
+    engine: &SyncEngine,
+    token: &str,
+    watched_dirs: &[String],
+    md_files: Vec<(String, String)>,
+    files_read_at: DateTime<Utc>,
+    state: &AppState,
+) -> Result<SyncStats, SyncError> {
+    let claims = {
+        let repo = state.repository.lock().expect("repository lock");
+        let first_sync = repo.get_sync_state().is_ok_and(|s| s.last_sync_at.is_none());
+        if first_sync {
+            local_claims(&repo, watched_dirs, &md_files)
+        } else {
+            Vec::new()
+        }
+    };
+
+    let result = engine.sync(token, md_files, claims, conflict_policy(state), || {
         // Callback to get pending reviews - locks repo briefly
         let repo = state.repository.lock().expect("repository lock");
         repo.get_pending_reviews().unwrap_or_default()
-    }, |ids| {
-        // Callback to mark reviews synced - locks repo briefly
+    }, |ids, failed| {
+        // Callback to mark reviews synced and dead-letter rejected ones - locks repo briefly
         let repo = state.repository.lock().expect("repository lock");
         let _ = repo.mark_reviews_synced(ids);
+        let _ = repo.dead_letter_reviews(failed);
     }, || {
         // Callback to get sync state
         let repo = state.repository.lock().expect("repository lock");
         repo.get_sync_state().ok()
-    }, |timestamp| {
-        // Callback to update sync state
-        let repo = state.repository.lock().expect("repository lock");
-        let _ = repo.update_sync_state(timestamp);
-    }, |cards: &[Card], synced_at: &str| {
-        // Callback to apply pulled cards
+    }, |pulled: &PulledChanges| {
+        // Callback to apply the pull in one transaction
         let repo = state.repository.lock().expect("repository lock");
-        repo.upsert_cards_from_sync(cards, synced_at).unwrap_or(0)
-    }, |states: &[(i64, CardState)]| {
-        // Callback to apply pulled card states
-        let repo = state.repository.lock().expect("repository lock");
-        repo.save_card_states_synced(states).unwrap_or(0)
-    }, |global_settings: &ApiGlobalSettings| {
-        // Callback to apply global settings from cloud
+        repo.apply_pulled_changes(pulled).map_err(|e| e.to_string())
+    }, || {
+        // Callback to get local states not yet synced (for conflict detection)
         let repo = state.repository.lock().expect("repository lock");
-        let _ = repo.save_global_settings_synced(global_settings);
-    }, |deck_settings: &[ApiDeckSettings]| {
-        // Callback to apply deck settings from cloud
+        repo.get_unsynced_card_states().unwrap_or_default()
+    }, |claimed: &[ClaimedId]| {
+        // Callback to re-key claimed cards to their assigned IDs
         let repo = state.repository.lock().expect("repository lock");
-        for settings in deck_settings {
-            let _ = repo.save_deck_settings_synced(settings);
-        }
-    }).await {
-        Ok(_stats) => Ok(engine.status().await),
-        Err(e) => {
-            Ok(SyncStatus::Failed {
-                error: e.to_string(),
-            })
-        }
+        let ids: Vec<(i64, i64)> = claimed.iter().map(|c| (c.local_id, c.id)).collect();
+        repo.adopt_card_ids(&ids).map(|_| ()).map_err(|e| e.to_string())
+    }).await;
+
+    // Replaces any earlier pause, so a restart never resumes a stale sync
+    save_pending_sync(engine, state).await;
+    let stats = result?;
+
+    // A sync only succeeds after uploading every file it was given. Cards
+    // left dirty by a failure here are just uploaded again next time.
+    let repo = state.repository.lock().expect("repository lock");
+    if let Err(e) = repo.mark_cards_uploaded(&files_read_at.to_rfc3339()) {
+        eprintln!("Failed to mark cards uploaded: {}", e);
     }
+    Ok(stats)
 }
 
 /// Get current sync status.
 #[tauri::command]
 pub async fn get_sync_status(
+    state: State<'_, AppState>,
     sync_state: State<'_, SyncEngineState>,
 ) -> Result<SyncStatus, CommandError> {
-    let engine_guard = sync_state.engine.lock().await;
-
-    match engine_guard.as_ref() {
-        Some(engine) => Ok(engine.status().await),
-        None => Ok(SyncStatus::Idle),
+    match paused_engine(&state, &sync_state).await {
+        Ok(engine) => Ok(engine.status().await),
+        Err(_) => Ok(SyncStatus::Idle),
     }
 }
 
 /// Cancel ongoing sync.
 #[tauri::command]
 pub async fn cancel_sync(
+    state: State<'_, AppState>,
     sync_state: State<'_, SyncEngineState>,
 ) -> Result<(), CommandError> {
     let mut engine_guard = sync_state.engine.lock().await;
     *engine_guard = None;
-    Ok(())
+    let repo = state.repository.lock().expect("repository lock");
+    repo.save_pending_sync(None).map_err(CommandError::database)
 }
 
 /// Confirm orphan deletion.
@@ -153,14 +217,8 @@ pub async fn confirm_orphan_deletion(
     state: State<'_, AppState>,
     sync_state: State<'_, SyncEngineState>,
 ) -> Result<usize, CommandError> {
-    // Get engine clone
-    let engine = {
-        let engine_guard = sync_state.engine.lock().await;
-        engine_guard
-            .as_ref()
-            .ok_or_else(|| CommandError::new("No sync in progress"))?
-            .clone()
-    };
+    // Get engine clone, resuming a sync paused before a restart
+    let engine = paused_engine(&state, &sync_state).await?;
 
     // Get device token - hold lock briefly
     let device_info = {
@@ -176,23 +234,45 @@ pub async fn confirm_orphan_deletion(
         .await
         .map_err(|e| CommandError::new(e.to_string()))?;
 
+    // The cards are gone on the backend now; keep the pause resumable
+    save_pending_sync(&engine, &state).await;
     Ok(deleted_count)
 }
 
+/// Load the next batch of orphans for confirmation.
+#[tauri::command]
+pub async fn next_orphan_batch(
+    state: State<'_, AppState>,
+    sync_state: State<'_, SyncEngineState>,
+) -> Result<Vec<OrphanInfo>, CommandError> {
+    // Get engine clone, resuming a sync paused before a restart
+    let engine = paused_engine(&state, &sync_state).await?;
+
+    // Get device token - hold lock briefly
+    let device_info = {
+        let repo = state.repository.lock().expect("repository lock");
+        repo.get_device_token()
+            .map_err(CommandError::database)?
+            .ok_or_else(|| CommandError::new("Not authenticated"))?
+    };
+
+    let orphans = engine
+        .next_orphan_batch(&device_info.token)
+        .await
+        .map_err(|e| CommandError::new(e.to_string()))?;
+
+    save_pending_sync(&engine, &state).await;
+    Ok(orphans)
+}
+
 /// Skip orphan deletion (keep orphaned cards).
 #[tauri::command]
 pub async fn skip_orphan_deletion(
     state: State<'_, AppState>,
     sync_state: State<'_, SyncEngineState>,
 ) -> Result<SyncStats, CommandError> {
-    // Get engine clone
-    let engine = {
-        let engine_guard = sync_state.engine.lock().await;
-        engine_guard
-            .as_ref()
-            .ok_or_else(|| CommandError::new("No sync in progress"))?
-            .clone()
-    };
+    // Get engine clone, resuming a sync paused before a restart
+    let engine = paused_engine(&state, &sync_state).await?;
 
     // Get device token - hold lock briefly
     let device_info = {
@@ -203,40 +283,191 @@ pub async fn skip_orphan_deletion(
     };
 
     // Continue sync without deleting orphans
-    let stats = engine
-        .continue_sync_without_orphans(&device_info.token, || {
+    let result = engine
+        .continue_sync_without_orphans(&device_info.token, conflict_policy(&state), || {
             let repo = state.repository.lock().expect("repository lock");
             repo.get_pending_reviews().unwrap_or_default()
-        }, |ids| {
+        }, |ids, failed| {
             let repo = state.repository.lock().expect("repository lock");
             let _ = repo.mark_reviews_synced(ids);
+            let _ = repo.dead_letter_reviews(failed);
         }, || {
             let repo = state.repository.lock().expect("repository lock");
             repo.get_sync_state().ok()
-        }, |timestamp| {
+        }, |pulled: &PulledChanges| {
             let repo = state.repository.lock().expect("repository lock");
-            let _ = repo.update_sync_state(timestamp);
-        }, |cards: &[Card], synced_at: &str| {
+            repo.apply_pulled_changes(pulled).map_err(|e| e.to_string())
+        }, || {
             let repo = state.repository.lock().expect("repository lock");
-            repo.upsert_cards_from_sync(cards, synced_at).unwrap_or(0)
-        }, |states: &[(i64, CardState)]| {
+            repo.get_unsynced_card_states().unwrap_or_default()
+        })
+        .await;
+
+    // A failed attempt stays resumable
+    if result.is_ok() {
+        save_pending_sync(&engine, &state).await;
+    }
+    let stats = result.map_err(|e| CommandError::new(e.to_string()))?;
+
+    let watched_dirs = state.watcher.lock().await.get_watched_directories();
+    let files = engine.take_files_to_write().await;
+    write_updated_files(&watched_dirs, &files, &state.file_locks)?;
+    Ok(stats)
+}
+
+/// Resolve sync conflicts with the user's choices and finish the sync.
+#[tauri::command]
+pub async fn resolve_sync_conflicts(
+    choices: Vec<ConflictResolution>,
+    state: State<'_, AppState>,
+    sync_state: State<'_, SyncEngineState>,
+) -> Result<SyncStats, CommandError> {
+    let engine = {
+        let engine_guard = sync_state.engine.lock().await;
+        engine_guard
+            .as_ref()
+            .ok_or_else(|| CommandError::new("No sync in progress"))?
+            .clone()
+    };
+
+    let stats = engine
+        .resolve_conflicts(&choices, |states: &[(i64, CardState)]| {
             let repo = state.repository.lock().expect("repository lock");
             repo.save_card_states_synced(states).unwrap_or(0)
-        }, |global_settings: &ApiGlobalSettings| {
+        }, |timestamp| {
+            let repo = state.repository.lock().expect("repository lock");
+            let _ = repo.update_sync_state(timestamp);
+        })
+        .await
+        .map_err(|e| CommandError::new(e.to_string()))?;
+
+    let watched_dirs = state.watcher.lock().await.get_watched_directories();
+    let files = engine.take_files_to_write().await;
+    write_updated_files(&watched_dirs, &files, &state.file_locks)?;
+    Ok(stats)
+}
+
+/// Resolve every sync conflict by keeping the side with more progress.
+#[tauri::command]
+pub async fn auto_resolve_sync_conflicts(
+    state: State<'_, AppState>,
+    sync_state: State<'_, SyncEngineState>,
+) -> Result<SyncStats, CommandError> {
+    let engine = {
+        let engine_guard = sync_state.engine.lock().await;
+        engine_guard
+            .as_ref()
+            .ok_or_else(|| CommandError::new("No sync in progress"))?
+            .clone()
+    };
+
+    let stats = engine
+        .auto_resolve_conflicts(|states: &[(i64, CardState)]| {
             let repo = state.repository.lock().expect("repository lock");
-            let _ = repo.save_global_settings_synced(global_settings);
-        }, |deck_settings: &[ApiDeckSettings]| {
+            repo.save_card_states_synced(states).unwrap_or(0)
+        }, |timestamp| {
             let repo = state.repository.lock().expect("repository lock");
-            for settings in deck_settings {
-                let _ = repo.save_deck_settings_synced(settings);
-            }
+            let _ = repo.update_sync_state(timestamp);
         })
         .await
         .map_err(|e| CommandError::new(e.to_string()))?;
 
+    let watched_dirs = state.watcher.lock().await.get_watched_directories();
+    let files = engine.take_files_to_write().await;
+    write_updated_files(&watched_dirs, &files, &state.file_locks)?;
     Ok(stats)
 }
 
+/// Get reviews the backend permanently rejected.
+#[tauri::command]
+pub async fn get_failed_reviews(
+    state: State<'_, AppState>,
+) -> Result<Vec<FailedReview>, CommandError> {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.get_failed_reviews().map_err(CommandError::database)
+}
+
+/// Get the auto-sync configuration.
+#[tauri::command]
+pub async fn get_auto_sync(
+    sync_state: State<'_, SyncEngineState>,
+) -> Result<AutoSyncConfig, CommandError> {
+    Ok(sync_state.auto_sync.config())
+}
+
+/// Update and save the auto-sync configuration; the interval is clamped to
+/// its allowed range.
+#[tauri::command]
+pub async fn set_auto_sync(
+    config: AutoSyncConfig,
+    state: State<'_, AppState>,
+    sync_state: State<'_, SyncEngineState>,
+) -> Result<AutoSyncConfig, CommandError> {
+    let config = sync_state.auto_sync.set_config(config);
+    let repo = state.repository.lock().expect("repository lock");
+    repo.save_auto_sync_config(&config).map_err(CommandError::database)?;
+    Ok(config)
+}
+
+/// Background loop that syncs on the auto-sync interval and on window focus.
+///
+/// Runs for the lifetime of the app. Each wake-up is skipped when auto-sync is
+/// disabled or paused, no backend is known yet, the device is unregistered, or
+/// a sync is already running or waiting on the user.
+pub async fn run_auto_sync(app: AppHandle) {
+    let sync_state = app.state::<SyncEngineState>();
+    let state = app.state::<AppState>();
+    let auto_sync = &sync_state.auto_sync;
+
+    loop {
+        let Some(trigger) = auto_sync.wait().await else {
+            continue;
+        };
+
+        let engine = sync_state.engine.lock().await.clone();
+        let status = match &engine {
+            Some(engine) => Some(engine.status().await),
+            None => None,
+        };
+        let device_info = {
+            let repo = state.repository.lock().expect("repository lock");
+            repo.get_device_token().ok().flatten()
+        };
+
+        if check_auto_sync(
+            &auto_sync.config(),
+            trigger,
+            status.as_ref(),
+            device_info.is_some(),
+            auto_sync.since_last_run(),
+        )
+        .is_err()
+        {
+            continue;
+        }
+        let (Some(engine), Some(device_info)) = (engine, device_info) else {
+            continue;
+        };
+
+        let watched_dirs = state.watcher.lock().await.get_watched_directories();
+        let files_read_at = Utc::now();
+        let md_files = read_sync_files(&app, &watched_dirs);
+
+        auto_sync.mark_run();
+        match run_sync(&engine, &device_info.token, &watched_dirs, md_files, files_read_at, &state)
+            .await
+        {
+            Ok(_) => {
+                let files = engine.take_files_to_write().await;
+                if let Err(e) = write_updated_files(&watched_dirs, &files, &state.file_locks) {
+                    eprintln!("Auto-sync failed: {}", e.message);
+                }
+            }
+            Err(e) => eprintln!("Auto-sync failed: {}", e),
+        }
+    }
+}
+
 /// Register device with backend.
 #[tauri::command]
 pub async fn register_device(
@@ -307,36 +538,264 @@ pub async fn get_local_sync_state(
         .map_err(|e| CommandError::database(e))
 }
 
+/// Report device registration, backend reachability and pending sync work.
+///
+/// An unreachable backend is reported as `backend_reachable: false` rather
+/// than as an error, so the rest of the report is still shown.
+#[tauri::command]
+pub async fn diagnostics(
+    backend_url: String,
+    state: State<'_, AppState>,
+) -> Result<Diagnostics, CommandError> {
+    let watched_directories = state.watcher.lock().await.get_watched_directories();
+    let mut report = {
+        let repo = state.repository.lock().expect("repository lock");
+        local_diagnostics(&repo, watched_directories).map_err(|e| CommandError::database(e))?
+    };
+    report.backend_reachable = SyncEngine::new(backend_url)
+        .check_connectivity()
+        .await
+        .unwrap_or(false);
+    Ok(report)
+}
+
 // === Helper functions ===
 
+/// The engine of the current sync, or one resumed from a sync saved while
+/// paused for orphan confirmation, e.g. before the app restarted.
+async fn paused_engine(
+    state: &AppState,
+    sync_state: &SyncEngineState,
+) -> Result<SyncEngine, CommandError> {
+    let mut engine_guard = sync_state.engine.lock().await;
+    if engine_guard.is_none() {
+        let repo = state.repository.lock().expect("repository lock");
+        let pending = repo.get_pending_sync().map_err(CommandError::database)?;
+        *engine_guard = pending.map(SyncEngine::resume);
+    }
+    engine_guard
+        .clone()
+        .ok_or_else(|| CommandError::new("No sync in progress"))
+}
+
+/// Save the engine's sync if it is paused for orphan confirmation, or clear
+/// the saved one otherwise.
+async fn save_pending_sync(engine: &SyncEngine, state: &AppState) {
+    let pending = engine.pending_sync().await;
+    let repo = state.repository.lock().expect("repository lock");
+    if let Err(e) = repo.save_pending_sync(pending.as_ref()) {
+        eprintln!("Failed to save paused sync: {}", e);
+    }
+}
+
+/// Policy for settling sync conflicts, or the default if settings can't be
+/// read.
+fn conflict_policy(state: &AppState) -> ConflictPolicy {
+    let repo = state.repository.lock().expect("repository lock");
+    repo.get_global_settings()
+        .map(|settings| settings.sync_conflict_policy)
+        .unwrap_or_default()
+}
+
+/// The parts of [`Diagnostics`] that come from the local database.
+fn local_diagnostics(
+    repo: &SqliteRepository,
+    watched_directories: Vec<String>,
+) -> Result<Diagnostics, DbError> {
+    let device = repo.get_device_token()?;
+    let sync_state = repo.get_sync_state()?;
+    Ok(Diagnostics {
+        registered: device.is_some(),
+        device_id: device.and_then(|d| d.device_id),
+        backend_reachable: false,
+        last_sync_at: sync_state.last_sync_at,
+        pending_reviews: repo.get_pending_reviews()?.len(),
+        pending_files: repo.get_pending_files()?.len(),
+        watched_directories,
+        schema_version: repo.schema_version()?,
+    })
+}
+
+/// Read the .md files of every watched directory for a sync.
+///
+/// Unreadable files are left out and reported in a `sync-file-errors` event.
+fn read_sync_files(app: &AppHandle, watched_dirs: &[String]) -> Vec<(String, String)> {
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    for path in watched_dirs.iter().map(Path::new).filter(|p| p.is_dir()) {
+        collect_md_files(path, path, &mut files, &mut errors);
+    }
+    if !errors.is_empty() {
+        let _ = app.emit("sync-file-errors", &errors);
+    }
+    files
+}
+
+/// Claims for the local IDs of cards without an ID line in `md_files`, found
+/// by the file each was imported from.
+fn local_claims(
+    repo: &SqliteRepository,
+    watched_dirs: &[String],
+    md_files: &[(String, String)],
+) -> Vec<NewIdAssignment> {
+    let mut claims = Vec::new();
+    for (path, content) in md_files {
+        let Some(source) = watched_dirs
+            .iter()
+            .map(|dir| Path::new(dir).join(path))
+            .find(|source| source.is_file())
+        else {
+            continue;
+        };
+        let Ok(cards) = flashcard_core::parse(content) else {
+            continue;
+        };
+        let ids = repo
+            .local_card_ids(&source.to_string_lossy(), &cards)
+            .unwrap_or_default();
+        claims.extend(ids.into_iter().map(|(line, id)| NewIdAssignment {
+            path: path.clone(),
+            line,
+            id,
+        }));
+    }
+    claims
+}
+
+/// Write the files a completed sync rewrote back to disk.
+///
+/// Each file goes into the watched directory it was read from, under its
+/// lock in `file_locks` so the watcher doesn't import it half-written.
+/// Files no longer in any watched directory are skipped rather than
+/// recreated.
+fn write_updated_files(
+    watched_dirs: &[String],
+    files: &[UpdatedFile],
+    file_locks: &FileLocks,
+) -> Result<(), CommandError> {
+    for file in files {
+        let target = watched_dirs
+            .iter()
+            .map(|dir| Path::new(dir).join(&file.path))
+            .find(|path| path.is_file());
+        if let Some(path) = target {
+            file_locks
+                .with_lock(&path, || fs::write(&path, &file.content))
+                .map_err(|e| CommandError::new(format!("Failed to write file: {}", e)))?;
+        }
+    }
+    Ok(())
+}
+
 /// Collect all .md files from a directory recursively.
-fn collect_md_files(
+///
+/// Files that can't be read, such as non-UTF-8 exports, are skipped and
+/// recorded in `errors` so they don't fail the whole sync. They are not
+/// decoded lossily: a sync may rewrite the file with assigned IDs, which
+/// would replace the original bytes.
+pub(crate) fn collect_md_files(
     base_path: &Path,
     current_path: &Path,
     files: &mut Vec<(String, String)>,
-) -> Result<(), CommandError> {
-    if current_path.is_dir() {
-        for entry in fs::read_dir(current_path)
-            .map_err(|e| CommandError::new(format!("Failed to read directory: {}", e)))?
-        {
-            let entry = entry.map_err(|e| CommandError::new(e.to_string()))?;
-            let path = entry.path();
-
-            if path.is_dir() {
-                collect_md_files(base_path, &path, files)?;
-            } else if path.extension().map(|e| e == "md").unwrap_or(false) {
-                let relative_path = path
-                    .strip_prefix(base_path)
-                    .map_err(|e| CommandError::new(e.to_string()))?
-                    .to_string_lossy()
-                    .to_string();
-
-                let content = fs::read_to_string(&path)
-                    .map_err(|e| CommandError::new(format!("Failed to read file: {}", e)))?;
-
-                files.push((relative_path, content));
+    errors: &mut Vec<FileReadError>,
+) {
+    let relative = |path: &Path| {
+        path.strip_prefix(base_path)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .to_string()
+    };
+    let entries = match fs::read_dir(current_path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            errors.push(FileReadError {
+                path: relative(current_path),
+                error: format!("Failed to read directory: {}", e),
+            });
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.is_dir() {
+            collect_md_files(base_path, &path, files, errors);
+        } else if path.extension().map(|e| e == "md").unwrap_or(false) {
+            match fs::read_to_string(&path) {
+                Ok(content) => files.push((relative(&path), content)),
+                Err(e) => errors.push(FileReadError {
+                    path: relative(&path),
+                    error: format!("Failed to read file: {}", e),
+                }),
             }
         }
     }
-    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unreadable_file_does_not_block_others() {
+        let dir = std::env::temp_dir().join(format!("flashcards-sync-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("rust")).unwrap();
+        fs::write(dir.join("rust/good.md"), "Q: Question\nA: Answer\n").unwrap();
+        // Latin-1 "café", which is not valid UTF-8
+        fs::write(dir.join("rust/latin1.md"), b"Q: caf\xe9\nA: Answer\n").unwrap();
+
+        let mut files = Vec::new();
+        let mut errors = Vec::new();
+        collect_md_files(&dir, &dir, &mut files, &mut errors);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let good = Path::new("rust").join("good.md").to_string_lossy().to_string();
+        let bad = Path::new("rust").join("latin1.md").to_string_lossy().to_string();
+        assert_eq!(files, vec![(good, "Q: Question\nA: Answer\n".to_string())]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, bad);
+        assert!(errors[0].error.contains("UTF-8"));
+    }
+
+    #[test]
+    fn test_updated_files_are_written_into_their_watched_directory() {
+        let dir = std::env::temp_dir().join(format!("flashcards-sync-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("rust")).unwrap();
+        fs::write(dir.join("rust/basics.md"), "Q: Question\nA: Answer\n").unwrap();
+        let watched_dirs = vec![dir.to_string_lossy().to_string()];
+
+        let files = vec![
+            UpdatedFile {
+                path: "rust/basics.md".to_string(),
+                content: "ID: 42\nQ: Question\nA: Answer\n".to_string(),
+            },
+            UpdatedFile {
+                path: "rust/removed.md".to_string(),
+                content: "ID: 43\nQ: Gone\nA: Gone\n".to_string(),
+            },
+        ];
+        write_updated_files(&watched_dirs, &files, &FileLocks::new()).unwrap();
+
+        let written = fs::read_to_string(dir.join("rust/basics.md")).unwrap();
+        let removed_exists = dir.join("rust/removed.md").exists();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(written, "ID: 42\nQ: Question\nA: Answer\n");
+        assert!(!removed_exists);
+    }
+
+    #[test]
+    fn test_diagnostics_for_unregistered_device() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let report = local_diagnostics(&repo, vec!["/notes".to_string()]).unwrap();
+
+        assert!(!report.registered);
+        assert_eq!(report.device_id, None);
+        assert_eq!(report.last_sync_at, None);
+        assert_eq!(report.pending_reviews, 0);
+        assert_eq!(report.pending_files, 0);
+        assert_eq!(report.watched_directories, vec!["/notes".to_string()]);
+        assert_eq!(report.schema_version, crate::db::schema::SCHEMA_VERSION);
+    }
 }
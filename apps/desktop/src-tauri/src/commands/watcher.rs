@@ -25,7 +25,11 @@ pub async fn start_watching(
 
     // Initialize watcher if not already started
     if !watcher.is_started() {
-        watcher.start(app_handle, state.repository.clone())?;
+        watcher.start(
+            app_handle,
+            state.repository.clone(),
+            state.file_locks.clone(),
+        )?;
     }
 
     watcher.watch(path)
@@ -1,6 +1,14 @@
 //! Date utilities for daily reset hour handling.
+//!
+//! Thin wrappers over [`DueCalculator`] in the machine's local timezone, so
+//! the desktop and backend agree on what "today" means.
 
-use chrono::{Duration, Local, NaiveDate, Timelike};
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use flashcard_core::DueCalculator;
+
+fn calculator(daily_reset_hour: u32) -> DueCalculator<Local> {
+    DueCalculator::new(daily_reset_hour, Local)
+}
 
 /// Get adjusted "today" based on daily_reset_hour.
 ///
@@ -14,15 +22,7 @@ use chrono::{Duration, Local, NaiveDate, Timelike};
 /// # Returns
 /// The adjusted date as a NaiveDate
 pub fn get_adjusted_today(daily_reset_hour: u32) -> NaiveDate {
-    let now = Local::now();
-    let current_hour = now.hour();
-
-    if current_hour < daily_reset_hour {
-        // Before reset hour, consider it still "yesterday"
-        (now - Duration::days(1)).date_naive()
-    } else {
-        now.date_naive()
-    }
+    calculator(daily_reset_hour).study_day(Utc::now())
 }
 
 /// Format adjusted today as YYYY-MM-DD string for SQL queries.
@@ -32,6 +32,33 @@ pub fn get_adjusted_today_string(daily_reset_hour: u32) -> String {
         .to_string()
 }
 
+/// Start of the current study day (the last reset hour), in UTC.
+///
+/// Reviews submitted after this instant count towards today's limits.
+pub fn get_study_day_start(daily_reset_hour: u32) -> DateTime<Utc> {
+    let calc = calculator(daily_reset_hour);
+    calc.day_bounds(calc.study_day(Utc::now())).0
+}
+
+/// End of the current study day (the next reset hour), in UTC.
+///
+/// Cards due before this instant belong to today's session, including
+/// learning cards scheduled minutes from now.
+pub fn get_study_day_end(daily_reset_hour: u32) -> DateTime<Utc> {
+    let calc = calculator(daily_reset_hour);
+    calc.day_bounds(calc.study_day(Utc::now())).1
+}
+
+/// Latest due time shown in today's session, in UTC.
+///
+/// Normally the end of the study day; a grace window extends it to
+/// `due_grace_hours` from now when that is later, so evening study can
+/// include cards falling due overnight.
+pub fn get_due_cutoff(daily_reset_hour: u32, due_grace_hours: u32) -> DateTime<Utc> {
+    let grace_end = Utc::now() + Duration::hours(i64::from(due_grace_hours));
+    get_study_day_end(daily_reset_hour).max(grace_end)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -52,4 +79,29 @@ mod tests {
         assert_eq!(&result[4..5], "-");
         assert_eq!(&result[7..8], "-");
     }
+
+    #[test]
+    fn test_study_day_end_is_within_next_day() {
+        let end = get_study_day_end(0);
+        let now = Utc::now();
+        assert!(end > now);
+        assert!(end <= now + Duration::days(1));
+    }
+
+    #[test]
+    fn test_due_cutoff_extends_past_day_end() {
+        assert_eq!(get_due_cutoff(0, 0), get_study_day_end(0));
+
+        let cutoff = get_due_cutoff(0, 48);
+        assert!(cutoff > get_study_day_end(0));
+        assert!(cutoff > Utc::now() + Duration::hours(47));
+    }
+
+    #[test]
+    fn test_study_day_start_is_before_now() {
+        let start = get_study_day_start(4);
+        let now = Utc::now();
+        assert!(start <= now);
+        assert!(start > now - Duration::days(1));
+    }
 }
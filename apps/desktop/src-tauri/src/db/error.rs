@@ -12,4 +12,7 @@ pub enum DbError {
 
     #[error("invalid data: {0}")]
     InvalidData(String),
+
+    #[error("database schema version {found} is newer than supported version {supported}")]
+    SchemaTooNew { found: i32, supported: i32 },
 }
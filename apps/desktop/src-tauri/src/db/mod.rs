@@ -7,7 +7,9 @@ pub mod schema;
 
 pub use error::DbError;
 pub use repository::{
-    CalendarData, CardRepository, DeckRepository, DeckStats, LocalDeviceInfo, LocalSyncState,
-    MdFileInfo, PendingReview, SettingsRepository, SqliteRepository, StateRepository,
-    StatsRepository, StudyStats, SyncRepository,
+    CalendarData, CardChange, CardRepository, CollectionStats, DateRange, DeckRepository,
+    DeckStats, FailedReview, LocalDeviceInfo, LocalSyncState, MdFileInfo, PendingReview,
+    ProgressReset, ReviewFilters, ReviewPage, SettingsRepository, SlowCard, SqliteRepository,
+    StateRepository, StatsRepository, StudyStats, SyncRepository, TimedCardState,
+    WorkloadProjection,
 };
@@ -1,44 +1,200 @@
 //! Repository pattern for database access.
 
-use crate::db::date_utils::{get_adjusted_today, get_adjusted_today_string};
+use crate::db::date_utils::{
+    get_adjusted_today, get_adjusted_today_string, get_due_cutoff, get_study_day_end,
+};
 use crate::db::error::DbError;
 use chrono::{DateTime, NaiveDate, Utc};
+use flashcard_core::algorithm::fsrs::Fsrs;
+use flashcard_core::matching::{normalize_whitespace, normalized_similarity};
 use flashcard_core::types::{
-    Algorithm, Card, CardState, CardStatus, Deck, DeckSettings, EffectiveSettings, GlobalSettings,
-    MatchingMode, RatingScale, RawCard, StudyQueue,
+    Algorithm, Card, CardMeta, CardState, CardStatus, Deck, DeckSettings, DeckSummaries,
+    DeckSummary, EffectiveSettings, GlobalSettings, Limit, MatchingMode, NormRule, RatingScale,
+    RawCard, StudyQueue,
 };
 use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::HashMap;
 use std::path::Path;
 
 type Result<T> = std::result::Result<T, DbError>;
 
+/// Upsert a locally parsed card. `updated_at` only moves when the content
+/// changes, so re-importing an unchanged file keeps the card's age. A content
+/// change marks the card dirty until the file is uploaded.
+const UPSERT_LOCAL_CARD: &str =
+    "INSERT INTO cards (id, deck_path, question_text, answer_text, source_file, updated_at, dirty)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1)
+     ON CONFLICT(id) DO UPDATE SET
+        updated_at = CASE
+            WHEN cards.deck_path IS excluded.deck_path
+                AND cards.question_text IS excluded.question_text
+                AND cards.answer_text IS excluded.answer_text
+                AND cards.source_file IS excluded.source_file
+            THEN COALESCE(cards.updated_at, excluded.updated_at)
+            ELSE excluded.updated_at
+        END,
+        dirty = CASE
+            WHEN cards.deck_path IS excluded.deck_path
+                AND cards.question_text IS excluded.question_text
+                AND cards.answer_text IS excluded.answer_text
+                AND cards.source_file IS excluded.source_file
+            THEN cards.dirty
+            ELSE 1
+        END,
+        deck_path = excluded.deck_path,
+        question_text = excluded.question_text,
+        answer_text = excluded.answer_text,
+        source_file = excluded.source_file,
+        deleted_at = NULL,
+        synced_at = NULL";
+
+/// Apply a card pulled from the backend unless the local copy is dirty or
+/// newer.
+const UPSERT_SYNCED_CARD: &str =
+    "INSERT INTO cards (id, deck_path, question_text, answer_text, source_file, deleted_at, synced_at, updated_at)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+     ON CONFLICT(id) DO UPDATE SET
+        deck_path = excluded.deck_path,
+        question_text = excluded.question_text,
+        answer_text = excluded.answer_text,
+        source_file = excluded.source_file,
+        deleted_at = excluded.deleted_at,
+        synced_at = excluded.synced_at,
+        updated_at = excluded.updated_at
+     WHERE cards.dirty = 0
+        AND (cards.updated_at IS NULL
+            OR excluded.updated_at IS NULL
+            OR julianday(cards.updated_at) <= julianday(excluded.updated_at))";
+
+/// Write a card's scheduling state. Local-only columns such as `suspended`
+/// and `new_priority` are left untouched.
+const UPSERT_CARD_STATE: &str =
+    "INSERT INTO card_states (card_id, status, interval_days, ease_factor, due_date, stability, difficulty, lapses, reviews_count, synced, learning_reps, updated_at)
+     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+     ON CONFLICT(card_id) DO UPDATE SET
+        status = excluded.status,
+        interval_days = excluded.interval_days,
+        ease_factor = excluded.ease_factor,
+        due_date = excluded.due_date,
+        stability = excluded.stability,
+        difficulty = excluded.difficulty,
+        lapses = excluded.lapses,
+        reviews_count = excluded.reviews_count,
+        synced = excluded.synced,
+        learning_reps = excluded.learning_reps,
+        updated_at = excluded.updated_at";
+
+fn parse_timestamp(value: Option<String>) -> Option<DateTime<Utc>> {
+    value.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc)))
+}
+
+/// Read a stored normalization rule spec. A spec this version can't read
+/// applies no rules rather than failing every settings lookup.
+fn parse_norm_rules(spec: Option<String>) -> Vec<NormRule> {
+    spec.and_then(|spec| NormRule::parse_spec(&spec).ok())
+        .unwrap_or_default()
+}
+
+/// Normalization rules as a stored spec, `NULL` when there are none.
+fn format_norm_rules(rules: &[NormRule]) -> Option<String> {
+    (!rules.is_empty()).then(|| NormRule::format_spec(rules))
+}
+
+/// How applying a pulled card changed the local database.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardChange {
+    /// The card is new here, or was deleted locally and is back.
+    Created,
+    Updated,
+    Deleted,
+}
+
 /// Repository for card operations.
 pub trait CardRepository {
     fn get_card(&self, id: i64) -> Result<Option<Card>>;
     fn get_cards_by_deck(&self, deck_path: &str) -> Result<Vec<Card>>;
+    /// Live cards with `tag` across all decks. The tag is normalized first.
+    fn get_cards_by_tag(&self, tag: &str) -> Result<Vec<Card>>;
+    /// Live cards whose question is at least `threshold` similar to
+    /// `question`, most similar first. Case and whitespace are ignored.
+    fn find_similar_cards(&self, question: &str, threshold: f64) -> Result<Vec<(Card, f64)>>;
     fn upsert_cards(&self, cards: &[Card]) -> Result<()>;
-    fn upsert_cards_from_sync(&self, cards: &[Card], synced_at: &str) -> Result<usize>;
+    /// Apply pulled cards, returning each visible change by card ID.
+    fn upsert_cards_from_sync(
+        &self,
+        cards: &[Card],
+        synced_at: &str,
+    ) -> Result<Vec<(i64, CardChange)>>;
+    /// Clear the dirty flag of cards last edited at or before `edited_before`,
+    /// once their files have been uploaded.
+    fn mark_cards_uploaded(&self, edited_before: &str) -> Result<usize>;
     fn delete_cards(&self, ids: &[i64]) -> Result<()>;
-    fn get_new_cards(&self, deck_path: Option<&str>, limit: usize) -> Result<Vec<Card>>;
+    /// New cards in the order they are introduced: highest
+    /// [`new_priority`](StateRepository::set_new_priority) first, then by ID.
+    /// With a `tag`, only cards carrying it (once normalized) are returned.
+    fn get_new_cards(
+        &self,
+        deck_path: Option<&str>,
+        tag: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<Card>>;
+    /// Cards due today. `limit` caps review-stage cards only; learning and
+    /// relearning cards are always returned so they can finish today. `tag`
+    /// filters as in [`get_new_cards`](Self::get_new_cards).
     fn get_due_cards(
         &self,
         deck_path: Option<&str>,
-        limit: usize,
+        tag: Option<&str>,
+        limit: i64,
         daily_reset_hour: u32,
+        due_grace_hours: u32,
     ) -> Result<Vec<Card>>;
+    /// Studied cards ranked hardest first, whether due or not: most lapses,
+    /// then lowest stability (or interval, for SM-2 cards), then lowest ease.
+    /// Suspended cards are left out.
+    fn get_difficult_cards(&self, deck_path: Option<&str>, limit: usize) -> Result<Vec<Card>>;
+}
+
+/// Card state with the time it last changed, for last-write-wins conflict
+/// resolution.
+#[derive(Debug, Clone)]
+pub struct TimedCardState {
+    pub card_id: i64,
+    pub state: CardState,
+    /// `None` for states saved before change times were recorded.
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 /// Repository for card state operations.
 pub trait StateRepository {
     fn get_card_state(&self, card_id: i64) -> Result<Option<CardState>>;
+    fn get_unsynced_card_states(&self) -> Result<Vec<TimedCardState>>;
+    /// Save a card's state from a local review. Fails with
+    /// [`DbError::InvalidData`] for a non-New state without a due date, which
+    /// would otherwise drop the card out of the due queue for good.
     fn save_card_state(&self, card_id: i64, state: &CardState) -> Result<()>;
     fn save_card_states_synced(&self, states: &[(i64, CardState)]) -> Result<usize>;
+    /// Set every card in a deck back to New, clearing its learning state.
+    /// With `archive_reviews`, the deck's reviews also move out of the
+    /// history into `archived_reviews`; they are never deleted.
+    fn reset_deck_progress(&self, deck_path: &str, archive_reviews: bool) -> Result<ProgressReset>;
+    /// IDs of studied cards with no due date. They never show up as due,
+    /// so any found here were saved by a scheduling bug.
+    fn get_stuck_card_ids(&self) -> Result<Vec<i64>>;
+    /// Move a new card up (positive) or down (negative) the new card queue;
+    /// 0 is the default. Local to this device.
+    fn set_new_priority(&self, card_id: i64, priority: i32) -> Result<()>;
 }
 
 /// Repository for deck operations.
 pub trait DeckRepository {
-    fn get_all_decks(&self, daily_reset_hour: u32) -> Result<Vec<Deck>>;
+    fn get_all_decks(&self, daily_reset_hour: u32, include_archived: bool) -> Result<Vec<Deck>>;
     fn get_deck(&self, path: &str, daily_reset_hour: u32) -> Result<Option<Deck>>;
+    fn get_deck_summaries(
+        &self,
+        daily_reset_hour: u32,
+        include_archived: bool,
+    ) -> Result<DeckSummaries>;
 }
 
 /// Repository for settings operations.
@@ -46,8 +202,12 @@ pub trait SettingsRepository {
     fn get_global_settings(&self) -> Result<GlobalSettings>;
     fn save_global_settings(&self, settings: &GlobalSettings) -> Result<()>;
     fn get_deck_settings(&self, deck_path: &str) -> Result<Option<DeckSettings>>;
+    /// All deck settings, shortest path first so ancestors come before
+    /// their descendants, as [`DeckSettings::cascade`] expects.
+    fn get_all_deck_settings(&self) -> Result<Vec<DeckSettings>>;
     fn save_deck_settings(&self, settings: &DeckSettings) -> Result<()>;
     fn delete_deck_settings(&self, deck_path: &str) -> Result<()>;
+    fn set_deck_archived(&self, deck_path: &str, archived: bool) -> Result<()>;
     fn get_effective_settings(&self, deck_path: Option<&str>) -> Result<EffectiveSettings>;
 }
 
@@ -63,7 +223,7 @@ pub struct DeckStats {
 }
 
 /// Overall study statistics.
-#[derive(Debug, Clone, serde::Serialize)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct StudyStats {
     pub reviews_today: usize,
     pub new_today: usize,
@@ -72,6 +232,13 @@ pub struct StudyStats {
     pub total_reviews: usize,
 }
 
+/// Inclusive range of days, as `YYYY-MM-DD` dates, for stats queries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
 /// Calendar data point.
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct CalendarData {
@@ -79,6 +246,42 @@ pub struct CalendarData {
     pub reviews: usize,
 }
 
+/// Reviews with a recorded response time a card needs before it is ranked
+/// by [`StatsRepository::get_slowest_cards`].
+pub const SLOW_CARD_MIN_REVIEWS: usize = 3;
+
+/// Card ranked by average response time.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SlowCard {
+    pub card_id: i64,
+    pub deck_path: String,
+    pub question: String,
+    pub average_time_ms: f64,
+    pub review_count: usize,
+}
+
+/// Daily review load at the current and at a proposed retention.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct WorkloadProjection {
+    /// Cards with an FSRS stability, the only ones the projection covers.
+    pub cards: usize,
+    /// The deck's effective retention, or the default over all decks.
+    pub current_retention: f64,
+    pub new_retention: f64,
+    /// Reviews per day once every card is on its interval at its own deck's
+    /// retention.
+    pub current_daily_reviews: f64,
+    /// Reviews per day once every card is on its interval at `new_retention`.
+    pub projected_daily_reviews: f64,
+}
+
+/// What a deck progress reset changed.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ProgressReset {
+    pub cards_reset: usize,
+    pub reviews_archived: usize,
+}
+
 /// Pending review record for sync.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct PendingReview {
@@ -96,6 +299,75 @@ pub struct PendingReview {
     pub ease_before: f64,
     pub ease_after: f64,
     pub algorithm: String,
+    pub params_hash: Option<String>,
+    /// Card status before the review; `None` for reviews logged before it
+    /// was recorded.
+    pub status_before: Option<String>,
+    /// A cram review, which left the card's schedule alone.
+    #[serde(default)]
+    pub cram: bool,
+}
+
+/// Largest page returned by [`StatsRepository::get_reviews`].
+pub const REVIEW_PAGE_SIZE: usize = 100;
+
+/// Filters for the review log; every filter is optional.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ReviewFilters {
+    pub card_id: Option<i64>,
+    /// Reviews at or after this time.
+    pub from: Option<DateTime<Utc>>,
+    /// Reviews before this time.
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default = "default_review_page_size")]
+    pub limit: usize,
+}
+
+fn default_review_page_size() -> usize {
+    REVIEW_PAGE_SIZE
+}
+
+impl Default for ReviewFilters {
+    fn default() -> Self {
+        Self {
+            card_id: None,
+            from: None,
+            to: None,
+            offset: 0,
+            limit: REVIEW_PAGE_SIZE,
+        }
+    }
+}
+
+/// A page of the review log, newest first.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ReviewPage {
+    pub reviews: Vec<PendingReview>,
+    /// More reviews follow this page.
+    pub has_more: bool,
+}
+
+/// Size of the whole local collection.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CollectionStats {
+    pub total_cards: usize,
+    pub total_decks: usize,
+    pub total_reviews: usize,
+    pub database_size_bytes: u64,
+    /// Objects the backend stores for this device, when registered and
+    /// reachable.
+    pub remote_file_count: Option<usize>,
+}
+
+/// Review moved out of the sync queue after the backend rejected it.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct FailedReview {
+    #[serde(flatten)]
+    pub review: PendingReview,
+    pub reason: String,
+    pub failed_at: String,
 }
 
 /// MD file sync info.
@@ -124,6 +396,8 @@ pub struct LocalDeviceInfo {
 pub trait SyncRepository {
     fn get_pending_reviews(&self) -> Result<Vec<PendingReview>>;
     fn mark_reviews_synced(&self, ids: &[i64]) -> Result<()>;
+    fn dead_letter_reviews(&self, failed: &[(i64, String)]) -> Result<()>;
+    fn get_failed_reviews(&self) -> Result<Vec<FailedReview>>;
     fn insert_pending_review(&self, review: &PendingReview) -> Result<i64>;
     fn get_pending_files(&self) -> Result<Vec<MdFileInfo>>;
     fn update_file_hash(&self, path: &str, hash: &str, last_modified: &str) -> Result<()>;
@@ -131,6 +405,10 @@ pub trait SyncRepository {
     fn clear_pending_upload(&self, path: &str) -> Result<()>;
     fn get_sync_state(&self) -> Result<LocalSyncState>;
     fn update_sync_state(&self, last_sync_at: &str) -> Result<()>;
+    /// The sync left paused for orphan confirmation, if any.
+    fn get_pending_sync(&self) -> Result<Option<PendingSync>>;
+    /// Save the paused sync, or clear it with `None`.
+    fn save_pending_sync(&self, pending: Option<&PendingSync>) -> Result<()>;
     fn increment_pending_changes(&self) -> Result<()>;
     fn reset_pending_changes(&self) -> Result<()>;
     fn get_device_token(&self) -> Result<Option<LocalDeviceInfo>>;
@@ -140,8 +418,48 @@ pub trait SyncRepository {
 /// Repository for statistics operations.
 pub trait StatsRepository {
     fn get_deck_stats(&self, deck_path: Option<&str>) -> Result<DeckStats>;
-    fn get_study_stats(&self, daily_reset_hour: u32) -> Result<StudyStats>;
+    /// Study stats for today, with totals and retention over all time. With
+    /// a `range`, the "today" counts, totals and retention all cover that
+    /// range instead; the streak always ends today.
+    ///
+    /// Without a `range`, stats saved by [`refresh_stats_cache`] earlier in
+    /// the same study day are returned as long as no review was added or
+    /// removed since.
+    ///
+    /// [`refresh_stats_cache`]: StatsRepository::refresh_stats_cache
+    fn get_study_stats(
+        &self,
+        daily_reset_hour: u32,
+        range: Option<DateRange>,
+    ) -> Result<StudyStats>;
+    /// Compute today's study stats and cache them for [`get_study_stats`].
+    ///
+    /// [`get_study_stats`]: StatsRepository::get_study_stats
+    fn refresh_stats_cache(&self, daily_reset_hour: u32) -> Result<StudyStats>;
     fn get_calendar_data(&self, days: usize, daily_reset_hour: u32) -> Result<Vec<CalendarData>>;
+    /// Review submissions since `since` (RFC 3339), including repeats of
+    /// learning cards. A review of a card that was still new introduces it
+    /// and counts against the new card limit instead.
+    fn count_reviews_since(&self, deck_path: Option<&str>, since: &str) -> Result<usize>;
+    /// Cards with the highest average `time_taken_ms`, slowest first. Cards
+    /// with fewer than [`SLOW_CARD_MIN_REVIEWS`] timed reviews are skipped.
+    fn get_slowest_cards(&self, deck_path: Option<&str>, limit: usize) -> Result<Vec<SlowCard>>;
+    /// Reviews per rating as `[again, hard, good, easy]`. Ratings given on
+    /// the 2-point scale count as Again or Good.
+    fn get_rating_distribution(&self, deck_path: Option<&str>) -> Result<[usize; 4]>;
+    /// Reviews matching `filters`, newest first, whether synced or not.
+    fn get_reviews(&self, filters: &ReviewFilters) -> Result<ReviewPage>;
+    /// Card, deck and review totals and the database's size on disk. The
+    /// remote file count is left for the caller to fill in.
+    fn get_collection_stats(&self) -> Result<CollectionStats>;
+    /// Estimate the daily review load if FSRS cards were scheduled at
+    /// `new_retention` instead of their decks' retention. Nothing is
+    /// rescheduled.
+    fn project_workload(
+        &self,
+        deck_path: Option<&str>,
+        new_retention: f64,
+    ) -> Result<WorkloadProjection>;
 }
 
 /// SQLite implementation of repositories.
@@ -168,36 +486,119 @@ impl SqliteRepository {
 
     fn initialize(&self) -> Result<()> {
         self.conn.execute_batch(super::schema::SCHEMA)?;
+        self.run_migrations()?;
         self.conn.execute_batch(super::schema::INIT_GLOBAL_SETTINGS)?;
         self.conn.execute_batch(super::schema::INIT_SYNC_STATE)?;
         Ok(())
     }
 
+    /// Bring an existing database up to [`SCHEMA_VERSION`](super::schema::SCHEMA_VERSION).
+    ///
+    /// Each migration runs in its own transaction together with its
+    /// `schema_version` row, so an interrupted upgrade resumes where it stopped.
+    /// Databases written by a newer app version are refused rather than
+    /// modified.
+    fn run_migrations(&self) -> Result<()> {
+        let current: i32 = self.conn.query_row(
+            "SELECT COALESCE(MAX(version), 1) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )?;
+
+        if current > super::schema::SCHEMA_VERSION {
+            return Err(DbError::SchemaTooNew {
+                found: current,
+                supported: super::schema::SCHEMA_VERSION,
+            });
+        }
+
+        for (version, sql) in super::schema::MIGRATIONS {
+            if *version > current {
+                let tx = self.conn.unchecked_transaction()?;
+                tx.execute_batch(sql)?;
+                tx.execute(
+                    "INSERT INTO schema_version (version) VALUES (?1)",
+                    params![version],
+                )?;
+                tx.commit()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The schema version this database has been migrated to.
+    pub fn schema_version(&self) -> Result<i32> {
+        Ok(self.conn.query_row(
+            "SELECT COALESCE(MAX(version), 1) FROM schema_version",
+            [],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Give cards without an ID their content-hash ID.
+    ///
+    /// An ID already held by a card with a different question is skipped, so a
+    /// re-import of the same card keeps its ID while collisions are probed past.
+    pub fn assign_content_ids(&self, raw_cards: &mut [RawCard]) -> Result<()> {
+        let mut stmt = self.conn.prepare("SELECT question_text FROM cards WHERE id = ?1")?;
+        let mut error = None;
+
+        flashcard_core::parser::assign_content_ids(raw_cards, |id, card| {
+            match stmt.query_row(params![id], |row| row.get::<_, String>(0)).optional() {
+                Ok(existing) => existing.is_some_and(|question| question != card.question),
+                Err(e) => {
+                    error.get_or_insert(e);
+                    false
+                }
+            }
+        });
+
+        error.map_or(Ok(()), |e| Err(e.into()))
+    }
+
     /// Import cards from parsed markdown.
     pub fn import_cards(&self, deck_path: &str, source_file: &str, raw_cards: &[RawCard]) -> Result<Vec<i64>> {
         let mut ids = Vec::with_capacity(raw_cards.len());
+        let now = Utc::now().to_rfc3339();
 
         for raw in raw_cards {
             let id = if let Some(id) = raw.id {
                 self.conn.execute(
-                    "INSERT OR REPLACE INTO cards (id, deck_path, question_text, answer_text, source_file) VALUES (?1, ?2, ?3, ?4, ?5)",
-                    params![id, deck_path, raw.question, raw.answer, source_file],
+                    UPSERT_LOCAL_CARD,
+                    params![id, deck_path, raw.question, raw.answer, source_file, now],
                 )?;
                 id
             } else {
                 self.conn.execute(
-                    "INSERT INTO cards (deck_path, question_text, answer_text, source_file) VALUES (?1, ?2, ?3, ?4)",
-                    params![deck_path, raw.question, raw.answer, source_file],
+                    "INSERT INTO cards (deck_path, question_text, answer_text, source_file, updated_at, dirty) VALUES (?1, ?2, ?3, ?4, ?5, 1)",
+                    params![deck_path, raw.question, raw.answer, source_file, now],
                 )?;
                 self.conn.last_insert_rowid()
             };
             ids.push(id);
 
-            // Initialize card state if not exists
-            self.conn.execute(
+            // Tags follow the file on every import
+            self.conn
+                .execute("DELETE FROM card_tags WHERE card_id = ?1", params![id])?;
+            for tag in &raw.meta.tags {
+                self.conn.execute(
+                    "INSERT OR IGNORE INTO card_tags (card_id, tag) VALUES (?1, ?2)",
+                    params![id, tag],
+                )?;
+            }
+
+            // Initialize card state if not exists; authored hints only seed new cards
+            let created = self.conn.execute(
                 "INSERT OR IGNORE INTO card_states (card_id) VALUES (?1)",
                 params![id],
-            )?;
+            )? > 0;
+            if created && !raw.meta.is_empty() {
+                self.conn.execute(
+                    "UPDATE card_states SET suspended = ?2, ease_factor = COALESCE(?3, ease_factor)
+                     WHERE card_id = ?1",
+                    params![id, raw.meta.suspended, raw.meta.ease],
+                )?;
+            }
         }
 
         Ok(ids)
@@ -212,13 +613,70 @@ impl SqliteRepository {
         )?;
         Ok(count)
     }
+
+    /// Local IDs of the live cards imported from `source_file` for the given
+    /// cards without an ID line, as `(line_number, id)`.
+    ///
+    /// Cards are matched by question; a question imported more than once is
+    /// matched to its oldest copies first.
+    pub fn local_card_ids(&self, source_file: &str, raw_cards: &[RawCard]) -> Result<Vec<(usize, i64)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id FROM cards
+             WHERE source_file = ?1 AND question_text = ?2 AND deleted_at IS NULL
+             ORDER BY id",
+        )?;
+        let mut matched = Vec::new();
+        for raw in raw_cards.iter().filter(|raw| raw.id.is_none()) {
+            let ids = stmt
+                .query_map(params![source_file, raw.question], |row| row.get::<_, i64>(0))?
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            let unclaimed = ids.into_iter().find(|id| !matched.iter().any(|(_, m)| m == id));
+            if let Some(id) = unclaimed {
+                matched.push((raw.line_number, id));
+            }
+        }
+        Ok(matched)
+    }
+
+    /// Re-key local cards to the IDs the backend assigned them, keeping their
+    /// state, tags and reviews. Returns how many cards were re-keyed; a card
+    /// whose new ID is already in use locally is left as it is.
+    pub fn adopt_card_ids(&self, ids: &[(i64, i64)]) -> Result<usize> {
+        self.in_transaction(|repo| {
+            let mut adopted = 0;
+            for &(local_id, id) in ids {
+                let changed = repo.conn.execute(
+                    "UPDATE cards SET id = ?2
+                     WHERE id = ?1 AND NOT EXISTS (SELECT 1 FROM cards WHERE id = ?2)",
+                    params![local_id, id],
+                )?;
+                if changed == 0 {
+                    continue;
+                }
+                for table in [
+                    "card_states",
+                    "card_tags",
+                    "pending_reviews",
+                    "failed_reviews",
+                    "archived_reviews",
+                ] {
+                    repo.conn.execute(
+                        &format!("UPDATE {} SET card_id = ?2 WHERE card_id = ?1", table),
+                        params![local_id, id],
+                    )?;
+                }
+                adopted += 1;
+            }
+            Ok(adopted)
+        })
+    }
 }
 
 impl CardRepository for SqliteRepository {
     fn get_card(&self, id: i64) -> Result<Option<Card>> {
         self.conn
             .query_row(
-                "SELECT id, deck_path, question_text, answer_text, source_file, deleted_at FROM cards WHERE id = ?1",
+                "SELECT id, deck_path, question_text, answer_text, source_file, deleted_at, updated_at FROM cards WHERE id = ?1",
                 params![id],
                 |row| {
                     Ok(Card {
@@ -227,7 +685,8 @@ impl CardRepository for SqliteRepository {
                         question: row.get(2)?,
                         answer: row.get(3)?,
                         source_file: row.get(4)?,
-                        deleted_at: row.get::<_, Option<String>>(5)?.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc))),
+                        deleted_at: parse_timestamp(row.get(5)?),
+                        updated_at: parse_timestamp(row.get(6)?),
                     })
                 },
             )
@@ -237,7 +696,7 @@ impl CardRepository for SqliteRepository {
 
     fn get_cards_by_deck(&self, deck_path: &str) -> Result<Vec<Card>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, deck_path, question_text, answer_text, source_file, deleted_at FROM cards WHERE deck_path = ?1 AND deleted_at IS NULL",
+            "SELECT id, deck_path, question_text, answer_text, source_file, updated_at FROM cards WHERE deck_path = ?1 AND deleted_at IS NULL",
         )?;
 
         let cards = stmt
@@ -249,6 +708,7 @@ impl CardRepository for SqliteRepository {
                     answer: row.get(3)?,
                     source_file: row.get(4)?,
                     deleted_at: None,
+                    updated_at: parse_timestamp(row.get(5)?),
                 })
             })?
             .collect::<std::result::Result<Vec<_>, _>>()?;
@@ -256,11 +716,60 @@ impl CardRepository for SqliteRepository {
         Ok(cards)
     }
 
+    fn get_cards_by_tag(&self, tag: &str) -> Result<Vec<Card>> {
+        let Some(tag) = CardMeta::normalize_tag(tag) else {
+            return Ok(Vec::new());
+        };
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file, c.updated_at
+            FROM cards c
+            JOIN card_tags t ON c.id = t.card_id
+            WHERE t.tag = ?1 AND c.deleted_at IS NULL
+            ORDER BY c.deck_path, c.id",
+        )?;
+
+        let cards = stmt.query_map(params![tag], Self::row_to_card)?;
+        cards.collect::<std::result::Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
+    fn find_similar_cards(&self, question: &str, threshold: f64) -> Result<Vec<(Card, f64)>> {
+        let normalize = |s: &str| normalize_whitespace(&s.to_lowercase());
+        let target = normalize(question);
+        let target_len = target.chars().count();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT id, deck_path, question_text, answer_text, source_file, updated_at
+            FROM cards WHERE deleted_at IS NULL",
+        )?;
+        let cards = stmt.query_map([], Self::row_to_card)?;
+
+        let mut similar = Vec::new();
+        for card in cards {
+            let card = card?;
+            let candidate = normalize(&card.question);
+            // Similarity can't exceed the ratio of the lengths, so most
+            // questions are ruled out without computing the distance
+            let len = candidate.chars().count();
+            let longest = len.max(target_len);
+            if longest > 0 && (len.min(target_len) as f64 / longest as f64) < threshold {
+                continue;
+            }
+            let score = normalized_similarity(&target, &candidate);
+            if score >= threshold {
+                similar.push((card, score));
+            }
+        }
+        similar.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Ok(similar)
+    }
+
     fn upsert_cards(&self, cards: &[Card]) -> Result<()> {
+        let now = Utc::now();
         for card in cards {
+            let updated_at = card.updated_at.unwrap_or(now).to_rfc3339();
             self.conn.execute(
-                "INSERT OR REPLACE INTO cards (id, deck_path, question_text, answer_text, source_file) VALUES (?1, ?2, ?3, ?4, ?5)",
-                params![card.id, card.deck_path, card.question, card.answer, card.source_file],
+                UPSERT_LOCAL_CARD,
+                params![card.id, card.deck_path, card.question, card.answer, card.source_file, updated_at],
             )?;
         }
         Ok(())
@@ -277,14 +786,29 @@ impl CardRepository for SqliteRepository {
         Ok(())
     }
 
-    fn upsert_cards_from_sync(&self, cards: &[Card], synced_at: &str) -> Result<usize> {
-        let mut count = 0;
+    fn upsert_cards_from_sync(
+        &self,
+        cards: &[Card],
+        synced_at: &str,
+    ) -> Result<Vec<(i64, CardChange)>> {
+        let mut changes = Vec::new();
         for card in cards {
+            // `Some(deleted_at)` if the card exists locally
+            let local: Option<Option<String>> = self
+                .conn
+                .query_row(
+                    "SELECT deleted_at FROM cards WHERE id = ?1",
+                    params![card.id],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            let was_live = matches!(local, Some(None));
+
             let deleted_at_str = card.deleted_at.map(|d| d.to_rfc3339());
-            self.conn.execute(
-                "INSERT OR REPLACE INTO cards (id, deck_path, question_text, answer_text, source_file, deleted_at, synced_at)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-                params![card.id, card.deck_path, card.question, card.answer, card.source_file, deleted_at_str, synced_at],
+            let updated_at_str = card.updated_at.map(|d| d.to_rfc3339());
+            let applied = self.conn.execute(
+                UPSERT_SYNCED_CARD,
+                params![card.id, card.deck_path, card.question, card.answer, card.source_file, deleted_at_str, synced_at, updated_at_str],
             )?;
 
             // Initialize card state if not exists
@@ -293,30 +817,65 @@ impl CardRepository for SqliteRepository {
                 params![card.id],
             )?;
 
-            count += 1;
+            if applied == 0 {
+                continue;
+            }
+            let change = match (card.deleted_at.is_some(), was_live) {
+                (true, true) => CardChange::Deleted,
+                // Deleting a card this device never showed changes nothing visible
+                (true, false) => continue,
+                (false, true) => CardChange::Updated,
+                (false, false) => CardChange::Created,
+            };
+            changes.push((card.id, change));
         }
-        Ok(count)
+        Ok(changes)
+    }
+
+    fn mark_cards_uploaded(&self, edited_before: &str) -> Result<usize> {
+        self.conn
+            .execute(
+                "UPDATE cards SET dirty = 0
+                WHERE dirty = 1 AND julianday(updated_at) <= julianday(?1)",
+                params![edited_before],
+            )
+            .map_err(Into::into)
     }
 
-    fn get_new_cards(&self, deck_path: Option<&str>, limit: usize) -> Result<Vec<Card>> {
+    fn get_new_cards(
+        &self,
+        deck_path: Option<&str>,
+        tag: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<Card>> {
+        // A tag that normalizes to nothing matches no card
+        let tag = match tag.map(CardMeta::normalize_tag) {
+            Some(None) => return Ok(Vec::new()),
+            tag => tag.flatten(),
+        };
         let sql = match deck_path {
-            Some(_) => "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file
+            Some(_) => "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file, c.updated_at
                 FROM cards c
                 JOIN card_states cs ON c.id = cs.card_id
-                WHERE c.deck_path = ?1 AND c.deleted_at IS NULL AND cs.status = 'new'
+                WHERE c.deck_path = ?1 AND c.deleted_at IS NULL AND cs.suspended = 0 AND cs.status = 'new'
+                    AND (?3 IS NULL OR c.id IN (SELECT card_id FROM card_tags WHERE tag = ?3))
+                ORDER BY cs.new_priority DESC, c.id
                 LIMIT ?2",
-            None => "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file
+            None => "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file, c.updated_at
                 FROM cards c
                 JOIN card_states cs ON c.id = cs.card_id
-                WHERE c.deleted_at IS NULL AND cs.status = 'new'
+                WHERE c.deleted_at IS NULL AND cs.suspended = 0 AND cs.status = 'new'
+                    AND c.deck_path NOT IN (SELECT deck_path FROM deck_settings WHERE archived = 1)
+                    AND (?2 IS NULL OR c.id IN (SELECT card_id FROM card_tags WHERE tag = ?2))
+                ORDER BY cs.new_priority DESC, c.id
                 LIMIT ?1",
         };
 
         let mut stmt = self.conn.prepare(sql)?;
         let cards = if let Some(path) = deck_path {
-            stmt.query_map(params![path, limit], Self::row_to_card)?
+            stmt.query_map(params![path, limit, tag], Self::row_to_card)?
         } else {
-            stmt.query_map(params![limit], Self::row_to_card)?
+            stmt.query_map(params![limit, tag], Self::row_to_card)?
         };
 
         cards.collect::<std::result::Result<Vec<_>, _>>().map_err(Into::into)
@@ -325,37 +884,147 @@ impl CardRepository for SqliteRepository {
     fn get_due_cards(
         &self,
         deck_path: Option<&str>,
-        limit: usize,
+        tag: Option<&str>,
+        limit: i64,
         daily_reset_hour: u32,
+        due_grace_hours: u32,
     ) -> Result<Vec<Card>> {
-        let today = get_adjusted_today_string(daily_reset_hour);
+        // A tag that normalizes to nothing matches no card
+        let tag = match tag.map(CardMeta::normalize_tag) {
+            Some(None) => return Ok(Vec::new()),
+            tag => tag.flatten(),
+        };
+        // Compare full timestamps so learning cards due later today are included.
+        // Review-stage cards are ranked by due date and cut at `limit`.
+        let cutoff = get_due_cutoff(daily_reset_hour, due_grace_hours).to_rfc3339();
         let sql = match deck_path {
-            Some(_) => "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file
-                FROM cards c
-                JOIN card_states cs ON c.id = cs.card_id
-                WHERE c.deck_path = ?1 AND c.deleted_at IS NULL AND cs.status != 'new' AND cs.due_date <= ?2
-                ORDER BY cs.due_date
-                LIMIT ?3",
-            None => "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file
-                FROM cards c
-                JOIN card_states cs ON c.id = cs.card_id
-                WHERE c.deleted_at IS NULL AND cs.status != 'new' AND cs.due_date <= ?1
-                ORDER BY cs.due_date
-                LIMIT ?2",
+            Some(_) => "SELECT id, deck_path, question_text, answer_text, source_file, updated_at
+                FROM (
+                    SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file,
+                        c.updated_at, cs.status, cs.due_date,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY cs.status = 'review' ORDER BY julianday(cs.due_date)
+                        ) AS review_rank
+                    FROM cards c
+                    JOIN card_states cs ON c.id = cs.card_id
+                    WHERE c.deck_path = ?1 AND c.deleted_at IS NULL AND cs.suspended = 0 AND cs.status != 'new'
+                        AND julianday(cs.due_date) < julianday(?2)
+                        AND (?4 IS NULL OR c.id IN (SELECT card_id FROM card_tags WHERE tag = ?4))
+                )
+                WHERE status != 'review' OR review_rank <= ?3
+                ORDER BY julianday(due_date)",
+            None => "SELECT id, deck_path, question_text, answer_text, source_file, updated_at
+                FROM (
+                    SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file,
+                        c.updated_at, cs.status, cs.due_date,
+                        ROW_NUMBER() OVER (
+                            PARTITION BY cs.status = 'review' ORDER BY julianday(cs.due_date)
+                        ) AS review_rank
+                    FROM cards c
+                    JOIN card_states cs ON c.id = cs.card_id
+                    WHERE c.deleted_at IS NULL AND cs.suspended = 0 AND cs.status != 'new'
+                        AND julianday(cs.due_date) < julianday(?1)
+                        AND c.deck_path NOT IN (SELECT deck_path FROM deck_settings WHERE archived = 1)
+                        AND (?3 IS NULL OR c.id IN (SELECT card_id FROM card_tags WHERE tag = ?3))
+                )
+                WHERE status != 'review' OR review_rank <= ?2
+                ORDER BY julianday(due_date)",
         };
 
         let mut stmt = self.conn.prepare(sql)?;
         let cards = if let Some(path) = deck_path {
-            stmt.query_map(params![path, today, limit], Self::row_to_card)?
+            stmt.query_map(params![path, cutoff, limit, tag], Self::row_to_card)?
         } else {
-            stmt.query_map(params![today, limit], Self::row_to_card)?
+            stmt.query_map(params![cutoff, limit, tag], Self::row_to_card)?
         };
 
         cards.collect::<std::result::Result<Vec<_>, _>>().map_err(Into::into)
     }
+
+    fn get_difficult_cards(&self, deck_path: Option<&str>, limit: usize) -> Result<Vec<Card>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.deck_path, c.question_text, c.answer_text, c.source_file, c.updated_at
+             FROM cards c
+             JOIN card_states cs ON c.id = cs.card_id
+             WHERE c.deleted_at IS NULL AND cs.suspended = 0 AND cs.status != 'new'
+                 AND (?1 IS NULL OR c.deck_path = ?1)
+                 AND (?1 IS NOT NULL
+                     OR c.deck_path NOT IN (SELECT deck_path FROM deck_settings WHERE archived = 1))
+             ORDER BY cs.lapses DESC, COALESCE(cs.stability, cs.interval_days), cs.ease_factor, c.id
+             LIMIT ?2",
+        )?;
+
+        let cards = stmt
+            .query_map(params![deck_path, limit as i64], Self::row_to_card)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(cards)
+    }
 }
 
 impl SqliteRepository {
+    /// Map `status, interval_days, ease_factor, due_date, stability,
+    /// difficulty, lapses, reviews_count, learning_reps` columns to a card
+    /// state.
+    fn row_to_card_state(row: &rusqlite::Row) -> rusqlite::Result<CardState> {
+        let status_str: String = row.get(0)?;
+        let status = match status_str.as_str() {
+            "new" => CardStatus::New,
+            "learning" => CardStatus::Learning,
+            "review" => CardStatus::Review,
+            "relearning" => CardStatus::Relearning,
+            _ => CardStatus::New,
+        };
+        let due_str: Option<String> = row.get(3)?;
+        let due_date = due_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc)));
+
+        Ok(CardState {
+            status,
+            interval_days: row.get(1)?,
+            ease_factor: row.get(2)?,
+            due_date,
+            stability: row.get(4)?,
+            difficulty: row.get(5)?,
+            lapses: row.get(6)?,
+            reviews_count: row.get(7)?,
+            learning_reps: row.get(8)?,
+        })
+    }
+
+    /// Map `deck_path, algorithm, rating_scale, matching_mode,
+    /// fuzzy_threshold, new_cards_per_day, reviews_per_day, color, icon,
+    /// archived, normalization_rules, request_retention` columns to deck
+    /// settings.
+    fn row_to_deck_settings(row: &rusqlite::Row) -> rusqlite::Result<DeckSettings> {
+        let deck_path: String = row.get(0)?;
+        let algorithm_str: Option<String> = row.get(1)?;
+        let rating_scale_str: Option<String> = row.get(2)?;
+        let matching_mode_str: Option<String> = row.get(3)?;
+
+        Ok(DeckSettings {
+            deck_path,
+            algorithm: algorithm_str.and_then(|s| Algorithm::from_str(&s)),
+            rating_scale: rating_scale_str.map(|s| match s.as_str() {
+                "2point" => RatingScale::TwoPoint,
+                _ => RatingScale::FourPoint,
+            }),
+            matching_mode: matching_mode_str.map(|s| match s.as_str() {
+                "exact" => MatchingMode::Exact,
+                "case_insensitive" => MatchingMode::CaseInsensitive,
+                "contains" => MatchingMode::Contains,
+                _ => MatchingMode::Fuzzy,
+            }),
+            fuzzy_threshold: row.get(4)?,
+            new_cards_per_day: row.get::<_, Option<i64>>(5)?.map(Limit::from_db),
+            reviews_per_day: row.get::<_, Option<i64>>(6)?.map(Limit::from_db),
+            color: row.get(7)?,
+            icon: row.get(8)?,
+            archived: row.get(9)?,
+            normalization_rules: parse_norm_rules(row.get(10)?),
+            request_retention: row.get(11)?,
+        })
+    }
+
     fn row_to_card(row: &rusqlite::Row) -> rusqlite::Result<Card> {
         Ok(Card {
             id: row.get(0)?,
@@ -364,6 +1033,31 @@ impl SqliteRepository {
             answer: row.get(3)?,
             source_file: row.get(4)?,
             deleted_at: None,
+            updated_at: parse_timestamp(row.get(5)?),
+        })
+    }
+
+    /// Map the review columns shared by `pending_reviews` and
+    /// `failed_reviews`, in table order starting at `id`.
+    fn row_to_review(row: &rusqlite::Row) -> rusqlite::Result<PendingReview> {
+        Ok(PendingReview {
+            id: row.get(0)?,
+            card_id: row.get(1)?,
+            reviewed_at: row.get(2)?,
+            rating: row.get(3)?,
+            rating_scale: row.get(4)?,
+            answer_mode: row.get(5)?,
+            typed_answer: row.get(6)?,
+            was_correct: row.get::<_, Option<i32>>(7)?.map(|v| v != 0),
+            time_taken_ms: row.get(8)?,
+            interval_before: row.get(9)?,
+            interval_after: row.get(10)?,
+            ease_before: row.get(11)?,
+            ease_after: row.get(12)?,
+            algorithm: row.get(13)?,
+            params_hash: row.get(14)?,
+            status_before: row.get(15)?,
+            cram: row.get::<_, i32>(16)? != 0,
         })
     }
 }
@@ -372,36 +1066,28 @@ impl StateRepository for SqliteRepository {
     fn get_card_state(&self, card_id: i64) -> Result<Option<CardState>> {
         self.conn
             .query_row(
-                "SELECT status, interval_days, ease_factor, due_date, stability, difficulty, lapses, reviews_count FROM card_states WHERE card_id = ?1",
+                "SELECT status, interval_days, ease_factor, due_date, stability, difficulty, lapses, reviews_count, learning_reps FROM card_states WHERE card_id = ?1",
                 params![card_id],
-                |row| {
-                    let status_str: String = row.get(0)?;
-                    let status = match status_str.as_str() {
-                        "new" => CardStatus::New,
-                        "learning" => CardStatus::Learning,
-                        "review" => CardStatus::Review,
-                        "relearning" => CardStatus::Relearning,
-                        _ => CardStatus::New,
-                    };
-                    let due_str: Option<String> = row.get(3)?;
-                    let due_date = due_str.and_then(|s| DateTime::parse_from_rfc3339(&s).ok().map(|dt| dt.with_timezone(&Utc)));
-
-                    Ok(CardState {
-                        status,
-                        interval_days: row.get(1)?,
-                        ease_factor: row.get(2)?,
-                        due_date,
-                        stability: row.get(4)?,
-                        difficulty: row.get(5)?,
-                        lapses: row.get(6)?,
-                        reviews_count: row.get(7)?,
-                    })
-                },
+                Self::row_to_card_state,
             )
             .optional()
             .map_err(Into::into)
     }
 
+    fn get_unsynced_card_states(&self) -> Result<Vec<TimedCardState>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT status, interval_days, ease_factor, due_date, stability, difficulty, lapses, reviews_count, learning_reps, card_id, updated_at FROM card_states WHERE synced = 0",
+        )?;
+        let states = stmt.query_map([], |row| {
+            Ok(TimedCardState {
+                card_id: row.get(9)?,
+                state: Self::row_to_card_state(row)?,
+                updated_at: parse_timestamp(row.get(10)?),
+            })
+        })?;
+        states.collect::<std::result::Result<Vec<_>, _>>().map_err(Into::into)
+    }
+
     fn save_card_state(&self, card_id: i64, state: &CardState) -> Result<()> {
         let status_str = match state.status {
             CardStatus::New => "new",
@@ -409,16 +1095,23 @@ impl StateRepository for SqliteRepository {
             CardStatus::Review => "review",
             CardStatus::Relearning => "relearning",
         };
+        if state.status != CardStatus::New && state.due_date.is_none() {
+            return Err(DbError::InvalidData(format!(
+                "card {} has status {} but no due date",
+                card_id, status_str
+            )));
+        }
         let due_str = state.due_date.map(|d| d.to_rfc3339());
 
         self.conn.execute(
-            "INSERT OR REPLACE INTO card_states (card_id, status, interval_days, ease_factor, due_date, stability, difficulty, lapses, reviews_count, synced) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 0)",
-            params![card_id, status_str, state.interval_days, state.ease_factor, due_str, state.stability, state.difficulty, state.lapses, state.reviews_count],
+            UPSERT_CARD_STATE,
+            params![card_id, status_str, state.interval_days, state.ease_factor, due_str, state.stability, state.difficulty, state.lapses, state.reviews_count, 0, state.learning_reps, Utc::now().to_rfc3339()],
         )?;
         Ok(())
     }
 
     fn save_card_states_synced(&self, states: &[(i64, CardState)]) -> Result<usize> {
+        let now = Utc::now().to_rfc3339();
         let mut count = 0;
         for (card_id, state) in states {
             let status_str = match state.status {
@@ -430,31 +1123,101 @@ impl StateRepository for SqliteRepository {
             let due_str = state.due_date.map(|d| d.to_rfc3339());
 
             self.conn.execute(
-                "INSERT OR REPLACE INTO card_states (card_id, status, interval_days, ease_factor, due_date, stability, difficulty, lapses, reviews_count, synced)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 1)",
-                params![card_id, status_str, state.interval_days, state.ease_factor, due_str, state.stability, state.difficulty, state.lapses, state.reviews_count],
+                UPSERT_CARD_STATE,
+                params![card_id, status_str, state.interval_days, state.ease_factor, due_str, state.stability, state.difficulty, state.lapses, state.reviews_count, 1, state.learning_reps, now],
             )?;
             count += 1;
         }
         Ok(count)
     }
+
+    fn reset_deck_progress(&self, deck_path: &str, archive_reviews: bool) -> Result<ProgressReset> {
+        let deck_cards = "SELECT id FROM cards WHERE deck_path = ?1";
+        let tx = self.conn.unchecked_transaction()?;
+        // The backend's copy is reset through its own endpoint; see
+        // `commands::deck::reset_deck_progress`
+        let cards_reset = tx.execute(
+            &format!(
+                "UPDATE card_states SET status = 'new', interval_days = 0, ease_factor = 2.5,
+                    due_date = NULL, stability = NULL, difficulty = NULL, lapses = 0,
+                    reviews_count = 0, learning_reps = 0, synced = 0, updated_at = ?2
+                 WHERE card_id IN ({deck_cards})"
+            ),
+            params![deck_path, Utc::now().to_rfc3339()],
+        )?;
+
+        let reviews_archived = if archive_reviews {
+            tx.execute(
+                &format!(
+                    "INSERT INTO archived_reviews (id, card_id, reviewed_at, rating,
+                        rating_scale, answer_mode, typed_answer, was_correct, time_taken_ms,
+                        interval_before, interval_after, ease_before, ease_after, algorithm,
+                        params_hash, status_before, cram, synced, archived_at)
+                     SELECT id, card_id, reviewed_at, rating, rating_scale, answer_mode,
+                        typed_answer, was_correct, time_taken_ms, interval_before, interval_after,
+                        ease_before, ease_after, algorithm, params_hash, status_before, cram,
+                        synced, ?2
+                     FROM pending_reviews WHERE card_id IN ({deck_cards})"
+                ),
+                params![deck_path, Utc::now().to_rfc3339()],
+            )?;
+            tx.execute(
+                &format!("DELETE FROM pending_reviews WHERE card_id IN ({deck_cards})"),
+                params![deck_path],
+            )?
+        } else {
+            0
+        };
+
+        tx.commit()?;
+        Ok(ProgressReset {
+            cards_reset,
+            reviews_archived,
+        })
+    }
+
+    fn get_stuck_card_ids(&self) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id FROM cards c
+             JOIN card_states cs ON c.id = cs.card_id
+             WHERE c.deleted_at IS NULL AND cs.status != 'new' AND cs.due_date IS NULL
+             ORDER BY c.id",
+        )?;
+        let ids = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(ids)
+    }
+
+    fn set_new_priority(&self, card_id: i64, priority: i32) -> Result<()> {
+        let updated = self.conn.execute(
+            "UPDATE card_states SET new_priority = ?2 WHERE card_id = ?1",
+            params![card_id, priority],
+        )?;
+        if updated == 0 {
+            return Err(DbError::CardNotFound(card_id));
+        }
+        Ok(())
+    }
 }
 
 impl DeckRepository for SqliteRepository {
-    fn get_all_decks(&self, daily_reset_hour: u32) -> Result<Vec<Deck>> {
-        let today = get_adjusted_today_string(daily_reset_hour);
+    fn get_all_decks(&self, daily_reset_hour: u32, include_archived: bool) -> Result<Vec<Deck>> {
+        // Due counts match get_due_cards: anything due before the day ends
+        let day_end = get_study_day_end(daily_reset_hour).to_rfc3339();
         let mut stmt = self.conn.prepare(
             "SELECT deck_path, COUNT(*) as total,
-                SUM(CASE WHEN cs.status = 'new' THEN 1 ELSE 0 END) as new_count,
-                SUM(CASE WHEN cs.status != 'new' AND cs.due_date <= ?1 THEN 1 ELSE 0 END) as due_count
+                SUM(CASE WHEN cs.suspended = 0 AND cs.status = 'new' THEN 1 ELSE 0 END) as new_count,
+                SUM(CASE WHEN cs.suspended = 0 AND cs.status != 'new' AND julianday(cs.due_date) < julianday(?1) THEN 1 ELSE 0 END) as due_count
             FROM cards c
             LEFT JOIN card_states cs ON c.id = cs.card_id
             WHERE c.deleted_at IS NULL
+                AND (?2 OR c.deck_path NOT IN (SELECT deck_path FROM deck_settings WHERE archived = 1))
             GROUP BY deck_path",
         )?;
 
         let decks = stmt
-            .query_map(params![today], |row| {
+            .query_map(params![day_end, include_archived], |row| {
                 let path: String = row.get(0)?;
                 let name = path.rsplit('/').next().unwrap_or(&path).to_string();
                 Ok(Deck {
@@ -471,17 +1234,17 @@ impl DeckRepository for SqliteRepository {
     }
 
     fn get_deck(&self, path: &str, daily_reset_hour: u32) -> Result<Option<Deck>> {
-        let today = get_adjusted_today_string(daily_reset_hour);
+        let day_end = get_study_day_end(daily_reset_hour).to_rfc3339();
         self.conn
             .query_row(
                 "SELECT deck_path, COUNT(*) as total,
-                    SUM(CASE WHEN cs.status = 'new' THEN 1 ELSE 0 END) as new_count,
-                    SUM(CASE WHEN cs.status != 'new' AND cs.due_date <= ?1 THEN 1 ELSE 0 END) as due_count
+                    SUM(CASE WHEN cs.suspended = 0 AND cs.status = 'new' THEN 1 ELSE 0 END) as new_count,
+                    SUM(CASE WHEN cs.suspended = 0 AND cs.status != 'new' AND julianday(cs.due_date) < julianday(?1) THEN 1 ELSE 0 END) as due_count
                 FROM cards c
                 LEFT JOIN card_states cs ON c.id = cs.card_id
                 WHERE c.deleted_at IS NULL AND c.deck_path = ?2
                 GROUP BY deck_path",
-                params![today, path],
+                params![day_end, path],
                 |row| {
                     let path: String = row.get(0)?;
                     let name = path.rsplit('/').next().unwrap_or(&path).to_string();
@@ -497,18 +1260,54 @@ impl DeckRepository for SqliteRepository {
             .optional()
             .map_err(Into::into)
     }
+
+    fn get_deck_summaries(
+        &self,
+        daily_reset_hour: u32,
+        include_archived: bool,
+    ) -> Result<DeckSummaries> {
+        let day_end = get_study_day_end(daily_reset_hour).to_rfc3339();
+        let mut stmt = self.conn.prepare(
+            "SELECT deck_path, COUNT(*) as total,
+                SUM(CASE WHEN cs.suspended = 0 AND cs.status = 'new' THEN 1 ELSE 0 END) as new_count,
+                SUM(CASE WHEN cs.suspended = 0 AND cs.status != 'new' AND julianday(cs.due_date) < julianday(?1) THEN 1 ELSE 0 END) as due_count
+            FROM cards c
+            LEFT JOIN card_states cs ON c.id = cs.card_id
+            WHERE c.deleted_at IS NULL
+                AND (?2 OR c.deck_path NOT IN (SELECT deck_path FROM deck_settings WHERE archived = 1))
+            GROUP BY deck_path
+            ORDER BY deck_path",
+        )?;
+
+        let decks = stmt
+            .query_map(params![day_end, include_archived], |row| {
+                Ok(DeckSummary {
+                    path: row.get(0)?,
+                    total: row.get(1)?,
+                    new_count: row.get(2)?,
+                    due_count: row.get(3)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(DeckSummaries::from_decks(decks))
+    }
 }
 
 impl SettingsRepository for SqliteRepository {
     fn get_global_settings(&self) -> Result<GlobalSettings> {
         self.conn
             .query_row(
-                "SELECT algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, daily_reset_hour FROM global_settings WHERE id = 1",
+                "SELECT algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, daily_reset_hour, again_delay_minutes, due_grace_hours, graduation_requirement, deck_path_separator, suggest_easy_threshold, suggest_hard_threshold, queue_order, min_ease, max_ease, sync_conflict_policy, new_cards_paused, study_direction, session_limit, min_answer_chars FROM global_settings WHERE id = 1",
                 [],
                 |row| {
                     let algorithm_str: String = row.get(0)?;
                     let rating_scale_str: String = row.get(1)?;
                     let matching_mode_str: String = row.get(2)?;
+                    let queue_order_str: String = row.get(13)?;
+                    let conflict_policy_str: String = row.get(16)?;
+                    let direction_str: String = row.get(18)?;
+                    let session_limit_str: String = row.get(19)?;
 
                     Ok(GlobalSettings {
                         algorithm: Algorithm::from_str(&algorithm_str).unwrap_or_default(),
@@ -519,12 +1318,27 @@ impl SettingsRepository for SqliteRepository {
                         matching_mode: match matching_mode_str.as_str() {
                             "exact" => MatchingMode::Exact,
                             "case_insensitive" => MatchingMode::CaseInsensitive,
+                            "contains" => MatchingMode::Contains,
                             _ => MatchingMode::Fuzzy,
                         },
                         fuzzy_threshold: row.get(3)?,
-                        new_cards_per_day: row.get(4)?,
-                        reviews_per_day: row.get(5)?,
+                        new_cards_per_day: Limit::from_db(row.get(4)?),
+                        reviews_per_day: Limit::from_db(row.get(5)?),
                         daily_reset_hour: row.get(6)?,
+                        again_delay_minutes: row.get(7)?,
+                        due_grace_hours: row.get(8)?,
+                        graduation_requirement: row.get(9)?,
+                        deck_path_separator: row.get(10)?,
+                        suggest_easy_threshold: row.get(11)?,
+                        suggest_hard_threshold: row.get(12)?,
+                        queue_order: queue_order_str.parse().unwrap_or_default(),
+                        min_ease: row.get(14)?,
+                        max_ease: row.get(15)?,
+                        sync_conflict_policy: conflict_policy_str.parse().unwrap_or_default(),
+                        new_cards_paused: row.get(17)?,
+                        study_direction: direction_str.parse().unwrap_or_default(),
+                        session_limit: session_limit_str.parse().unwrap_or_default(),
+                        min_answer_chars: row.get(20)?,
                     })
                 },
             )
@@ -540,19 +1354,34 @@ impl SettingsRepository for SqliteRepository {
         let matching_mode_str = match settings.matching_mode {
             MatchingMode::Exact => "exact",
             MatchingMode::CaseInsensitive => "case_insensitive",
+            MatchingMode::Contains => "contains",
             MatchingMode::Fuzzy => "fuzzy",
         };
 
         self.conn.execute(
-            "UPDATE global_settings SET algorithm = ?1, rating_scale = ?2, matching_mode = ?3, fuzzy_threshold = ?4, new_cards_per_day = ?5, reviews_per_day = ?6, daily_reset_hour = ?7, synced = 0 WHERE id = 1",
+            "UPDATE global_settings SET algorithm = ?1, rating_scale = ?2, matching_mode = ?3, fuzzy_threshold = ?4, new_cards_per_day = ?5, reviews_per_day = ?6, daily_reset_hour = ?7, again_delay_minutes = ?8, due_grace_hours = ?9, graduation_requirement = ?10, deck_path_separator = ?11, suggest_easy_threshold = ?12, suggest_hard_threshold = ?13, queue_order = ?14, min_ease = ?15, max_ease = ?16, sync_conflict_policy = ?17, new_cards_paused = ?18, study_direction = ?19, session_limit = ?20, min_answer_chars = ?21, synced = 0 WHERE id = 1",
             params![
                 algorithm_str,
                 rating_scale_str,
                 matching_mode_str,
                 settings.fuzzy_threshold,
-                settings.new_cards_per_day,
-                settings.reviews_per_day,
+                settings.new_cards_per_day.to_db(),
+                settings.reviews_per_day.to_db(),
                 settings.daily_reset_hour,
+                settings.again_delay_minutes,
+                settings.due_grace_hours,
+                settings.graduation_requirement,
+                settings.deck_path_separator,
+                settings.suggest_easy_threshold,
+                settings.suggest_hard_threshold,
+                settings.queue_order.as_str(),
+                settings.min_ease,
+                settings.max_ease,
+                settings.sync_conflict_policy.as_str(),
+                settings.new_cards_paused,
+                settings.study_direction.as_str(),
+                settings.session_limit.to_string(),
+                settings.min_answer_chars,
             ],
         )?;
 
@@ -562,36 +1391,24 @@ impl SettingsRepository for SqliteRepository {
     fn get_deck_settings(&self, deck_path: &str) -> Result<Option<DeckSettings>> {
         self.conn
             .query_row(
-                "SELECT deck_path, algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day FROM deck_settings WHERE deck_path = ?1",
+                "SELECT deck_path, algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, color, icon, archived, normalization_rules, request_retention FROM deck_settings WHERE deck_path = ?1",
                 params![deck_path],
-                |row| {
-                    let deck_path: String = row.get(0)?;
-                    let algorithm_str: Option<String> = row.get(1)?;
-                    let rating_scale_str: Option<String> = row.get(2)?;
-                    let matching_mode_str: Option<String> = row.get(3)?;
-
-                    Ok(DeckSettings {
-                        deck_path,
-                        algorithm: algorithm_str.and_then(|s| Algorithm::from_str(&s)),
-                        rating_scale: rating_scale_str.map(|s| match s.as_str() {
-                            "2point" => RatingScale::TwoPoint,
-                            _ => RatingScale::FourPoint,
-                        }),
-                        matching_mode: matching_mode_str.map(|s| match s.as_str() {
-                            "exact" => MatchingMode::Exact,
-                            "case_insensitive" => MatchingMode::CaseInsensitive,
-                            _ => MatchingMode::Fuzzy,
-                        }),
-                        fuzzy_threshold: row.get(4)?,
-                        new_cards_per_day: row.get(5)?,
-                        reviews_per_day: row.get(6)?,
-                    })
-                },
+                Self::row_to_deck_settings,
             )
             .optional()
             .map_err(Into::into)
     }
 
+    fn get_all_deck_settings(&self) -> Result<Vec<DeckSettings>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT deck_path, algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, color, icon, archived, normalization_rules, request_retention FROM deck_settings ORDER BY LENGTH(deck_path), deck_path",
+        )?;
+        let settings = stmt
+            .query_map([], Self::row_to_deck_settings)?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        Ok(settings)
+    }
+
     fn save_deck_settings(&self, settings: &DeckSettings) -> Result<()> {
         let algorithm_str = settings.algorithm.map(|a| a.as_str().to_string());
         let rating_scale_str = settings.rating_scale.map(|rs| match rs {
@@ -601,19 +1418,25 @@ impl SettingsRepository for SqliteRepository {
         let matching_mode_str = settings.matching_mode.map(|mm| match mm {
             MatchingMode::Exact => "exact".to_string(),
             MatchingMode::CaseInsensitive => "case_insensitive".to_string(),
+            MatchingMode::Contains => "contains".to_string(),
             MatchingMode::Fuzzy => "fuzzy".to_string(),
         });
 
         self.conn.execute(
-            "INSERT OR REPLACE INTO deck_settings (deck_path, algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, synced) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 0)",
+            "INSERT OR REPLACE INTO deck_settings (deck_path, algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, color, icon, archived, normalization_rules, request_retention, synced) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, 0)",
             params![
                 settings.deck_path,
                 algorithm_str,
                 rating_scale_str,
                 matching_mode_str,
                 settings.fuzzy_threshold,
-                settings.new_cards_per_day,
-                settings.reviews_per_day,
+                settings.new_cards_per_day.map(|l| l.to_db()),
+                settings.reviews_per_day.map(|l| l.to_db()),
+                settings.color,
+                settings.icon,
+                settings.archived,
+                format_norm_rules(&settings.normalization_rules),
+                settings.request_retention,
             ],
         )?;
 
@@ -628,52 +1451,142 @@ impl SettingsRepository for SqliteRepository {
         Ok(())
     }
 
+    fn set_deck_archived(&self, deck_path: &str, archived: bool) -> Result<()> {
+        // Leaves any overrides as they are, and creates an empty row if none exist
+        self.conn.execute(
+            "INSERT INTO deck_settings (deck_path, archived, synced) VALUES (?1, ?2, 0)
+            ON CONFLICT(deck_path) DO UPDATE SET archived = excluded.archived",
+            params![deck_path, archived],
+        )?;
+        Ok(())
+    }
+
     fn get_effective_settings(&self, deck_path: Option<&str>) -> Result<EffectiveSettings> {
         let global = self.get_global_settings()?;
         let deck = match deck_path {
-            Some(path) => self.get_deck_settings(path)?,
+            Some(path) => DeckSettings::cascade(&self.get_all_deck_settings()?, path),
             None => None,
         };
         Ok(EffectiveSettings::merge(&global, deck.as_ref()))
     }
 }
 
-use crate::sync::{ApiDeckSettings, ApiGlobalSettings};
+use crate::sync::scheduler::AutoSyncConfig;
+use crate::sync::{ApiDeckSettings, ApiGlobalSettings, PendingSync, PulledChanges};
 
 impl SqliteRepository {
+    /// Run `f` in one transaction, committed only if it succeeds.
+    ///
+    /// Repository calls made by `f` share the transaction, so an error or
+    /// crash part way through leaves none of their writes behind.
+    pub fn in_transaction<T>(&self, f: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        let tx = self.conn.unchecked_transaction()?;
+        let value = f(self)?;
+        tx.commit()?;
+        Ok(value)
+    }
+
+    /// Apply a pull atomically, recording the sync time if it completes the
+    /// sync.
+    pub fn apply_pulled_changes(&self, pulled: &PulledChanges) -> Result<Vec<(i64, CardChange)>> {
+        self.in_transaction(|repo| {
+            let changes = repo.upsert_cards_from_sync(pulled.cards, pulled.synced_at)?;
+            repo.save_card_states_synced(pulled.states)?;
+            repo.save_global_settings_synced(pulled.global_settings)?;
+            for settings in pulled.deck_settings {
+                repo.save_deck_settings_synced(settings)?;
+            }
+            if pulled.completes_sync {
+                repo.update_sync_state(pulled.synced_at)?;
+            }
+            Ok(changes)
+        })
+    }
+
     /// Save global settings from cloud sync (marks as synced).
     pub fn save_global_settings_synced(&self, settings: &ApiGlobalSettings) -> Result<()> {
         self.conn.execute(
-            "UPDATE global_settings SET algorithm = ?1, rating_scale = ?2, matching_mode = ?3, fuzzy_threshold = ?4, new_cards_per_day = ?5, reviews_per_day = ?6, daily_reset_hour = ?7, synced = 1 WHERE id = 1",
+            "UPDATE global_settings SET algorithm = ?1, rating_scale = ?2, matching_mode = ?3, fuzzy_threshold = ?4, new_cards_per_day = ?5, reviews_per_day = ?6, daily_reset_hour = ?7, again_delay_minutes = ?8, due_grace_hours = ?9, graduation_requirement = ?10, deck_path_separator = ?11, suggest_easy_threshold = ?12, suggest_hard_threshold = ?13, queue_order = ?14, min_ease = ?15, max_ease = ?16, sync_conflict_policy = ?17, new_cards_paused = ?18, study_direction = ?19, session_limit = ?20, min_answer_chars = ?21, synced = 1 WHERE id = 1",
             params![
                 settings.algorithm,
                 settings.rating_scale,
                 settings.matching_mode,
                 settings.fuzzy_threshold,
-                settings.new_cards_per_day,
-                settings.reviews_per_day,
+                settings.new_cards_per_day.to_db(),
+                settings.reviews_per_day.to_db(),
                 settings.daily_reset_hour,
+                settings.again_delay_minutes,
+                settings.due_grace_hours,
+                settings.graduation_requirement,
+                settings.deck_path_separator,
+                settings.suggest_easy_threshold,
+                settings.suggest_hard_threshold,
+                settings.queue_order,
+                settings.min_ease,
+                settings.max_ease,
+                settings.sync_conflict_policy,
+                settings.new_cards_paused,
+                settings.study_direction,
+                settings.session_limit,
+                settings.min_answer_chars,
             ],
         )?;
         Ok(())
     }
 
     /// Save deck settings from cloud sync (marks as synced).
+    ///
+    /// The archived flag is local to this device and is kept.
     pub fn save_deck_settings_synced(&self, settings: &ApiDeckSettings) -> Result<()> {
         self.conn.execute(
-            "INSERT OR REPLACE INTO deck_settings (deck_path, algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, synced) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, 1)",
+            "INSERT INTO deck_settings (deck_path, algorithm, rating_scale, matching_mode, fuzzy_threshold, new_cards_per_day, reviews_per_day, color, icon, normalization_rules, request_retention, synced) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, 1)
+            ON CONFLICT(deck_path) DO UPDATE SET algorithm = excluded.algorithm, rating_scale = excluded.rating_scale, matching_mode = excluded.matching_mode, fuzzy_threshold = excluded.fuzzy_threshold, new_cards_per_day = excluded.new_cards_per_day, reviews_per_day = excluded.reviews_per_day, color = excluded.color, icon = excluded.icon, normalization_rules = excluded.normalization_rules, request_retention = excluded.request_retention, synced = 1",
             params![
                 settings.deck_path,
                 settings.algorithm,
                 settings.rating_scale,
                 settings.matching_mode,
                 settings.fuzzy_threshold,
-                settings.new_cards_per_day,
-                settings.reviews_per_day,
+                settings.new_cards_per_day.map(|l| l.to_db()),
+                settings.reviews_per_day.map(|l| l.to_db()),
+                settings.color,
+                settings.icon,
+                format_norm_rules(&settings.normalization_rules),
+                settings.request_retention,
             ],
         )?;
         Ok(())
     }
+
+    /// The auto-sync configuration last saved on this device.
+    pub fn get_auto_sync_config(&self) -> Result<AutoSyncConfig> {
+        self.conn
+            .query_row(
+                "SELECT auto_sync_enabled, auto_sync_interval_minutes, auto_sync_paused
+                 FROM global_settings WHERE id = 1",
+                [],
+                |row| {
+                    Ok(AutoSyncConfig {
+                        enabled: row.get(0)?,
+                        interval_minutes: row.get(1)?,
+                        paused: row.get(2)?,
+                    })
+                },
+            )
+            .map_err(Into::into)
+    }
+
+    /// Save the auto-sync configuration. It belongs to this device, so the
+    /// settings are not marked for sync.
+    pub fn save_auto_sync_config(&self, config: &AutoSyncConfig) -> Result<()> {
+        self.conn.execute(
+            "UPDATE global_settings SET auto_sync_enabled = ?1, auto_sync_interval_minutes = ?2,
+                auto_sync_paused = ?3
+             WHERE id = 1",
+            params![config.enabled, config.interval_minutes, config.paused],
+        )?;
+        Ok(())
+    }
 }
 
 impl StatsRepository for SqliteRepository {
@@ -683,9 +1596,9 @@ impl StatsRepository for SqliteRepository {
                 self.conn.query_row(
                     "SELECT
                         COUNT(*) as total,
-                        SUM(CASE WHEN cs.status = 'new' THEN 1 ELSE 0 END) as new_count,
-                        SUM(CASE WHEN cs.status = 'learning' OR cs.status = 'relearning' THEN 1 ELSE 0 END) as learning_count,
-                        SUM(CASE WHEN cs.status = 'review' THEN 1 ELSE 0 END) as review_count,
+                        COALESCE(SUM(CASE WHEN cs.status = 'new' THEN 1 ELSE 0 END), 0) as new_count,
+                        COALESCE(SUM(CASE WHEN cs.status = 'learning' OR cs.status = 'relearning' THEN 1 ELSE 0 END), 0) as learning_count,
+                        COALESCE(SUM(CASE WHEN cs.status = 'review' THEN 1 ELSE 0 END), 0) as review_count,
                         COALESCE(AVG(cs.ease_factor), 2.5) as avg_ease,
                         COALESCE(AVG(CASE WHEN cs.interval_days > 0 THEN cs.interval_days END), 0) as avg_interval
                     FROM cards c
@@ -706,9 +1619,9 @@ impl StatsRepository for SqliteRepository {
                 self.conn.query_row(
                     "SELECT
                         COUNT(*) as total,
-                        SUM(CASE WHEN cs.status = 'new' THEN 1 ELSE 0 END) as new_count,
-                        SUM(CASE WHEN cs.status = 'learning' OR cs.status = 'relearning' THEN 1 ELSE 0 END) as learning_count,
-                        SUM(CASE WHEN cs.status = 'review' THEN 1 ELSE 0 END) as review_count,
+                        COALESCE(SUM(CASE WHEN cs.status = 'new' THEN 1 ELSE 0 END), 0) as new_count,
+                        COALESCE(SUM(CASE WHEN cs.status = 'learning' OR cs.status = 'relearning' THEN 1 ELSE 0 END), 0) as learning_count,
+                        COALESCE(SUM(CASE WHEN cs.status = 'review' THEN 1 ELSE 0 END), 0) as review_count,
                         COALESCE(AVG(cs.ease_factor), 2.5) as avg_ease,
                         COALESCE(AVG(CASE WHEN cs.interval_days > 0 THEN cs.interval_days END), 0) as avg_interval
                     FROM cards c
@@ -737,31 +1650,302 @@ impl StatsRepository for SqliteRepository {
         })
     }
 
-    fn get_study_stats(&self, daily_reset_hour: u32) -> Result<StudyStats> {
-        let today = get_adjusted_today_string(daily_reset_hour);
+    fn get_study_stats(
+        &self,
+        daily_reset_hour: u32,
+        range: Option<DateRange>,
+    ) -> Result<StudyStats> {
+        if range.is_none() {
+            if let Some(stats) = self.cached_study_stats(daily_reset_hour)? {
+                return Ok(stats);
+            }
+        }
+        self.compute_study_stats(daily_reset_hour, range)
+    }
+
+    fn refresh_stats_cache(&self, daily_reset_hour: u32) -> Result<StudyStats> {
+        let stats = self.compute_study_stats(daily_reset_hour, None)?;
+        let json = serde_json::to_string(&stats).map_err(|e| DbError::InvalidData(e.to_string()))?;
+        self.conn.execute(
+            "INSERT OR REPLACE INTO stats_cache (id, study_day, daily_reset_hour, stats, computed_at)
+             VALUES (1, ?1, ?2, ?3, ?4)",
+            params![
+                get_adjusted_today_string(daily_reset_hour),
+                daily_reset_hour,
+                json,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(stats)
+    }
+
+    fn get_calendar_data(&self, days: usize, daily_reset_hour: u32) -> Result<Vec<CalendarData>> {
+        let mut data = Vec::new();
+        let today = get_adjusted_today(daily_reset_hour);
+
+        for i in 0..days {
+            let date = today - chrono::Duration::days(i as i64);
+            let date_str = date.format("%Y-%m-%d").to_string();
+
+            let reviews: usize = self.conn.query_row(
+                "SELECT COUNT(*) FROM pending_reviews WHERE date(reviewed_at) = ?1",
+                params![date_str],
+                |row| row.get(0),
+            )?;
+
+            data.push(CalendarData {
+                date: date_str,
+                reviews,
+            });
+        }
+
+        // Reverse so oldest is first
+        data.reverse();
+        Ok(data)
+    }
+
+    fn count_reviews_since(&self, deck_path: Option<&str>, since: &str) -> Result<usize> {
+        self.conn
+            .query_row(
+                "SELECT COUNT(*)
+                 FROM pending_reviews pr
+                 JOIN cards c ON c.id = pr.card_id
+                 WHERE julianday(pr.reviewed_at) >= julianday(?1)
+                     AND (?2 IS NULL OR c.deck_path = ?2)
+                     AND pr.status_before IS NOT 'new'
+                     AND pr.cram = 0",
+                params![since, deck_path],
+                |row| row.get(0),
+            )
+            .map_err(Into::into)
+    }
+
+    fn get_slowest_cards(&self, deck_path: Option<&str>, limit: usize) -> Result<Vec<SlowCard>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.id, c.deck_path, c.question_text, AVG(pr.time_taken_ms), COUNT(pr.time_taken_ms)
+             FROM pending_reviews pr
+             JOIN cards c ON c.id = pr.card_id
+             WHERE pr.time_taken_ms IS NOT NULL
+                 AND c.deleted_at IS NULL
+                 AND (?1 IS NULL OR c.deck_path = ?1)
+             GROUP BY c.id
+             HAVING COUNT(pr.time_taken_ms) >= ?2
+             ORDER BY AVG(pr.time_taken_ms) DESC, c.id
+             LIMIT ?3",
+        )?;
+
+        let cards = stmt
+            .query_map(
+                params![deck_path, SLOW_CARD_MIN_REVIEWS as i64, limit as i64],
+                |row| {
+                    Ok(SlowCard {
+                        card_id: row.get(0)?,
+                        deck_path: row.get(1)?,
+                        question: row.get(2)?,
+                        average_time_ms: row.get(3)?,
+                        review_count: row.get(4)?,
+                    })
+                },
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(cards)
+    }
+
+    fn get_reviews(&self, filters: &ReviewFilters) -> Result<ReviewPage> {
+        let limit = filters.limit.clamp(1, REVIEW_PAGE_SIZE);
+        let mut stmt = self.conn.prepare(
+            "SELECT id, card_id, reviewed_at, rating, rating_scale, answer_mode, typed_answer,
+                    was_correct, time_taken_ms, interval_before, interval_after,
+                    ease_before, ease_after, algorithm, params_hash, status_before, cram
+             FROM pending_reviews
+             WHERE (?1 IS NULL OR card_id = ?1)
+                 AND (?2 IS NULL OR julianday(reviewed_at) >= julianday(?2))
+                 AND (?3 IS NULL OR julianday(reviewed_at) < julianday(?3))
+             ORDER BY julianday(reviewed_at) DESC, id DESC
+             LIMIT ?4 OFFSET ?5",
+        )?;
+
+        // One extra row is fetched to detect a further page
+        let mut reviews = stmt
+            .query_map(
+                params![
+                    filters.card_id,
+                    filters.from.map(|t| t.to_rfc3339()),
+                    filters.to.map(|t| t.to_rfc3339()),
+                    (limit + 1) as i64,
+                    filters.offset as i64,
+                ],
+                Self::row_to_review,
+            )?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        let has_more = reviews.len() > limit;
+        reviews.truncate(limit);
+
+        Ok(ReviewPage { reviews, has_more })
+    }
+
+    fn get_rating_distribution(&self, deck_path: Option<&str>) -> Result<[usize; 4]> {
+        let mut stmt = self.conn.prepare(
+            "SELECT pr.rating, pr.rating_scale, COUNT(*)
+             FROM pending_reviews pr
+             JOIN cards c ON c.id = pr.card_id
+             WHERE c.deleted_at IS NULL
+                 AND pr.cram = 0
+                 AND (?1 IS NULL OR c.deck_path = ?1)
+             GROUP BY pr.rating, pr.rating_scale",
+        )?;
+
+        let mut distribution = [0; 4];
+        let rows = stmt.query_map(params![deck_path], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+        for row in rows {
+            let (rating, rating_scale, count) = row?;
+            let rating = rating_scale
+                .parse::<RatingScale>()
+                .ok()
+                .zip(u8::try_from(rating).ok())
+                .and_then(|(scale, value)| scale.rating(value));
+            if let Some(rating) = rating {
+                distribution[rating.to_value() as usize - 1] += count as usize;
+            }
+        }
+
+        Ok(distribution)
+    }
+
+    fn get_collection_stats(&self) -> Result<CollectionStats> {
+        let (total_cards, total_decks, total_reviews, database_size_bytes) = self.conn.query_row(
+            "SELECT
+                (SELECT COUNT(*) FROM cards WHERE deleted_at IS NULL),
+                (SELECT COUNT(DISTINCT deck_path) FROM cards WHERE deleted_at IS NULL),
+                (SELECT COUNT(*) FROM pending_reviews),
+                (SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size())",
+            [],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, i64>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            },
+        )?;
+
+        Ok(CollectionStats {
+            total_cards: total_cards as usize,
+            total_decks: total_decks as usize,
+            total_reviews: total_reviews as usize,
+            database_size_bytes: database_size_bytes as u64,
+            remote_file_count: None,
+        })
+    }
+
+    fn project_workload(
+        &self,
+        deck_path: Option<&str>,
+        new_retention: f64,
+    ) -> Result<WorkloadProjection> {
+        let mut stmt = self.conn.prepare(
+            "SELECT c.deck_path, cs.stability
+             FROM card_states cs
+             JOIN cards c ON c.id = cs.card_id
+             WHERE cs.stability IS NOT NULL
+                 AND cs.status != 'new'
+                 AND c.deleted_at IS NULL
+                 AND (?1 IS NULL OR c.deck_path = ?1)",
+        )?;
+        let cards = stmt
+            .query_map(params![deck_path], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?))
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        // Each deck schedules at its own retention today
+        let mut current: HashMap<&str, Fsrs> = HashMap::new();
+        for (path, _) in &cards {
+            if !current.contains_key(path.as_str()) {
+                let request_retention = self.get_effective_settings(Some(path))?.request_retention;
+                current.insert(
+                    path,
+                    Fsrs {
+                        request_retention,
+                        ..Fsrs::default()
+                    },
+                );
+            }
+        }
+        let projected = Fsrs {
+            request_retention: new_retention,
+            ..Fsrs::default()
+        };
+        // A card on an interval of I days comes up 1/I times a day
+        let current_daily_reviews = cards
+            .iter()
+            .map(|(path, s)| 1.0 / current[path.as_str()].interval_from_stability(*s))
+            .sum();
+        let projected_daily_reviews = cards
+            .iter()
+            .map(|(_, s)| 1.0 / projected.interval_from_stability(*s))
+            .sum();
+
+        Ok(WorkloadProjection {
+            cards: cards.len(),
+            current_retention: self.get_effective_settings(deck_path)?.request_retention,
+            new_retention,
+            current_daily_reviews,
+            projected_daily_reviews,
+        })
+    }
+}
+
+impl SqliteRepository {
+    /// Study stats computed from the review log, without the cache.
+    fn compute_study_stats(
+        &self,
+        daily_reset_hour: u32,
+        range: Option<DateRange>,
+    ) -> Result<StudyStats> {
+        let today = get_adjusted_today_string(daily_reset_hour);
         let today_date = get_adjusted_today(daily_reset_hour);
 
+        let format = |date: NaiveDate| date.format("%Y-%m-%d").to_string();
+        let (period_from, period_to) = match range {
+            Some(range) => (format(range.from), format(range.to)),
+            None => (today.clone(), today),
+        };
+        // Totals are unbounded unless a range is given
+        let total_from = range.map(|r| format(r.from));
+        let total_to = range.map(|r| format(r.to));
+
         // Get today's review count
         let reviews_today: usize = self.conn.query_row(
-            "SELECT COUNT(*) FROM pending_reviews WHERE date(reviewed_at) = ?1",
-            params![today],
+            "SELECT COUNT(*) FROM pending_reviews WHERE date(reviewed_at) BETWEEN ?1 AND ?2",
+            params![period_from, period_to],
             |row| row.get(0),
-        ).unwrap_or(0);
+        )?;
 
         // Get today's new cards seen (cards that were 'new' status and got reviewed today)
         let new_today: usize = self.conn.query_row(
             "SELECT COUNT(DISTINCT card_id) FROM pending_reviews
-             WHERE date(reviewed_at) = ?1",
-            params![today],
+             WHERE date(reviewed_at) BETWEEN ?1 AND ?2",
+            params![period_from, period_to],
             |row| row.get(0),
-        ).unwrap_or(0);
+        )?;
 
         // Get total reviews
         let total_reviews: usize = self.conn.query_row(
-            "SELECT COUNT(*) FROM pending_reviews",
-            [],
+            "SELECT COUNT(*) FROM pending_reviews
+             WHERE ?1 IS NULL OR date(reviewed_at) BETWEEN ?1 AND ?2",
+            params![total_from, total_to],
             |row| row.get(0),
-        ).unwrap_or(0);
+        )?;
 
         // Calculate streak (consecutive days with reviews)
         let mut streak_days = 0usize;
@@ -773,7 +1957,7 @@ impl StatsRepository for SqliteRepository {
                 "SELECT COUNT(*) FROM pending_reviews WHERE date(reviewed_at) = ?1",
                 params![date_str],
                 |row| row.get(0),
-            ).unwrap_or(0);
+            )?;
 
             if count > 0 {
                 streak_days += 1;
@@ -797,10 +1981,11 @@ impl StatsRepository for SqliteRepository {
                 CAST(SUM(CASE WHEN rating >= 3 THEN 1 ELSE 0 END) AS REAL) /
                 NULLIF(COUNT(*), 0),
                 0.0
-            ) FROM pending_reviews",
-            [],
+            ) FROM pending_reviews
+            WHERE cram = 0 AND (?1 IS NULL OR date(reviewed_at) BETWEEN ?1 AND ?2)",
+            params![total_from, total_to],
             |row| row.get(0),
-        ).unwrap_or(0.0);
+        )?;
 
         Ok(StudyStats {
             reviews_today,
@@ -811,29 +1996,22 @@ impl StatsRepository for SqliteRepository {
         })
     }
 
-    fn get_calendar_data(&self, days: usize, daily_reset_hour: u32) -> Result<Vec<CalendarData>> {
-        let mut data = Vec::new();
-        let today = get_adjusted_today(daily_reset_hour);
-
-        for i in 0..days {
-            let date = today - chrono::Duration::days(i as i64);
-            let date_str = date.format("%Y-%m-%d").to_string();
-
-            let reviews: usize = self.conn.query_row(
-                "SELECT COUNT(*) FROM pending_reviews WHERE date(reviewed_at) = ?1",
-                params![date_str],
+    /// Stats saved by `refresh_stats_cache` for the current study day, if
+    /// still fresh. Stats for another day or reset hour are stale.
+    fn cached_study_stats(&self, daily_reset_hour: u32) -> Result<Option<StudyStats>> {
+        let json: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT stats FROM stats_cache
+                 WHERE id = 1 AND study_day = ?1 AND daily_reset_hour = ?2",
+                params![get_adjusted_today_string(daily_reset_hour), daily_reset_hour],
                 |row| row.get(0),
-            ).unwrap_or(0);
-
-            data.push(CalendarData {
-                date: date_str,
-                reviews,
-            });
-        }
-
-        // Reverse so oldest is first
-        data.reverse();
-        Ok(data)
+            )
+            .optional()?;
+        json.map(|json| {
+            serde_json::from_str(&json).map_err(|e| DbError::InvalidData(e.to_string()))
+        })
+        .transpose()
     }
 }
 
@@ -842,29 +2020,12 @@ impl SyncRepository for SqliteRepository {
         let mut stmt = self.conn.prepare(
             "SELECT id, card_id, reviewed_at, rating, rating_scale, answer_mode, typed_answer,
                     was_correct, time_taken_ms, interval_before, interval_after,
-                    ease_before, ease_after, algorithm
+                    ease_before, ease_after, algorithm, params_hash, status_before, cram
              FROM pending_reviews WHERE synced = 0",
         )?;
 
         let reviews = stmt
-            .query_map([], |row| {
-                Ok(PendingReview {
-                    id: row.get(0)?,
-                    card_id: row.get(1)?,
-                    reviewed_at: row.get(2)?,
-                    rating: row.get(3)?,
-                    rating_scale: row.get(4)?,
-                    answer_mode: row.get(5)?,
-                    typed_answer: row.get(6)?,
-                    was_correct: row.get::<_, Option<i32>>(7)?.map(|v| v != 0),
-                    time_taken_ms: row.get(8)?,
-                    interval_before: row.get(9)?,
-                    interval_after: row.get(10)?,
-                    ease_before: row.get(11)?,
-                    ease_after: row.get(12)?,
-                    algorithm: row.get(13)?,
-                })
-            })?
+            .query_map([], Self::row_to_review)?
             .collect::<std::result::Result<Vec<_>, _>>()?;
 
         Ok(reviews)
@@ -885,12 +2046,55 @@ impl SyncRepository for SqliteRepository {
         Ok(())
     }
 
+    fn dead_letter_reviews(&self, failed: &[(i64, String)]) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.unchecked_transaction()?;
+        for (id, reason) in failed {
+            tx.execute(
+                "INSERT OR REPLACE INTO failed_reviews (id, card_id, reviewed_at, rating,
+                    rating_scale, answer_mode, typed_answer, was_correct, time_taken_ms,
+                    interval_before, interval_after, ease_before, ease_after, algorithm,
+                    params_hash, status_before, cram, reason, failed_at)
+                 SELECT id, card_id, reviewed_at, rating, rating_scale, answer_mode,
+                    typed_answer, was_correct, time_taken_ms, interval_before, interval_after,
+                    ease_before, ease_after, algorithm, params_hash, status_before, cram, ?2, ?3
+                 FROM pending_reviews WHERE id = ?1",
+                params![id, reason, now],
+            )?;
+            tx.execute("DELETE FROM pending_reviews WHERE id = ?1", params![id])?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_failed_reviews(&self) -> Result<Vec<FailedReview>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, card_id, reviewed_at, rating, rating_scale, answer_mode, typed_answer,
+                    was_correct, time_taken_ms, interval_before, interval_after,
+                    ease_before, ease_after, algorithm, params_hash, status_before, cram,
+                    reason, failed_at
+             FROM failed_reviews ORDER BY failed_at DESC, id",
+        )?;
+
+        let reviews = stmt
+            .query_map([], |row| {
+                Ok(FailedReview {
+                    review: Self::row_to_review(row)?,
+                    reason: row.get(17)?,
+                    failed_at: row.get(18)?,
+                })
+            })?
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(reviews)
+    }
+
     fn insert_pending_review(&self, review: &PendingReview) -> Result<i64> {
         self.conn.execute(
             "INSERT INTO pending_reviews (card_id, reviewed_at, rating, rating_scale, answer_mode,
                 typed_answer, was_correct, time_taken_ms, interval_before, interval_after,
-                ease_before, ease_after, algorithm, synced)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, 0)",
+                ease_before, ease_after, algorithm, params_hash, status_before, cram, synced)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, 0)",
             params![
                 review.card_id,
                 review.reviewed_at,
@@ -905,6 +2109,9 @@ impl SyncRepository for SqliteRepository {
                 review.ease_before,
                 review.ease_after,
                 review.algorithm,
+                review.params_hash,
+                review.status_before,
+                review.cram,
             ],
         )?;
         Ok(self.conn.last_insert_rowid())
@@ -994,6 +2201,30 @@ impl SyncRepository for SqliteRepository {
         Ok(())
     }
 
+    fn get_pending_sync(&self) -> Result<Option<PendingSync>> {
+        let json: Option<String> = self.conn.query_row(
+            "SELECT pending_sync FROM sync_state WHERE id = 1",
+            [],
+            |row| row.get(0),
+        )?;
+        json.map(|json| {
+            serde_json::from_str(&json).map_err(|e| DbError::InvalidData(e.to_string()))
+        })
+        .transpose()
+    }
+
+    fn save_pending_sync(&self, pending: Option<&PendingSync>) -> Result<()> {
+        let json = pending
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| DbError::InvalidData(e.to_string()))?;
+        self.conn.execute(
+            "UPDATE sync_state SET pending_sync = ?1 WHERE id = 1",
+            params![json],
+        )?;
+        Ok(())
+    }
+
     fn increment_pending_changes(&self) -> Result<()> {
         self.conn.execute(
             "UPDATE sync_state SET pending_changes = pending_changes + 1 WHERE id = 1",
@@ -1036,3 +2267,1337 @@ impl SyncRepository for SqliteRepository {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_card(question: &str) -> RawCard {
+        RawCard {
+            id: None,
+            question: question.to_string(),
+            answer: "Answer".to_string(),
+            line_number: 1,
+            media: Vec::new(),
+            meta: flashcard_core::types::CardMeta::default(),
+        }
+    }
+
+    fn pending_review(card_id: i64) -> PendingReview {
+        PendingReview {
+            id: 0,
+            card_id,
+            reviewed_at: Utc::now().to_rfc3339(),
+            rating: 3,
+            rating_scale: "4point".to_string(),
+            answer_mode: "flip".to_string(),
+            typed_answer: None,
+            was_correct: None,
+            time_taken_ms: None,
+            interval_before: 0.0,
+            interval_after: 1.0,
+            ease_before: 2.5,
+            ease_after: 2.5,
+            algorithm: "sm2".to_string(),
+            params_hash: None,
+            status_before: Some("learning".to_string()),
+            cram: false,
+        }
+    }
+
+    #[test]
+    fn test_deck_summaries_totals_match_decks() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        repo.import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+        let ids = repo
+            .import_cards("go", "go.md", &[raw_card("Q3"), raw_card("Q4"), raw_card("Q5")])
+            .unwrap();
+
+        // Make one go card due today
+        let due = CardState {
+            status: CardStatus::Review,
+            interval_days: 1.0,
+            due_date: Some(Utc::now() - chrono::Duration::days(1)),
+            ..CardState::default()
+        };
+        repo.save_card_state(ids[0], &due).unwrap();
+
+        let summaries = repo.get_deck_summaries(0, false).unwrap();
+
+        let paths: Vec<&str> = summaries.decks.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(paths, vec!["go", "rust"]);
+        assert_eq!(summaries.decks[0].new_count, 2);
+        assert_eq!(summaries.decks[0].due_count, 1);
+
+        let sum = |f: fn(&DeckSummary) -> usize| summaries.decks.iter().map(f).sum::<usize>();
+        assert_eq!(summaries.total_cards, sum(|d| d.total));
+        assert_eq!(summaries.total_new, sum(|d| d.new_count));
+        assert_eq!(summaries.total_due, sum(|d| d.due_count));
+        assert_eq!(summaries.total_cards, 5);
+    }
+
+    #[test]
+    fn test_archived_deck_hidden_from_default_list() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        repo.import_cards("rust", "rust.md", &[raw_card("Q1")]).unwrap();
+        repo.import_cards("go", "go.md", &[raw_card("Q2")]).unwrap();
+
+        repo.set_deck_archived("go", true).unwrap();
+
+        let paths = |include_archived| {
+            let mut paths: Vec<String> = repo
+                .get_all_decks(0, include_archived)
+                .unwrap()
+                .into_iter()
+                .map(|d| d.path)
+                .collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(paths(false), vec!["rust"]);
+        assert_eq!(paths(true), vec!["go", "rust"]);
+        assert_eq!(repo.get_deck_summaries(0, false).unwrap().total_cards, 1);
+        assert_eq!(repo.get_deck_summaries(0, true).unwrap().decks.len(), 2);
+
+        // Archiving alone adds no overrides, and a sync pull keeps the flag
+        let settings = repo.get_deck_settings("go").unwrap().unwrap();
+        assert!(settings.archived);
+        assert!(settings.algorithm.is_none() && settings.new_cards_per_day.is_none());
+        let pulled: ApiDeckSettings = serde_json::from_value(serde_json::json!({
+            "deck_path": "go",
+            "new_cards_per_day": 5,
+        }))
+        .unwrap();
+        repo.save_deck_settings_synced(&pulled).unwrap();
+        assert!(repo.get_deck_settings("go").unwrap().unwrap().archived);
+
+        repo.set_deck_archived("go", false).unwrap();
+        assert_eq!(paths(false), vec!["go", "rust"]);
+    }
+
+    #[test]
+    fn test_archived_deck_excluded_from_all_deck_queue() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        repo.import_cards("rust", "rust.md", &[raw_card("Q1")]).unwrap();
+        let go_ids = repo
+            .import_cards("go", "go.md", &[raw_card("Q2"), raw_card("Q3")])
+            .unwrap();
+        let due = CardState {
+            status: CardStatus::Review,
+            interval_days: 1.0,
+            due_date: Some(Utc::now() - chrono::Duration::days(1)),
+            ..CardState::default()
+        };
+        repo.save_card_state(go_ids[0], &due).unwrap();
+
+        repo.set_deck_archived("go", true).unwrap();
+
+        let new_cards = repo.get_new_cards(None, None, 10).unwrap();
+        assert_eq!(new_cards.len(), 1);
+        assert_eq!(new_cards[0].deck_path, "rust");
+        assert!(repo.get_due_cards(None, None, 10, 0, 0).unwrap().is_empty());
+
+        // The deck can still be studied on its own
+        assert_eq!(repo.get_new_cards(Some("go"), None, 10).unwrap().len(), 1);
+        assert_eq!(repo.get_due_cards(Some("go"), None, 10, 0, 0).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_find_similar_cards() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let existing = [
+            raw_card("What is the capital of France?"),
+            raw_card("How does borrowing work in Rust?"),
+        ];
+        repo.import_cards("geo", "geo.md", &existing).unwrap();
+
+        // A typo and different spacing still match
+        let similar = repo
+            .find_similar_cards("what is the  captial of France?", 0.9)
+            .unwrap();
+        assert_eq!(similar.len(), 1);
+        assert_eq!(similar[0].0.question, "What is the capital of France?");
+        assert!(similar[0].1 >= 0.9);
+
+        assert!(repo
+            .find_similar_cards("What is the capital of Spain?", 0.9)
+            .unwrap()
+            .is_empty());
+        assert!(repo
+            .find_similar_cards("Name a prime number", 0.9)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_cards_by_tag() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let tagged = |id: i64, question: &str, tags: &[&str]| RawCard {
+            id: Some(id),
+            meta: CardMeta {
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                ..CardMeta::default()
+            },
+            ..raw_card(question)
+        };
+        let rust = [tagged(1, "Q1", &["exam"]), tagged(2, "Q2", &["oral"])];
+        repo.import_cards("rust", "rust.md", &rust).unwrap();
+        repo.import_cards("go", "go.md", &[tagged(3, "Q3", &["exam", "oral"])])
+            .unwrap();
+
+        let questions = |tag: &str| -> Vec<String> {
+            let cards = repo.get_cards_by_tag(tag).unwrap();
+            cards.into_iter().map(|c| c.question).collect()
+        };
+        assert_eq!(questions("exam"), vec!["Q3", "Q1"]);
+        assert_eq!(questions("#Exam"), vec!["Q3", "Q1"]);
+        assert_eq!(questions("oral"), vec!["Q3", "Q2"]);
+        assert!(questions("final").is_empty());
+
+        // Removing the tag from the file drops the card from the tag
+        repo.import_cards("rust", "rust.md", &[tagged(1, "Q1", &[])]).unwrap();
+        assert_eq!(questions("exam"), vec!["Q3"]);
+    }
+
+    #[test]
+    fn test_unsynced_card_states() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+
+        let reviewed = CardState {
+            status: CardStatus::Review,
+            reviews_count: 1,
+            due_date: Some(Utc::now()),
+            ..CardState::default()
+        };
+        repo.save_card_state(ids[0], &reviewed).unwrap();
+        repo.save_card_states_synced(&[(ids[1], reviewed.clone())])
+            .unwrap();
+
+        let unsynced = repo.get_unsynced_card_states().unwrap();
+        assert_eq!(unsynced.len(), 1);
+        assert_eq!(unsynced[0].card_id, ids[0]);
+        assert_eq!(unsynced[0].state.status, CardStatus::Review);
+        assert!(unsynced[0].updated_at.is_some());
+    }
+
+    #[test]
+    fn test_deck_appearance_round_trips() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let mut settings = DeckSettings::new("rust".to_string());
+        settings.color = Some("#33aa77".to_string());
+        settings.icon = Some("🦀".to_string());
+        repo.save_deck_settings(&settings).unwrap();
+
+        let saved = repo.get_deck_settings("rust").unwrap().unwrap();
+        assert_eq!(saved.color.as_deref(), Some("#33aa77"));
+        assert_eq!(saved.icon.as_deref(), Some("🦀"));
+
+        // Pulled from another device
+        let pulled: ApiDeckSettings = serde_json::from_value(serde_json::json!({
+            "deck_path": "rust",
+            "color": "#fff",
+            "icon": "📚",
+        }))
+        .unwrap();
+        repo.save_deck_settings_synced(&pulled).unwrap();
+        let synced = repo.get_deck_settings("rust").unwrap().unwrap();
+        assert_eq!(synced.color.as_deref(), Some("#fff"));
+        assert_eq!(synced.icon.as_deref(), Some("📚"));
+    }
+
+    #[test]
+    fn test_deck_normalization_rules_round_trip() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let mut settings = DeckSettings::new("french".to_string());
+        settings.normalization_rules = vec![NormRule::Diacritics, NormRule::NumericTolerance(0.01)];
+        repo.save_deck_settings(&settings).unwrap();
+
+        let saved = repo.get_deck_settings("french").unwrap().unwrap();
+        assert_eq!(saved.normalization_rules, settings.normalization_rules);
+        let effective = repo.get_effective_settings(Some("french")).unwrap();
+        assert_eq!(effective.normalization_rules, settings.normalization_rules);
+
+        // Pulled from another device
+        let pulled: ApiDeckSettings = serde_json::from_value(serde_json::json!({
+            "deck_path": "french",
+            "normalization_rules": ["whitespace"],
+        }))
+        .unwrap();
+        repo.save_deck_settings_synced(&pulled).unwrap();
+        let synced = repo.get_deck_settings("french").unwrap().unwrap();
+        assert_eq!(synced.normalization_rules, vec![NormRule::Whitespace]);
+
+        // Clearing the rules stores no spec
+        settings.normalization_rules.clear();
+        repo.save_deck_settings(&settings).unwrap();
+        assert!(repo.get_deck_settings("french").unwrap().unwrap().normalization_rules.is_empty());
+    }
+
+    #[test]
+    fn test_deck_request_retention_round_trip() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        assert_eq!(repo.get_effective_settings(Some("french")).unwrap().request_retention, 0.9);
+
+        let mut settings = DeckSettings::new("french".to_string());
+        settings.request_retention = Some(0.85);
+        repo.save_deck_settings(&settings).unwrap();
+        let saved = repo.get_deck_settings("french").unwrap().unwrap();
+        assert_eq!(saved.request_retention, Some(0.85));
+        // Subdecks inherit it
+        let effective = repo.get_effective_settings(Some("french/verbs")).unwrap();
+        assert_eq!(effective.request_retention, 0.85);
+
+        // Pulled from another device
+        let pulled: ApiDeckSettings = serde_json::from_value(serde_json::json!({
+            "deck_path": "french",
+            "request_retention": 0.95,
+        }))
+        .unwrap();
+        repo.save_deck_settings_synced(&pulled).unwrap();
+        let synced = repo.get_deck_settings("french").unwrap().unwrap();
+        assert_eq!(synced.request_retention, Some(0.95));
+    }
+
+    #[test]
+    fn test_deck_appearance_overrides_no_global_setting() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let global = repo.get_effective_settings(None).unwrap();
+
+        let mut settings = DeckSettings::new("rust".to_string());
+        settings.color = Some("#33aa77".to_string());
+        settings.icon = Some("🦀".to_string());
+        repo.save_deck_settings(&settings).unwrap();
+
+        let effective = repo.get_effective_settings(Some("rust")).unwrap();
+        assert_eq!(
+            serde_json::to_value(&effective).unwrap(),
+            serde_json::to_value(&global).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_effective_settings_inherit_ancestor_overrides() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        for (path, algorithm, new_cards) in [
+            ("lang/es", None, Some(Limit::Count(10))),
+            ("lang", Some(Algorithm::Fsrs), Some(Limit::Count(5))),
+        ] {
+            repo.save_deck_settings(&DeckSettings {
+                algorithm,
+                new_cards_per_day: new_cards,
+                ..DeckSettings::new(path.to_string())
+            })
+            .unwrap();
+        }
+
+        let paths: Vec<String> = repo
+            .get_all_deck_settings()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.deck_path)
+            .collect();
+        assert_eq!(paths, vec!["lang", "lang/es"]);
+
+        let effective = repo.get_effective_settings(Some("lang/es/verbs")).unwrap();
+        assert_eq!(effective.algorithm, Algorithm::Fsrs);
+        assert_eq!(effective.new_cards_per_day, Limit::Count(10));
+    }
+
+    #[test]
+    fn test_deck_appearance_validation() {
+        assert!(DeckSettings::validate_appearance(Some("#3a7"), Some("🦀")).is_ok());
+        assert!(DeckSettings::validate_appearance(Some("#33AA77"), None).is_ok());
+        assert!(DeckSettings::validate_appearance(None, None).is_ok());
+
+        for color in ["33aa77", "#33aa7", "#ggg", "red"] {
+            assert!(DeckSettings::validate_appearance(Some(color), None).is_err(), "{color}");
+        }
+        assert!(DeckSettings::validate_appearance(None, Some("")).is_err());
+        assert!(DeckSettings::validate_appearance(None, Some("a very long icon")).is_err());
+    }
+
+    #[test]
+    fn test_migrations_add_again_delay_setting() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let version: i32 = repo
+            .conn
+            .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, super::super::schema::SCHEMA_VERSION);
+
+        let mut settings = repo.get_global_settings().unwrap();
+        assert_eq!(settings.again_delay_minutes, 1);
+
+        settings.again_delay_minutes = 5;
+        repo.save_global_settings(&settings).unwrap();
+        assert_eq!(repo.get_global_settings().unwrap().again_delay_minutes, 5);
+        assert_eq!(repo.get_global_settings().unwrap().due_grace_hours, 0);
+    }
+
+    #[test]
+    fn test_graduation_progress_is_stored() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let mut settings = repo.get_global_settings().unwrap();
+        assert_eq!(settings.graduation_requirement, 1);
+        settings.graduation_requirement = 2;
+        repo.save_global_settings(&settings).unwrap();
+        let effective = repo.get_effective_settings(None).unwrap();
+        assert_eq!(effective.graduation_requirement, 2);
+
+        let ids = repo.import_cards("rust", "rust.md", &[raw_card("Q1")]).unwrap();
+        let learning = CardState {
+            status: CardStatus::Learning,
+            reviews_count: 1,
+            learning_reps: 1,
+            due_date: Some(Utc::now()),
+            ..Default::default()
+        };
+        repo.save_card_state(ids[0], &learning).unwrap();
+        let stored = repo.get_card_state(ids[0]).unwrap().unwrap();
+        assert_eq!(stored.learning_reps, 1);
+        assert_eq!(repo.get_unsynced_card_states().unwrap()[0].state.learning_reps, 1);
+    }
+
+    #[test]
+    fn test_new_priority_moves_card_to_front() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2"), raw_card("Q3")])
+            .unwrap();
+        let new_ids = || -> Vec<i64> {
+            repo.get_new_cards(None, None, 10).unwrap().iter().map(|c| c.id).collect()
+        };
+        assert_eq!(new_ids(), ids);
+
+        repo.set_new_priority(ids[2], 5).unwrap();
+        assert_eq!(new_ids(), vec![ids[2], ids[0], ids[1]]);
+        assert_eq!(repo.get_new_cards(Some("rust"), None, 1).unwrap()[0].id, ids[2]);
+
+        // Ties keep ID order
+        repo.set_new_priority(ids[1], 5).unwrap();
+        assert_eq!(new_ids(), vec![ids[1], ids[2], ids[0]]);
+
+        assert!(matches!(
+            repo.set_new_priority(999, 1),
+            Err(DbError::CardNotFound(999))
+        ));
+    }
+
+    #[test]
+    fn test_reset_deck_progress() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let rust = repo.import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")]).unwrap();
+        let go = repo.import_cards("go", "go.md", &[raw_card("Q3")]).unwrap();
+        let reviewed = CardState {
+            status: CardStatus::Review,
+            interval_days: 12.0,
+            stability: Some(12.0),
+            reviews_count: 4,
+            due_date: Some(Utc::now()),
+            ..Default::default()
+        };
+        for &id in rust.iter().chain(&go) {
+            repo.save_card_state(id, &reviewed).unwrap();
+            repo.insert_pending_review(&pending_review(id)).unwrap();
+        }
+
+        let reset = repo.reset_deck_progress("rust", true).unwrap();
+        assert_eq!(reset.cards_reset, 2);
+        assert_eq!(reset.reviews_archived, 2);
+
+        let new_cards: Vec<i64> = repo
+            .get_new_cards(Some("rust"), None, 10)
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(new_cards, rust);
+        for id in &rust {
+            let state = repo.get_card_state(*id).unwrap().unwrap();
+            assert_eq!(state.status, CardStatus::New);
+            assert_eq!(state.reviews_count, 0);
+            assert_eq!(state.stability, None);
+        }
+
+        // Other decks keep their progress and history
+        assert_eq!(repo.get_card_state(go[0]).unwrap().unwrap().status, CardStatus::Review);
+        let pending: Vec<i64> = repo.get_pending_reviews().unwrap().iter().map(|r| r.card_id).collect();
+        assert_eq!(pending, go);
+        let archived: i64 = repo
+            .conn
+            .query_row("SELECT COUNT(*) FROM archived_reviews", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(archived, 2);
+    }
+
+    #[test]
+    fn test_save_card_state_requires_due_date_once_studied() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo.import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")]).unwrap();
+        let undated = CardState {
+            status: CardStatus::Review,
+            reviews_count: 1,
+            ..CardState::default()
+        };
+
+        let err = repo.save_card_state(ids[0], &undated).unwrap_err();
+        assert!(matches!(err, DbError::InvalidData(_)));
+        assert_eq!(repo.get_card_state(ids[0]).unwrap().unwrap().status, CardStatus::New);
+        repo.save_card_state(ids[0], &CardState::default()).unwrap();
+
+        // Synced states are stored as-is, so stuck cards can still turn up
+        assert!(repo.get_stuck_card_ids().unwrap().is_empty());
+        repo.save_card_states_synced(&[(ids[1], undated)]).unwrap();
+        assert_eq!(repo.get_stuck_card_ids().unwrap(), vec![ids[1]]);
+    }
+
+    #[test]
+    fn test_deck_path_separator_round_trips() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let mut settings = repo.get_global_settings().unwrap();
+        assert_eq!(settings.deck_path_separator, None);
+        settings.deck_path_separator = Some("::".to_string());
+        repo.save_global_settings(&settings).unwrap();
+        assert_eq!(
+            repo.get_global_settings().unwrap().deck_path_separator.as_deref(),
+            Some("::")
+        );
+    }
+
+    #[test]
+    fn test_auto_sync_config_is_saved_without_marking_settings_for_sync() {
+        use crate::sync::scheduler::AutoSyncConfig;
+
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        assert_eq!(repo.get_auto_sync_config().unwrap(), AutoSyncConfig::default());
+
+        let config = AutoSyncConfig {
+            enabled: true,
+            interval_minutes: 30,
+            paused: true,
+        };
+        repo.save_auto_sync_config(&config).unwrap();
+        assert_eq!(repo.get_auto_sync_config().unwrap(), config);
+
+        let synced: bool = repo
+            .conn
+            .query_row("SELECT synced FROM global_settings WHERE id = 1", [], |row| row.get(0))
+            .unwrap();
+        assert!(synced);
+    }
+
+    fn pulled_card(id: i64, question: &str, updated_at: DateTime<Utc>) -> Card {
+        Card {
+            id,
+            deck_path: "rust".to_string(),
+            question: question.to_string(),
+            answer: "Answer".to_string(),
+            source_file: "rust.md".to_string(),
+            deleted_at: None,
+            updated_at: Some(updated_at),
+        }
+    }
+
+    #[test]
+    fn test_failed_sync_apply_rolls_back() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let before = repo.get_sync_state().unwrap().last_sync_at;
+        let now = Utc::now().to_rfc3339();
+
+        let result: Result<()> = repo.in_transaction(|repo| {
+            repo.upsert_cards_from_sync(&[pulled_card(42, "Pulled", Utc::now())], &now)?;
+            repo.update_sync_state(&now)?;
+            Err(DbError::InvalidData("simulated failure".to_string()))
+        });
+
+        assert!(result.is_err());
+        assert!(repo.get_card(42).unwrap().is_none());
+        assert_eq!(repo.get_sync_state().unwrap().last_sync_at, before);
+    }
+
+    #[test]
+    fn test_locally_newer_card_survives_older_pull() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let edited = RawCard {
+            id: Some(42),
+            ..raw_card("Edited locally")
+        };
+        repo.import_cards("rust", "rust.md", &[edited]).unwrap();
+        let now = Utc::now().to_rfc3339();
+
+        // Stale server copy from before the local edit
+        let stale = pulled_card(42, "Old server copy", Utc::now() - chrono::Duration::hours(1));
+        assert_eq!(repo.upsert_cards_from_sync(&[stale], &now).unwrap().len(), 0);
+        assert_eq!(repo.get_card(42).unwrap().unwrap().question, "Edited locally");
+
+        // A newer server copy still wins once the edit is uploaded
+        repo.mark_cards_uploaded(&now).unwrap();
+        let newer_at = Utc::now() + chrono::Duration::minutes(1);
+        let newer = pulled_card(42, "Newer server copy", newer_at);
+        assert_eq!(repo.upsert_cards_from_sync(&[newer], &now).unwrap().len(), 1);
+        let card = repo.get_card(42).unwrap().unwrap();
+        assert_eq!(card.question, "Newer server copy");
+        assert_eq!(card.updated_at.unwrap().timestamp(), newer_at.timestamp());
+    }
+
+    #[test]
+    fn test_dirty_card_is_not_clobbered_by_stale_pull() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let card = |question: &str| RawCard {
+            id: Some(42),
+            ..raw_card(question)
+        };
+        let question = || repo.get_card(42).unwrap().unwrap().question;
+        let synced_at = Utc::now().to_rfc3339();
+        repo.import_cards("rust", "rust.md", &[card("Original")]).unwrap();
+        repo.mark_cards_uploaded(&synced_at).unwrap();
+
+        // Edited locally, then a pull arrives whose timestamp beats the edit
+        // (clock skew, or a server copy touched after the last upload)
+        repo.import_cards("rust", "rust.md", &[card("Edited locally")])
+            .unwrap();
+        let stale = [pulled_card(42, "Original", Utc::now() + chrono::Duration::minutes(5))];
+        assert_eq!(repo.upsert_cards_from_sync(&stale, &synced_at).unwrap().len(), 0);
+        assert_eq!(question(), "Edited locally");
+
+        // Re-importing the unchanged file keeps the card dirty
+        repo.import_cards("rust", "rust.md", &[card("Edited locally")])
+            .unwrap();
+        assert_eq!(repo.upsert_cards_from_sync(&stale, &synced_at).unwrap().len(), 0);
+
+        // Files read before the edit don't clear it
+        let before_edit = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+        assert_eq!(repo.mark_cards_uploaded(&before_edit).unwrap(), 0);
+
+        // Once uploaded, pulls apply again
+        let uploaded_at = Utc::now().to_rfc3339();
+        assert_eq!(repo.mark_cards_uploaded(&uploaded_at).unwrap(), 1);
+        assert_eq!(repo.upsert_cards_from_sync(&stale, &synced_at).unwrap().len(), 1);
+        assert_eq!(question(), "Original");
+    }
+
+    #[test]
+    fn test_reimport_unchanged_card_keeps_updated_at() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let raw = RawCard {
+            id: Some(7),
+            ..raw_card("Q1")
+        };
+        repo.import_cards("rust", "rust.md", std::slice::from_ref(&raw)).unwrap();
+        let first = repo.get_card(7).unwrap().unwrap().updated_at;
+        assert!(first.is_some());
+
+        repo.import_cards("rust", "rust.md", &[raw]).unwrap();
+        assert_eq!(repo.get_card(7).unwrap().unwrap().updated_at, first);
+    }
+
+    #[test]
+    fn test_content_hash_ids_survive_reimport_and_skip_collisions() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let other = RawCard {
+            id: Some(flashcard_core::parser::content_hash_id("Q2")),
+            ..raw_card("Different card")
+        };
+        repo.import_cards("misc", "misc.md", &[other]).unwrap();
+
+        let mut cards = vec![raw_card("Q1"), raw_card("Q2")];
+        repo.assign_content_ids(&mut cards).unwrap();
+        let first = repo.import_cards("rust", "rust.md", &cards).unwrap();
+        assert_eq!(first[0], flashcard_core::parser::content_hash_id("Q1"));
+        assert_eq!(first[1], flashcard_core::parser::content_hash_id("Q2") + 1);
+
+        // A re-import on another machine (or after restart) yields the same IDs
+        let mut cards = vec![raw_card("Q1"), raw_card("Q2")];
+        repo.assign_content_ids(&mut cards).unwrap();
+        let second = repo.import_cards("rust", "rust.md", &cards).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(repo.get_cards_by_deck("rust").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_pending_reviews_keep_params_hash_per_weight_set() {
+        use flashcard_core::SpacedRepetitionAlgorithm;
+
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo.import_cards("rust", "rust.md", &[raw_card("Q1")]).unwrap();
+
+        let mut tuned = Fsrs::default();
+        tuned.w[0] = 0.5;
+        for algorithm in [Fsrs::default(), tuned] {
+            let review = PendingReview {
+                id: 0,
+                card_id: ids[0],
+                reviewed_at: Utc::now().to_rfc3339(),
+                rating: 3,
+                rating_scale: "4point".to_string(),
+                answer_mode: "flip".to_string(),
+                typed_answer: None,
+                was_correct: None,
+                time_taken_ms: None,
+                interval_before: 0.0,
+                interval_after: 1.0,
+                ease_before: 2.5,
+                ease_after: 2.5,
+                algorithm: "fsrs".to_string(),
+                params_hash: Some(algorithm.params_hash()),
+                status_before: None,
+                cram: false,
+            };
+            repo.insert_pending_review(&review).unwrap();
+        }
+
+        let pending = repo.get_pending_reviews().unwrap();
+        assert_eq!(pending.len(), 2);
+        assert!(pending.iter().all(|r| r.params_hash.is_some()));
+        assert_ne!(pending[0].params_hash, pending[1].params_hash);
+    }
+
+    #[test]
+    fn test_dead_lettered_review_leaves_queue() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+
+        // The first card was deleted on the server, so its review is rejected
+        let rejected = repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+        let accepted = repo.insert_pending_review(&pending_review(ids[1])).unwrap();
+
+        repo.mark_reviews_synced(&[accepted]).unwrap();
+        repo.dead_letter_reviews(&[(rejected, "card_not_found".to_string())])
+            .unwrap();
+
+        // Later reviews sync normally instead of queueing behind the failure
+        let later = repo.insert_pending_review(&pending_review(ids[1])).unwrap();
+        let pending: Vec<i64> = repo
+            .get_pending_reviews()
+            .unwrap()
+            .iter()
+            .map(|r| r.id)
+            .collect();
+        assert_eq!(pending, vec![later]);
+
+        let failed = repo.get_failed_reviews().unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].review.id, rejected);
+        assert_eq!(failed[0].review.card_id, ids[0]);
+        assert_eq!(failed[0].reason, "card_not_found");
+    }
+
+    #[test]
+    fn test_claimed_cards_keep_progress_under_assigned_ids() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let content = "Q: Q1\nA: A1\n\nQ: Q2\nA: A2\n";
+        let cards = flashcard_core::parse(content).unwrap();
+        let local = repo.import_cards("rust", "/notes/rust.md", &cards).unwrap();
+        let studied = CardState {
+            status: CardStatus::Review,
+            interval_days: 3.0,
+            reviews_count: 2,
+            due_date: Some(Utc::now()),
+            ..CardState::default()
+        };
+        repo.save_card_state(local[1], &studied).unwrap();
+        repo.insert_pending_review(&pending_review(local[1])).unwrap();
+
+        // Claims go out by line; the backend assigns IDs from its own sequence
+        let claims = repo.local_card_ids("/notes/rust.md", &cards).unwrap();
+        assert_eq!(claims, vec![(1, local[0]), (4, local[1])]);
+        let assigned = [(local[0], 500), (local[1], 501)];
+        assert_eq!(repo.adopt_card_ids(&assigned).unwrap(), 2);
+
+        // The file comes back with the assigned IDs and imports without duplicates
+        let updated = "ID: 500\nQ: Q1\nA: A1\n\nID: 501\nQ: Q2\nA: A2\n";
+        let cards = flashcard_core::parse(updated).unwrap();
+        repo.import_cards("rust", "/notes/rust.md", &cards).unwrap();
+        assert_eq!(repo.get_cards_by_deck("rust").unwrap().len(), 2);
+        assert!(repo.get_card(local[1]).unwrap().is_none());
+        assert_eq!(repo.get_card_state(501).unwrap().unwrap().reviews_count, 2);
+        let pending = repo.get_pending_reviews().unwrap();
+        assert_eq!(pending[0].card_id, 501);
+
+        // IDs already in use locally are left alone
+        assert_eq!(repo.adopt_card_ids(&[(500, 501)]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_learning_repeats_count_against_review_cap() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+        let since = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+
+        // Introducing a card is a new card, not a review
+        repo.insert_pending_review(&PendingReview {
+            status_before: Some("new".to_string()),
+            ..pending_review(ids[0])
+        })
+        .unwrap();
+        assert_eq!(repo.count_reviews_since(Some("rust"), &since).unwrap(), 0);
+
+        // Each Again repeat while learning is a review submission
+        repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+        repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+        assert_eq!(repo.count_reviews_since(Some("rust"), &since).unwrap(), 2);
+        assert_eq!(repo.count_reviews_since(None, &since).unwrap(), 2);
+        assert_eq!(repo.count_reviews_since(Some("other"), &since).unwrap(), 0);
+
+        // Cram reviews don't use up the day's reviews
+        repo.insert_pending_review(&PendingReview {
+            cram: true,
+            ..pending_review(ids[1])
+        })
+        .unwrap();
+        assert_eq!(repo.count_reviews_since(Some("rust"), &since).unwrap(), 2);
+
+        let later = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        assert_eq!(repo.count_reviews_since(Some("rust"), &later).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_first_local_review_of_a_studied_card_counts_as_review() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1")])
+            .unwrap();
+        let since = (Utc::now() - chrono::Duration::hours(1)).to_rfc3339();
+
+        // Introduced on another device: no earlier review here, but the card
+        // was already in review
+        repo.insert_pending_review(&PendingReview {
+            status_before: Some("review".to_string()),
+            ..pending_review(ids[0])
+        })
+        .unwrap();
+        assert_eq!(repo.count_reviews_since(Some("rust"), &since).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_exhausted_review_cap_offers_only_learning_cards() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards(
+                "rust",
+                "rust.md",
+                &[raw_card("Q1"), raw_card("Q2"), raw_card("Q3")],
+            )
+            .unwrap();
+
+        let now = Utc::now();
+        let learning = CardState {
+            status: CardStatus::Learning,
+            due_date: Some(now),
+            ..CardState::default()
+        };
+        let review = CardState {
+            status: CardStatus::Review,
+            interval_days: 3.0,
+            due_date: Some(now - chrono::Duration::days(1)),
+            ..CardState::default()
+        };
+        repo.save_card_state(ids[0], &learning).unwrap();
+        repo.save_card_state(ids[1], &review).unwrap();
+        repo.save_card_state(ids[2], &review).unwrap();
+
+        let due = |limit| -> Vec<i64> {
+            repo.get_due_cards(Some("rust"), None, limit, 0, 0)
+                .unwrap()
+                .iter()
+                .map(|c| c.id)
+                .collect()
+        };
+        assert_eq!(due(10).len(), 3);
+
+        // One review left: the learning card plus the most overdue review card
+        let capped = due(1);
+        assert_eq!(capped.len(), 2);
+        assert!(capped.contains(&ids[0]));
+
+        // Cap reached: only the in-progress learning card remains
+        assert_eq!(due(0), vec![ids[0]]);
+    }
+
+    #[test]
+    fn test_suspended_meta_card_is_imported_suspended_and_left_out_of_queue() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let content = "Q: Active\nA: 1\n\nQ: Parked\nA: 2\nMETA: suspended=true ease=2.1\n";
+        let raw = flashcard_core::parser::parse(content).unwrap();
+        let ids = repo.import_cards("rust", "rust.md", &raw).unwrap();
+
+        let suspended: bool = repo
+            .conn
+            .query_row(
+                "SELECT suspended FROM card_states WHERE card_id = ?1",
+                params![ids[1]],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert!(suspended);
+        assert_eq!(repo.get_card_state(ids[1]).unwrap().unwrap().ease_factor, 2.1);
+
+        let new_cards: Vec<i64> = repo
+            .get_new_cards(Some("rust"), None, 10)
+            .unwrap()
+            .iter()
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(new_cards, vec![ids[0]]);
+
+        // Even once due, a suspended card stays out of the queue
+        let due = CardState {
+            status: CardStatus::Review,
+            due_date: Some(Utc::now() - chrono::Duration::days(1)),
+            ..CardState::default()
+        };
+        repo.save_card_state(ids[1], &due).unwrap();
+        assert!(repo.get_due_cards(Some("rust"), None, 10, 0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_meta_hints_only_seed_new_cards() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let plain = flashcard_core::parser::parse("ID: 5\nQ: Q1\nA: A1").unwrap();
+        repo.import_cards("rust", "rust.md", &plain).unwrap();
+
+        // Adding META to an already-imported card leaves its state alone
+        let hinted = flashcard_core::parser::parse("ID: 5\nQ: Q1\nA: A1\nMETA: suspended=true")
+            .unwrap();
+        repo.import_cards("rust", "rust.md", &hinted).unwrap();
+        assert_eq!(repo.get_new_cards(Some("rust"), None, 10).unwrap().len(), 1);
+    }
+
+    fn columns(repo: &SqliteRepository, table: &str) -> Vec<String> {
+        let mut stmt = repo
+            .conn
+            .prepare(&format!("PRAGMA table_info({table})"))
+            .unwrap();
+        stmt.query_map([], |row| row.get(1))
+            .unwrap()
+            .collect::<std::result::Result<_, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_migrations_are_ordered_up_to_schema_version() {
+        let versions: Vec<i32> = super::super::schema::MIGRATIONS
+            .iter()
+            .map(|(version, _)| *version)
+            .collect();
+        let expected: Vec<i32> = (2..=super::super::schema::SCHEMA_VERSION).collect();
+        assert_eq!(versions, expected);
+    }
+
+    #[test]
+    fn test_old_schema_database_is_migrated_to_current() {
+        // A database created by the first release: base schema, no migrations
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(super::super::schema::SCHEMA).unwrap();
+        conn.execute_batch(super::super::schema::INIT_GLOBAL_SETTINGS)
+            .unwrap();
+        conn.execute(
+            "INSERT INTO cards (id, deck_path, question_text, answer_text, source_file)
+             VALUES (1, 'rust', 'Q', 'A', 'rust.md')",
+            [],
+        )
+        .unwrap();
+
+        let repo = SqliteRepository { conn };
+        repo.initialize().unwrap();
+
+        let fresh = SqliteRepository::open_in_memory().unwrap();
+        for table in [
+            "cards",
+            "global_settings",
+            "deck_settings",
+            "pending_reviews",
+            "failed_reviews",
+        ] {
+            assert_eq!(columns(&repo, table), columns(&fresh, table), "{table}");
+        }
+        assert!(columns(&repo, "cards").contains(&"updated_at".to_string()));
+
+        // Existing data survives and a second open is a no-op
+        repo.initialize().unwrap();
+        assert_eq!(repo.get_card(1).unwrap().unwrap().question, "Q");
+        assert_eq!(repo.get_global_settings().unwrap().again_delay_minutes, 1);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let newer = super::super::schema::SCHEMA_VERSION + 1;
+        repo.conn
+            .execute(
+                "INSERT INTO schema_version (version) VALUES (?1)",
+                params![newer],
+            )
+            .unwrap();
+
+        assert!(matches!(
+            repo.initialize(),
+            Err(DbError::SchemaTooNew { found, .. }) if found == newer
+        ));
+    }
+
+    #[test]
+    fn test_again_on_learning_card_stays_in_todays_queue() {
+        use flashcard_core::algorithm::get_algorithm;
+        use flashcard_core::types::Rating;
+
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo.import_cards("rust", "rust.md", &[raw_card("Q1")]).unwrap();
+
+        let now = Utc::now();
+        let algorithm = get_algorithm("sm2").unwrap();
+        let result = algorithm
+            .schedule(&algorithm.initial_state(), Rating::Again, now)
+            .with_again_delay(Rating::Again, now, 1);
+        assert!(result.requeue);
+        repo.save_card_state(ids[0], &result.new_state).unwrap();
+
+        let due = repo.get_due_cards(Some("rust"), None, 10, 0, 0).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, ids[0]);
+        // Deck counts agree with the queue
+        assert_eq!(repo.get_deck("rust", 0).unwrap().unwrap().due_count, 1);
+
+        // A card graduated to tomorrow is not part of today's session
+        let result = algorithm.schedule(&result.new_state, Rating::Good, now);
+        repo.save_card_state(ids[0], &result.new_state).unwrap();
+        assert!(repo.get_due_cards(Some("rust"), None, 10, 0, 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_due_grace_window_includes_cards_due_soon() {
+        use chrono::{Duration, Local, Timelike};
+
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+        let now = Utc::now();
+        for (id, hours) in [(ids[0], 6), (ids[1], 10)] {
+            let state = CardState {
+                status: CardStatus::Review,
+                due_date: Some(now + Duration::hours(hours)),
+                ..CardState::default()
+            };
+            repo.save_card_state(id, &state).unwrap();
+        }
+
+        // Reset within the hour, so only the grace window reaches either card
+        let reset_hour = (Local::now().hour() + 1) % 24;
+        assert!(repo.get_due_cards(Some("rust"), None, 10, reset_hour, 0).unwrap().is_empty());
+
+        let due = repo.get_due_cards(Some("rust"), None, 10, reset_hour, 8).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, ids[0]);
+    }
+
+    #[test]
+    fn test_stats_on_empty_database_are_zero() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+
+        let stats = repo.get_study_stats(0, None).unwrap();
+        assert_eq!(stats.reviews_today, 0);
+        assert_eq!(stats.total_reviews, 0);
+        assert_eq!(stats.retention_rate, 0.0);
+
+        let calendar = repo.get_calendar_data(3, 0).unwrap();
+        assert!(calendar.iter().all(|day| day.reviews == 0));
+
+        let deck = repo.get_deck_stats(Some("missing")).unwrap();
+        assert_eq!(deck.total_cards, 0);
+        assert_eq!(deck.new_cards, 0);
+    }
+
+    #[test]
+    fn test_slowest_cards_rank_by_average_time() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards(
+                "rust",
+                "rust.md",
+                &[raw_card("Fast"), raw_card("Slow"), raw_card("Rare")],
+            )
+            .unwrap();
+
+        let timed = |card_id: i64, ms: i32| PendingReview {
+            time_taken_ms: Some(ms),
+            ..pending_review(card_id)
+        };
+        for _ in 0..SLOW_CARD_MIN_REVIEWS {
+            repo.insert_pending_review(&timed(ids[0], 1_000)).unwrap();
+            repo.insert_pending_review(&timed(ids[1], 10_000)).unwrap();
+        }
+        // Too few samples to rank, however slow
+        repo.insert_pending_review(&timed(ids[2], 60_000)).unwrap();
+
+        let slowest = repo.get_slowest_cards(Some("rust"), 10).unwrap();
+        let ranked: Vec<i64> = slowest.iter().map(|c| c.card_id).collect();
+        assert_eq!(ranked, vec![ids[1], ids[0]]);
+        assert_eq!(slowest[0].average_time_ms, 10_000.0);
+        assert_eq!(slowest[0].review_count, SLOW_CARD_MIN_REVIEWS);
+
+        assert_eq!(repo.get_slowest_cards(None, 1).unwrap().len(), 1);
+        assert!(repo.get_slowest_cards(Some("go"), 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_difficult_cards_rank_lapses_first_and_skip_suspended() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards(
+                "rust",
+                "rust.md",
+                &[raw_card("Easy"), raw_card("Leech"), raw_card("Parked"), raw_card("Unseen")],
+            )
+            .unwrap();
+
+        // Due far in the future: difficulty ignores due dates
+        let reviewed = |lapses: u32| CardState {
+            status: CardStatus::Review,
+            interval_days: 10.0,
+            lapses,
+            reviews_count: 8,
+            due_date: Some(Utc::now() + chrono::Duration::days(30)),
+            ..CardState::default()
+        };
+        repo.save_card_state(ids[0], &reviewed(0)).unwrap();
+        repo.save_card_state(ids[1], &reviewed(5)).unwrap();
+        repo.save_card_state(ids[2], &reviewed(9)).unwrap();
+        repo.conn
+            .execute("UPDATE card_states SET suspended = 1 WHERE card_id = ?1", params![ids[2]])
+            .unwrap();
+
+        let difficult = repo.get_difficult_cards(Some("rust"), 10).unwrap();
+        let ranked: Vec<i64> = difficult.iter().map(|c| c.id).collect();
+        assert_eq!(ranked, vec![ids[1], ids[0]]);
+        assert_eq!(repo.get_difficult_cards(None, 1).unwrap()[0].id, ids[1]);
+    }
+
+    #[test]
+    fn test_get_reviews_filters_by_card_and_date() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+        let now = Utc::now();
+        let review_at = |card_id: i64, days_ago: i64| PendingReview {
+            reviewed_at: (now - chrono::Duration::days(days_ago)).to_rfc3339(),
+            ..pending_review(card_id)
+        };
+        let old = repo.insert_pending_review(&review_at(ids[0], 10)).unwrap();
+        let middle = repo.insert_pending_review(&review_at(ids[0], 3)).unwrap();
+        let recent = repo.insert_pending_review(&review_at(ids[0], 1)).unwrap();
+        repo.insert_pending_review(&review_at(ids[1], 2)).unwrap();
+        // Synced reviews stay in the log
+        repo.mark_reviews_synced(&[middle]).unwrap();
+
+        let page = repo
+            .get_reviews(&ReviewFilters {
+                card_id: Some(ids[0]),
+                from: Some(now - chrono::Duration::days(5)),
+                to: Some(now),
+                ..Default::default()
+            })
+            .unwrap();
+        let found: Vec<i64> = page.reviews.iter().map(|r| r.id).collect();
+        assert_eq!(found, vec![recent, middle]);
+        assert!(!page.has_more);
+
+        // Pages are newest first and don't overlap
+        let first = repo
+            .get_reviews(&ReviewFilters {
+                limit: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        let second = repo
+            .get_reviews(&ReviewFilters {
+                offset: 2,
+                limit: 2,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(first.has_more);
+        assert!(!second.has_more);
+        assert_eq!(first.reviews[0].id, recent);
+        assert_eq!(second.reviews.last().unwrap().id, old);
+        assert_eq!(first.reviews.len() + second.reviews.len(), 4);
+    }
+
+    #[test]
+    fn test_collection_stats_count_cards_decks_and_reviews() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+        repo.import_cards("go", "go.md", &[raw_card("Q3")]).unwrap();
+        repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+
+        let stats = repo.get_collection_stats().unwrap();
+        assert_eq!(stats.total_cards, 3);
+        assert_eq!(stats.total_decks, 2);
+        assert_eq!(stats.total_reviews, 1);
+        assert!(stats.database_size_bytes > 0);
+        assert_eq!(stats.remote_file_count, None);
+    }
+
+    #[test]
+    fn test_rating_distribution_counts_each_rating() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")])
+            .unwrap();
+        let go_ids = repo.import_cards("go", "go.md", &[raw_card("Q3")]).unwrap();
+
+        for rating in [1, 3, 3, 4] {
+            let review = PendingReview {
+                rating,
+                ..pending_review(ids[0])
+            };
+            repo.insert_pending_review(&review).unwrap();
+        }
+        assert_eq!(repo.get_rating_distribution(Some("rust")).unwrap(), [1, 0, 2, 1]);
+
+        // 2-point Correct is a Good, not a Hard
+        let two_point = PendingReview {
+            rating: 2,
+            rating_scale: "2point".to_string(),
+            ..pending_review(go_ids[0])
+        };
+        repo.insert_pending_review(&two_point).unwrap();
+        assert_eq!(repo.get_rating_distribution(Some("go")).unwrap(), [0, 0, 1, 0]);
+        assert_eq!(repo.get_rating_distribution(None).unwrap(), [1, 0, 3, 1]);
+    }
+
+    #[test]
+    fn test_lower_retention_projects_fewer_reviews() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo
+            .import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2"), raw_card("Q3")])
+            .unwrap();
+        for (&id, stability) in ids.iter().zip([3.0, 20.0]) {
+            let state = CardState {
+                status: CardStatus::Review,
+                stability: Some(stability),
+                difficulty: Some(5.0),
+                reviews_count: 3,
+                ..Default::default()
+            };
+            repo.save_card_state(id, &state).unwrap();
+        }
+
+        let same = repo.project_workload(Some("rust"), 0.9).unwrap();
+        assert_eq!(same.cards, 2);
+        assert_eq!(same.current_retention, 0.9);
+        assert!((same.projected_daily_reviews - same.current_daily_reviews).abs() < 1e-9);
+
+        let lower = repo.project_workload(Some("rust"), 0.85).unwrap();
+        assert_eq!(lower.new_retention, 0.85);
+        assert!(lower.current_daily_reviews > 0.0);
+        assert!(lower.projected_daily_reviews < lower.current_daily_reviews);
+
+        let empty = repo.project_workload(Some("go"), 0.85).unwrap();
+        assert_eq!(empty.cards, 0);
+        assert_eq!(empty.projected_daily_reviews, 0.0);
+
+        // The current load is at the deck's own retention
+        let mut settings = DeckSettings::new("rust".to_string());
+        settings.request_retention = Some(0.85);
+        repo.save_deck_settings(&settings).unwrap();
+        let at_deck = repo.project_workload(Some("rust"), 0.85).unwrap();
+        assert_eq!(at_deck.current_retention, 0.85);
+        assert!((at_deck.current_daily_reviews - lower.projected_daily_reviews).abs() < 1e-9);
+        assert_eq!(repo.project_workload(None, 0.85).unwrap().current_retention, 0.9);
+    }
+
+    #[test]
+    fn test_stats_surface_query_errors() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo.import_cards("rust", "rust.md", &[raw_card("Q1")]).unwrap();
+        repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+
+        // Simulate schema drift in pending_reviews
+        repo.conn
+            .execute_batch("ALTER TABLE pending_reviews RENAME COLUMN reviewed_at TO seen_at;")
+            .unwrap();
+
+        assert!(repo.get_study_stats(0, None).is_err());
+        assert!(repo.get_calendar_data(3, 0).is_err());
+    }
+
+    #[test]
+    fn test_stats_cache_matches_live_stats_until_stale() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo.import_cards("rust", "rust.md", &[raw_card("Q1"), raw_card("Q2")]).unwrap();
+        repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+        repo.insert_pending_review(&PendingReview {
+            rating: 1,
+            ..pending_review(ids[1])
+        })
+        .unwrap();
+
+        let live = repo.compute_study_stats(0, None).unwrap();
+        assert_eq!(repo.refresh_stats_cache(0).unwrap(), live);
+        assert_eq!(repo.cached_study_stats(0).unwrap(), Some(live.clone()));
+        assert_eq!(repo.get_study_stats(0, None).unwrap(), live);
+
+        // A new review invalidates the cache
+        repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+        assert_eq!(repo.cached_study_stats(0).unwrap(), None);
+        let stats = repo.get_study_stats(0, None).unwrap();
+        assert_eq!(stats.total_reviews, 3);
+
+        // Stats cached on an earlier study day or for another reset hour are
+        // recomputed
+        repo.refresh_stats_cache(0).unwrap();
+        assert_eq!(repo.cached_study_stats(5).unwrap(), None);
+        repo.conn
+            .execute(
+                "UPDATE stats_cache SET study_day = '2000-01-01', stats = ?1",
+                params![serde_json::to_string(&live).unwrap()],
+            )
+            .unwrap();
+        assert_eq!(repo.get_study_stats(0, None).unwrap().total_reviews, 3);
+    }
+
+    #[test]
+    fn test_study_stats_for_date_range() {
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let ids = repo.import_cards("rust", "rust.md", &[raw_card("Q1")]).unwrap();
+        repo.insert_pending_review(&pending_review(ids[0])).unwrap();
+        repo.insert_pending_review(&PendingReview {
+            rating: 1,
+            ..pending_review(ids[0])
+        })
+        .unwrap();
+
+        let today = Utc::now().date_naive();
+        let past = DateRange {
+            from: today - chrono::Duration::days(30),
+            to: today - chrono::Duration::days(2),
+        };
+        let stats = repo.get_study_stats(0, Some(past)).unwrap();
+        assert_eq!(stats.reviews_today, 0);
+        assert_eq!(stats.new_today, 0);
+        assert_eq!(stats.total_reviews, 0);
+        assert_eq!(stats.retention_rate, 0.0);
+
+        let recent = DateRange {
+            from: today - chrono::Duration::days(1),
+            to: today + chrono::Duration::days(1),
+        };
+        let stats = repo.get_study_stats(0, Some(recent)).unwrap();
+        assert_eq!(stats.reviews_today, 2);
+        assert_eq!(stats.new_today, 1);
+        assert_eq!(stats.total_reviews, 2);
+        assert_eq!(stats.retention_rate, 0.5);
+    }
+}
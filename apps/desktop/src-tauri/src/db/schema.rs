@@ -1,7 +1,196 @@
 //! SQLite schema definitions.
 
 /// Current schema version for migrations.
-pub const SCHEMA_VERSION: i32 = 1;
+pub const SCHEMA_VERSION: i32 = 30;
+
+/// Migrations applied on top of [`SCHEMA`] (version 1), keyed by the
+/// version they upgrade to.
+pub const MIGRATIONS: &[(i32, &str)] = &[
+    (
+        2,
+        "ALTER TABLE global_settings ADD COLUMN again_delay_minutes INTEGER NOT NULL DEFAULT 1;",
+    ),
+    (3, "ALTER TABLE cards ADD COLUMN updated_at TEXT;"),
+    (4, "ALTER TABLE pending_reviews ADD COLUMN params_hash TEXT;"),
+    (
+        5,
+        r#"
+-- Reviews the backend permanently rejected, kept for inspection
+CREATE TABLE IF NOT EXISTS failed_reviews (
+    id INTEGER PRIMARY KEY,
+    card_id INTEGER NOT NULL,
+    reviewed_at TEXT NOT NULL,
+    rating INTEGER NOT NULL,
+    rating_scale TEXT NOT NULL,
+    answer_mode TEXT NOT NULL,
+    typed_answer TEXT,
+    was_correct INTEGER,
+    time_taken_ms INTEGER,
+    interval_before REAL,
+    interval_after REAL,
+    ease_before REAL,
+    ease_after REAL,
+    algorithm TEXT NOT NULL,
+    params_hash TEXT,
+    reason TEXT NOT NULL,
+    failed_at TEXT NOT NULL
+);
+"#,
+    ),
+    (
+        6,
+        "ALTER TABLE card_states ADD COLUMN suspended INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        7,
+        "ALTER TABLE deck_settings ADD COLUMN color TEXT;
+         ALTER TABLE deck_settings ADD COLUMN icon TEXT;",
+    ),
+    (
+        8,
+        "ALTER TABLE deck_settings ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (9, "ALTER TABLE cards ADD COLUMN dirty INTEGER NOT NULL DEFAULT 0;"),
+    (
+        10,
+        r#"
+-- Tags from a card's META line, one row per tag
+CREATE TABLE IF NOT EXISTS card_tags (
+    card_id INTEGER NOT NULL REFERENCES cards(id),
+    tag TEXT NOT NULL,
+    PRIMARY KEY (card_id, tag)
+);
+CREATE INDEX IF NOT EXISTS idx_card_tags_tag ON card_tags(tag);
+"#,
+    ),
+    (
+        11,
+        "ALTER TABLE global_settings ADD COLUMN due_grace_hours INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        12,
+        "ALTER TABLE global_settings ADD COLUMN graduation_requirement INTEGER NOT NULL DEFAULT 1;
+         ALTER TABLE card_states ADD COLUMN learning_reps INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        13,
+        r#"
+-- Reviews moved out of the history by a deck progress reset
+CREATE TABLE IF NOT EXISTS archived_reviews (
+    id INTEGER PRIMARY KEY,
+    card_id INTEGER NOT NULL,
+    reviewed_at TEXT NOT NULL,
+    rating INTEGER NOT NULL,
+    rating_scale TEXT NOT NULL,
+    answer_mode TEXT NOT NULL,
+    typed_answer TEXT,
+    was_correct INTEGER,
+    time_taken_ms INTEGER,
+    interval_before REAL,
+    interval_after REAL,
+    ease_before REAL,
+    ease_after REAL,
+    algorithm TEXT NOT NULL,
+    params_hash TEXT,
+    synced INTEGER NOT NULL,
+    archived_at TEXT NOT NULL
+);
+"#,
+    ),
+    (14, "ALTER TABLE global_settings ADD COLUMN deck_path_separator TEXT;"),
+    (
+        15,
+        "ALTER TABLE global_settings ADD COLUMN suggest_easy_threshold REAL NOT NULL DEFAULT 1.0;
+         ALTER TABLE global_settings ADD COLUMN suggest_hard_threshold REAL NOT NULL DEFAULT 0.6;",
+    ),
+    (
+        16,
+        "ALTER TABLE global_settings ADD COLUMN queue_order TEXT NOT NULL DEFAULT 'new_first';",
+    ),
+    (
+        17,
+        "ALTER TABLE global_settings ADD COLUMN min_ease REAL NOT NULL DEFAULT 1.3;
+         ALTER TABLE global_settings ADD COLUMN max_ease REAL NOT NULL DEFAULT 5.0;",
+    ),
+    (18, "ALTER TABLE deck_settings ADD COLUMN normalization_rules TEXT;"),
+    (
+        19,
+        "ALTER TABLE global_settings ADD COLUMN sync_conflict_policy TEXT NOT NULL DEFAULT 'manual';
+         ALTER TABLE card_states ADD COLUMN updated_at TEXT;",
+    ),
+    (
+        20,
+        "ALTER TABLE global_settings ADD COLUMN new_cards_paused INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        21,
+        "ALTER TABLE global_settings ADD COLUMN study_direction TEXT NOT NULL DEFAULT 'forward';",
+    ),
+    (
+        22,
+        "ALTER TABLE global_settings ADD COLUMN session_limit TEXT NOT NULL DEFAULT 'none';",
+    ),
+    (
+        23,
+        "ALTER TABLE card_states ADD COLUMN new_priority INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (24, "ALTER TABLE sync_state ADD COLUMN pending_sync TEXT;"),
+    (
+        25,
+        r#"
+-- Study stats computed by refresh_stats_cache, valid for one study day
+CREATE TABLE IF NOT EXISTS stats_cache (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    study_day TEXT NOT NULL,
+    daily_reset_hour INTEGER NOT NULL,
+    stats TEXT NOT NULL,
+    computed_at TEXT NOT NULL
+);
+
+-- Any change to the review log makes the cached stats stale
+CREATE TRIGGER IF NOT EXISTS stats_cache_review_added AFTER INSERT ON pending_reviews
+BEGIN
+    DELETE FROM stats_cache;
+END;
+CREATE TRIGGER IF NOT EXISTS stats_cache_review_removed AFTER DELETE ON pending_reviews
+BEGIN
+    DELETE FROM stats_cache;
+END;
+"#,
+    ),
+    (
+        26,
+        r#"
+-- Card status before each review, telling introductions from repeats
+ALTER TABLE pending_reviews ADD COLUMN status_before TEXT;
+ALTER TABLE failed_reviews ADD COLUMN status_before TEXT;
+ALTER TABLE archived_reviews ADD COLUMN status_before TEXT;
+"#,
+    ),
+    (
+        27,
+        "ALTER TABLE global_settings ADD COLUMN min_answer_chars INTEGER NOT NULL DEFAULT 0;",
+    ),
+    (
+        28,
+        r#"
+-- Auto-sync configuration; per device, never synced
+ALTER TABLE global_settings ADD COLUMN auto_sync_enabled INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE global_settings ADD COLUMN auto_sync_interval_minutes INTEGER NOT NULL DEFAULT 15;
+ALTER TABLE global_settings ADD COLUMN auto_sync_paused INTEGER NOT NULL DEFAULT 0;
+"#,
+    ),
+    (
+        29,
+        r#"
+-- Cram reviews left the schedule alone, so limits and retention skip them
+ALTER TABLE pending_reviews ADD COLUMN cram INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE failed_reviews ADD COLUMN cram INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE archived_reviews ADD COLUMN cram INTEGER NOT NULL DEFAULT 0;
+"#,
+    ),
+    (30, "ALTER TABLE deck_settings ADD COLUMN request_retention REAL;"),
+];
 
 /// Complete schema for local SQLite database.
 pub const SCHEMA: &str = r#"
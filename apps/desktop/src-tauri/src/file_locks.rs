@@ -0,0 +1,101 @@
+//! Advisory per-file locks shared by the watcher, imports and sync.
+//!
+//! The watcher reads a file as soon as it changes, while a sync may still be
+//! writing assigned IDs back into it. Both sides take the file's lock around
+//! their disk access so a read never sees a half-written file. Paths are
+//! canonicalized first, so a file reached by different paths (relative, or
+//! through a watched directory) still has a single lock.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Locks keyed by file path. Cloning shares the same set of locks.
+#[derive(Clone, Default)]
+pub struct FileLocks {
+    locks: Arc<Mutex<HashMap<PathBuf, Arc<Mutex<()>>>>>,
+}
+
+impl FileLocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run `f` while holding the lock for `path`, waiting for any other
+    /// holder first. Locks for different paths don't block each other.
+    pub fn with_lock<T>(&self, path: &Path, f: impl FnOnce() -> T) -> T {
+        let key = lock_key(path);
+        let lock = {
+            let mut locks = self.locks.lock().expect("file locks");
+            locks.entry(key.clone()).or_default().clone()
+        };
+
+        let result = {
+            let _guard = lock.lock().unwrap_or_else(|e| e.into_inner());
+            f()
+        };
+
+        // Drop the entry once nobody else holds or waits on it
+        let mut locks = self.locks.lock().expect("file locks");
+        if Arc::strong_count(&lock) == 2 {
+            locks.remove(&key);
+        }
+        result
+    }
+}
+
+/// The key for `path`: its canonical form, or the path as given for a file
+/// that doesn't exist (yet).
+fn lock_key(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lock_serializes_access_to_same_path() {
+        let locks = FileLocks::new();
+        let path = Path::new("/decks/rust.md");
+        let (locked_tx, locked_rx) = channel();
+        let order = Arc::new(Mutex::new(Vec::new()));
+
+        let writer = {
+            let locks = locks.clone();
+            let order = order.clone();
+            thread::spawn(move || {
+                locks.with_lock(path, || {
+                    locked_tx.send(()).unwrap();
+                    thread::sleep(Duration::from_millis(50));
+                    order.lock().unwrap().push("write");
+                });
+            })
+        };
+
+        locked_rx.recv().unwrap();
+        locks.with_lock(path, || order.lock().unwrap().push("read"));
+        writer.join().unwrap();
+
+        assert_eq!(*order.lock().unwrap(), vec!["write", "read"]);
+        assert!(locks.locks.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lock_key_is_shared_by_paths_to_the_same_file() {
+        let dir = std::env::temp_dir().join(format!("flashcards-locks-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("rust")).unwrap();
+        let file = dir.join("rust/basics.md");
+        std::fs::write(&file, "Q: Basics\nA: Answer\n").unwrap();
+
+        // As typed into import_file, and as joined by sync write-back
+        let typed = dir.join("rust/../rust/./basics.md");
+        let (typed_key, joined_key) = (lock_key(&typed), lock_key(&file));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(typed_key, joined_key);
+    }
+}
@@ -1,26 +1,40 @@
 mod commands;
 mod db;
+mod file_locks;
 mod parser;
 mod state;
 mod sync;
 mod watcher;
 
-use commands::deck::{get_deck, import_directory, import_file, list_decks};
+use commands::deck::{
+    get_deck, get_deck_summaries, import_directory, import_file, list_decks, reset_deck_progress,
+    scan_directory,
+};
 use commands::settings::{
     delete_deck_settings, get_deck_settings, get_effective_settings, get_global_settings,
-    save_deck_settings, save_global_settings,
+    save_deck_settings, save_global_settings, set_deck_archived,
+};
+use commands::stats::{
+    collection_stats, get_calendar_data, get_deck_stats, get_rating_distribution, get_reviews,
+    get_slowest_cards, get_study_stats, project_workload, refresh_stats_cache,
+};
+use commands::study::{
+    compare_typed_answer, get_card, get_card_state, get_card_with_state, get_cards_by_tag,
+    get_difficult_cards, get_difficult_study_queue, get_study_queue, preview_review_debug,
+    set_new_priority, submit_review,
 };
-use commands::stats::{get_calendar_data, get_deck_stats, get_study_stats};
-use commands::study::{compare_typed_answer, get_card, get_card_state, get_study_queue, submit_review};
 use commands::sync::{
-    cancel_sync, check_connectivity, confirm_orphan_deletion, get_device_status,
-    get_local_sync_state, get_sync_status, register_device, skip_orphan_deletion, start_sync,
+    auto_resolve_sync_conflicts, cancel_sync, check_connectivity, confirm_orphan_deletion,
+    diagnostics, get_auto_sync, get_device_status, get_failed_reviews, get_local_sync_state,
+    get_sync_status, next_orphan_batch, register_device, resolve_sync_conflicts, run_auto_sync,
+    set_auto_sync, skip_orphan_deletion, start_sync,
 };
 use commands::watcher::{get_watched_directories, start_watching, stop_watching};
 use commands::SyncEngineState;
 use db::SqliteRepository;
 use state::AppState;
 use std::path::PathBuf;
+use tauri::Manager;
 
 fn get_db_path() -> PathBuf {
     // Use app data directory for production, fallback to current dir
@@ -40,31 +54,48 @@ pub fn run() {
 
     // Open database
     let repository = SqliteRepository::open(&db_path).expect("failed to open database");
+    let auto_sync_config = repository.get_auto_sync_config().unwrap_or_default();
     let app_state = AppState::new(repository);
 
     let sync_engine_state = SyncEngineState::new();
+    sync_engine_state.auto_sync.set_config(auto_sync_config);
 
     tauri::Builder::default()
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(app_state)
         .manage(sync_engine_state)
-        .setup(|_app| {
+        .setup(|app| {
             // File watcher will be started when the first directory is watched
             // via the start_watching command
+            tauri::async_runtime::spawn(run_auto_sync(app.handle().clone()));
             Ok(())
         })
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Focused(true) = event {
+                window.state::<SyncEngineState>().auto_sync.notify_focus();
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             // Deck commands
             list_decks,
             import_file,
             import_directory,
+            scan_directory,
             get_deck,
+            reset_deck_progress,
+            get_deck_summaries,
             // Study commands
             get_study_queue,
             submit_review,
+            preview_review_debug,
             get_card,
+            get_card_with_state,
+            get_cards_by_tag,
+            get_difficult_cards,
+            get_difficult_study_queue,
             get_card_state,
+            set_new_priority,
             compare_typed_answer,
             // Settings commands
             get_global_settings,
@@ -72,11 +103,18 @@ pub fn run() {
             get_deck_settings,
             save_deck_settings,
             delete_deck_settings,
+            set_deck_archived,
             get_effective_settings,
             // Stats commands
             get_deck_stats,
             get_study_stats,
+            refresh_stats_cache,
             get_calendar_data,
+            get_slowest_cards,
+            get_rating_distribution,
+            get_reviews,
+            project_workload,
+            collection_stats,
             // Watcher commands
             start_watching,
             stop_watching,
@@ -86,11 +124,18 @@ pub fn run() {
             get_sync_status,
             cancel_sync,
             confirm_orphan_deletion,
+            next_orphan_batch,
             skip_orphan_deletion,
+            resolve_sync_conflicts,
+            auto_resolve_sync_conflicts,
             register_device,
             get_device_status,
             check_connectivity,
             get_local_sync_state,
+            diagnostics,
+            get_failed_reviews,
+            get_auto_sync,
+            set_auto_sync,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
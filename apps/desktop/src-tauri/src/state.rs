@@ -1,6 +1,7 @@
 //! Application state.
 
 use crate::db::SqliteRepository;
+use crate::file_locks::FileLocks;
 use crate::watcher::FileWatcher;
 use std::sync::{Arc, Mutex};
 use tokio::sync::Mutex as AsyncMutex;
@@ -9,6 +10,7 @@ use tokio::sync::Mutex as AsyncMutex;
 pub struct AppState {
     pub repository: Arc<Mutex<SqliteRepository>>,
     pub watcher: AsyncMutex<FileWatcher>,
+    pub file_locks: FileLocks,
 }
 
 impl AppState {
@@ -16,6 +18,7 @@ impl AppState {
         Self {
             repository: Arc::new(Mutex::new(repository)),
             watcher: AsyncMutex::new(FileWatcher::new()),
+            file_locks: FileLocks::new(),
         }
     }
 }
@@ -1,14 +1,19 @@
 //! Sync engine for cloud synchronization.
 
-use chrono::{DateTime, Utc};
+pub mod scheduler;
+
+use chrono::{DateTime, Local, Utc};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use crate::db::{LocalSyncState, PendingReview};
-use flashcard_core::types::{Card, CardState, CardStatus};
+use crate::db::{CardChange, LocalSyncState, PendingReview, TimedCardState};
+use flashcard_core::types::{
+    Card, CardState, CardStatus, ConflictPolicy, Limit, NormRule, ReviewSubmission,
+};
 
 /// Sync errors.
 #[derive(Debug, thiserror::Error)]
@@ -36,6 +41,12 @@ pub enum SyncError {
 
     #[error("Parse error: {0}")]
     Parse(String),
+
+    #[error("No conflicts awaiting resolution")]
+    NoPendingConflicts,
+
+    #[error("No further orphans to review")]
+    NoPendingOrphans,
 }
 
 /// Sync status for UI.
@@ -44,7 +55,8 @@ pub enum SyncError {
 pub enum SyncStatus {
     Idle,
     Syncing { stage: SyncStage, progress: f32 },
-    AwaitingOrphanConfirmation { orphans: Vec<OrphanInfo> },
+    AwaitingOrphanConfirmation { orphans: Vec<OrphanInfo>, has_more: bool },
+    AwaitingConflictResolution { conflicts: Vec<StateConflict> },
     Completed { synced_at: String, stats: SyncStats },
     Failed { error: String },
 }
@@ -64,14 +76,25 @@ pub enum SyncStage {
 }
 
 /// Sync statistics.
-#[derive(Debug, Clone, Serialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct SyncStats {
     pub files_uploaded: usize,
     pub cards_created: usize,
     pub cards_updated: usize,
     pub orphans_deleted: usize,
     pub reviews_synced: usize,
+    pub reviews_failed: usize,
     pub states_pulled: usize,
+    /// Pulled card changes by deck path.
+    pub per_deck: HashMap<String, DeckDelta>,
+}
+
+/// Cards a sync changed in one deck.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeckDelta {
+    pub created: usize,
+    pub updated: usize,
+    pub deleted: usize,
 }
 
 /// Orphan card info.
@@ -81,11 +104,223 @@ pub struct OrphanInfo {
     pub question_preview: String,
 }
 
+/// Everything a pull changes locally, applied in one transaction.
+#[derive(Debug)]
+pub struct PulledChanges<'a> {
+    pub cards: &'a [Card],
+    /// Pulled states, minus those left as conflicts.
+    pub states: &'a [(i64, CardState)],
+    pub global_settings: &'a ApiGlobalSettings,
+    pub deck_settings: &'a [ApiDeckSettings],
+    pub synced_at: &'a str,
+    /// Whether to record `synced_at` as the last sync. False while conflicts
+    /// await resolution.
+    pub completes_sync: bool,
+}
+
+/// A card whose pulled state disagrees with unsynced local progress.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateConflict {
+    pub card_id: i64,
+    pub local: CardState,
+    pub remote: CardState,
+    #[serde(default)]
+    pub local_updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub remote_updated_at: Option<DateTime<Utc>>,
+}
+
+/// Which side of a conflict to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictChoice {
+    KeepLocal,
+    KeepRemote,
+}
+
+/// User's choice for one conflicting card.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictResolution {
+    pub card_id: i64,
+    pub choice: ConflictChoice,
+}
+
+impl StateConflict {
+    /// Side with more progress: more reviews wins, ties keep the remote state.
+    pub fn auto_choice(&self) -> ConflictChoice {
+        if self.local.reviews_count > self.remote.reviews_count {
+            ConflictChoice::KeepLocal
+        } else {
+            ConflictChoice::KeepRemote
+        }
+    }
+
+    /// Side `policy` keeps, or `None` when the user has to choose.
+    ///
+    /// Last-write-wins falls back to [`auto_choice`](Self::auto_choice) when
+    /// either side's change time is unknown; ties keep the remote state.
+    pub fn policy_choice(&self, policy: ConflictPolicy) -> Option<ConflictChoice> {
+        match policy {
+            ConflictPolicy::Manual => None,
+            ConflictPolicy::MostProgress => Some(self.auto_choice()),
+            ConflictPolicy::LastWriteWins => match (self.local_updated_at, self.remote_updated_at) {
+                (Some(local), Some(remote)) if local > remote => Some(ConflictChoice::KeepLocal),
+                (Some(_), Some(_)) => Some(ConflictChoice::KeepRemote),
+                _ => Some(self.auto_choice()),
+            },
+        }
+    }
+
+    fn chosen(&self, choice: ConflictChoice) -> CardState {
+        match choice {
+            ConflictChoice::KeepLocal => self.local.clone(),
+            ConflictChoice::KeepRemote => self.remote.clone(),
+        }
+    }
+}
+
+/// Split pulled states into ones safe to apply and conflicts with unsynced
+/// local states that changed differently.
+///
+/// Conflicts `policy` settles are applied with the side it keeps; only
+/// those left for the user are returned as conflicts.
+pub fn split_state_conflicts(
+    pulled: Vec<TimedCardState>,
+    local_unsynced: &[TimedCardState],
+    policy: ConflictPolicy,
+) -> (Vec<(i64, CardState)>, Vec<StateConflict>) {
+    let local: HashMap<i64, &TimedCardState> =
+        local_unsynced.iter().map(|s| (s.card_id, s)).collect();
+
+    let mut apply = Vec::new();
+    let mut conflicts = Vec::new();
+    for remote in pulled {
+        let conflict = match local.get(&remote.card_id) {
+            Some(local) if !same_progress(&local.state, &remote.state) => StateConflict {
+                card_id: remote.card_id,
+                local: local.state.clone(),
+                remote: remote.state,
+                local_updated_at: local.updated_at,
+                remote_updated_at: remote.updated_at,
+            },
+            _ => {
+                apply.push((remote.card_id, remote.state));
+                continue;
+            }
+        };
+        match conflict.policy_choice(policy) {
+            Some(choice) => apply.push((conflict.card_id, conflict.chosen(choice))),
+            None => conflicts.push(conflict),
+        }
+    }
+    (apply, conflicts)
+}
+
+/// Backend page size for orphan listings.
+const ORPHAN_PAGE_SIZE: i64 = 100;
+
+/// Position in the paginated orphan listing of the current sync.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct OrphanScan {
+    /// Card IDs present in the uploaded files.
+    current_card_ids: Vec<i64>,
+    /// Offset of the displayed page.
+    offset: i64,
+    /// Card IDs on the displayed page.
+    page: Vec<i64>,
+    /// Cards from the displayed page that were deleted.
+    deleted: usize,
+    has_more: bool,
+}
+
+impl OrphanScan {
+    /// Offset of the next page. Deleted cards drop out of the listing, so only
+    /// the kept ones are skipped.
+    fn next_offset(&self) -> i64 {
+        self.offset + self.page.len().saturating_sub(self.deleted) as i64
+    }
+}
+
+/// Card IDs declared in the files as they are after the upload.
+fn uploaded_card_ids(md_files: &[(String, String)], updated_files: &[UpdatedFile]) -> Vec<i64> {
+    md_files
+        .iter()
+        .map(|(path, content)| {
+            updated_files
+                .iter()
+                .find(|f| &f.path == path)
+                .map_or(content.as_str(), |f| f.content.as_str())
+        })
+        .filter_map(|content| flashcard_core::parse(content).ok())
+        .flatten()
+        .filter_map(|card| card.id)
+        .collect()
+}
+
+/// Split pushed reviews into accepted IDs and `(id, reason)` pairs the
+/// backend rejected for good.
+fn split_push_result(
+    reviews: &[PendingReview],
+    rejected: &[RejectedReview],
+) -> (Vec<i64>, Vec<(i64, String)>) {
+    let failed: Vec<(i64, String)> = rejected
+        .iter()
+        .filter_map(|r| reviews.get(r.index).map(|review| (review.id, r.reason.clone())))
+        .collect();
+    let accepted = reviews
+        .iter()
+        .map(|r| r.id)
+        .filter(|id| !failed.iter().any(|(failed_id, _)| failed_id == id))
+        .collect();
+    (accepted, failed)
+}
+
+/// Tally applied card changes under each card's deck.
+fn add_deck_deltas(
+    per_deck: &mut HashMap<String, DeckDelta>,
+    cards: &[Card],
+    changes: &[(i64, CardChange)],
+) {
+    let decks: HashMap<i64, &str> = cards.iter().map(|c| (c.id, c.deck_path.as_str())).collect();
+    for (id, change) in changes {
+        let Some(deck) = decks.get(id) else {
+            continue;
+        };
+        let delta = per_deck.entry(deck.to_string()).or_default();
+        match change {
+            CardChange::Created => delta.created += 1,
+            CardChange::Updated => delta.updated += 1,
+            CardChange::Deleted => delta.deleted += 1,
+        }
+    }
+}
+
+fn orphan_infos(cards: &[OrphanedCard]) -> Vec<OrphanInfo> {
+    cards
+        .iter()
+        .map(|o| OrphanInfo {
+            card_id: o.id,
+            question_preview: o.question_preview.clone(),
+        })
+        .collect()
+}
+
+fn same_progress(a: &CardState, b: &CardState) -> bool {
+    a.status == b.status
+        && a.reviews_count == b.reviews_count
+        && a.lapses == b.lapses
+        && a.interval_days == b.interval_days
+        && a.due_date == b.due_date
+}
+
 // === API Request/Response Types ===
 
 #[derive(Debug, Serialize)]
 struct SyncUploadRequest {
     files: Vec<SyncFile>,
+    /// Ask for the IDs assigned to `claimed_ids`; honored on the first upload.
+    claim_existing_ids: bool,
+    claimed_ids: Vec<NewIdAssignment>,
 }
 
 #[derive(Debug, Serialize)]
@@ -100,22 +335,34 @@ struct SyncUploadResponse {
     updated_files: Vec<UpdatedFile>,
     new_ids: Vec<NewIdAssignment>,
     orphaned_cards: Vec<OrphanedCard>,
+    #[serde(default)]
+    has_more_orphans: bool,
+    #[serde(default)]
+    claimed_ids: Vec<ClaimedId>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
-struct UpdatedFile {
-    path: String,
-    content: String,
+/// A synced file the backend rewrote, e.g. to add assigned card IDs.
+/// `path` is relative to the watched directory it was read from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatedFile {
+    pub path: String,
+    pub content: String,
 }
 
-#[derive(Debug, Deserialize)]
-struct NewIdAssignment {
-    #[allow(dead_code)]
-    path: String,
-    #[allow(dead_code)]
-    line: usize,
-    #[allow(dead_code)]
-    id: i64,
+/// A card ID by file path and line: assigned by the backend, or a local ID
+/// claimed on the first upload.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewIdAssignment {
+    pub path: String,
+    pub line: usize,
+    pub id: i64,
+}
+
+/// A claimed local card ID and the ID the backend assigned its card.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ClaimedId {
+    pub local_id: i64,
+    pub id: i64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -129,31 +376,24 @@ struct PushReviewsRequest {
     reviews: Vec<ReviewSubmission>,
 }
 
-#[derive(Debug, Serialize)]
-struct ReviewSubmission {
-    card_id: i64,
-    reviewed_at: DateTime<Utc>,
-    rating: i32,
-    rating_scale: String,
-    answer_mode: String,
-    typed_answer: Option<String>,
-    was_correct: Option<bool>,
-    time_taken_ms: Option<i32>,
-    interval_before: f64,
-    interval_after: f64,
-    ease_before: f64,
-    ease_after: f64,
-    algorithm: String,
-}
-
 #[derive(Debug, Deserialize)]
 struct PushReviewsResponse {
     synced_count: usize,
+    #[serde(default)]
+    rejected: Vec<RejectedReview>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RejectedReview {
+    index: usize,
+    reason: String,
 }
 
 #[derive(Debug, Serialize)]
 struct SyncPullRequest {
     last_sync_at: Option<DateTime<Utc>>,
+    /// So the backend's study days start at the reset hour in this timezone
+    utc_offset_minutes: i32,
 }
 
 #[derive(Debug, Deserialize)]
@@ -171,6 +411,8 @@ struct ApiCard {
     answer: String,
     source_file: String,
     deleted_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -184,6 +426,10 @@ struct ApiCardState {
     lapses: u32,
     reviews_count: u32,
     due_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    learning_reps: u32,
+    #[serde(default)]
+    updated_at: Option<DateTime<Utc>>,
 }
 
 /// Global settings from API.
@@ -193,9 +439,77 @@ pub struct ApiGlobalSettings {
     pub rating_scale: String,
     pub matching_mode: String,
     pub fuzzy_threshold: f64,
-    pub new_cards_per_day: u32,
-    pub reviews_per_day: u32,
+    pub new_cards_per_day: Limit,
+    pub reviews_per_day: Limit,
     pub daily_reset_hour: u32,
+    #[serde(default = "default_again_delay_minutes")]
+    pub again_delay_minutes: u32,
+    #[serde(default)]
+    pub due_grace_hours: u32,
+    #[serde(default = "default_graduation_requirement")]
+    pub graduation_requirement: u32,
+    #[serde(default)]
+    pub deck_path_separator: Option<String>,
+    #[serde(default = "default_suggest_easy_threshold")]
+    pub suggest_easy_threshold: f64,
+    #[serde(default = "default_suggest_hard_threshold")]
+    pub suggest_hard_threshold: f64,
+    #[serde(default = "default_queue_order")]
+    pub queue_order: String,
+    #[serde(default = "default_min_ease")]
+    pub min_ease: f64,
+    #[serde(default = "default_max_ease")]
+    pub max_ease: f64,
+    #[serde(default = "default_sync_conflict_policy")]
+    pub sync_conflict_policy: String,
+    #[serde(default)]
+    pub new_cards_paused: bool,
+    #[serde(default = "default_study_direction")]
+    pub study_direction: String,
+    #[serde(default = "default_session_limit")]
+    pub session_limit: String,
+    #[serde(default)]
+    pub min_answer_chars: u32,
+}
+
+fn default_again_delay_minutes() -> u32 {
+    flashcard_core::algorithm::DEFAULT_AGAIN_DELAY_MINUTES
+}
+
+fn default_suggest_easy_threshold() -> f64 {
+    flashcard_core::matching::DEFAULT_SUGGEST_EASY_THRESHOLD
+}
+
+fn default_suggest_hard_threshold() -> f64 {
+    flashcard_core::matching::DEFAULT_SUGGEST_HARD_THRESHOLD
+}
+
+fn default_graduation_requirement() -> u32 {
+    flashcard_core::algorithm::DEFAULT_GRADUATION_REQUIREMENT
+}
+
+fn default_min_ease() -> f64 {
+    flashcard_core::algorithm::DEFAULT_MIN_EASE
+}
+
+fn default_max_ease() -> f64 {
+    flashcard_core::algorithm::DEFAULT_MAX_EASE
+}
+
+fn default_sync_conflict_policy() -> String {
+    ConflictPolicy::default().as_str().to_string()
+}
+
+fn default_study_direction() -> String {
+    flashcard_core::types::StudyDirection::default().as_str().to_string()
+}
+
+fn default_session_limit() -> String {
+    flashcard_core::types::SessionLimit::default().to_string()
+}
+
+fn default_queue_order() -> String {
+    flashcard_core::types::QueueOrder::default().as_str().to_string()
 }
 
 /// Deck settings from API.
@@ -206,8 +520,16 @@ pub struct ApiDeckSettings {
     pub rating_scale: Option<String>,
     pub matching_mode: Option<String>,
     pub fuzzy_threshold: Option<f64>,
-    pub new_cards_per_day: Option<u32>,
-    pub reviews_per_day: Option<u32>,
+    pub new_cards_per_day: Option<Limit>,
+    pub reviews_per_day: Option<Limit>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub normalization_rules: Vec<NormRule>,
+    #[serde(default)]
+    pub request_retention: Option<f64>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -216,6 +538,19 @@ struct SyncedSettings {
     decks: Vec<ApiDeckSettings>,
 }
 
+#[derive(Debug, Serialize)]
+struct OrphansRequest {
+    current_card_ids: Vec<i64>,
+    offset: i64,
+    limit: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrphansResponse {
+    orphaned_cards: Vec<OrphanedCard>,
+    has_more: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct ConfirmDeleteRequest {
     card_ids: Vec<i64>,
@@ -226,6 +561,17 @@ struct ConfirmDeleteResponse {
     deleted_count: usize,
 }
 
+#[derive(Debug, Deserialize)]
+struct StorageStatsResponse {
+    object_count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ResetProgressRequest {
+    confirm: bool,
+    archive_reviews: bool,
+}
+
 #[derive(Debug, Serialize)]
 struct DeviceRegisterRequest {
     name: Option<String>,
@@ -237,6 +583,31 @@ struct DeviceRegisterResponse {
     token: String,
 }
 
+/// A sync paused for orphan confirmation, saved locally so it can be
+/// resumed after a restart instead of uploading everything again.
+///
+/// Orphans already confirmed for deletion are gone on the backend by then,
+/// so resuming only has to pull and apply.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingSync {
+    backend_url: String,
+    orphans: Vec<OrphanInfo>,
+    has_more: bool,
+    orphan_scan: OrphanScan,
+    updated_files: Vec<UpdatedFile>,
+    stats: SyncStats,
+}
+
+/// State conflicts a sync paused on.
+#[derive(Default)]
+struct PendingConflicts {
+    conflicts: Vec<StateConflict>,
+    /// When the pull that found them ran. Recorded as last_sync_at once they
+    /// are resolved, so server changes made while they waited are pulled
+    /// next time.
+    synced_at: String,
+}
+
 /// Inner state shared across clones.
 struct SyncEngineInner {
     client: Client,
@@ -244,6 +615,9 @@ struct SyncEngineInner {
     status: Mutex<SyncStatus>,
     stats: Mutex<SyncStats>,
     pending_updated_files: Mutex<Vec<UpdatedFile>>,
+    files_to_write: Mutex<Vec<UpdatedFile>>,
+    pending_conflicts: Mutex<PendingConflicts>,
+    orphan_scan: Mutex<Option<OrphanScan>>,
 }
 
 /// Sync engine for managing cloud synchronization.
@@ -265,15 +639,60 @@ impl SyncEngine {
                 status: Mutex::new(SyncStatus::Idle),
                 stats: Mutex::new(SyncStats::default()),
                 pending_updated_files: Mutex::new(Vec::new()),
+                files_to_write: Mutex::new(Vec::new()),
+                pending_conflicts: Mutex::new(PendingConflicts::default()),
+                orphan_scan: Mutex::new(None),
             }),
         }
     }
 
+    /// Recreate the engine of a sync paused for orphan confirmation, e.g.
+    /// after the app restarted. The engine is left awaiting confirmation.
+    pub fn resume(pending: PendingSync) -> Self {
+        let mut engine = Self::new(pending.backend_url);
+        let inner = Arc::get_mut(&mut engine.inner).expect("new engine is unshared");
+        *inner.status.get_mut() = SyncStatus::AwaitingOrphanConfirmation {
+            orphans: pending.orphans,
+            has_more: pending.has_more,
+        };
+        *inner.stats.get_mut() = pending.stats;
+        *inner.pending_updated_files.get_mut() = pending.updated_files;
+        *inner.orphan_scan.get_mut() = Some(pending.orphan_scan);
+        engine
+    }
+
+    /// The sync to save for [`SyncEngine::resume`], while one is paused for
+    /// orphan confirmation.
+    pub async fn pending_sync(&self) -> Option<PendingSync> {
+        let SyncStatus::AwaitingOrphanConfirmation { orphans, has_more } = self.status().await
+        else {
+            return None;
+        };
+        let orphan_scan = self.inner.orphan_scan.lock().await.clone()?;
+        Some(PendingSync {
+            backend_url: self.inner.backend_url.clone(),
+            orphans,
+            has_more,
+            orphan_scan,
+            updated_files: self.inner.pending_updated_files.lock().await.clone(),
+            stats: self.inner.stats.lock().await.clone(),
+        })
+    }
+
     /// Get current sync status.
     pub async fn status(&self) -> SyncStatus {
         self.inner.status.lock().await.clone()
     }
 
+    /// Take the files the last completed sync rewrote, for the caller to
+    /// write back to disk. Empty while a sync is paused.
+    pub async fn take_files_to_write(&self) -> Vec<UpdatedFile> {
+        if !matches!(*self.inner.status.lock().await, SyncStatus::Completed { .. }) {
+            return Vec::new();
+        }
+        std::mem::take(&mut *self.inner.files_to_write.lock().await)
+    }
+
     /// Check if backend is reachable.
     pub async fn check_connectivity(&self) -> Result<bool, SyncError> {
         let url = format!("{}/health", self.inner.backend_url);
@@ -283,6 +702,72 @@ impl SyncEngine {
         }
     }
 
+    /// Count the files the backend stores for this device.
+    pub async fn remote_file_count(&self, token: &str) -> Result<usize, SyncError> {
+        let url = format!("{}/api/stats/storage", self.inner.backend_url);
+
+        let resp = self
+            .inner
+            .client
+            .get(&url)
+            .bearer_auth(token)
+            .send()
+            .await
+            .map_err(|e| SyncError::Network(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let message = resp.text().await.unwrap_or_default();
+            return Err(SyncError::Backend { status, message });
+        }
+
+        let response: StorageStatsResponse = resp
+            .json()
+            .await
+            .map_err(|e| SyncError::Parse(e.to_string()))?;
+
+        Ok(response.object_count)
+    }
+
+    /// Set every card in a deck back to New on the backend, optionally
+    /// archiving the deck's reviews there.
+    pub async fn reset_deck_progress(
+        &self,
+        token: &str,
+        deck_path: &str,
+        archive_reviews: bool,
+    ) -> Result<(), SyncError> {
+        let mut url = reqwest::Url::parse(&self.inner.backend_url)
+            .map_err(|e| SyncError::Network(e.to_string()))?;
+        // Pushed as one segment, so the deck path's slashes are escaped
+        url.path_segments_mut()
+            .map_err(|_| SyncError::Network("Invalid backend URL".to_string()))?
+            .pop_if_empty()
+            .extend(["api", "decks", deck_path, "reset-progress"]);
+        let request = ResetProgressRequest {
+            confirm: true,
+            archive_reviews,
+        };
+
+        let resp = self
+            .inner
+            .client
+            .post(url)
+            .bearer_auth(token)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| SyncError::Network(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let message = resp.text().await.unwrap_or_default();
+            return Err(SyncError::Backend { status, message });
+        }
+
+        Ok(())
+    }
+
     /// Register a new device with the backend.
     pub async fn register_device(
         &self,
@@ -317,28 +802,30 @@ impl SyncEngine {
     /// Run full sync operation.
     ///
     /// Uses callbacks for database operations to avoid holding MutexGuard across await points.
-    pub async fn sync<F1, F2, F3, F4, F5, F6, F7, F8>(
+    ///
+    /// `claims` are the local IDs of cards without an ID line, sent on the
+    /// device's first sync. The IDs the backend assigned them are handed to
+    /// `adopt_ids` before any review is pushed, so local progress follows.
+    pub async fn sync<F1, F2, F3, F4, F5, F6>(
         &self,
         token: &str,
         md_files: Vec<(String, String)>,
+        claims: Vec<NewIdAssignment>,
+        conflict_policy: ConflictPolicy,
         get_pending_reviews: F1,
         mark_reviews_synced: F2,
         get_sync_state: F3,
-        update_sync_state: F4,
-        apply_cards_from_sync: F5,
-        apply_states_from_sync: F6,
-        apply_global_settings: F7,
-        apply_deck_settings: F8,
+        apply_pulled: F4,
+        get_unsynced_states: F5,
+        adopt_ids: F6,
     ) -> Result<SyncStats, SyncError>
     where
         F1: Fn() -> Vec<PendingReview> + Send + Sync,
-        F2: Fn(&[i64]) + Send + Sync,
+        F2: Fn(&[i64], &[(i64, String)]) + Send + Sync,
         F3: Fn() -> Option<LocalSyncState> + Send + Sync,
-        F4: Fn(&str) + Send + Sync,
-        F5: Fn(&[Card], &str) -> usize + Send + Sync,
-        F6: Fn(&[(i64, CardState)]) -> usize + Send + Sync,
-        F7: Fn(&ApiGlobalSettings) + Send + Sync,
-        F8: Fn(&[ApiDeckSettings]) + Send + Sync,
+        F4: Fn(&PulledChanges) -> Result<Vec<(i64, CardChange)>, String> + Send + Sync,
+        F5: Fn() -> Vec<TimedCardState> + Send + Sync,
+        F6: Fn(&[ClaimedId]) -> Result<(), String> + Send + Sync,
     {
         // Check if sync already in progress
         {
@@ -377,7 +864,10 @@ impl SyncEngine {
         })
         .await;
 
-        let upload_result = self.upload_files(token, &md_files).await?;
+        let upload_result = self.upload_files(token, &md_files, claims).await?;
+        if !upload_result.claimed_ids.is_empty() {
+            adopt_ids(&upload_result.claimed_ids).map_err(SyncError::Database)?;
+        }
 
         {
             let mut stats = self.inner.stats.lock().await;
@@ -387,19 +877,21 @@ impl SyncEngine {
 
         // 3. Check for orphans - if any, pause for user confirmation
         if !upload_result.orphaned_cards.is_empty() {
-            let orphans: Vec<OrphanInfo> = upload_result
-                .orphaned_cards
-                .iter()
-                .map(|o| OrphanInfo {
-                    card_id: o.id,
-                    question_preview: o.question_preview.clone(),
-                })
-                .collect();
+            let orphans = orphan_infos(&upload_result.orphaned_cards);
+            let has_more = upload_result.has_more_orphans;
+
+            *self.inner.orphan_scan.lock().await = Some(OrphanScan {
+                current_card_ids: uploaded_card_ids(&md_files, &upload_result.updated_files),
+                offset: 0,
+                page: orphans.iter().map(|o| o.card_id).collect(),
+                deleted: 0,
+                has_more,
+            });
 
             // Store updated files for later
             *self.inner.pending_updated_files.lock().await = upload_result.updated_files;
 
-            self.set_status(SyncStatus::AwaitingOrphanConfirmation { orphans })
+            self.set_status(SyncStatus::AwaitingOrphanConfirmation { orphans, has_more })
                 .await;
 
             // Sync will be resumed by confirm_orphan_deletion or skip_orphan_deletion
@@ -410,81 +902,70 @@ impl SyncEngine {
         self.continue_sync_internal(
             token,
             &upload_result.updated_files,
+            conflict_policy,
             get_pending_reviews,
             mark_reviews_synced,
             get_sync_state,
-            update_sync_state,
-            apply_cards_from_sync,
-            apply_states_from_sync,
-            apply_global_settings,
-            apply_deck_settings,
+            apply_pulled,
+            get_unsynced_states,
         )
         .await
     }
 
     /// Continue sync after orphan confirmation (without orphan deletion).
-    pub async fn continue_sync_without_orphans<F1, F2, F3, F4, F5, F6, F7, F8>(
+    pub async fn continue_sync_without_orphans<F1, F2, F3, F4, F5>(
         &self,
         token: &str,
+        conflict_policy: ConflictPolicy,
         get_pending_reviews: F1,
         mark_reviews_synced: F2,
         get_sync_state: F3,
-        update_sync_state: F4,
-        apply_cards_from_sync: F5,
-        apply_states_from_sync: F6,
-        apply_global_settings: F7,
-        apply_deck_settings: F8,
+        apply_pulled: F4,
+        get_unsynced_states: F5,
     ) -> Result<SyncStats, SyncError>
     where
         F1: Fn() -> Vec<PendingReview> + Send + Sync,
-        F2: Fn(&[i64]) + Send + Sync,
+        F2: Fn(&[i64], &[(i64, String)]) + Send + Sync,
         F3: Fn() -> Option<LocalSyncState> + Send + Sync,
-        F4: Fn(&str) + Send + Sync,
-        F5: Fn(&[Card], &str) -> usize + Send + Sync,
-        F6: Fn(&[(i64, CardState)]) -> usize + Send + Sync,
-        F7: Fn(&ApiGlobalSettings) + Send + Sync,
-        F8: Fn(&[ApiDeckSettings]) + Send + Sync,
+        F4: Fn(&PulledChanges) -> Result<Vec<(i64, CardChange)>, String> + Send + Sync,
+        F5: Fn() -> Vec<TimedCardState> + Send + Sync,
     {
         let updated_files = self.inner.pending_updated_files.lock().await.clone();
         self.continue_sync_internal(
             token,
             &updated_files,
+            conflict_policy,
             get_pending_reviews,
             mark_reviews_synced,
             get_sync_state,
-            update_sync_state,
-            apply_cards_from_sync,
-            apply_states_from_sync,
-            apply_global_settings,
-            apply_deck_settings,
+            apply_pulled,
+            get_unsynced_states,
         )
         .await
     }
 
     /// Internal continue sync implementation.
-    async fn continue_sync_internal<F1, F2, F3, F4, F5, F6, F7, F8>(
+    async fn continue_sync_internal<F1, F2, F3, F4, F5>(
         &self,
         token: &str,
         updated_files: &[UpdatedFile],
+        conflict_policy: ConflictPolicy,
         get_pending_reviews: F1,
         mark_reviews_synced: F2,
         get_sync_state: F3,
-        update_sync_state: F4,
-        apply_cards_from_sync: F5,
-        apply_states_from_sync: F6,
-        apply_global_settings: F7,
-        apply_deck_settings: F8,
+        apply_pulled: F4,
+        get_unsynced_states: F5,
     ) -> Result<SyncStats, SyncError>
     where
         F1: Fn() -> Vec<PendingReview> + Send + Sync,
-        F2: Fn(&[i64]) + Send + Sync,
+        F2: Fn(&[i64], &[(i64, String)]) + Send + Sync,
         F3: Fn() -> Option<LocalSyncState> + Send + Sync,
-        F4: Fn(&str) + Send + Sync,
-        F5: Fn(&[Card], &str) -> usize + Send + Sync,
-        F6: Fn(&[(i64, CardState)]) -> usize + Send + Sync,
-        F7: Fn(&ApiGlobalSettings) + Send + Sync,
-        F8: Fn(&[ApiDeckSettings]) + Send + Sync,
+        F4: Fn(&PulledChanges) -> Result<Vec<(i64, CardChange)>, String> + Send + Sync,
+        F5: Fn() -> Vec<TimedCardState> + Send + Sync,
     {
+        *self.inner.pending_conflicts.lock().await = PendingConflicts::default();
+        *self.inner.orphan_scan.lock().await = None;
+
         // 3. Push pending reviews
         self.set_status(SyncStatus::Syncing {
             stage: SyncStage::PushingReviews { count: 0 },
@@ -495,13 +976,16 @@ impl SyncEngine {
         let pending_reviews = get_pending_reviews();
 
         if !pending_reviews.is_empty() {
-            let synced_count = self.push_reviews(token, &pending_reviews).await?;
+            let response = self.push_reviews(token, &pending_reviews).await?;
 
-            // Mark reviews as synced
-            let ids: Vec<i64> = pending_reviews.iter().map(|r| r.id).collect();
-            mark_reviews_synced(&ids);
+            // Mark reviews as synced; rejected ones leave the queue so they
+            // cannot block later syncs
+            let (synced, failed) = split_push_result(&pending_reviews, &response.rejected);
+            mark_reviews_synced(&synced, &failed);
 
-            self.inner.stats.lock().await.reviews_synced = synced_count;
+            let mut stats = self.inner.stats.lock().await;
+            stats.reviews_synced = response.synced_count;
+            stats.reviews_failed = failed.len();
         }
 
         // 4. Pull state
@@ -532,28 +1016,27 @@ impl SyncEngine {
         })
         .await;
 
-        // Convert and apply pulled cards
+        // Convert pulled cards
         let now = Utc::now().to_rfc3339();
-        if !pull_response.cards.is_empty() {
-            let cards: Vec<Card> = pull_response
-                .cards
-                .iter()
-                .map(|c| Card {
-                    id: c.id,
-                    deck_path: c.deck_path.clone(),
-                    question: c.question.clone(),
-                    answer: c.answer.clone(),
-                    source_file: c.source_file.clone(),
-                    deleted_at: c.deleted_at,
-                })
-                .collect();
-            let applied = apply_cards_from_sync(&cards, &now);
-            self.inner.stats.lock().await.cards_updated += applied;
-        }
+        let cards: Vec<Card> = pull_response
+            .cards
+            .iter()
+            .map(|c| Card {
+                id: c.id,
+                deck_path: c.deck_path.clone(),
+                question: c.question.clone(),
+                answer: c.answer.clone(),
+                source_file: c.source_file.clone(),
+                deleted_at: c.deleted_at,
+                updated_at: c.updated_at,
+            })
+            .collect();
 
-        // Convert and apply pulled card states
+        // Convert pulled card states
+        let mut states = Vec::new();
+        let mut conflicts = Vec::new();
         if !pull_response.card_states.is_empty() {
-            let states: Vec<(i64, CardState)> = pull_response
+            let pulled: Vec<TimedCardState> = pull_response
                 .card_states
                 .iter()
                 .map(|s| {
@@ -563,9 +1046,9 @@ impl SyncEngine {
                         "relearning" => CardStatus::Relearning,
                         _ => CardStatus::New,
                     };
-                    (
-                        s.card_id,
-                        CardState {
+                    TimedCardState {
+                        card_id: s.card_id,
+                        state: CardState {
                             status,
                             interval_days: s.interval_days,
                             ease_factor: s.ease_factor,
@@ -574,17 +1057,51 @@ impl SyncEngine {
                             lapses: s.lapses,
                             reviews_count: s.reviews_count,
                             due_date: s.due_date,
+                            learning_reps: s.learning_reps,
                         },
-                    )
+                        updated_at: s.updated_at,
+                    }
                 })
                 .collect();
-            apply_states_from_sync(&states);
+            // Cards with unsynced local progress that differs are settled by
+            // the conflict policy, or left for the user
+            (states, conflicts) =
+                split_state_conflicts(pulled, &get_unsynced_states(), conflict_policy);
         }
 
-        // Apply pulled settings
-        apply_global_settings(&pull_response.settings.global);
-        if !pull_response.settings.decks.is_empty() {
-            apply_deck_settings(&pull_response.settings.decks);
+        // Apply everything at once so a failure leaves the local DB and
+        // last_sync_at as they were. A conflict pause leaves last_sync_at
+        // for the resolution to write.
+        let changes = apply_pulled(&PulledChanges {
+            cards: &cards,
+            states: &states,
+            global_settings: &pull_response.settings.global,
+            deck_settings: &pull_response.settings.decks,
+            synced_at: &now,
+            completes_sync: conflicts.is_empty(),
+        })
+        .map_err(SyncError::Database)?;
+
+        {
+            let mut stats = self.inner.stats.lock().await;
+            stats.cards_updated += changes.len();
+            add_deck_deltas(&mut stats.per_deck, &cards, &changes);
+        }
+        *self.inner.pending_conflicts.lock().await = PendingConflicts {
+            conflicts,
+            synced_at: now.clone(),
+        };
+
+        // Kept across a conflict pause; written once the sync completes
+        *self.inner.files_to_write.lock().await = updated_files.to_vec();
+
+        // Pause for conflict resolution; resumed by resolve_conflicts or
+        // auto_resolve_conflicts
+        let conflicts = self.inner.pending_conflicts.lock().await.conflicts.clone();
+        if !conflicts.is_empty() {
+            self.set_status(SyncStatus::AwaitingConflictResolution { conflicts })
+                .await;
+            return Ok(self.inner.stats.lock().await.clone());
         }
 
         // 6. Write updated files to disk
@@ -598,23 +1115,61 @@ impl SyncEngine {
             })
             .await;
 
-            // Note: File writing should be handled by the caller
-            // since we need to know the base directory
+            // The caller writes them via take_files_to_write, since only it
+            // knows the watched directories
         }
 
-        // 7. Update sync state
-        let now = Utc::now().to_rfc3339();
-        update_sync_state(&now);
+        // 7. Sync state was updated with the pulled changes
+        Ok(self.finish_sync(now).await)
+    }
 
-        let stats = self.inner.stats.lock().await.clone();
+    /// Apply the chosen side of each pending conflict and finish the sync.
+    ///
+    /// Conflicts without a choice fall back to [`StateConflict::auto_choice`].
+    /// Resolved states are saved as synced so they don't conflict again.
+    pub async fn resolve_conflicts<F1, F2>(
+        &self,
+        choices: &[ConflictResolution],
+        apply_states_from_sync: F1,
+        update_sync_state: F2,
+    ) -> Result<SyncStats, SyncError>
+    where
+        F1: Fn(&[(i64, CardState)]) -> usize + Send + Sync,
+        F2: Fn(&str) + Send + Sync,
+    {
+        let pending = std::mem::take(&mut *self.inner.pending_conflicts.lock().await);
+        if pending.conflicts.is_empty() {
+            return Err(SyncError::NoPendingConflicts);
+        }
 
-        self.set_status(SyncStatus::Completed {
-            synced_at: now,
-            stats: stats.clone(),
-        })
-        .await;
+        let resolved: Vec<(i64, CardState)> = pending
+            .conflicts
+            .iter()
+            .map(|conflict| {
+                let choice = choices
+                    .iter()
+                    .find(|c| c.card_id == conflict.card_id)
+                    .map_or_else(|| conflict.auto_choice(), |c| c.choice);
+                (conflict.card_id, conflict.chosen(choice))
+            })
+            .collect();
+        apply_states_from_sync(&resolved);
+
+        self.complete_sync(pending.synced_at, update_sync_state).await
+    }
 
-        Ok(stats)
+    /// Resolve every pending conflict by keeping the side with more progress.
+    pub async fn auto_resolve_conflicts<F1, F2>(
+        &self,
+        apply_states_from_sync: F1,
+        update_sync_state: F2,
+    ) -> Result<SyncStats, SyncError>
+    where
+        F1: Fn(&[(i64, CardState)]) -> usize + Send + Sync,
+        F2: Fn(&str) + Send + Sync,
+    {
+        self.resolve_conflicts(&[], apply_states_from_sync, update_sync_state)
+            .await
     }
 
     /// Confirm orphan deletion.
@@ -624,7 +1179,9 @@ impl SyncEngine {
         card_ids: Vec<i64>,
     ) -> Result<usize, SyncError> {
         let url = format!("{}/api/sync/confirm-delete", self.inner.backend_url);
-        let request = ConfirmDeleteRequest { card_ids };
+        let request = ConfirmDeleteRequest {
+            card_ids: card_ids.clone(),
+        };
 
         let resp = self
             .inner
@@ -647,21 +1204,111 @@ impl SyncEngine {
             .await
             .map_err(|e| SyncError::Parse(e.to_string()))?;
 
-        self.inner.stats.lock().await.orphans_deleted = response.deleted_count;
+        self.inner.stats.lock().await.orphans_deleted += response.deleted_count;
+        if let Some(scan) = self.inner.orphan_scan.lock().await.as_mut() {
+            scan.deleted += card_ids.iter().filter(|id| scan.page.contains(id)).count();
+        }
 
         Ok(response.deleted_count)
     }
 
+    /// Load the next page of orphans after the displayed one was handled.
+    ///
+    /// Updates the status to show the new page.
+    pub async fn next_orphan_batch(&self, token: &str) -> Result<Vec<OrphanInfo>, SyncError> {
+        let scan = self
+            .inner
+            .orphan_scan
+            .lock()
+            .await
+            .clone()
+            .filter(|scan| scan.has_more)
+            .ok_or(SyncError::NoPendingOrphans)?;
+
+        let url = format!("{}/api/sync/orphans", self.inner.backend_url);
+        let offset = scan.next_offset();
+        let request = OrphansRequest {
+            current_card_ids: scan.current_card_ids.clone(),
+            offset,
+            limit: ORPHAN_PAGE_SIZE,
+        };
+
+        let resp = self
+            .inner
+            .client
+            .post(&url)
+            .bearer_auth(token)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| SyncError::Network(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status().as_u16();
+            let message = resp.text().await.unwrap_or_default();
+            return Err(SyncError::Backend { status, message });
+        }
+
+        let response: OrphansResponse = resp
+            .json()
+            .await
+            .map_err(|e| SyncError::Parse(e.to_string()))?;
+
+        let orphans = orphan_infos(&response.orphaned_cards);
+        *self.inner.orphan_scan.lock().await = Some(OrphanScan {
+            offset,
+            page: orphans.iter().map(|o| o.card_id).collect(),
+            deleted: 0,
+            has_more: response.has_more,
+            ..scan
+        });
+
+        self.set_status(SyncStatus::AwaitingOrphanConfirmation {
+            orphans: orphans.clone(),
+            has_more: response.has_more,
+        })
+        .await;
+
+        Ok(orphans)
+    }
+
     // === Private methods ===
 
     async fn set_status(&self, status: SyncStatus) {
         *self.inner.status.lock().await = status;
     }
 
+    /// Record `synced_at`, the time of the sync's pull, as the last sync.
+    async fn complete_sync<F>(
+        &self,
+        synced_at: String,
+        update_sync_state: F,
+    ) -> Result<SyncStats, SyncError>
+    where
+        F: Fn(&str) + Send + Sync,
+    {
+        update_sync_state(&synced_at);
+        Ok(self.finish_sync(synced_at).await)
+    }
+
+    /// Report the sync as completed at `synced_at`, once last_sync_at is saved.
+    async fn finish_sync(&self, synced_at: String) -> SyncStats {
+        let stats = self.inner.stats.lock().await.clone();
+
+        self.set_status(SyncStatus::Completed {
+            synced_at,
+            stats: stats.clone(),
+        })
+        .await;
+
+        stats
+    }
+
     async fn upload_files(
         &self,
         token: &str,
         files: &[(String, String)],
+        claims: Vec<NewIdAssignment>,
     ) -> Result<SyncUploadResponse, SyncError> {
         let url = format!("{}/api/sync/upload", self.inner.backend_url);
 
@@ -674,7 +1321,11 @@ impl SyncEngine {
             })
             .collect();
 
-        let request = SyncUploadRequest { files: sync_files };
+        let request = SyncUploadRequest {
+            files: sync_files,
+            claim_existing_ids: !claims.is_empty(),
+            claimed_ids: claims,
+        };
 
         let resp = self
             .inner
@@ -701,7 +1352,7 @@ impl SyncEngine {
         &self,
         token: &str,
         reviews: &[PendingReview],
-    ) -> Result<usize, SyncError> {
+    ) -> Result<PushReviewsResponse, SyncError> {
         let url = format!("{}/api/sync/push-reviews", self.inner.backend_url);
 
         let submissions: Vec<ReviewSubmission> = reviews
@@ -722,6 +1373,8 @@ impl SyncEngine {
                 ease_before: r.ease_before,
                 ease_after: r.ease_after,
                 algorithm: r.algorithm.clone(),
+                params_hash: r.params_hash.clone(),
+                cram: r.cram,
             })
             .collect();
 
@@ -743,12 +1396,9 @@ impl SyncEngine {
             return Err(SyncError::Backend { status, message });
         }
 
-        let response: PushReviewsResponse = resp
-            .json()
+        resp.json()
             .await
-            .map_err(|e| SyncError::Parse(e.to_string()))?;
-
-        Ok(response.synced_count)
+            .map_err(|e| SyncError::Parse(e.to_string()))
     }
 
     async fn pull_state(
@@ -757,7 +1407,10 @@ impl SyncEngine {
         last_sync_at: Option<DateTime<Utc>>,
     ) -> Result<SyncPullResponse, SyncError> {
         let url = format!("{}/api/sync/pull", self.inner.backend_url);
-        let request = SyncPullRequest { last_sync_at };
+        let request = SyncPullRequest {
+            last_sync_at,
+            utc_offset_minutes: Local::now().offset().local_minus_utc() / 60,
+        };
 
         let resp = self
             .inner
@@ -787,3 +1440,406 @@ pub fn hash_content(content: &str) -> String {
     hasher.update(content.as_bytes());
     format!("{:x}", hasher.finalize())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    fn state(status: CardStatus, reviews_count: u32, interval_days: f64) -> CardState {
+        CardState {
+            status,
+            interval_days,
+            reviews_count,
+            ..CardState::default()
+        }
+    }
+
+    fn timed(card_id: i64, state: CardState) -> TimedCardState {
+        TimedCardState {
+            card_id,
+            state,
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_orphan_scan_next_offset_skips_only_kept_cards() {
+        let mut scan = OrphanScan {
+            offset: 100,
+            page: (1..=100).collect(),
+            has_more: true,
+            ..OrphanScan::default()
+        };
+        assert_eq!(scan.next_offset(), 200);
+
+        // Deleted cards leave the listing, so the next page starts earlier
+        scan.deleted = 40;
+        assert_eq!(scan.next_offset(), 160);
+
+        scan.deleted = 100;
+        assert_eq!(scan.next_offset(), 100);
+    }
+
+    #[test]
+    fn test_split_push_result_dead_letters_rejected_reviews() {
+        let review = |id, card_id| PendingReview {
+            id,
+            card_id,
+            reviewed_at: Utc::now().to_rfc3339(),
+            rating: 3,
+            rating_scale: "4point".to_string(),
+            answer_mode: "flip".to_string(),
+            typed_answer: None,
+            was_correct: None,
+            time_taken_ms: None,
+            interval_before: 0.0,
+            interval_after: 1.0,
+            ease_before: 2.5,
+            ease_after: 2.5,
+            algorithm: "sm2".to_string(),
+            params_hash: None,
+            status_before: None,
+            cram: false,
+        };
+        let pending = vec![review(10, 1), review(11, 404), review(12, 2)];
+        let rejected = vec![RejectedReview {
+            index: 1,
+            reason: "card_not_found".to_string(),
+        }];
+
+        let (synced, failed) = split_push_result(&pending, &rejected);
+        assert_eq!(synced, vec![10, 12]);
+        assert_eq!(failed, vec![(11, "card_not_found".to_string())]);
+    }
+
+    #[test]
+    fn test_uploaded_card_ids_prefers_updated_content() {
+        let md_files = vec![
+            ("a.md".to_string(), "Q: one\nA: 1\n".to_string()),
+            ("b.md".to_string(), "ID: 5\nQ: two\nA: 2\n".to_string()),
+        ];
+        let updated = vec![UpdatedFile {
+            path: "a.md".to_string(),
+            content: "ID: 9\nQ: one\nA: 1\n".to_string(),
+        }];
+
+        let mut ids = uploaded_card_ids(&md_files, &updated);
+        ids.sort();
+        assert_eq!(ids, vec![5, 9]);
+    }
+
+    #[test]
+    fn test_split_state_conflicts() {
+        let pulled = vec![
+            timed(1, state(CardStatus::Review, 3, 4.0)),
+            timed(2, state(CardStatus::Review, 2, 2.0)),
+            timed(3, state(CardStatus::Learning, 1, 0.0)),
+        ];
+        let local = vec![
+            timed(1, state(CardStatus::Review, 4, 9.0)),
+            timed(2, state(CardStatus::Review, 2, 2.0)),
+        ];
+
+        let (apply, conflicts) = split_state_conflicts(pulled, &local, ConflictPolicy::Manual);
+
+        let applied: Vec<i64> = apply.iter().map(|(id, _)| *id).collect();
+        assert_eq!(applied, vec![2, 3]);
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].card_id, 1);
+        assert_eq!(conflicts[0].auto_choice(), ConflictChoice::KeepLocal);
+    }
+
+    #[test]
+    fn test_conflict_policies_settle_conflict_differently() {
+        // The remote side changed last, but the local side has more reviews
+        let now = Utc::now();
+        let local = [TimedCardState {
+            updated_at: Some(now - chrono::Duration::hours(1)),
+            ..timed(7, state(CardStatus::Review, 5, 12.0))
+        }];
+        let pulled = || {
+            vec![TimedCardState {
+                updated_at: Some(now),
+                ..timed(7, state(CardStatus::Review, 2, 3.0))
+            }]
+        };
+
+        let (apply, conflicts) =
+            split_state_conflicts(pulled(), &local, ConflictPolicy::LastWriteWins);
+        assert!(conflicts.is_empty());
+        assert_eq!(apply[0].1.reviews_count, 2);
+
+        let (apply, conflicts) =
+            split_state_conflicts(pulled(), &local, ConflictPolicy::MostProgress);
+        assert!(conflicts.is_empty());
+        assert_eq!(apply[0].1.reviews_count, 5);
+
+        let (apply, conflicts) = split_state_conflicts(pulled(), &local, ConflictPolicy::Manual);
+        assert!(apply.is_empty());
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].local_updated_at, local[0].updated_at);
+
+        // Without both change times, last-write-wins keeps the most progress
+        let (apply, _) = split_state_conflicts(
+            pulled(),
+            &[timed(7, state(CardStatus::Review, 5, 12.0))],
+            ConflictPolicy::LastWriteWins,
+        );
+        assert_eq!(apply[0].1.reviews_count, 5);
+    }
+
+    #[tokio::test]
+    async fn test_conflict_pauses_sync_and_keep_local_applies_local_state() {
+        let engine = SyncEngine::new("http://localhost".to_string());
+        let (_, conflicts) = split_state_conflicts(
+            vec![timed(7, state(CardStatus::Review, 2, 3.0))],
+            &[timed(7, state(CardStatus::Review, 5, 12.0))],
+            ConflictPolicy::Manual,
+        );
+        let pulled_at = "2024-03-10T08:00:00+00:00".to_string();
+        *engine.inner.pending_conflicts.lock().await = PendingConflicts {
+            conflicts: conflicts.clone(),
+            synced_at: pulled_at.clone(),
+        };
+        engine
+            .set_status(SyncStatus::AwaitingConflictResolution { conflicts })
+            .await;
+        assert!(matches!(
+            engine.status().await,
+            SyncStatus::AwaitingConflictResolution { ref conflicts } if conflicts.len() == 1
+        ));
+
+        let saved = StdMutex::new(Vec::new());
+        let synced_at = StdMutex::new(None);
+        engine
+            .resolve_conflicts(
+                &[ConflictResolution {
+                    card_id: 7,
+                    choice: ConflictChoice::KeepLocal,
+                }],
+                |states| {
+                    saved.lock().unwrap().extend_from_slice(states);
+                    states.len()
+                },
+                |ts| *synced_at.lock().unwrap() = Some(ts.to_string()),
+            )
+            .await
+            .unwrap();
+
+        let saved = saved.into_inner().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].0, 7);
+        assert_eq!(saved[0].1.reviews_count, 5);
+        assert_eq!(saved[0].1.interval_days, 12.0);
+        // The pull's time, not the resolution's, so nothing changed on the
+        // server meanwhile is skipped by the next pull
+        assert_eq!(synced_at.into_inner().unwrap(), Some(pulled_at.clone()));
+        assert!(matches!(
+            engine.status().await,
+            SyncStatus::Completed { synced_at, .. } if synced_at == pulled_at
+        ));
+    }
+
+    #[test]
+    fn test_deck_deltas_from_applied_pull() {
+        use crate::db::{CardRepository, SqliteRepository};
+
+        let repo = SqliteRepository::open_in_memory().unwrap();
+        let card = |id, deck: &str, deleted: bool| Card {
+            id,
+            deck_path: deck.to_string(),
+            question: format!("Q{}", id),
+            answer: "A".to_string(),
+            source_file: format!("{}.md", deck),
+            deleted_at: deleted.then(Utc::now),
+            updated_at: Some(Utc::now()),
+        };
+        let now = Utc::now().to_rfc3339();
+        repo.upsert_cards_from_sync(
+            &[card(1, "rust", false), card(2, "rust", false), card(3, "go", false)],
+            &now,
+        )
+        .unwrap();
+
+        let pulled = [
+            card(1, "rust", false),
+            card(3, "go", true),
+            card(4, "go", false),
+            card(5, "go", true),
+        ];
+        let changes = repo.upsert_cards_from_sync(&pulled, &now).unwrap();
+        let mut per_deck = HashMap::new();
+        add_deck_deltas(&mut per_deck, &pulled, &changes);
+
+        assert_eq!(per_deck.len(), 2);
+        assert_eq!(
+            per_deck["rust"],
+            DeckDelta {
+                updated: 1,
+                ..DeckDelta::default()
+            }
+        );
+        assert_eq!(
+            per_deck["go"],
+            DeckDelta {
+                created: 1,
+                deleted: 1,
+                ..DeckDelta::default()
+            }
+        );
+    }
+
+    /// Serve one HTTP request with a JSON `body`, returning the server's URL.
+    async fn serve_once(body: &'static str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            // Read the headers and body before answering
+            let mut request = Vec::new();
+            let mut buf = [0; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|line| {
+                            let line = line.to_ascii_lowercase();
+                            line.strip_prefix("content-length:")?.trim().parse().ok()
+                        })
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length || n == 0 {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_sync_resumes_after_crash_following_orphan_confirmation() {
+        use crate::db::{CardRepository, SqliteRepository, SyncRepository};
+
+        let repo = StdMutex::new(SqliteRepository::open_in_memory().unwrap());
+        let url = serve_once(
+            r#"{"cards": [{"id": 1, "deck_path": "rust", "question": "Q1", "answer": "A1",
+                "source_file": "rust.md", "deleted_at": null}],
+               "card_states": [],
+               "settings": {"global": {"algorithm": "sm2", "rating_scale": "4point",
+                   "matching_mode": "fuzzy", "fuzzy_threshold": 0.8, "new_cards_per_day": 20,
+                   "reviews_per_day": 200, "daily_reset_hour": 4}, "decks": []}}"#,
+        )
+        .await;
+
+        // A sync paused on orphans, with one of them confirmed deleted
+        let engine = SyncEngine::new(url);
+        let updated = UpdatedFile {
+            path: "rust.md".to_string(),
+            content: "ID: 1\nQ: Q1\nA: A1".to_string(),
+        };
+        *engine.inner.pending_updated_files.lock().await = vec![updated];
+        *engine.inner.orphan_scan.lock().await = Some(OrphanScan {
+            current_card_ids: vec![1],
+            page: vec![9],
+            deleted: 1,
+            ..OrphanScan::default()
+        });
+        engine.inner.stats.lock().await.orphans_deleted = 1;
+        engine
+            .set_status(SyncStatus::AwaitingOrphanConfirmation {
+                orphans: vec![OrphanInfo {
+                    card_id: 9,
+                    question_preview: "Old".to_string(),
+                }],
+                has_more: false,
+            })
+            .await;
+        let pending = engine.pending_sync().await;
+        repo.lock().unwrap().save_pending_sync(pending.as_ref()).unwrap();
+        drop(engine);
+
+        // After the crash, the saved sync picks up where it stopped
+        let pending = repo.lock().unwrap().get_pending_sync().unwrap().unwrap();
+        let engine = SyncEngine::resume(pending);
+        assert!(matches!(
+            engine.status().await,
+            SyncStatus::AwaitingOrphanConfirmation { ref orphans, .. } if orphans.len() == 1
+        ));
+        let stats = engine
+            .continue_sync_without_orphans(
+                "token",
+                ConflictPolicy::default(),
+                Vec::new,
+                |_, _| {},
+                || repo.lock().unwrap().get_sync_state().ok(),
+                |pulled: &PulledChanges| {
+                    let repo = repo.lock().unwrap();
+                    repo.apply_pulled_changes(pulled).map_err(|e| e.to_string())
+                },
+                Vec::new,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(stats.orphans_deleted, 1);
+        assert!(matches!(engine.status().await, SyncStatus::Completed { .. }));
+        assert!(engine.pending_sync().await.is_none());
+        assert_eq!(engine.take_files_to_write().await.len(), 1);
+        let repo = repo.into_inner().unwrap();
+        assert_eq!(repo.get_card(1).unwrap().unwrap().question, "Q1");
+        assert!(repo.get_sync_state().unwrap().last_sync_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_updated_files_survive_conflict_pause() {
+        let engine = SyncEngine::new("http://localhost".to_string());
+        let (_, conflicts) = split_state_conflicts(
+            vec![timed(7, state(CardStatus::Review, 2, 3.0))],
+            &[timed(7, state(CardStatus::Review, 5, 12.0))],
+            ConflictPolicy::Manual,
+        );
+        let with_id = UpdatedFile {
+            path: "rust/basics.md".to_string(),
+            content: "ID: 42\nQ: Question\nA: Answer\n".to_string(),
+        };
+        // As left by continue_sync_internal when it pauses
+        *engine.inner.files_to_write.lock().await = vec![with_id.clone()];
+        *engine.inner.pending_conflicts.lock().await = PendingConflicts {
+            conflicts: conflicts.clone(),
+            synced_at: Utc::now().to_rfc3339(),
+        };
+        engine
+            .set_status(SyncStatus::AwaitingConflictResolution { conflicts })
+            .await;
+
+        assert!(engine.take_files_to_write().await.is_empty());
+
+        engine.auto_resolve_conflicts(|states| states.len(), |_| {}).await.unwrap();
+
+        let files = engine.take_files_to_write().await;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, with_id.path);
+        assert!(files[0].content.starts_with("ID: 42\n"));
+        // Handed out once
+        assert!(engine.take_files_to_write().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_without_pending_conflicts_fails() {
+        let engine = SyncEngine::new("http://localhost".to_string());
+        let result = engine.auto_resolve_conflicts(|_| 0, |_| {}).await;
+        assert!(matches!(result, Err(SyncError::NoPendingConflicts)));
+    }
+}
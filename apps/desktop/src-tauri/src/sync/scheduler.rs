@@ -0,0 +1,292 @@
+//! Background auto-sync scheduling.
+//!
+//! Decides when the desktop syncs on its own: on a jittered interval, so many
+//! devices don't hit the backend in lockstep, and when the window regains
+//! focus. The loop acting on these decisions is `commands::sync::run_auto_sync`.
+
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+use super::SyncStatus;
+
+/// Default minutes between automatic syncs.
+pub const DEFAULT_AUTO_SYNC_INTERVAL_MINUTES: u32 = 15;
+
+/// Allowed range for the auto-sync interval, in minutes.
+pub const AUTO_SYNC_INTERVAL_RANGE: std::ops::RangeInclusive<u32> = 1..=24 * 60;
+
+/// Fraction of the interval randomly added or removed before each sync.
+pub const AUTO_SYNC_JITTER: f64 = 0.1;
+
+/// A focus sync is skipped if an automatic sync started this recently.
+pub const FOCUS_SYNC_MIN_GAP: Duration = Duration::from_secs(60);
+
+/// User-facing auto-sync configuration, saved per device in the local
+/// `global_settings` row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AutoSyncConfig {
+    pub enabled: bool,
+    pub interval_minutes: u32,
+    /// Temporarily stop automatic syncs without disabling them.
+    pub paused: bool,
+}
+
+impl Default for AutoSyncConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: DEFAULT_AUTO_SYNC_INTERVAL_MINUTES,
+            paused: false,
+        }
+    }
+}
+
+/// What woke the scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoSyncTrigger {
+    Interval,
+    Focus,
+}
+
+/// Why an automatic sync did not run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    Disabled,
+    Paused,
+    /// No backend URL yet: the user has not registered or synced this session.
+    NoBackend,
+    Unregistered,
+    SyncInProgress,
+    /// A sync is waiting for orphan or conflict decisions.
+    AwaitingUser,
+    TooSoon,
+}
+
+/// Decide whether an automatic sync may start.
+///
+/// `status` is `None` when no sync engine exists yet. `since_last` is the time
+/// since the previous automatic sync started, if any.
+pub fn check_auto_sync(
+    config: &AutoSyncConfig,
+    trigger: AutoSyncTrigger,
+    status: Option<&SyncStatus>,
+    registered: bool,
+    since_last: Option<Duration>,
+) -> Result<(), SkipReason> {
+    if !config.enabled {
+        return Err(SkipReason::Disabled);
+    }
+    if config.paused {
+        return Err(SkipReason::Paused);
+    }
+    let status = status.ok_or(SkipReason::NoBackend)?;
+    if !registered {
+        return Err(SkipReason::Unregistered);
+    }
+    match status {
+        SyncStatus::Syncing { .. } => return Err(SkipReason::SyncInProgress),
+        SyncStatus::AwaitingOrphanConfirmation { .. }
+        | SyncStatus::AwaitingConflictResolution { .. } => return Err(SkipReason::AwaitingUser),
+        _ => {}
+    }
+    if trigger == AutoSyncTrigger::Focus && since_last.is_some_and(|d| d < FOCUS_SYNC_MIN_GAP) {
+        return Err(SkipReason::TooSoon);
+    }
+    Ok(())
+}
+
+/// Interval with jitter applied; `unit` is a random value in `[0, 1)`.
+pub fn jittered_interval(interval_minutes: u32, unit: f64) -> Duration {
+    let base = Duration::from_secs(u64::from(interval_minutes) * 60);
+    base.mul_f64(1.0 + AUTO_SYNC_JITTER * (2.0 * unit.clamp(0.0, 1.0) - 1.0))
+}
+
+/// Random value in `[0, 1)` for jitter.
+pub fn random_unit() -> f64 {
+    // The low 53 bits of a v4 UUID are random (version and variant bits are higher)
+    const MANTISSA: u64 = 1 << 53;
+    (uuid::Uuid::new_v4().as_u128() as u64 % MANTISSA) as f64 / MANTISSA as f64
+}
+
+/// Shared auto-sync state: configuration, wake-ups, and the last run.
+#[derive(Default)]
+pub struct AutoSync {
+    config: Mutex<AutoSyncConfig>,
+    last_run: Mutex<Option<Instant>>,
+    focused: Notify,
+    reconfigured: Notify,
+}
+
+impl AutoSync {
+    pub fn config(&self) -> AutoSyncConfig {
+        *self.config.lock().expect("auto-sync lock")
+    }
+
+    /// Replace the configuration, clamping the interval to its allowed range.
+    pub fn set_config(&self, mut config: AutoSyncConfig) -> AutoSyncConfig {
+        config.interval_minutes = config.interval_minutes.clamp(
+            *AUTO_SYNC_INTERVAL_RANGE.start(),
+            *AUTO_SYNC_INTERVAL_RANGE.end(),
+        );
+        *self.config.lock().expect("auto-sync lock") = config;
+        self.reconfigured.notify_one();
+        config
+    }
+
+    /// Wake the scheduler because the window gained focus.
+    pub fn notify_focus(&self) {
+        self.focused.notify_one();
+    }
+
+    /// Sleep until the next interval, a focus event, or a config change.
+    ///
+    /// Returns `None` after a config change so the caller restarts the wait
+    /// with the new interval.
+    pub async fn wait(&self) -> Option<AutoSyncTrigger> {
+        let delay = jittered_interval(self.config().interval_minutes, random_unit());
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => Some(AutoSyncTrigger::Interval),
+            _ = self.focused.notified() => Some(AutoSyncTrigger::Focus),
+            _ = self.reconfigured.notified() => None,
+        }
+    }
+
+    pub fn since_last_run(&self) -> Option<Duration> {
+        self.last_run
+            .lock()
+            .expect("auto-sync lock")
+            .map(|at| at.elapsed())
+    }
+
+    pub fn mark_run(&self) {
+        *self.last_run.lock().expect("auto-sync lock") = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::SyncStage;
+
+    fn enabled() -> AutoSyncConfig {
+        AutoSyncConfig {
+            enabled: true,
+            ..AutoSyncConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_skips_while_already_syncing() {
+        let syncing = SyncStatus::Syncing {
+            stage: SyncStage::Connecting,
+            progress: 0.0,
+        };
+        let check = |status: &SyncStatus| {
+            check_auto_sync(
+                &enabled(),
+                AutoSyncTrigger::Interval,
+                Some(status),
+                true,
+                None,
+            )
+        };
+
+        assert_eq!(check(&syncing), Err(SkipReason::SyncInProgress));
+        assert_eq!(
+            check(&SyncStatus::AwaitingConflictResolution { conflicts: vec![] }),
+            Err(SkipReason::AwaitingUser)
+        );
+        assert_eq!(check(&SyncStatus::Idle), Ok(()));
+        assert_eq!(
+            check(&SyncStatus::Failed {
+                error: "offline".to_string()
+            }),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_honors_pause_and_registration() {
+        let idle = SyncStatus::Idle;
+        let paused = AutoSyncConfig {
+            paused: true,
+            ..enabled()
+        };
+        let interval = AutoSyncTrigger::Interval;
+
+        assert_eq!(
+            check_auto_sync(&paused, interval, Some(&idle), true, None),
+            Err(SkipReason::Paused)
+        );
+        assert_eq!(
+            check_auto_sync(
+                &AutoSyncConfig::default(),
+                interval,
+                Some(&idle),
+                true,
+                None
+            ),
+            Err(SkipReason::Disabled)
+        );
+        assert_eq!(
+            check_auto_sync(&enabled(), interval, Some(&idle), false, None),
+            Err(SkipReason::Unregistered)
+        );
+        assert_eq!(
+            check_auto_sync(&enabled(), interval, None, true, None),
+            Err(SkipReason::NoBackend)
+        );
+    }
+
+    #[test]
+    fn test_focus_sync_waits_for_gap() {
+        let idle = SyncStatus::Idle;
+        let recent = Some(Duration::from_secs(5));
+
+        assert_eq!(
+            check_auto_sync(
+                &enabled(),
+                AutoSyncTrigger::Focus,
+                Some(&idle),
+                true,
+                recent
+            ),
+            Err(SkipReason::TooSoon)
+        );
+        assert_eq!(
+            check_auto_sync(
+                &enabled(),
+                AutoSyncTrigger::Interval,
+                Some(&idle),
+                true,
+                recent
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bounds() {
+        let base = Duration::from_secs(15 * 60);
+        assert_eq!(jittered_interval(15, 0.5), base);
+        assert_eq!(jittered_interval(15, 0.0), base.mul_f64(0.9));
+        assert!(jittered_interval(15, 0.999) < base.mul_f64(1.1));
+
+        for _ in 0..100 {
+            assert!((0.0..1.0).contains(&random_unit()));
+        }
+    }
+
+    #[test]
+    fn test_interval_is_clamped() {
+        let auto = AutoSync::default();
+        let config = auto.set_config(AutoSyncConfig {
+            interval_minutes: 0,
+            ..enabled()
+        });
+        assert_eq!(config.interval_minutes, 1);
+        assert_eq!(auto.config(), config);
+    }
+}
@@ -1,12 +1,14 @@
 //! File system watcher for monitoring markdown file changes.
 
-use crate::db::SqliteRepository;
+use crate::db::{SettingsRepository, SqliteRepository};
+use crate::file_locks::FileLocks;
+use flashcard_core::deck_path_from_file_name;
 use notify::{
     event::{CreateKind, ModifyKind, RemoveKind},
     Config, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher,
 };
 use std::collections::HashSet;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
@@ -53,6 +55,7 @@ impl FileWatcher {
         &mut self,
         app_handle: AppHandle,
         repository: Arc<Mutex<SqliteRepository>>,
+        file_locks: FileLocks,
     ) -> Result<(), String> {
         if self.watcher.is_some() {
             return Ok(()); // Already running
@@ -76,8 +79,9 @@ impl FileWatcher {
 
         // Spawn a thread to handle events
         let handle = app_handle.clone();
+        let watched_dirs = self.watched_dirs.clone();
         thread::spawn(move || {
-            Self::event_loop(rx, stop_rx, handle, repository);
+            Self::event_loop(rx, stop_rx, handle, repository, file_locks, watched_dirs);
         });
 
         Ok(())
@@ -144,6 +148,8 @@ impl FileWatcher {
         stop_rx: Receiver<()>,
         app_handle: AppHandle,
         repository: Arc<Mutex<SqliteRepository>>,
+        file_locks: FileLocks,
+        watched_dirs: Arc<Mutex<HashSet<PathBuf>>>,
     ) {
         loop {
             // Check for stop signal
@@ -154,7 +160,13 @@ impl FileWatcher {
             // Process events with a timeout
             match rx.recv_timeout(Duration::from_millis(100)) {
                 Ok(event) => {
-                    Self::handle_event(&event, &app_handle, &repository);
+                    Self::handle_event(
+                        &event,
+                        &app_handle,
+                        &repository,
+                        &file_locks,
+                        &watched_dirs,
+                    );
                 }
                 Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {
                     // No events, continue
@@ -171,6 +183,8 @@ impl FileWatcher {
         event: &Event,
         app_handle: &AppHandle,
         repository: &Arc<Mutex<SqliteRepository>>,
+        file_locks: &FileLocks,
+        watched_dirs: &Arc<Mutex<HashSet<PathBuf>>>,
     ) {
         // Only process markdown files
         let md_paths: Vec<_> = event
@@ -195,23 +209,22 @@ impl FileWatcher {
             _ => return, // Ignore other events
         };
 
+        let roots = watched_dirs.lock().map(|dirs| dirs.clone()).unwrap_or_default();
+        let separator = repository
+            .lock()
+            .ok()
+            .and_then(|repo| repo.get_global_settings().ok())
+            .and_then(|settings| settings.deck_path_separator);
+
         for path in md_paths {
             let source_file = path.to_string_lossy().to_string();
-            let deck_path = Self::get_deck_path_from_file(path).unwrap_or_default();
+            let deck_path = Self::get_deck_path_from_file(path, &roots, separator.as_deref())
+                .unwrap_or_default();
 
             // Auto-import: update local SQLite database
             match kind_str {
                 "created" | "modified" => {
-                    // Read and parse the file, then import cards
-                    if let Ok(content) = std::fs::read_to_string(path) {
-                        if let Ok(cards) = flashcard_core::parser::parse(&content) {
-                            if let Ok(repo) = repository.lock() {
-                                if let Err(e) = repo.import_cards(&deck_path, &source_file, &cards) {
-                                    eprintln!("Failed to auto-import cards from {}: {}", source_file, e);
-                                }
-                            }
-                        }
-                    }
+                    import_changed_file(path, &deck_path, repository, file_locks);
                 }
                 "deleted" => {
                     // Soft-delete cards from this file
@@ -242,13 +255,94 @@ impl FileWatcher {
     }
 
     /// Extract deck path from a markdown file path.
-    /// This assumes the deck name is the parent directory of the file.
-    fn get_deck_path_from_file(file_path: &PathBuf) -> Option<String> {
-        file_path
-            .parent()
-            .and_then(|p| p.file_name())
-            .map(|name| name.to_string_lossy().to_string())
+    /// The deck is the file's directory relative to the innermost watched
+    /// root containing it, or just the parent directory name outside any root.
+    /// A file name containing `separator` names its deck instead.
+    fn get_deck_path_from_file(
+        file_path: &Path,
+        roots: &HashSet<PathBuf>,
+        separator: Option<&str>,
+    ) -> Option<String> {
+        match innermost_root(roots, file_path) {
+            Some(root) => Some(deck_path_for(root, file_path, separator)),
+            None => deck_path_from_name(file_path, separator).or_else(|| {
+                file_path
+                    .parent()
+                    .and_then(|p| p.file_name())
+                    .map(|name| name.to_string_lossy().to_string())
+            }),
+        }
+    }
+}
+
+/// Read, parse and import a changed markdown file into its deck.
+///
+/// The file is read under its lock in `file_locks`, so a sync writing IDs
+/// back into it finishes first instead of leaving a partial file to parse.
+/// Files that can't be read or parsed are left alone.
+pub fn import_changed_file(
+    path: &Path,
+    deck_path: &str,
+    repository: &Arc<Mutex<SqliteRepository>>,
+    file_locks: &FileLocks,
+) {
+    let source_file = path.to_string_lossy().to_string();
+    let Ok(content) = file_locks.with_lock(path, || std::fs::read_to_string(path)) else {
+        return;
+    };
+    if let Ok(cards) = flashcard_core::parser::parse(&content) {
+        if let Ok(repo) = repository.lock() {
+            if let Err(e) = repo.import_cards(deck_path, &source_file, &cards) {
+                eprintln!("Failed to auto-import cards from {}: {}", source_file, e);
+            }
+        }
+    }
+}
+
+/// The innermost of the watched `roots` containing `path`, which deck paths
+/// under it are relative to.
+pub fn innermost_root<'a>(
+    roots: impl IntoIterator<Item = &'a PathBuf>,
+    path: &Path,
+) -> Option<&'a Path> {
+    roots
+        .into_iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
+        .map(PathBuf::as_path)
+}
+
+/// Deck path for a markdown file under `root`: the file's directory relative
+/// to `root`, joined with `/`.
+///
+/// Matches the backend's `extract_deck_path` for the relative paths sent on
+/// sync, so `programming/rust/basics.md` belongs to `programming/rust` and
+/// files directly in `root` to the empty deck path. With a `separator`, a
+/// file name containing it names the deck instead, see
+/// [`deck_path_from_file_name`].
+pub fn deck_path_for(root: &Path, file_path: &Path, separator: Option<&str>) -> String {
+    if let Some(deck_path) = deck_path_from_name(file_path, separator) {
+        return deck_path;
     }
+    let relative = file_path.strip_prefix(root).unwrap_or(file_path);
+    relative
+        .parent()
+        .map(|dir| {
+            dir.components()
+                .filter_map(|c| match c {
+                    Component::Normal(name) => Some(name.to_string_lossy()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default()
+}
+
+/// Deck path encoded in the file's name with `separator`, if any.
+pub fn deck_path_from_name(file_path: &Path, separator: Option<&str>) -> Option<String> {
+    let name = file_path.file_name()?.to_string_lossy();
+    deck_path_from_file_name(&name, separator?)
 }
 
 impl Default for FileWatcher {
@@ -256,3 +350,93 @@ impl Default for FileWatcher {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_file_keeps_deck_hierarchy() {
+        let root = Path::new("/notes");
+        let file = root.join("programming/rust/basics.md");
+        assert_eq!(deck_path_for(root, &file, None), "programming/rust");
+        assert_eq!(deck_path_for(root, &root.join("rust/ownership.md"), None), "rust");
+        assert_eq!(deck_path_for(root, &root.join("inbox.md"), None), "");
+    }
+
+    #[test]
+    fn test_separator_in_file_name_sets_deck() {
+        let root = Path::new("/notes");
+        let file = root.join("Programming::Rust::Basics.md");
+        assert_eq!(deck_path_for(root, &file, Some("::")), "Programming/Rust");
+        assert_eq!(deck_path_for(root, &file, None), "");
+
+        // Names without the separator still use the directories
+        let plain = root.join("rust/ownership.md");
+        assert_eq!(deck_path_for(root, &plain, Some("::")), "rust");
+        assert_eq!(
+            FileWatcher::get_deck_path_from_file(&file, &HashSet::new(), Some("::")).as_deref(),
+            Some("Programming/Rust")
+        );
+    }
+
+    #[test]
+    fn test_innermost_watched_root_wins() {
+        let file = Path::new("/notes/programming/rust/basics.md");
+        let mut roots = HashSet::from([PathBuf::from("/notes")]);
+        assert_eq!(
+            FileWatcher::get_deck_path_from_file(file, &roots, None).as_deref(),
+            Some("programming/rust")
+        );
+
+        roots.insert(PathBuf::from("/notes/programming"));
+        assert_eq!(
+            FileWatcher::get_deck_path_from_file(file, &roots, None).as_deref(),
+            Some("rust")
+        );
+
+        // Outside every root only the parent directory is known
+        assert_eq!(
+            FileWatcher::get_deck_path_from_file(file, &HashSet::new(), None).as_deref(),
+            Some("rust")
+        );
+    }
+
+    #[test]
+    fn test_import_waits_for_sync_write_back() {
+        use crate::db::CardRepository;
+        use std::io::Write;
+        use std::sync::mpsc::channel;
+
+        let dir = std::env::temp_dir().join(format!("flashcards-watch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rust.md");
+        std::fs::write(&path, "Q: One\nA: 1\n").unwrap();
+
+        let repository = Arc::new(Mutex::new(SqliteRepository::open_in_memory().unwrap()));
+        let file_locks = FileLocks::new();
+
+        // A sync write-back that rewrites the file in two steps
+        let (started_tx, started_rx) = channel();
+        let write_back = {
+            let (file_locks, path) = (file_locks.clone(), path.clone());
+            thread::spawn(move || {
+                file_locks.with_lock(&path, || {
+                    let mut file = std::fs::File::create(&path).unwrap();
+                    file.write_all(b"Q: One\nA: 1\n").unwrap();
+                    started_tx.send(()).unwrap();
+                    thread::sleep(Duration::from_millis(100));
+                    file.write_all(b"\nQ: Two\nA: 2\n").unwrap();
+                });
+            })
+        };
+
+        started_rx.recv().unwrap();
+        import_changed_file(&path, "rust", &repository, &file_locks);
+        write_back.join().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let cards = repository.lock().unwrap().get_cards_by_deck("rust").unwrap();
+        assert_eq!(cards.len(), 2);
+    }
+}
@@ -24,6 +24,29 @@ describe('AnswerComparison', () => {
     expect(screen.getByText('Incorrect')).toBeInTheDocument();
   });
 
+  it('should display No answer status when nothing was typed', () => {
+    const result = createMockCompareAnswerResponse({
+      is_correct: false,
+      no_answer: true,
+    });
+
+    render(<AnswerComparison result={result} correctAnswer="test answer" />);
+
+    expect(screen.getByText('No answer')).toBeInTheDocument();
+  });
+
+  it('should list items missing from a list answer', () => {
+    const result = createMockCompareAnswerResponse({
+      is_correct: false,
+      missing_items: ['Executive'],
+    });
+
+    render(<AnswerComparison result={result} correctAnswer={'- Legislative\n- Executive'} />);
+
+    expect(screen.getByText('Missing')).toBeInTheDocument();
+    expect(screen.getByText('Executive')).toBeInTheDocument();
+  });
+
   it('should apply correct CSS class based on correctness', () => {
     const correctResult = createMockCompareAnswerResponse({ is_correct: true });
     const { container: correctContainer } = render(
@@ -11,13 +11,15 @@ export function AnswerComparison({ result, correctAnswer }: AnswerComparisonProp
       ? 'Exact'
       : result.matching_mode === 'case_insensitive'
       ? 'Case Insensitive'
+      : result.matching_mode === 'contains'
+      ? 'Contains'
       : 'Fuzzy';
 
   return (
     <div className={`answer-comparison ${result.is_correct ? 'correct' : 'incorrect'}`}>
       <div className="comparison-header">
         <span className={`comparison-result ${result.is_correct ? 'correct' : 'incorrect'}`}>
-          {result.is_correct ? 'Correct!' : 'Incorrect'}
+          {result.is_correct ? 'Correct!' : result.no_answer ? 'No answer' : 'Incorrect'}
         </span>
         {result.matching_mode === 'fuzzy' && (
           <span className="comparison-similarity">
@@ -35,6 +37,17 @@ export function AnswerComparison({ result, correctAnswer }: AnswerComparisonProp
         </div>
       </div>
 
+      {result.missing_items.length > 0 && !result.no_answer && (
+        <div className="comparison-section">
+          <div className="comparison-label">Missing</div>
+          <ul className="comparison-missing">
+            {result.missing_items.map((item) => (
+              <li key={item}>{item}</li>
+            ))}
+          </ul>
+        </div>
+      )}
+
       <div className="comparison-section">
         <div className="comparison-label">Correct Answer</div>
         <div className="comparison-text">{correctAnswer}</div>
@@ -12,7 +12,7 @@ interface StudySessionProps {
 
 export function StudySession({ deckPath }: StudySessionProps) {
   const {
-    currentCard,
+    shownCard,
     currentIndex,
     total,
     revealed,
@@ -40,7 +40,7 @@ export function StudySession({ deckPath }: StudySessionProps) {
     return <StudyComplete onRestart={restart} />;
   }
 
-  if (!currentCard) {
+  if (!shownCard) {
     return (
       <div className="no-cards">
         <h2>No cards to study</h2>
@@ -64,7 +64,7 @@ export function StudySession({ deckPath }: StudySessionProps) {
 
       {answerMode === 'flip' ? (
         <>
-          <Card card={currentCard} revealed={revealed} onReveal={reveal} />
+          <Card card={shownCard} revealed={revealed} onReveal={reveal} />
           {revealed && (
             <RatingButtons
               onRate={rate}
@@ -80,7 +80,7 @@ export function StudySession({ deckPath }: StudySessionProps) {
             <div className="card-content">
               <div className="card-question">
                 <div className="card-label">Question</div>
-                <div className="card-text">{currentCard.question}</div>
+                <div className="card-text">{shownCard.question}</div>
               </div>
 
               {!revealed ? (
@@ -95,7 +95,7 @@ export function StudySession({ deckPath }: StudySessionProps) {
                   {compareResult && (
                     <AnswerComparison
                       result={compareResult}
-                      correctAnswer={currentCard.answer}
+                      correctAnswer={shownCard.answer}
                     />
                   )}
                 </>
@@ -12,6 +12,11 @@ interface DeckRefreshEvent {
   deck_path: string;
 }
 
+interface FileReadError {
+  path: string;
+  error: string;
+}
+
 interface Toast {
   id: string;
   message: string;
@@ -74,6 +79,7 @@ export function useFileWatcher() {
   useEffect(() => {
     let unlistenFile: (() => void) | undefined;
     let unlistenDeck: (() => void) | undefined;
+    let unlistenSyncErrors: (() => void) | undefined;
 
     const setupListeners = async () => {
       unlistenFile = await listen<FileChangeEvent>('file-changed', (event) => {
@@ -90,6 +96,12 @@ export function useFileWatcher() {
         queryClient.invalidateQueries({ queryKey: ['deck-stats', deck_path] });
         addToast(`Deck updated: ${deck_path}`, 'success');
       });
+
+      unlistenSyncErrors = await listen<FileReadError[]>('sync-file-errors', (event) => {
+        for (const { path, error } of event.payload) {
+          addToast(`Skipped ${path} during sync: ${error}`, 'warning');
+        }
+      });
     };
 
     setupListeners();
@@ -97,6 +109,7 @@ export function useFileWatcher() {
     return () => {
       unlistenFile?.();
       unlistenDeck?.();
+      unlistenSyncErrors?.();
     };
   }, [queryClient]);
 
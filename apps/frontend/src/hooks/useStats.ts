@@ -1,4 +1,5 @@
 import { useQuery } from '@tanstack/react-query';
+import type { DateRange } from '@jirehs-flashcards/shared-types';
 import { tauri } from '../lib/tauri';
 
 /**
@@ -12,12 +13,12 @@ export function useDeckStats(deckPath?: string) {
 }
 
 /**
- * Hook for fetching overall study statistics.
+ * Hook for fetching overall study statistics, optionally for a date range.
  */
-export function useStudyStats() {
+export function useStudyStats(range?: DateRange) {
   return useQuery({
-    queryKey: ['study-stats'],
-    queryFn: tauri.getStudyStats,
+    queryKey: ['study-stats', range],
+    queryFn: () => tauri.getStudyStats(range),
   });
 }
 
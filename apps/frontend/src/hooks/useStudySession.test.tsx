@@ -2,10 +2,11 @@ import { describe, it, expect, vi, beforeEach } from 'vitest';
 import { renderHook, waitFor, act } from '@testing-library/react';
 import { QueryClient, QueryClientProvider } from '@tanstack/react-query';
 import type { ReactNode } from 'react';
-import { useStudySession } from './useStudySession';
+import { orderSessionCards, useStudySession } from './useStudySession';
 import { useStudyStore } from '../stores/studyStore';
 import {
   createMockCard,
+  createMockCardState,
   createMockStudyQueue,
   createMockEffectiveSettings,
   createMockCompareAnswerResponse,
@@ -119,6 +120,28 @@ describe('useStudySession', () => {
     expect(result.current.currentCard).toEqual(newCard);
   });
 
+  it('should start with due cards when queue_order is due_first', async () => {
+    const newCard = createMockCard({ id: 1 });
+    const reviewCard = createMockCard({ id: 2 });
+    vi.mocked(tauri.getStudyQueue).mockResolvedValue(
+      createMockStudyQueue({
+        new_cards: [newCard],
+        review_cards: [reviewCard],
+        queue_order: 'due_first',
+      })
+    );
+
+    const { result } = renderHook(() => useStudySession('/decks/test'), {
+      wrapper: createWrapper(),
+    });
+
+    await waitFor(() => {
+      expect(result.current.isLoading).toBe(false);
+    });
+
+    expect(result.current.currentCard).toEqual(reviewCard);
+  });
+
   it('should return currentCard at current index', async () => {
     const card1 = createMockCard({ id: 1, question: 'Q1' });
     const card2 = createMockCard({ id: 2, question: 'Q2' });
@@ -192,6 +215,43 @@ describe('useStudySession', () => {
     expect(result.current.isComplete).toBe(true);
   });
 
+  it('should bring a requeued card back at the end of the session', async () => {
+    const card1 = createMockCard({ id: 1, question: 'Q1' });
+    const card2 = createMockCard({ id: 2, question: 'Q2' });
+    vi.mocked(tauri.getStudyQueue).mockResolvedValue(
+      createMockStudyQueue({ new_cards: [card1, card2] })
+    );
+    vi.mocked(tauri.submitReview).mockResolvedValue({
+      new_state: createMockCardState({ status: 'learning' }),
+      next_due: new Date().toISOString(),
+      requeue: true,
+    });
+
+    const { result } = renderHook(() => useStudySession('/decks/test'), {
+      wrapper: createWrapper(),
+    });
+
+    await waitFor(() => {
+      expect(result.current.isLoading).toBe(false);
+    });
+
+    act(() => {
+      result.current.rate(1);
+    });
+
+    await waitFor(() => {
+      expect(result.current.currentCard?.question).toBe('Q2');
+    });
+    expect(result.current.total).toBe(3);
+
+    act(() => {
+      useStudyStore.getState().setCurrentIndex(2);
+    });
+
+    expect(result.current.currentCard).toEqual(card1);
+    expect(result.current.isComplete).toBe(false);
+  });
+
   it('should toggle answer mode', async () => {
     const mockQueue = createMockStudyQueue({
       new_cards: [createMockCard()],
@@ -279,3 +339,29 @@ describe('useStudySession', () => {
     expect(result.current.ratingScale).toBe('4point');
   });
 });
+
+describe('orderSessionCards', () => {
+  const newCards = [1, 2].map((id) => createMockCard({ id }));
+  const dueCards = [11, 12, 13, 14].map((id) => createMockCard({ id }));
+  const ids = (cards: { id: number }[]) => cards.map((card) => card.id);
+
+  it('should put due cards first for due_first', () => {
+    expect(ids(orderSessionCards(newCards, dueCards, 'due_first'))).toEqual([
+      11, 12, 13, 14, 1, 2,
+    ]);
+  });
+
+  it('should put new cards first for new_first and by default', () => {
+    const expected = [1, 2, 11, 12, 13, 14];
+    expect(ids(orderSessionCards(newCards, dueCards, 'new_first'))).toEqual(expected);
+    expect(ids(orderSessionCards(newCards, dueCards))).toEqual(expected);
+  });
+
+  it('should spread new cards evenly for interleaved', () => {
+    expect(ids(orderSessionCards(newCards, dueCards, 'interleaved'))).toEqual([
+      1, 11, 12, 2, 13, 14,
+    ]);
+    expect(ids(orderSessionCards(newCards, [], 'interleaved'))).toEqual([1, 2]);
+    expect(ids(orderSessionCards([], dueCards, 'interleaved'))).toEqual([11, 12, 13, 14]);
+  });
+});
@@ -1,9 +1,58 @@
+import { useMemo, useRef, useState } from 'react';
 import { useMutation, useQuery, useQueryClient } from '@tanstack/react-query';
-import type { Rating, ReviewRequest, AnswerMode, RatingScale } from '@jirehs-flashcards/shared-types';
+import type {
+  Card,
+  QueueOrder,
+  SessionLimit,
+  Rating,
+  ReviewRequest,
+  AnswerMode,
+  RatingScale,
+} from '@jirehs-flashcards/shared-types';
 import { tauri } from '../lib/tauri';
 import { useStudyStore } from '../stores/studyStore';
 import { useEffectiveSettings } from './useSettings';
 
+/**
+ * Combine new and due cards in the session order. `interleaved` spreads new
+ * cards evenly among due ones, in proportion to how many there are of each.
+ */
+export function orderSessionCards(
+  newCards: Card[],
+  dueCards: Card[],
+  order: QueueOrder = 'new_first'
+): Card[] {
+  switch (order) {
+    case 'due_first':
+      return [...dueCards, ...newCards];
+    case 'new_first':
+      return [...newCards, ...dueCards];
+    case 'interleaved': {
+      const cards: Card[] = [];
+      let n = 0;
+      let d = 0;
+      while (n < newCards.length || d < dueCards.length) {
+        // Take a new card whenever new cards are behind their share
+        const newIsDue =
+          d >= dueCards.length || n * dueCards.length <= d * newCards.length;
+        if (n < newCards.length && newIsDue) {
+          cards.push(newCards[n++]);
+        } else {
+          cards.push(dueCards[d++]);
+        }
+      }
+      return cards;
+    }
+  }
+}
+
+/** Minutes a session may run, if its limit is a time cap. */
+export function sessionMinutes(limit?: SessionLimit): number | null {
+  if (!limit?.startsWith('minutes:')) return null;
+  const minutes = parseInt(limit.slice('minutes:'.length));
+  return Number.isNaN(minutes) ? null : minutes;
+}
+
 export function useStudySession(deckPath?: string) {
   const queryClient = useQueryClient();
   const {
@@ -31,11 +80,20 @@ export function useStudySession(deckPath?: string) {
     queryFn: () => tauri.getStudyQueue(deckPath),
   });
 
+  // Cards rated Again come back at the end of the session
+  const [requeued, setRequeued] = useState<Card[]>([]);
+
   const submitReview = useMutation({
     mutationFn: (request: ReviewRequest) => tauri.submitReview(request),
-    onSuccess: () => {
+    onSuccess: (response, request) => {
+      if (response.requeue) {
+        const card = allCards.find((c) => c.id === request.card_id);
+        if (card) setRequeued((cards) => [...cards, card]);
+      }
       nextCard();
-      queryClient.invalidateQueries({ queryKey: ['study-queue'] });
+      // The queue is kept for the session so indexes stay put; deck counts
+      // are refreshed instead
+      queryClient.invalidateQueries({ queryKey: ['decks'] });
     },
   });
 
@@ -48,9 +106,30 @@ export function useStudySession(deckPath?: string) {
     },
   });
 
-  const allCards = [...(queue.data?.new_cards ?? []), ...(queue.data?.review_cards ?? [])];
-  const currentCard = allCards[currentIndex];
-  const isComplete = currentIndex >= allCards.length && queue.isSuccess;
+  const allCards = [
+    ...orderSessionCards(
+      queue.data?.new_cards ?? [],
+      queue.data?.review_cards ?? [],
+      queue.data?.queue_order
+    ),
+    ...requeued,
+  ];
+  // Card caps are applied by the queue; a time cap ends the session once
+  // the card in progress is rated
+  const sessionStartedAt = useRef(Date.now());
+  const minutes = sessionMinutes(queue.data?.session_limit);
+  const timeUp = useMemo(
+    () => minutes !== null && Date.now() - sessionStartedAt.current >= minutes * 60_000,
+    // Checked as each card comes up, not while one is being answered
+    [currentIndex, minutes]
+  );
+  const currentCard = timeUp ? undefined : allCards[currentIndex];
+  // The card as studied: sides swapped when the study direction reverses it
+  const shownCard =
+    currentCard && queue.data?.reversed_card_ids?.includes(currentCard.id)
+      ? { ...currentCard, question: currentCard.answer, answer: currentCard.question }
+      : currentCard;
+  const isComplete = (currentIndex >= allCards.length || timeUp) && queue.isSuccess;
   const total = allCards.length;
   const progress = total > 0 ? currentIndex / total : 0;
 
@@ -60,11 +139,11 @@ export function useStudySession(deckPath?: string) {
   };
 
   const submitTypedAnswer = () => {
-    if (!currentCard || !typedAnswer.trim()) return;
+    if (!shownCard || !typedAnswer.trim()) return;
     startTimer();
     compareAnswer.mutate({
       typed: typedAnswer,
-      correct: currentCard.answer,
+      correct: shownCard.answer,
     });
   };
 
@@ -82,6 +161,8 @@ export function useStudySession(deckPath?: string) {
   };
 
   const restart = () => {
+    setRequeued([]);
+    sessionStartedAt.current = Date.now();
     reset();
     queryClient.invalidateQueries({ queryKey: ['study-queue'] });
   };
@@ -93,6 +174,7 @@ export function useStudySession(deckPath?: string) {
   return {
     queue,
     currentCard,
+    shownCard,
     currentIndex,
     total,
     progress,
@@ -3,31 +3,83 @@ import type {
   CalendarData,
   Card,
   CardState,
+  CardWithState,
   CompareAnswerResponse,
+  DateRange,
   Deck,
   DeckSettings,
   DeckStats,
+  DirectoryScan,
   EffectiveSettings,
   GlobalSettings,
+  IdStrategy,
   ImportResult,
+  ProgressReset,
+  Rating,
+  RatingDistribution,
+  RatingScale,
+  ReviewDebugResponse,
   ReviewRequest,
   ReviewResponse,
+  SessionLimit,
+  SlowCard,
   StudyQueue,
   StudyStats,
+  WorkloadProjection,
 } from '@jirehs-flashcards/shared-types';
 
 export const tauri = {
   // Deck commands
-  listDecks: () => invoke<Deck[]>('list_decks'),
+  listDecks: (includeArchived?: boolean) =>
+    invoke<Deck[]>('list_decks', { includeArchived }),
   getDeck: (deckPath: string) => invoke<Deck | null>('get_deck', { deckPath }),
-  importFile: (filePath: string) => invoke<ImportResult>('import_file', { filePath }),
-  importDirectory: (dirPath: string) => invoke<ImportResult>('import_directory', { dirPath }),
+  importFile: (filePath: string, idStrategy?: IdStrategy) =>
+    invoke<ImportResult>('import_file', { filePath, idStrategy }),
+  importDirectory: (dirPath: string, idStrategy?: IdStrategy) =>
+    invoke<ImportResult>('import_directory', { dirPath, idStrategy }),
+  scanDirectory: (dirPath: string) => invoke<DirectoryScan>('scan_directory', { dirPath }),
+  resetDeckProgress: (
+    deckPath: string,
+    confirm: boolean,
+    archiveReviews?: boolean,
+    backendUrl?: string
+  ) =>
+    invoke<ProgressReset>('reset_deck_progress', {
+      deckPath,
+      confirm,
+      archiveReviews,
+      backendUrl,
+    }),
 
   // Study commands
-  getStudyQueue: (deckPath?: string) => invoke<StudyQueue>('get_study_queue', { deckPath }),
+  getStudyQueue: (
+    deckPath?: string,
+    newCardsLimitOverride?: number,
+    reviewLimitOverride?: number,
+    sessionLimit?: SessionLimit,
+    tag?: string
+  ) =>
+    invoke<StudyQueue>('get_study_queue', {
+      deckPath,
+      tag,
+      newCardsLimitOverride,
+      reviewLimitOverride,
+      sessionLimit,
+    }),
   submitReview: (request: ReviewRequest) => invoke<ReviewResponse>('submit_review', { request }),
+  previewReviewDebug: (cardId: number, rating: Rating, ratingScale: RatingScale) =>
+    invoke<ReviewDebugResponse>('preview_review_debug', { cardId, rating, ratingScale }),
   getCard: (cardId: number) => invoke<Card | null>('get_card', { cardId }),
   getCardState: (cardId: number) => invoke<CardState | null>('get_card_state', { cardId }),
+  setNewPriority: (cardId: number, priority: number) =>
+    invoke<void>('set_new_priority', { cardId, priority }),
+  getCardWithState: (cardId: number) =>
+    invoke<CardWithState | null>('get_card_with_state', { cardId }),
+  getCardsByTag: (tag: string) => invoke<Card[]>('get_cards_by_tag', { tag }),
+  getDifficultCards: (deckPath?: string, limit?: number) =>
+    invoke<Card[]>('get_difficult_cards', { deckPath, limit }),
+  getDifficultStudyQueue: (deckPath?: string, limit?: number) =>
+    invoke<StudyQueue>('get_difficult_study_queue', { deckPath, limit }),
   compareTypedAnswer: (typedAnswer: string, correctAnswer: string, deckPath?: string) =>
     invoke<CompareAnswerResponse>('compare_typed_answer', { typedAnswer, correctAnswer, deckPath }),
 
@@ -41,15 +93,27 @@ export const tauri = {
     invoke<void>('save_deck_settings', { settings }),
   deleteDeckSettings: (deckPath: string) =>
     invoke<void>('delete_deck_settings', { deckPath }),
+  setDeckArchived: (deckPath: string, archived: boolean) =>
+    invoke<void>('set_deck_archived', { deckPath, archived }),
   getEffectiveSettings: (deckPath?: string) =>
     invoke<EffectiveSettings>('get_effective_settings', { deckPath }),
 
   // Stats commands
   getDeckStats: (deckPath?: string) =>
     invoke<DeckStats>('get_deck_stats', { deckPath }),
-  getStudyStats: () => invoke<StudyStats>('get_study_stats'),
+  getStudyStats: (range?: DateRange) => invoke<StudyStats>('get_study_stats', { range }),
+  refreshStatsCache: () => invoke<StudyStats>('refresh_stats_cache'),
   getCalendarData: (days?: number) =>
     invoke<CalendarData[]>('get_calendar_data', { days }),
+  getSlowestCards: (deckPath?: string, limit?: number) =>
+    invoke<SlowCard[]>('get_slowest_cards', { deckPath, limit }),
+  getRatingDistribution: (deckPath?: string) =>
+    invoke<RatingDistribution>('get_rating_distribution', { deckPath }),
+  getReviews: (filters: ReviewFilters = {}) => invoke<ReviewPage>('get_reviews', { filters }),
+  projectWorkload: (newRetention: number, deckPath?: string) =>
+    invoke<WorkloadProjection>('project_workload', { deckPath, newRetention }),
+  collectionStats: (backendUrl?: string) =>
+    invoke<CollectionStats>('collection_stats', { backendUrl }),
 
   // File watcher commands
   startWatching: (dirPath: string) => invoke<void>('start_watching', { dirPath }),
@@ -63,6 +127,7 @@ export const tauri = {
   cancelSync: () => invoke<void>('cancel_sync'),
   confirmOrphanDeletion: (cardIds: number[]) =>
     invoke<number>('confirm_orphan_deletion', { cardIds }),
+  nextOrphanBatch: () => invoke<OrphanInfo[]>('next_orphan_batch'),
   skipOrphanDeletion: () => invoke<SyncStats>('skip_orphan_deletion'),
   registerDevice: (backendUrl: string, deviceName?: string) =>
     invoke<DeviceInfo>('register_device', { backendUrl, deviceName }),
@@ -70,6 +135,10 @@ export const tauri = {
   checkConnectivity: (backendUrl: string) =>
     invoke<boolean>('check_connectivity', { backendUrl }),
   getLocalSyncState: () => invoke<LocalSyncState>('get_local_sync_state'),
+  diagnostics: (backendUrl: string) => invoke<Diagnostics>('diagnostics', { backendUrl }),
+  getFailedReviews: () => invoke<FailedReview[]>('get_failed_reviews'),
+  getAutoSync: () => invoke<AutoSyncConfig>('get_auto_sync'),
+  setAutoSync: (config: AutoSyncConfig) => invoke<AutoSyncConfig>('set_auto_sync', { config }),
 };
 
 // Sync types
@@ -78,6 +147,7 @@ export interface SyncStatus {
   stage?: SyncStage;
   progress?: number;
   orphans?: OrphanInfo[];
+  has_more?: boolean;
   synced_at?: string;
   stats?: SyncStats;
   error?: string;
@@ -97,7 +167,15 @@ export interface SyncStats {
   cards_updated: number;
   orphans_deleted: number;
   reviews_synced: number;
+  reviews_failed: number;
   states_pulled: number;
+  per_deck: Record<string, DeckDelta>;
+}
+
+export interface DeckDelta {
+  created: number;
+  updated: number;
+  deleted: number;
 }
 
 export interface OrphanInfo {
@@ -114,3 +192,68 @@ export interface LocalSyncState {
   last_sync_at: string | null;
   pending_changes: number;
 }
+
+export interface Diagnostics {
+  registered: boolean;
+  device_id: string | null;
+  backend_reachable: boolean;
+  last_sync_at: string | null;
+  pending_reviews: number;
+  pending_files: number;
+  watched_directories: string[];
+  schema_version: number;
+}
+
+export interface AutoSyncConfig {
+  enabled: boolean;
+  interval_minutes: number;
+  paused: boolean;
+}
+
+export interface ReviewLogEntry {
+  id: number;
+  card_id: number;
+  reviewed_at: string;
+  rating: number;
+  rating_scale: string;
+  answer_mode: string;
+  typed_answer: string | null;
+  was_correct: boolean | null;
+  time_taken_ms: number | null;
+  interval_before: number;
+  interval_after: number;
+  ease_before: number;
+  ease_after: number;
+  algorithm: string;
+  params_hash: string | null;
+  status_before: string | null;
+  cram: boolean;
+}
+
+export interface FailedReview extends ReviewLogEntry {
+  reason: string;
+  failed_at: string;
+}
+
+// Every filter is optional; `from` is inclusive and `to` exclusive
+export interface ReviewFilters {
+  card_id?: number;
+  from?: string;
+  to?: string;
+  offset?: number;
+  limit?: number;
+}
+
+export interface ReviewPage {
+  reviews: ReviewLogEntry[];
+  has_more: boolean;
+}
+
+// remote_file_count is null when unregistered or the backend is unreachable
+export interface CollectionStats {
+  total_cards: number;
+  total_decks: number;
+  total_reviews: number;
+  database_size_bytes: number;
+  remote_file_count: number | null;
+}
@@ -1,5 +1,14 @@
 import { useState, useEffect } from 'react';
-import type { GlobalSettings, Algorithm, RatingScale, MatchingMode } from '@jirehs-flashcards/shared-types';
+import type {
+  GlobalSettings,
+  Algorithm,
+  RatingScale,
+  MatchingMode,
+  QueueOrder,
+  ConflictPolicy,
+  StudyDirection,
+  SessionLimit,
+} from '@jirehs-flashcards/shared-types';
 import { useSettings } from '../hooks/useSettings';
 import { useFileWatcher } from '../hooks/useFileWatcher';
 import { useSync, useDeviceRegistration, useLocalSyncState } from '../hooks/useSync';
@@ -57,6 +66,9 @@ export function Settings() {
     );
   }
 
+  const [sessionLimitKind, sessionLimitCount] = (formData.session_limit ?? 'none').split(':');
+  const sessionLimitValue = parseInt(sessionLimitCount) || 20;
+
   const handleChange = <K extends keyof GlobalSettings>(
     key: K,
     value: GlobalSettings[K]
@@ -156,6 +168,7 @@ export function Settings() {
             >
               <option value="exact">Exact Match</option>
               <option value="case_insensitive">Case Insensitive</option>
+              <option value="contains">Contains</option>
               <option value="fuzzy">Fuzzy Match</option>
             </select>
             <span className="form-hint">
@@ -180,6 +193,51 @@ export function Settings() {
               </div>
             </div>
           )}
+
+          <div className="form-group">
+            <label className="form-label">Suggest Easy From</label>
+            <input
+              type="range"
+              className="form-range"
+              min="0.5"
+              max="1"
+              step="0.05"
+              value={formData.suggest_easy_threshold ?? 1}
+              onChange={(e) => handleChange('suggest_easy_threshold', parseFloat(e.target.value))}
+            />
+            <div className="range-value">
+              Correct answers {Math.round((formData.suggest_easy_threshold ?? 1) * 100)}% similar suggest Easy
+            </div>
+          </div>
+
+          <div className="form-group">
+            <label className="form-label">Suggest Hard From</label>
+            <input
+              type="range"
+              className="form-range"
+              min="0"
+              max="1"
+              step="0.05"
+              value={formData.suggest_hard_threshold ?? 0.6}
+              onChange={(e) => handleChange('suggest_hard_threshold', parseFloat(e.target.value))}
+            />
+            <div className="range-value">
+              Wrong answers {Math.round((formData.suggest_hard_threshold ?? 0.6) * 100)}% similar suggest Hard
+            </div>
+          </div>
+
+          <div className="form-group">
+            <label className="form-label">Minimum Answer Length</label>
+            <input
+              type="number"
+              className="form-input"
+              min="0"
+              max="50"
+              value={formData.min_answer_chars ?? 0}
+              onChange={(e) => handleChange('min_answer_chars', Math.max(0, parseInt(e.target.value) || 0))}
+            />
+            <span className="form-hint">Typed answers with fewer characters are marked as no answer.</span>
+          </div>
         </section>
 
         {/* Daily Limits Section */}
@@ -192,10 +250,35 @@ export function Settings() {
               className="form-input"
               min="0"
               max="999"
-              value={formData.new_cards_per_day}
+              disabled={formData.new_cards_per_day === 'unlimited'}
+              value={formData.new_cards_per_day === 'unlimited' ? '' : formData.new_cards_per_day}
               onChange={(e) => handleChange('new_cards_per_day', parseInt(e.target.value) || 0)}
             />
-            <span className="form-hint">Maximum number of new cards to introduce each day.</span>
+            <label className="form-label">
+              <input
+                type="checkbox"
+                checked={formData.new_cards_per_day === 'unlimited'}
+                onChange={(e) =>
+                  handleChange('new_cards_per_day', e.target.checked ? 'unlimited' : 20)
+                }
+              />{' '}
+              Unlimited
+            </label>
+            <span className="form-hint">
+              Maximum number of new cards to introduce each day. 0 shows no new cards.
+            </span>
+          </div>
+
+          <div className="form-group">
+            <label className="form-label">
+              <input
+                type="checkbox"
+                checked={formData.new_cards_paused ?? false}
+                onChange={(e) => handleChange('new_cards_paused', e.target.checked)}
+              />{' '}
+              Pause New Cards
+            </label>
+            <span className="form-hint">Show no new cards in any deck while you catch up on reviews.</span>
           </div>
 
           <div className="form-group">
@@ -205,12 +288,87 @@ export function Settings() {
               className="form-input"
               min="0"
               max="9999"
-              value={formData.reviews_per_day}
+              disabled={formData.reviews_per_day === 'unlimited'}
+              value={formData.reviews_per_day === 'unlimited' ? '' : formData.reviews_per_day}
               onChange={(e) => handleChange('reviews_per_day', parseInt(e.target.value) || 0)}
             />
+            <label className="form-label">
+              <input
+                type="checkbox"
+                checked={formData.reviews_per_day === 'unlimited'}
+                onChange={(e) =>
+                  handleChange('reviews_per_day', e.target.checked ? 'unlimited' : 200)
+                }
+              />{' '}
+              Unlimited
+            </label>
             <span className="form-hint">Maximum number of review cards per day.</span>
           </div>
 
+          <div className="form-group">
+            <label className="form-label">Queue Order</label>
+            <select
+              className="form-select"
+              value={formData.queue_order ?? 'new_first'}
+              onChange={(e) => handleChange('queue_order', e.target.value as QueueOrder)}
+            >
+              <option value="new_first">New cards first</option>
+              <option value="due_first">Due cards first</option>
+              <option value="interleaved">Mix new cards among due cards</option>
+            </select>
+            <span className="form-hint">Order of new and due cards in a study session.</span>
+          </div>
+
+          <div className="form-group">
+            <label className="form-label">Study Direction</label>
+            <select
+              className="form-select"
+              value={formData.study_direction ?? 'forward'}
+              onChange={(e) => handleChange('study_direction', e.target.value as StudyDirection)}
+            >
+              <option value="forward">Question first</option>
+              <option value="reverse">Answer first</option>
+              <option value="both">Both, alternating daily</option>
+            </select>
+            <span className="form-hint">Which side of each card to show; the other is the one to recall.</span>
+          </div>
+
+          <div className="form-group">
+            <label className="form-label">Session Length</label>
+            <select
+              className="form-select"
+              value={sessionLimitKind}
+              onChange={(e) =>
+                handleChange(
+                  'session_limit',
+                  e.target.value === 'none'
+                    ? 'none'
+                    : (`${e.target.value}:${sessionLimitValue}` as SessionLimit)
+                )
+              }
+            >
+              <option value="none">No limit</option>
+              <option value="cards">Cards</option>
+              <option value="minutes">Minutes</option>
+            </select>
+            {sessionLimitKind !== 'none' && (
+              <input
+                type="number"
+                className="form-input"
+                min="1"
+                max="999"
+                value={sessionLimitValue}
+                onChange={(e) =>
+                  handleChange(
+                    'session_limit',
+                    `${sessionLimitKind}:${Math.max(1, parseInt(e.target.value) || 1)}` as SessionLimit
+                  )
+                }
+              />
+            )}
+            <span className="form-hint">End each study session after this many cards or minutes.</span>
+          </div>
+
           <div className="form-group">
             <label className="form-label">Daily Reset Hour</label>
             <select
@@ -226,6 +384,87 @@ export function Settings() {
             </select>
             <span className="form-hint">When daily card counts reset (local time).</span>
           </div>
+
+          <div className="form-group">
+            <label className="form-label">Due Grace Hours</label>
+            <input
+              type="number"
+              className="form-input"
+              min="0"
+              max="24"
+              value={formData.due_grace_hours ?? 0}
+              onChange={(e) => handleChange('due_grace_hours', parseInt(e.target.value) || 0)}
+            />
+            <span className="form-hint">Also show reviews due within this many hours from now.</span>
+          </div>
+
+          <div className="form-group">
+            <label className="form-label">Graduation Requirement</label>
+            <input
+              type="number"
+              className="form-input"
+              min="1"
+              max="10"
+              value={formData.graduation_requirement ?? 1}
+              onChange={(e) => handleChange('graduation_requirement', parseInt(e.target.value) || 1)}
+            />
+            <span className="form-hint">Passing ratings in a row a new card needs before it leaves learning.</span>
+          </div>
+
+          {formData.algorithm === 'sm2' && (
+            <div className="form-group">
+              <label className="form-label">Ease Range</label>
+              <div className="form-row">
+                <input
+                  type="number"
+                  className="form-input"
+                  min="1"
+                  max="10"
+                  step="0.05"
+                  value={formData.min_ease ?? 1.3}
+                  onChange={(e) => handleChange('min_ease', parseFloat(e.target.value) || 1.3)}
+                />
+                <input
+                  type="number"
+                  className="form-input"
+                  min="1"
+                  max="10"
+                  step="0.05"
+                  value={formData.max_ease ?? 5}
+                  onChange={(e) => handleChange('max_ease', parseFloat(e.target.value) || 5)}
+                />
+              </div>
+              <span className="form-hint">Lowest and highest ease an SM-2 card can reach.</span>
+            </div>
+          )}
+
+          <div className="form-group">
+            <label className="form-label">Deck Path Separator</label>
+            <input
+              type="text"
+              className="form-input"
+              placeholder="::"
+              value={formData.deck_path_separator ?? ''}
+              onChange={(e) => handleChange('deck_path_separator', e.target.value || null)}
+            />
+            <span className="form-hint">
+              Split file names into decks, e.g. Programming::Rust::Basics.md goes in Programming/Rust. Leave empty to use folders.
+            </span>
+          </div>
+
+          <div className="form-group">
+            <label className="form-label">Sync Conflicts</label>
+            <select
+              className="form-select"
+              value={formData.sync_conflict_policy ?? 'manual'}
+              onChange={(e) => handleChange('sync_conflict_policy', e.target.value as ConflictPolicy)}
+            >
+              <option value="manual">Ask me for each card</option>
+              <option value="most_progress">Keep the side with more reviews</option>
+              <option value="last_write_wins">Keep the latest change</option>
+            </select>
+            <span className="form-hint">What a sync does with cards studied on more than one device.</span>
+          </div>
         </section>
 
         {/* Form Actions */}
@@ -118,6 +118,7 @@ export function createMockEffectiveSettings(
     new_cards_per_day: 20,
     reviews_per_day: 200,
     daily_reset_hour: 4,
+    rating_buttons: ['again', 'hard', 'good', 'easy'],
     ...overrides,
   };
 }
@@ -178,7 +179,10 @@ export function createMockCompareAnswerResponse(
     matching_mode: 'exact',
     typed_normalized: 'answer',
     correct_normalized: 'answer',
+    no_answer: false,
     diff: [{ text: 'answer', diff_type: 'Same' }],
+    missing_items: [],
+    suggested_rating: 'easy',
     ...overrides,
   };
 }
@@ -241,7 +245,9 @@ export function createMockSyncStats(overrides: Partial<SyncStats> = {}): SyncSta
     cards_updated: 0,
     orphans_deleted: 0,
     reviews_synced: 0,
+    reviews_failed: 0,
     states_pulled: 0,
+    per_deck: {},
     ...overrides,
   };
 }
@@ -16,6 +16,7 @@ import type {
 } from '@jirehs-flashcards/shared-types';
 import type {
   DeviceInfo,
+  Diagnostics,
   LocalSyncState,
   SyncStats,
   SyncStatus,
@@ -41,6 +42,7 @@ export const mockDefaults = {
     new_cards_per_day: 20,
     reviews_per_day: 200,
     daily_reset_hour: 4,
+    due_grace_hours: 0,
   } as GlobalSettings,
   deckSettings: null as DeckSettings | null,
   effectiveSettings: {
@@ -51,6 +53,8 @@ export const mockDefaults = {
     new_cards_per_day: 20,
     reviews_per_day: 200,
     daily_reset_hour: 4,
+    due_grace_hours: 0,
+    rating_buttons: ['again', 'hard', 'good', 'easy'],
   } as EffectiveSettings,
   deckStats: {
     total_cards: 0,
@@ -75,9 +79,20 @@ export const mockDefaults = {
     last_sync_at: null,
     pending_changes: 0,
   } as LocalSyncState,
+  diagnostics: {
+    registered: false,
+    device_id: null,
+    backend_reachable: true,
+    last_sync_at: null,
+    pending_reviews: 0,
+    pending_files: 0,
+    watched_directories: [],
+    schema_version: 12,
+  } as Diagnostics,
   importResult: {
     imported: 0,
     deck_path: '',
+    warnings: [],
   } as ImportResult,
   reviewResponse: {
     new_state: {
@@ -88,6 +103,7 @@ export const mockDefaults = {
       reviews_count: 1,
     },
     next_due: new Date().toISOString(),
+    requeue: false,
   } as ReviewResponse,
   compareAnswerResponse: {
     is_correct: true,
@@ -95,7 +111,10 @@ export const mockDefaults = {
     matching_mode: 'exact',
     typed_normalized: '',
     correct_normalized: '',
+    no_answer: false,
     diff: [],
+    missing_items: [],
+    suggested_rating: 'easy',
   } as CompareAnswerResponse,
 };
 
@@ -106,12 +125,23 @@ export const mockTauriCommands = {
   get_deck: vi.fn(() => Promise.resolve(mockDefaults.deck)),
   import_file: vi.fn(() => Promise.resolve(mockDefaults.importResult)),
   import_directory: vi.fn(() => Promise.resolve(mockDefaults.importResult)),
+  scan_directory: vi.fn(() =>
+    Promise.resolve({ files: 0, total_cards: 0, cards_without_ids: 0, parse_errors: [], decks: [] })
+  ),
+  reset_deck_progress: vi.fn(() => Promise.resolve({ cards_reset: 0, reviews_archived: 0 })),
 
   // Study commands
   get_study_queue: vi.fn(() => Promise.resolve(mockDefaults.studyQueue)),
   submit_review: vi.fn(() => Promise.resolve(mockDefaults.reviewResponse)),
   get_card: vi.fn(() => Promise.resolve(mockDefaults.card)),
   get_card_state: vi.fn(() => Promise.resolve(mockDefaults.cardState)),
+  set_new_priority: vi.fn(() => Promise.resolve()),
+  get_card_with_state: vi.fn(() =>
+    Promise.resolve({ card: mockDefaults.card, state: mockDefaults.cardState, retrievability: null })
+  ),
+  get_cards_by_tag: vi.fn(() => Promise.resolve([])),
+  get_difficult_cards: vi.fn(() => Promise.resolve([])),
+  get_difficult_study_queue: vi.fn(() => Promise.resolve(mockDefaults.studyQueue)),
   compare_typed_answer: vi.fn(() => Promise.resolve(mockDefaults.compareAnswerResponse)),
 
   // Settings commands
@@ -120,12 +150,35 @@ export const mockTauriCommands = {
   get_deck_settings: vi.fn(() => Promise.resolve(mockDefaults.deckSettings)),
   save_deck_settings: vi.fn(() => Promise.resolve()),
   delete_deck_settings: vi.fn(() => Promise.resolve()),
+  set_deck_archived: vi.fn(() => Promise.resolve()),
   get_effective_settings: vi.fn(() => Promise.resolve(mockDefaults.effectiveSettings)),
 
   // Stats commands
   get_deck_stats: vi.fn(() => Promise.resolve(mockDefaults.deckStats)),
   get_study_stats: vi.fn(() => Promise.resolve(mockDefaults.studyStats)),
+  refresh_stats_cache: vi.fn(() => Promise.resolve(mockDefaults.studyStats)),
   get_calendar_data: vi.fn(() => Promise.resolve(mockDefaults.calendarData)),
+  get_slowest_cards: vi.fn(() => Promise.resolve([])),
+  get_rating_distribution: vi.fn(() => Promise.resolve([0, 0, 0, 0])),
+  get_reviews: vi.fn(() => Promise.resolve({ reviews: [], has_more: false })),
+  project_workload: vi.fn(() =>
+    Promise.resolve({
+      cards: 0,
+      current_retention: 0.9,
+      new_retention: 0.9,
+      current_daily_reviews: 0,
+      projected_daily_reviews: 0,
+    })
+  ),
+  collection_stats: vi.fn(() =>
+    Promise.resolve({
+      total_cards: 0,
+      total_decks: 0,
+      total_reviews: 0,
+      database_size_bytes: 4096,
+      remote_file_count: null,
+    })
+  ),
 
   // File watcher commands
   start_watching: vi.fn(() => Promise.resolve()),
@@ -145,12 +198,14 @@ export const mockTauriCommands = {
       orphans_deleted: 0,
       reviews_synced: 0,
       states_pulled: 0,
+      per_deck: {},
     } as SyncStats)
   ),
   register_device: vi.fn(() => Promise.resolve(mockDefaults.deviceInfo)),
   get_device_status: vi.fn(() => Promise.resolve(mockDefaults.deviceInfo)),
   check_connectivity: vi.fn(() => Promise.resolve(true)),
   get_local_sync_state: vi.fn(() => Promise.resolve(mockDefaults.localSyncState)),
+  diagnostics: vi.fn(() => Promise.resolve(mockDefaults.diagnostics)),
 };
 
 // Setup invoke mock to route to correct command mock
@@ -5,9 +5,26 @@
 //! - Stability (S): Days until retention drops to target
 //! - Retrievability (R): Probability of recall
 
-use super::{SchedulingResult, SpacedRepetitionAlgorithm};
+use super::{
+    hash_params, next_learning_reps, SchedulingDebug, SchedulingPath, SchedulingResult,
+    SpacedRepetitionAlgorithm, DEFAULT_GRADUATION_REQUIREMENT, DEFAULT_REQUEST_RETENTION,
+};
 use crate::types::{CardState, CardStatus, Rating};
 use chrono::{DateTime, Duration, Utc};
+use thiserror::Error;
+
+/// Number of FSRS-4.5 weights.
+pub const FSRS_WEIGHT_COUNT: usize = 17;
+
+/// Why a set of FSRS weights was rejected by [`Fsrs::with_weights`].
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum InvalidWeights {
+    #[error("expected 17 FSRS weights, got {0}")]
+    WrongCount(usize),
+
+    #[error("FSRS weight w[{index}] is not a finite number")]
+    NotFinite { index: usize },
+}
 
 /// FSRS algorithm with configurable parameters.
 #[derive(Debug, Clone)]
@@ -15,13 +32,16 @@ pub struct Fsrs {
     pub request_retention: f64,
     pub maximum_interval: f64,
     /// FSRS-4.5 parameters (17 weights).
-    pub w: [f64; 17],
+    pub w: [f64; FSRS_WEIGHT_COUNT],
+    /// Consecutive passing ratings a New or Learning card needs to reach
+    /// Review. A scheduling policy rather than a weight, so not hashed.
+    pub graduation_requirement: u32,
 }
 
 impl Default for Fsrs {
     fn default() -> Self {
         Self {
-            request_retention: 0.9,
+            request_retention: DEFAULT_REQUEST_RETENTION,
             maximum_interval: 36500.0,
             w: [
                 0.4, 0.6, 2.4, 5.8, // w[0-3]: initial stability for Again, Hard, Good, Easy
@@ -39,6 +59,7 @@ impl Default for Fsrs {
                 0.29,  // w[15]: hard penalty
                 2.61,  // w[16]: easy bonus
             ],
+            graduation_requirement: DEFAULT_GRADUATION_REQUIREMENT,
         }
     }
 }
@@ -49,31 +70,46 @@ impl SpacedRepetitionAlgorithm for Fsrs {
     }
 
     fn initial_state(&self) -> CardState {
-        CardState {
-            status: CardStatus::New,
-            interval_days: 0.0,
-            ease_factor: 2.5,
-            stability: None,
-            difficulty: None,
-            lapses: 0,
-            reviews_count: 0,
-            due_date: None,
-        }
+        CardState::default()
     }
 
     fn schedule(&self, state: &CardState, rating: Rating, now: DateTime<Utc>) -> SchedulingResult {
+        self.schedule_with_debug(state, rating, now).0
+    }
+
+    fn schedule_with_debug(
+        &self,
+        state: &CardState,
+        rating: Rating,
+        now: DateTime<Utc>,
+    ) -> (SchedulingResult, SchedulingDebug) {
+        // Cards last scheduled by SM-2 carry no stability yet
+        let state = &state.migrate_sm2_to_fsrs();
         let rating_value = rating.to_value();
         let is_first_review = state.reviews_count == 0
             || state.stability.is_none()
             || state.difficulty.is_none();
 
-        let (new_stability, new_difficulty, new_status, new_lapses) = if is_first_review {
-            self.schedule_first_review(state.status, rating_value, state.lapses)
+        let (elapsed, retrievability) = if is_first_review {
+            (None, None)
         } else {
-            self.schedule_subsequent_review(state, rating_value, now)
+            let elapsed = Self::elapsed_days(state, now);
+            let r = self.retrievability(elapsed, state.stability.unwrap_or(1.0));
+            (Some(elapsed), Some(r))
+        };
+
+        let learning_reps = next_learning_reps(state, rating);
+        let graduates = learning_reps >= self.graduation_requirement;
+
+        let (new_stability, new_difficulty, new_status, new_lapses) = match retrievability {
+            None => self.schedule_first_review(state.status, rating_value, state.lapses, graduates),
+            Some(r) => self.schedule_subsequent_review(state, rating_value, r, graduates),
         };
 
-        let new_interval = if rating_value == 1 {
+        // Cards still learning, including passing ones held back by the
+        // graduation requirement, come back the same day
+        let short_term = new_status != CardStatus::Review;
+        let new_interval = if short_term {
             self.short_term_interval(new_stability)
         } else {
             self.interval_from_stability(new_stability)
@@ -81,7 +117,26 @@ impl SpacedRepetitionAlgorithm for Fsrs {
 
         let next_due = now + Duration::seconds((new_interval * 86400.0) as i64);
 
-        SchedulingResult {
+        let debug = SchedulingDebug {
+            algorithm: self.name(),
+            path: match (is_first_review, rating_value == 1) {
+                (true, _) => SchedulingPath::FirstReview,
+                (false, true) => SchedulingPath::Lapse,
+                (false, false) => SchedulingPath::Recall,
+            },
+            elapsed_days: elapsed,
+            retrievability,
+            stability_before: state.stability,
+            stability_after: Some(new_stability),
+            difficulty_before: state.difficulty,
+            difficulty_after: Some(new_difficulty),
+            ease_before: state.ease_factor,
+            ease_after: state.ease_factor,
+            interval_days: new_interval,
+            short_term,
+        };
+
+        let result = SchedulingResult {
             new_state: CardState {
                 status: new_status,
                 interval_days: new_interval,
@@ -91,13 +146,50 @@ impl SpacedRepetitionAlgorithm for Fsrs {
                 lapses: new_lapses,
                 reviews_count: state.reviews_count + 1,
                 due_date: Some(next_due),
+                learning_reps: if new_status == CardStatus::Review {
+                    0
+                } else {
+                    learning_reps
+                },
             },
             next_due,
-        }
+            requeue: false,
+        };
+        (result, debug)
+    }
+
+    fn params_hash(&self) -> String {
+        let mut params = vec![self.request_retention, self.maximum_interval];
+        params.extend_from_slice(&self.w);
+        hash_params(self.name(), &params)
     }
 }
 
 impl Fsrs {
+    /// FSRS with custom weights and default retention and limits.
+    ///
+    /// Malformed weights would schedule every card at a NaN interval and
+    /// corrupt its due date, so they are rejected; callers should fall back
+    /// to [`Fsrs::default`] and report the error.
+    pub fn with_weights(weights: &[f64]) -> Result<Self, InvalidWeights> {
+        let w: [f64; FSRS_WEIGHT_COUNT] = weights
+            .try_into()
+            .map_err(|_| InvalidWeights::WrongCount(weights.len()))?;
+        if let Some(index) = w.iter().position(|weight| !weight.is_finite()) {
+            return Err(InvalidWeights::NotFinite { index });
+        }
+        Ok(Self { w, ..Self::default() })
+    }
+
+    /// Probability of recalling a card right now.
+    ///
+    /// `None` for cards that have never been reviewed or carry no FSRS
+    /// stability (for example, cards scheduled by SM-2).
+    pub fn current_retrievability(&self, state: &CardState, now: DateTime<Utc>) -> Option<f64> {
+        let stability = state.stability.filter(|_| state.reviews_count > 0)?;
+        Some(self.retrievability(Self::elapsed_days(state, now), stability))
+    }
+
     /// Calculate initial stability for a new card based on first rating.
     /// S0(G) = w[G-1] where G is rating 1-4
     fn initial_stability(&self, rating: u8) -> f64 {
@@ -177,7 +269,7 @@ impl Fsrs {
 
     /// Calculate optimal interval from stability.
     /// I = 9 * S * (1/R - 1) where R = request_retention
-    fn interval_from_stability(&self, stability: f64) -> f64 {
+    pub fn interval_from_stability(&self, stability: f64) -> f64 {
         if self.request_retention <= 0.0 || self.request_retention >= 1.0 {
             return stability;
         }
@@ -206,13 +298,16 @@ impl Fsrs {
         }
     }
 
-    /// Determine new status based on current status and rating.
-    fn determine_status(current: CardStatus, rating: u8) -> CardStatus {
+    /// Determine new status based on current status and rating. A passing
+    /// rating only moves a New or Learning card to Review if it `graduates`.
+    fn determine_status(current: CardStatus, rating: u8, graduates: bool) -> CardStatus {
         match (current, rating) {
             (CardStatus::New, 1) => CardStatus::Learning,
-            (CardStatus::New, _) => CardStatus::Review,
+            (CardStatus::New, _) if graduates => CardStatus::Review,
+            (CardStatus::New, _) => CardStatus::Learning,
             (CardStatus::Learning, 1) => CardStatus::Learning,
-            (CardStatus::Learning, _) => CardStatus::Review,
+            (CardStatus::Learning, _) if graduates => CardStatus::Review,
+            (CardStatus::Learning, _) => CardStatus::Learning,
             (CardStatus::Review, 1) => CardStatus::Relearning,
             (CardStatus::Review, _) => CardStatus::Review,
             (CardStatus::Relearning, 1) => CardStatus::Relearning,
@@ -226,26 +321,26 @@ impl Fsrs {
         current_status: CardStatus,
         rating: u8,
         current_lapses: u32,
+        graduates: bool,
     ) -> (f64, f64, CardStatus, u32) {
         let stability = self.initial_stability(rating);
         let difficulty = self.initial_difficulty(rating);
-        let status = Self::determine_status(current_status, rating);
+        let status = Self::determine_status(current_status, rating, graduates);
         (stability, difficulty, status, current_lapses)
     }
 
     /// Schedule subsequent review - update stability and difficulty.
+    /// `r` is the card's retrievability at review time.
     fn schedule_subsequent_review(
         &self,
         state: &CardState,
         rating: u8,
-        now: DateTime<Utc>,
+        r: f64,
+        graduates: bool,
     ) -> (f64, f64, CardStatus, u32) {
         let current_s = state.stability.unwrap_or(1.0);
         let current_d = state.difficulty.unwrap_or(5.0);
 
-        let elapsed = Self::elapsed_days(state, now);
-        let r = self.retrievability(elapsed, current_s);
-
         // Update difficulty
         let new_d = self.next_difficulty(current_d, rating);
 
@@ -260,7 +355,7 @@ impl Fsrs {
             (s, state.lapses)
         };
 
-        let status = Self::determine_status(state.status, rating);
+        let status = Self::determine_status(state.status, rating, graduates);
         (new_s, new_d, status, lapses)
     }
 }
@@ -273,6 +368,41 @@ mod tests {
         Utc::now()
     }
 
+    #[test]
+    fn with_weights_rejects_malformed_weights() {
+        let defaults = Fsrs::default().w;
+        let custom = Fsrs::with_weights(&defaults).unwrap();
+        assert_eq!(custom.w, defaults);
+
+        assert_eq!(
+            Fsrs::with_weights(&defaults[..16]).unwrap_err(),
+            InvalidWeights::WrongCount(16)
+        );
+        let mut nan = defaults;
+        nan[8] = f64::NAN;
+        assert_eq!(
+            Fsrs::with_weights(&nan).unwrap_err(),
+            InvalidWeights::NotFinite { index: 8 }
+        );
+        let mut infinite = defaults;
+        infinite[0] = f64::INFINITY;
+        assert!(Fsrs::with_weights(&infinite).is_err());
+    }
+
+    #[test]
+    fn fallback_for_invalid_weights_schedules_normally() {
+        let mut nan = Fsrs::default().w;
+        nan[10] = f64::NAN;
+        let fsrs = Fsrs::with_weights(&nan).unwrap_or_default();
+
+        let first = fsrs.schedule(&fsrs.initial_state(), Rating::Good, now());
+        let second = fsrs.schedule(&first.new_state, Rating::Good, first.next_due);
+        assert!(second.new_state.stability.unwrap().is_finite());
+        assert!(second.new_state.interval_days.is_finite());
+        assert!(second.new_state.interval_days >= 1.0);
+        assert!(second.next_due > first.next_due);
+    }
+
     #[test]
     fn new_card_first_review_good() {
         let fsrs = Fsrs::default();
@@ -321,6 +451,7 @@ mod tests {
             lapses: 0,
             reviews_count: 5,
             due_date: Some(current_time),
+            learning_reps: 0,
         };
 
         let result = fsrs.schedule(&state, Rating::Good, current_time);
@@ -340,6 +471,7 @@ mod tests {
             lapses: 0,
             reviews_count: 5,
             due_date: Some(current_time),
+            learning_reps: 0,
         };
 
         let result = fsrs.schedule(&state, Rating::Again, current_time);
@@ -441,6 +573,21 @@ mod tests {
         assert!((r - 0.5).abs() < 0.001);
     }
 
+    #[test]
+    fn current_retrievability_decays_after_review() {
+        let fsrs = Fsrs::default();
+        let reviewed = now();
+        assert_eq!(fsrs.current_retrievability(&fsrs.initial_state(), reviewed), None);
+
+        let state = fsrs.schedule(&fsrs.initial_state(), Rating::Good, reviewed).new_state;
+        let fresh = fsrs.current_retrievability(&state, reviewed).unwrap();
+        let later = fsrs
+            .current_retrievability(&state, reviewed + Duration::days(30))
+            .unwrap();
+        assert!((fresh - 1.0).abs() < 0.001);
+        assert!(later < fresh);
+    }
+
     #[test]
     fn learning_card_graduates_on_good() {
         let fsrs = Fsrs::default();
@@ -550,4 +697,67 @@ mod tests {
         assert!(d_hard > d_good);
         assert!(d_good > d_easy);
     }
+
+    #[test]
+    fn debug_matches_committed_state() {
+        let fsrs = Fsrs::default();
+        let current_time = now();
+        let state = CardState {
+            status: CardStatus::Review,
+            interval_days: 5.0,
+            ease_factor: 2.5,
+            stability: Some(5.0),
+            difficulty: Some(5.0),
+            lapses: 0,
+            reviews_count: 5,
+            due_date: Some(current_time),
+            learning_reps: 0,
+        };
+
+        for rating in [Rating::Again, Rating::Good] {
+            let (result, debug) = fsrs.schedule_with_debug(&state, rating, current_time);
+            let plain = fsrs.schedule(&state, rating, current_time);
+            assert_eq!(result.new_state.stability, plain.new_state.stability);
+            assert_eq!(result.next_due, plain.next_due);
+            assert_eq!(debug.stability_before, state.stability);
+            assert_eq!(debug.stability_after, result.new_state.stability);
+            assert_eq!(debug.difficulty_after, result.new_state.difficulty);
+            assert_eq!(debug.interval_days, result.new_state.interval_days);
+
+            // Reviewed exactly when due, after the full 5-day interval
+            let elapsed = debug.elapsed_days.unwrap();
+            assert!((elapsed - 5.0).abs() < 1e-6);
+            let expected_r = fsrs.retrievability(elapsed, 5.0);
+            assert_eq!(debug.retrievability, Some(expected_r));
+        }
+
+        let (_, lapse) = fsrs.schedule_with_debug(&state, Rating::Again, current_time);
+        assert_eq!(lapse.path, SchedulingPath::Lapse);
+        assert!(lapse.short_term);
+
+        let (_, first) = fsrs.schedule_with_debug(&fsrs.initial_state(), Rating::Good, now());
+        assert_eq!(first.path, SchedulingPath::FirstReview);
+        assert_eq!(first.retrievability, None);
+        assert_eq!(first.stability_after, Some(fsrs.initial_stability(3)));
+    }
+
+    #[test]
+    fn mature_sm2_card_keeps_its_interval_under_fsrs() {
+        let current_time = now();
+        let sm2_state = CardState {
+            status: CardStatus::Review,
+            interval_days: 60.0,
+            ease_factor: 2.5,
+            reviews_count: 8,
+            due_date: Some(current_time),
+            ..Default::default()
+        };
+
+        let (result, debug) =
+            Fsrs::default().schedule_with_debug(&sm2_state, Rating::Good, current_time);
+        assert_eq!(debug.path, SchedulingPath::Recall);
+        assert_eq!(result.new_state.status, CardStatus::Review);
+        assert!(result.new_state.interval_days > 60.0);
+        assert_eq!(result.new_state.reviews_count, 9);
+    }
 }
@@ -3,14 +3,138 @@
 pub mod fsrs;
 pub mod sm2;
 
-use crate::types::{CardState, Rating};
-use chrono::{DateTime, Utc};
+use crate::hash::fnv1a;
+use crate::types::{Algorithm, CardState, CardStatus, Rating};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// Default delay before a card rated Again while learning comes back.
+pub const DEFAULT_AGAIN_DELAY_MINUTES: u32 = 1;
+
+/// Default number of consecutive passing ratings a learning card needs to
+/// graduate: the first one.
+pub const DEFAULT_GRADUATION_REQUIREMENT: u32 = 1;
+
+/// Default FSRS target probability of recall when a card comes due.
+pub const DEFAULT_REQUEST_RETENTION: f64 = 0.9;
+
+/// Default lowest SM-2 ease: the classic SuperMemo floor.
+pub const DEFAULT_MIN_EASE: f64 = 1.3;
+
+/// Default highest SM-2 ease, high enough that only long runs of Easy
+/// ratings reach it.
+pub const DEFAULT_MAX_EASE: f64 = 5.0;
+
+/// Learning reps a card will have after `rating`: Again starts over, any
+/// other rating adds one. Only New and Learning cards count reps.
+pub(crate) fn next_learning_reps(state: &CardState, rating: Rating) -> u32 {
+    match state.status {
+        CardStatus::New | CardStatus::Learning if rating != Rating::Again => {
+            state.learning_reps + 1
+        }
+        _ => 0,
+    }
+}
 
 /// Result of scheduling a card after review.
 #[derive(Debug, Clone)]
 pub struct SchedulingResult {
     pub new_state: CardState,
     pub next_due: DateTime<Utc>,
+    /// The card should be shown again later in the current session.
+    pub requeue: bool,
+}
+
+impl SchedulingResult {
+    /// Bring a learning or relearning card rated Again back after
+    /// `again_delay_minutes` instead of on a later day.
+    ///
+    /// Only the due time changes; the interval computed by the algorithm is
+    /// kept so graduation works as before.
+    pub fn with_again_delay(
+        mut self,
+        rating: Rating,
+        now: DateTime<Utc>,
+        again_delay_minutes: u32,
+    ) -> Self {
+        let learning = matches!(
+            self.new_state.status,
+            CardStatus::Learning | CardStatus::Relearning
+        );
+        if rating == Rating::Again && learning {
+            let due = now + Duration::minutes(again_delay_minutes as i64);
+            self.new_state.due_date = Some(due);
+            self.next_due = due;
+            self.requeue = true;
+        }
+        self
+    }
+}
+
+/// Which branch of the algorithm produced a new state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulingPath {
+    /// No prior memory state: initial values for the rating were used.
+    FirstReview,
+    /// The card was remembered.
+    Recall,
+    /// The card was forgotten (rated Again).
+    Lapse,
+}
+
+/// Intermediate values behind a scheduling decision, for showing users why
+/// a card got its interval. Values an algorithm doesn't track are `None`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SchedulingDebug {
+    pub algorithm: &'static str,
+    pub path: SchedulingPath,
+    /// Days since the previous review, as the algorithm estimated them.
+    pub elapsed_days: Option<f64>,
+    /// Probability of recall at review time.
+    pub retrievability: Option<f64>,
+    pub stability_before: Option<f64>,
+    pub stability_after: Option<f64>,
+    pub difficulty_before: Option<f64>,
+    pub difficulty_after: Option<f64>,
+    pub ease_before: f64,
+    pub ease_after: f64,
+    pub interval_days: f64,
+    /// The interval is a same-day learning step rather than derived from
+    /// stability.
+    pub short_term: bool,
+}
+
+impl SchedulingDebug {
+    /// Debug info derived only from the states before and after a review.
+    pub fn from_states(
+        algorithm: &'static str,
+        before: &CardState,
+        rating: Rating,
+        after: &CardState,
+    ) -> Self {
+        let path = if before.reviews_count == 0 {
+            SchedulingPath::FirstReview
+        } else if rating == Rating::Again {
+            SchedulingPath::Lapse
+        } else {
+            SchedulingPath::Recall
+        };
+        Self {
+            algorithm,
+            path,
+            elapsed_days: None,
+            retrievability: None,
+            stability_before: before.stability,
+            stability_after: after.stability,
+            difficulty_before: before.difficulty,
+            difficulty_after: after.difficulty,
+            ease_before: before.ease_factor,
+            ease_after: after.ease_factor,
+            interval_days: after.interval_days,
+            short_term: false,
+        }
+    }
 }
 
 /// Trait for spaced repetition algorithms.
@@ -21,8 +145,35 @@ pub trait SpacedRepetitionAlgorithm: Send + Sync {
     /// Calculate next review state after a review.
     fn schedule(&self, state: &CardState, rating: Rating, now: DateTime<Utc>) -> SchedulingResult;
 
-    /// Initial state for a new card.
+    /// [`schedule`](Self::schedule), also reporting the intermediate values
+    /// behind the result. The result is identical to `schedule`'s.
+    fn schedule_with_debug(
+        &self,
+        state: &CardState,
+        rating: Rating,
+        now: DateTime<Utc>,
+    ) -> (SchedulingResult, SchedulingDebug) {
+        let result = self.schedule(state, rating, now);
+        let debug = SchedulingDebug::from_states(self.name(), state, rating, &result.new_state);
+        (result, debug)
+    }
+
+    /// Initial state for a new card: [`CardStatus::New`] with zeroed
+    /// counters and no due date. Scheduling it must not depend on elapsed
+    /// time, since the card has never been reviewed.
     fn initial_state(&self) -> CardState;
+
+    /// Fingerprint of the algorithm and its parameters, recorded with each
+    /// review so reviews can be grouped by the weights that scheduled them.
+    fn params_hash(&self) -> String;
+}
+
+/// Hash an algorithm name and its parameters into a short hex fingerprint.
+pub(crate) fn hash_params(name: &str, params: &[f64]) -> String {
+    let bytes = name
+        .bytes()
+        .chain(params.iter().flat_map(|p| p.to_le_bytes()));
+    format!("{:016x}", fnv1a(bytes))
 }
 
 /// Get algorithm by name.
@@ -33,3 +184,310 @@ pub fn get_algorithm(name: &str) -> Option<Box<dyn SpacedRepetitionAlgorithm>> {
         _ => None,
     }
 }
+
+/// Statically dispatched algorithm.
+///
+/// Build once (e.g. per deck) and reuse when folding many reviews; unlike
+/// [`get_algorithm`] it neither allocates nor goes through a vtable.
+#[derive(Debug, Clone)]
+pub enum AlgorithmKind {
+    Sm2(sm2::Sm2),
+    Fsrs(fsrs::Fsrs),
+}
+
+impl AlgorithmKind {
+    /// Get algorithm by name with default parameters.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Algorithm::from_str(name).map(Self::from)
+    }
+
+    /// Require `requirement` consecutive passing ratings before a learning
+    /// card graduates. Zero is treated as one.
+    pub fn with_graduation_requirement(mut self, requirement: u32) -> Self {
+        match &mut self {
+            Self::Sm2(a) => a.graduation_requirement = requirement,
+            Self::Fsrs(a) => a.graduation_requirement = requirement,
+        }
+        self
+    }
+
+    /// Schedule FSRS cards to come due at `retention` probability of
+    /// recall. SM-2 has no retention target, so it is unaffected.
+    pub fn with_request_retention(mut self, retention: f64) -> Self {
+        if let Self::Fsrs(a) = &mut self {
+            a.request_retention = retention;
+        }
+        self
+    }
+
+    /// Keep SM-2 ease between `min` and `max`. FSRS has no ease, so it is
+    /// unaffected.
+    pub fn with_ease_bounds(mut self, min: f64, max: f64) -> Self {
+        if let Self::Sm2(a) = &mut self {
+            a.minimum_ease = min;
+            a.maximum_ease = max;
+        }
+        self
+    }
+}
+
+impl From<Algorithm> for AlgorithmKind {
+    fn from(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Sm2 => Self::Sm2(sm2::Sm2::default()),
+            Algorithm::Fsrs => Self::Fsrs(fsrs::Fsrs::default()),
+        }
+    }
+}
+
+impl SpacedRepetitionAlgorithm for AlgorithmKind {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Sm2(a) => a.name(),
+            Self::Fsrs(a) => a.name(),
+        }
+    }
+
+    fn schedule(&self, state: &CardState, rating: Rating, now: DateTime<Utc>) -> SchedulingResult {
+        match self {
+            Self::Sm2(a) => a.schedule(state, rating, now),
+            Self::Fsrs(a) => a.schedule(state, rating, now),
+        }
+    }
+
+    fn schedule_with_debug(
+        &self,
+        state: &CardState,
+        rating: Rating,
+        now: DateTime<Utc>,
+    ) -> (SchedulingResult, SchedulingDebug) {
+        match self {
+            Self::Sm2(a) => a.schedule_with_debug(state, rating, now),
+            Self::Fsrs(a) => a.schedule_with_debug(state, rating, now),
+        }
+    }
+
+    fn initial_state(&self) -> CardState {
+        match self {
+            Self::Sm2(a) => a.initial_state(),
+            Self::Fsrs(a) => a.initial_state(),
+        }
+    }
+
+    fn params_hash(&self) -> String {
+        match self {
+            Self::Sm2(a) => a.params_hash(),
+            Self::Fsrs(a) => a.params_hash(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn learning_state() -> CardState {
+        CardState {
+            status: CardStatus::Learning,
+            reviews_count: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn again_on_learning_card_is_due_within_session() {
+        let now = Utc::now();
+        for name in ["sm2", "fsrs"] {
+            let algorithm = get_algorithm(name).unwrap();
+            let result = algorithm
+                .schedule(&learning_state(), Rating::Again, now)
+                .with_again_delay(Rating::Again, now, 1);
+
+            assert!(result.requeue, "{name}");
+            assert_eq!(result.next_due, now + Duration::minutes(1), "{name}");
+            assert_eq!(result.new_state.due_date, Some(result.next_due), "{name}");
+            assert!(result.next_due < now + Duration::days(1), "{name}");
+        }
+    }
+
+    #[test]
+    fn again_on_lapsed_review_card_is_requeued() {
+        let now = Utc::now();
+        let state = CardState {
+            status: CardStatus::Review,
+            interval_days: 10.0,
+            reviews_count: 5,
+            ..Default::default()
+        };
+        let result = sm2::Sm2::default()
+            .schedule(&state, Rating::Again, now)
+            .with_again_delay(Rating::Again, now, 10);
+
+        assert_eq!(result.new_state.status, CardStatus::Relearning);
+        assert!(result.requeue);
+        assert_eq!(result.next_due, now + Duration::minutes(10));
+    }
+
+    #[test]
+    fn again_delay_ignored_for_passing_ratings() {
+        let now = Utc::now();
+        let sm2 = sm2::Sm2::default();
+        let result = sm2
+            .schedule(&sm2.initial_state(), Rating::Good, now)
+            .with_again_delay(Rating::Good, now, 1);
+
+        assert!(!result.requeue);
+        assert!(result.next_due >= now + Duration::days(1));
+    }
+
+    #[test]
+    fn first_review_of_initial_state_is_sane() {
+        let now = Utc::now();
+        let later = now + Duration::days(365);
+        for algorithm in [Algorithm::Sm2, Algorithm::Fsrs].map(AlgorithmKind::from) {
+            let name = algorithm.name();
+            let initial = algorithm.initial_state();
+            assert_eq!(initial.status, CardStatus::New, "{name}");
+            assert_eq!((initial.reviews_count, initial.lapses), (0, 0), "{name}");
+            assert_eq!(initial.interval_days, 0.0, "{name}");
+            assert!(initial.due_date.is_none(), "{name}");
+
+            for rating in [Rating::Again, Rating::Hard, Rating::Good, Rating::Easy] {
+                let result = algorithm.schedule(&initial, rating, now);
+                let interval = result.new_state.interval_days;
+                assert!(interval.is_finite() && interval >= 0.0, "{name} {rating:?}");
+                assert!(result.next_due >= now, "{name} {rating:?}");
+                assert_eq!(result.new_state.reviews_count, 1, "{name} {rating:?}");
+                if rating.to_value() >= 3 {
+                    assert!(interval > 0.0, "{name} {rating:?}");
+                }
+
+                // Never reviewed, so when the review happens doesn't matter
+                let delayed = algorithm.schedule(&initial, rating, later);
+                assert_eq!(delayed.new_state.interval_days, interval, "{name} {rating:?}");
+                assert_eq!(delayed.next_due - later, result.next_due - now, "{name} {rating:?}");
+            }
+        }
+    }
+
+    /// Fold `count` reviews with a cycling rating pattern.
+    fn fold_reviews<A: SpacedRepetitionAlgorithm + ?Sized>(
+        algorithm: &A,
+        count: usize,
+    ) -> CardState {
+        const RATINGS: [Rating; 4] = [Rating::Good, Rating::Again, Rating::Hard, Rating::Easy];
+
+        let start = (algorithm.initial_state(), Utc::now());
+        let (state, _) = (0..count).fold(start, |(state, now), i| {
+            let result = algorithm.schedule(&state, RATINGS[i % RATINGS.len()], now);
+            (result.new_state, result.next_due)
+        });
+        state
+    }
+
+    #[test]
+    fn algorithm_kind_matches_dynamic_dispatch() {
+        for name in ["sm2", "fsrs"] {
+            let kind = AlgorithmKind::from_name(name).unwrap();
+            let dynamic = get_algorithm(name).unwrap();
+            assert_eq!(kind.name(), dynamic.name());
+
+            let folded = fold_reviews(&kind, 10_000);
+            let expected = fold_reviews(dynamic.as_ref(), 10_000);
+
+            assert_eq!(folded.reviews_count, 10_000, "{name}");
+            assert_eq!(folded.status, expected.status, "{name}");
+            assert_eq!(folded.interval_days, expected.interval_days, "{name}");
+            assert_eq!(folded.ease_factor, expected.ease_factor, "{name}");
+            assert_eq!(folded.stability, expected.stability, "{name}");
+        }
+    }
+
+    #[test]
+    fn graduation_requirement_holds_back_learning_cards() {
+        let now = Utc::now();
+        for algorithm in [Algorithm::Sm2, Algorithm::Fsrs].map(AlgorithmKind::from) {
+            let name = algorithm.name();
+            let algorithm = algorithm.with_graduation_requirement(2);
+
+            let first = algorithm.schedule(&algorithm.initial_state(), Rating::Good, now);
+            assert_eq!(first.new_state.status, CardStatus::Learning, "{name}");
+            assert_eq!(first.new_state.learning_reps, 1, "{name}");
+            assert!(first.new_state.interval_days < 1.0, "{name}");
+
+            let second = algorithm.schedule(&first.new_state, Rating::Good, now);
+            assert_eq!(second.new_state.status, CardStatus::Review, "{name}");
+            assert_eq!(second.new_state.learning_reps, 0, "{name}");
+
+            // Again starts the count over
+            let again = algorithm.schedule(&first.new_state, Rating::Again, now);
+            assert_eq!(again.new_state.learning_reps, 0, "{name}");
+            let retry = algorithm.schedule(&again.new_state, Rating::Good, now);
+            assert_eq!(retry.new_state.status, CardStatus::Learning, "{name}");
+        }
+    }
+
+    #[test]
+    fn algorithm_kind_from_unknown_name() {
+        assert!(AlgorithmKind::from_name("anki").is_none());
+    }
+
+    #[test]
+    fn lower_request_retention_lengthens_fsrs_intervals() {
+        let now = Utc::now();
+        let state = CardState {
+            status: CardStatus::Review,
+            interval_days: 10.0,
+            stability: Some(10.0),
+            difficulty: Some(5.0),
+            reviews_count: 3,
+            due_date: Some(now),
+            ..Default::default()
+        };
+        let interval = |kind: AlgorithmKind| {
+            kind.schedule(&state, Rating::Good, now).new_state.interval_days
+        };
+
+        let fsrs = AlgorithmKind::from(Algorithm::Fsrs);
+        assert!(interval(fsrs.clone().with_request_retention(0.8)) > interval(fsrs.clone()));
+        assert_ne!(
+            fsrs.clone().with_request_retention(0.8).params_hash(),
+            fsrs.params_hash()
+        );
+        let sm2 = AlgorithmKind::from(Algorithm::Sm2);
+        assert_eq!(interval(sm2.clone().with_request_retention(0.8)), interval(sm2));
+    }
+
+    #[test]
+    fn params_hash_changes_with_weights() {
+        let default = fsrs::Fsrs::default();
+        let mut tuned = fsrs::Fsrs::default();
+        tuned.w[4] = 5.1;
+
+        assert_eq!(default.params_hash(), fsrs::Fsrs::default().params_hash());
+        assert_ne!(default.params_hash(), tuned.params_hash());
+        assert_ne!(default.params_hash(), sm2::Sm2::default().params_hash());
+        assert_eq!(
+            AlgorithmKind::Fsrs(tuned.clone()).params_hash(),
+            tuned.params_hash()
+        );
+        assert_eq!(default.params_hash().len(), 16);
+    }
+
+    #[test]
+    fn default_debug_reflects_states() {
+        let now = Utc::now();
+        let kind = AlgorithmKind::from_name("sm2").unwrap();
+        let state = kind
+            .schedule(&kind.initial_state(), Rating::Good, now)
+            .new_state;
+
+        let (result, debug) = kind.schedule_with_debug(&state, Rating::Hard, now);
+        assert_eq!(debug.algorithm, "sm2");
+        assert_eq!(debug.path, SchedulingPath::Recall);
+        assert_eq!(debug.ease_before, state.ease_factor);
+        assert_eq!(debug.ease_after, result.new_state.ease_factor);
+        assert_eq!(debug.interval_days, result.new_state.interval_days);
+        assert_eq!(debug.retrievability, None);
+    }
+}
@@ -2,7 +2,10 @@
 //!
 //! Based on SuperMemo 2 with configurable parameters.
 
-use super::{SchedulingResult, SpacedRepetitionAlgorithm};
+use super::{
+    hash_params, next_learning_reps, SchedulingResult, SpacedRepetitionAlgorithm,
+    DEFAULT_GRADUATION_REQUIREMENT, DEFAULT_MAX_EASE, DEFAULT_MIN_EASE,
+};
 use crate::types::{CardState, CardStatus, Rating};
 use chrono::{DateTime, Duration, Utc};
 
@@ -11,21 +14,28 @@ use chrono::{DateTime, Duration, Utc};
 pub struct Sm2 {
     pub initial_ease: f64,
     pub minimum_ease: f64,
+    /// Ease never grows past this, however many Easy ratings a card gets.
+    pub maximum_ease: f64,
     pub easy_bonus: f64,
     pub hard_multiplier: f64,
     pub graduating_interval: f64,
     pub easy_interval: f64,
+    /// Consecutive passing ratings a New or Learning card needs to reach
+    /// Review. A scheduling policy rather than a weight, so not hashed.
+    pub graduation_requirement: u32,
 }
 
 impl Default for Sm2 {
     fn default() -> Self {
         Self {
             initial_ease: 2.5,
-            minimum_ease: 1.3,
+            minimum_ease: DEFAULT_MIN_EASE,
+            maximum_ease: DEFAULT_MAX_EASE,
             easy_bonus: 1.3,
             hard_multiplier: 1.2,
             graduating_interval: 1.0,
             easy_interval: 4.0,
+            graduation_requirement: DEFAULT_GRADUATION_REQUIREMENT,
         }
     }
 }
@@ -37,23 +47,23 @@ impl SpacedRepetitionAlgorithm for Sm2 {
 
     fn initial_state(&self) -> CardState {
         CardState {
-            status: CardStatus::New,
-            interval_days: 0.0,
             ease_factor: self.initial_ease,
-            stability: None,
-            difficulty: None,
-            lapses: 0,
-            reviews_count: 0,
-            due_date: None,
+            ..CardState::default()
         }
     }
 
     fn schedule(&self, state: &CardState, rating: Rating, now: DateTime<Utc>) -> SchedulingResult {
         let rating_value = rating.to_value();
+        let learning_reps = next_learning_reps(state, rating);
+        let graduates = learning_reps >= self.graduation_requirement;
 
         let (new_status, new_interval, new_ease, new_lapses) = match state.status {
-            CardStatus::New | CardStatus::Learning => self.schedule_learning(state, rating_value),
-            CardStatus::Review | CardStatus::Relearning => self.schedule_review(state, rating_value),
+            CardStatus::New | CardStatus::Learning => {
+                self.schedule_learning(state, rating_value, graduates)
+            }
+            CardStatus::Review | CardStatus::Relearning => {
+                self.schedule_review(state, rating_value, Self::earliness_factor(state, now))
+            }
         };
 
         let next_due = now + Duration::days(new_interval.ceil() as i64);
@@ -68,33 +78,87 @@ impl SpacedRepetitionAlgorithm for Sm2 {
                 lapses: new_lapses,
                 reviews_count: state.reviews_count + 1,
                 due_date: Some(next_due),
+                learning_reps: if new_status == CardStatus::Review {
+                    0
+                } else {
+                    learning_reps
+                },
             },
             next_due,
+            requeue: false,
         }
     }
+
+    fn params_hash(&self) -> String {
+        hash_params(
+            self.name(),
+            &[
+                self.initial_ease,
+                self.minimum_ease,
+                self.maximum_ease,
+                self.easy_bonus,
+                self.hard_multiplier,
+                self.graduating_interval,
+                self.easy_interval,
+            ],
+        )
+    }
 }
 
 impl Sm2 {
-    fn schedule_learning(&self, state: &CardState, rating: u8) -> (CardStatus, f64, f64, u32) {
-        if rating >= 3 {
+    fn schedule_learning(
+        &self,
+        state: &CardState,
+        rating: u8,
+        graduates: bool,
+    ) -> (CardStatus, f64, f64, u32) {
+        if rating >= 3 && graduates {
             let interval = if rating == 4 {
                 self.easy_interval
             } else {
                 self.graduating_interval
             };
-            (CardStatus::Review, interval, state.ease_factor, state.lapses)
+            (CardStatus::Review, interval, self.bound_ease(state.ease_factor), state.lapses)
         } else {
-            (CardStatus::Learning, 0.0, state.ease_factor, state.lapses)
+            (CardStatus::Learning, 0.0, self.bound_ease(state.ease_factor), state.lapses)
         }
     }
 
-    fn schedule_review(&self, state: &CardState, rating: u8) -> (CardStatus, f64, f64, u32) {
+    /// Clamp an ease to the configured floor and cap. The floor wins if the
+    /// bounds are crossed.
+    fn bound_ease(&self, ease: f64) -> f64 {
+        ease.min(self.maximum_ease).max(self.minimum_ease)
+    }
+
+    /// Share of the scheduled interval that has passed at `now`, from 0.0
+    /// (reviewed right after the last review) to 1.0 (on time or late).
+    ///
+    /// Interval growth on a pass is scaled by this, so a card crammed days
+    /// before it is due barely grows instead of being multiplied by the full
+    /// ease. The last review is taken as the due date minus the interval;
+    /// cards without a due date count as on time.
+    pub fn earliness_factor(state: &CardState, now: DateTime<Utc>) -> f64 {
+        match state.due_date {
+            Some(due) if state.interval_days > 0.0 => {
+                let early_days = due.signed_duration_since(now).num_seconds() as f64 / 86400.0;
+                ((state.interval_days - early_days) / state.interval_days).clamp(0.0, 1.0)
+            }
+            _ => 1.0,
+        }
+    }
+
+    fn schedule_review(
+        &self,
+        state: &CardState,
+        rating: u8,
+        earliness: f64,
+    ) -> (CardStatus, f64, f64, u32) {
         if rating == 1 {
             // Lapse: reset to relearning
             (
                 CardStatus::Relearning,
                 1.0,
-                (state.ease_factor - 0.2).max(self.minimum_ease),
+                self.bound_ease(state.ease_factor - 0.2),
                 state.lapses + 1,
             )
         } else {
@@ -108,8 +172,10 @@ impl Sm2 {
                 4 => state.ease_factor * self.easy_bonus,
                 _ => state.ease_factor,
             };
-            let new_interval = (state.interval_days * multiplier).max(1.0);
-            let new_ease = (state.ease_factor + ease_adj).max(self.minimum_ease);
+            // Only the elapsed share of the interval earns growth
+            let growth = 1.0 + (multiplier - 1.0) * earliness;
+            let new_interval = (state.interval_days * growth).max(1.0);
+            let new_ease = self.bound_ease(state.ease_factor + ease_adj);
             (CardStatus::Review, new_interval, new_ease, state.lapses)
         }
     }
@@ -168,4 +234,78 @@ mod tests {
         let result = sm2.schedule(&state, Rating::Again, now());
         assert!(result.new_state.ease_factor >= sm2.minimum_ease);
     }
+
+    #[test]
+    fn repeated_ratings_keep_ease_within_configured_bounds() {
+        let sm2 = Sm2 {
+            minimum_ease: 1.5,
+            maximum_ease: 3.0,
+            ..Sm2::default()
+        };
+        let mut state = CardState {
+            status: CardStatus::Review,
+            interval_days: 10.0,
+            ..Default::default()
+        };
+
+        let mut lapsed = state.clone();
+        for _ in 0..8 {
+            lapsed = sm2.schedule(&lapsed, Rating::Again, now()).new_state;
+            assert!(lapsed.ease_factor >= 1.5);
+        }
+        assert_eq!(lapsed.ease_factor, 1.5);
+
+        for _ in 0..8 {
+            state = sm2.schedule(&state, Rating::Easy, now()).new_state;
+            assert!(state.ease_factor <= 3.0);
+        }
+        assert_eq!(state.ease_factor, 3.0);
+    }
+
+    fn review_state_due_at(due: DateTime<Utc>) -> CardState {
+        CardState {
+            status: CardStatus::Review,
+            interval_days: 10.0,
+            ease_factor: 2.5,
+            reviews_count: 5,
+            due_date: Some(due),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn on_time_good_grows_more_than_early_good() {
+        let sm2 = Sm2::default();
+        let now = now();
+
+        let on_time = sm2.schedule(&review_state_due_at(now), Rating::Good, now);
+        let early = sm2.schedule(
+            &review_state_due_at(now + Duration::days(8)),
+            Rating::Good,
+            now,
+        );
+
+        assert_eq!(on_time.new_state.interval_days, 25.0);
+        assert!(early.new_state.interval_days < on_time.new_state.interval_days);
+        // Two of ten days elapsed: a fifth of the 1.5x growth
+        assert!((early.new_state.interval_days - 13.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn early_review_never_shrinks_interval() {
+        let sm2 = Sm2::default();
+        let now = now();
+        let state = review_state_due_at(now + Duration::days(10));
+        let result = sm2.schedule(&state, Rating::Good, now);
+        assert_eq!(result.new_state.interval_days, 10.0);
+    }
+
+    #[test]
+    fn earliness_factor_caps_late_reviews_at_one() {
+        let now = now();
+        let late = review_state_due_at(now - Duration::days(5));
+        assert_eq!(Sm2::earliness_factor(&late, now), 1.0);
+        let halfway = review_state_due_at(now + Duration::days(5));
+        assert!((Sm2::earliness_factor(&halfway, now) - 0.5).abs() < 1e-6);
+    }
 }
@@ -0,0 +1,64 @@
+//! Deck paths encoded in file names.
+//!
+//! Decks normally follow the directory structure (`programming/rust/basics.md`
+//! is in `programming/rust`). With [`GlobalSettings::deck_path_separator`]
+//! set, a file name containing the separator names its own deck instead, as
+//! in Anki: with `::`, `Programming::Rust::Basics.md` is in `Programming/Rust`.
+//!
+//! [`GlobalSettings::deck_path_separator`]: crate::types::GlobalSettings::deck_path_separator
+
+/// Deck path from a file name split on `separator`, joined with `/`.
+///
+/// The last segment names the file itself and is dropped. Returns `None`
+/// when the name (without its `.md` extension) doesn't contain the separator,
+/// so the caller can fall back to the directory structure.
+///
+/// ```
+/// use flashcard_core::deck_path_from_file_name;
+///
+/// let deck = deck_path_from_file_name("Programming::Rust::Basics.md", "::");
+/// assert_eq!(deck.as_deref(), Some("Programming/Rust"));
+/// assert_eq!(deck_path_from_file_name("basics.md", "::"), None);
+/// ```
+pub fn deck_path_from_file_name(file_name: &str, separator: &str) -> Option<String> {
+    if separator.is_empty() {
+        return None;
+    }
+    let stem = file_name.strip_suffix(".md").unwrap_or(file_name);
+    let mut segments: Vec<&str> = stem
+        .split(separator)
+        .map(str::trim)
+        .filter(|segment| !segment.is_empty())
+        .collect();
+    if segments.len() < 2 {
+        return None;
+    }
+    segments.pop();
+    Some(segments.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separator_splits_file_name_into_deck_path() {
+        let deck = deck_path_from_file_name("Programming::Rust::Basics.md", "::");
+        assert_eq!(deck.as_deref(), Some("Programming/Rust"));
+        let deck = deck_path_from_file_name("Rust::Basics.md", "::");
+        assert_eq!(deck.as_deref(), Some("Rust"));
+    }
+
+    #[test]
+    fn names_without_the_separator_have_no_deck_path() {
+        assert_eq!(deck_path_from_file_name("Basics.md", "::"), None);
+        assert_eq!(deck_path_from_file_name("::Basics.md", "::"), None);
+        assert_eq!(deck_path_from_file_name("Rust::Basics.md", ""), None);
+    }
+
+    #[test]
+    fn empty_segments_are_skipped() {
+        let deck = deck_path_from_file_name("Programming:: ::Rust::Basics.md", "::");
+        assert_eq!(deck.as_deref(), Some("Programming/Rust"));
+    }
+}
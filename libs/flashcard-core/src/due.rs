@@ -0,0 +1,126 @@
+//! Study-day arithmetic shared by the desktop and backend.
+//!
+//! A study day starts at the daily reset hour in the user's timezone, not at
+//! midnight: with a 04:00 reset, 02:00 still belongs to the previous day. A
+//! card is due once its due time falls before the end of the current study
+//! day, so cards due later today are studied today.
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Timelike, Utc};
+
+/// Works out study days and due cards for a reset hour in a timezone.
+///
+/// The desktop uses the machine's [`chrono::Local`] timezone; the backend
+/// uses the fixed UTC offset the device last reported.
+#[derive(Debug, Clone, Copy)]
+pub struct DueCalculator<Tz: TimeZone = Utc> {
+    reset_hour: u32,
+    timezone: Tz,
+}
+
+impl<Tz: TimeZone> DueCalculator<Tz> {
+    /// Hours past 23 are treated as 23.
+    pub fn new(reset_hour: u32, timezone: Tz) -> Self {
+        Self {
+            reset_hour: reset_hour.min(23),
+            timezone,
+        }
+    }
+
+    /// The study day `now` falls in.
+    pub fn study_day(&self, now: DateTime<Utc>) -> NaiveDate {
+        let local = now.with_timezone(&self.timezone);
+        if local.hour() < self.reset_hour {
+            local.date_naive() - Duration::days(1)
+        } else {
+            local.date_naive()
+        }
+    }
+
+    /// Start and end of `day`, from its reset hour to the next one.
+    pub fn day_bounds(&self, day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        (
+            self.reset_instant(day),
+            self.reset_instant(day + Duration::days(1)),
+        )
+    }
+
+    /// Whether a card due at `due` should be studied at `now`.
+    ///
+    /// Equivalent to `study_day(due) <= study_day(now)`, which is how stores
+    /// that keep due dates per day compare them.
+    pub fn is_due(&self, due: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        let (_, day_end) = self.day_bounds(self.study_day(now));
+        due < day_end
+    }
+
+    /// The reset hour on `date`, in UTC.
+    fn reset_instant(&self, date: NaiveDate) -> DateTime<Utc> {
+        let naive = date
+            .and_hms_opt(self.reset_hour, 0, 0)
+            .expect("valid reset hour");
+        // A reset hour skipped by a DST change falls back to UTC
+        self.timezone
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|dt| dt.with_timezone(&Utc))
+            .unwrap_or_else(|| naive.and_utc())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, day, hour, minute, 0).unwrap()
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+    }
+
+    #[test]
+    fn test_study_day_turns_over_at_reset_hour() {
+        let calc = DueCalculator::new(4, Utc);
+        assert_eq!(calc.study_day(at(10, 3, 59)), date(9));
+        assert_eq!(calc.study_day(at(10, 4, 0)), date(10));
+        assert_eq!(calc.day_bounds(date(9)), (at(9, 4, 0), at(10, 4, 0)));
+    }
+
+    #[test]
+    fn test_study_day_uses_timezone() {
+        // 04:00 reset in UTC+2 is 02:00 UTC
+        let calc = DueCalculator::new(4, FixedOffset::east_opt(2 * 3600).unwrap());
+        assert_eq!(calc.study_day(at(10, 1, 59)), date(9));
+        assert_eq!(calc.study_day(at(10, 2, 0)), date(10));
+        assert_eq!(calc.day_bounds(date(10)), (at(10, 2, 0), at(11, 2, 0)));
+    }
+
+    #[test]
+    fn test_is_due_includes_rest_of_study_day() {
+        let calc = DueCalculator::new(4, Utc);
+        let now = at(10, 3, 0);
+        assert!(calc.is_due(at(9, 12, 0), now));
+        assert!(calc.is_due(at(10, 3, 59), now));
+        assert!(!calc.is_due(at(10, 4, 0), now));
+    }
+
+    #[test]
+    fn test_timestamp_and_day_comparisons_agree_near_reset() {
+        // The desktop compares due timestamps, the backend due days
+        let calc = DueCalculator::new(4, FixedOffset::west_opt(5 * 3600).unwrap());
+        let instants: Vec<_> = (0..48)
+            .map(|quarter| at(10, 6, 0) + Duration::minutes(quarter * 15))
+            .collect();
+        for &now in &instants {
+            for &due in &instants {
+                assert_eq!(
+                    calc.is_due(due, now),
+                    calc.study_day(due) <= calc.study_day(now),
+                    "due {due} at {now}"
+                );
+            }
+        }
+    }
+}
@@ -6,20 +6,82 @@ use thiserror::Error;
 pub type Result<T> = std::result::Result<T, ParseError>;
 
 /// Errors that can occur during markdown parsing.
+///
+/// Variants tied to a line carry a `snippet` of that line's text, when the
+/// parser has one, so callers can show context next to the line number.
 #[derive(Debug, Error)]
 pub enum ParseError {
-    #[error("missing question at line {line}")]
-    MissingQuestion { line: usize },
+    #[error("missing question at line {line}{}", snippet_suffix(.snippet))]
+    MissingQuestion {
+        line: usize,
+        snippet: Option<String>,
+    },
 
-    #[error("missing answer at line {line}")]
-    MissingAnswer { line: usize },
+    /// `snippet` is the card's question line.
+    #[error("missing answer at line {line}{}", snippet_suffix(.snippet))]
+    MissingAnswer {
+        line: usize,
+        snippet: Option<String>,
+    },
 
     #[error("invalid ID format at line {line}: {value}")]
-    InvalidId { line: usize, value: String },
+    InvalidId {
+        line: usize,
+        value: String,
+        snippet: Option<String>,
+    },
 
-    #[error("duplicate ID {id} at line {line}")]
-    DuplicateId { id: i64, line: usize },
+    #[error("duplicate ID {id} at line {line}{}", snippet_suffix(.snippet))]
+    DuplicateId {
+        id: i64,
+        line: usize,
+        snippet: Option<String>,
+    },
+
+    #[error("invalid metadata at line {line}: {message}{}", snippet_suffix(.snippet))]
+    InvalidMeta {
+        line: usize,
+        message: String,
+        snippet: Option<String>,
+    },
+
+    /// Only raised with [`crate::ParserConfig::reject_identical_qa`].
+    #[error("question and answer are identical at line {line}{}", snippet_suffix(.snippet))]
+    IdenticalQuestionAnswer {
+        line: usize,
+        snippet: Option<String>,
+    },
 
     #[error("empty file")]
     EmptyFile,
+
+    /// Reading the input failed, e.g. it is not valid UTF-8.
+    #[error("failed to read line {line}: {source}")]
+    Read {
+        line: usize,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+impl ParseError {
+    /// Text of the offending line, if known.
+    pub fn snippet(&self) -> Option<&str> {
+        match self {
+            Self::MissingQuestion { snippet, .. }
+            | Self::MissingAnswer { snippet, .. }
+            | Self::InvalidId { snippet, .. }
+            | Self::DuplicateId { snippet, .. }
+            | Self::InvalidMeta { snippet, .. }
+            | Self::IdenticalQuestionAnswer { snippet, .. } => snippet.as_deref(),
+            Self::EmptyFile | Self::Read { .. } => None,
+        }
+    }
+}
+
+fn snippet_suffix(snippet: &Option<String>) -> String {
+    snippet
+        .as_ref()
+        .map(|s| format!(" ({:?})", s))
+        .unwrap_or_default()
 }
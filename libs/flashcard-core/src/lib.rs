@@ -4,19 +4,39 @@
 //! - Markdown parser for flashcard files
 //! - Spaced repetition algorithm implementations (SM-2, FSRS)
 //! - Answer matching for typed mode (Levenshtein distance)
+//! - Study-day and due-date calculation honoring the daily reset hour
+//! - Offline review buffer for clients without a local database
 //! - Shared types (Card, CardState, Rating, etc.)
 
 pub mod algorithm;
+pub mod deck_path;
+pub mod due;
 pub mod error;
+mod hash;
 pub mod matching;
 pub mod parser;
+pub mod review_buffer;
 pub mod types;
 
-pub use algorithm::{SchedulingResult, SpacedRepetitionAlgorithm};
+pub use algorithm::{AlgorithmKind, SchedulingResult, SpacedRepetitionAlgorithm};
+pub use deck_path::deck_path_from_file_name;
+pub use due::DueCalculator;
 pub use error::{ParseError, Result};
-pub use matching::{compare_answers, levenshtein_distance, normalized_similarity, word_diff, DiffSegment, DiffType, MatchResult};
-pub use parser::parse;
+pub use matching::{
+    all_correct, answer_alternatives, answer_list_items, compare_answers, compare_answers_multi,
+    compare_answers_with_options, compare_list_answer, is_table_block, levenshtein_distance,
+    levenshtein_distance_graphemes, normalize_whitespace, normalized_similarity,
+    normalized_similarity_graphemes, word_diff, DiffSegment, DiffType, MatchOptions, MatchResult,
+    MAX_WORD_DIFF_CELLS,
+};
+pub use parser::{
+    assign_content_ids, content_hash_id, extract_media_refs, parse, parse_lenient, parse_reader,
+    parse_reader_with_config, parse_with_config, unescape_field_prefix, CardReader, CardSeparator,
+    ParseWarning, ParserConfig, MAX_CONTENT_ID,
+};
+pub use review_buffer::{BufferedReview, ReviewBuffer};
 pub use types::{
-    Algorithm, Card, CardState, CardStatus, DeckSettings, EffectiveSettings, GlobalSettings,
-    MatchingMode, Rating, RatingScale, RawCard,
+    Algorithm, Card, CardMeta, CardState, CardStatus, ConflictPolicy, DeckSettings, DeckSummaries,
+    DeckSummary, EffectiveSettings, GlobalSettings, IdStrategy, Limit, MatchingMode, NormRule,
+    QueueOrder, Rating, RatingScale, RawCard, ReviewSubmission, SessionLimit, StudyDirection,
 };
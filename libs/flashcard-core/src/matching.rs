@@ -1,7 +1,55 @@
 //! Answer matching for typed mode study sessions.
 
-use crate::types::MatchingMode;
+use crate::types::{MatchingMode, NormRule};
 use serde::{Deserialize, Serialize};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Default lowest similarity of a correct typed answer that suggests Easy
+/// rather than Good: only a perfect match.
+pub const DEFAULT_SUGGEST_EASY_THRESHOLD: f64 = 1.0;
+
+/// Default lowest similarity of an incorrect typed answer that suggests Hard
+/// rather than Again.
+pub const DEFAULT_SUGGEST_HARD_THRESHOLD: f64 = 0.6;
+
+/// Options that tune how answers are compared.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MatchOptions {
+    /// Measure edit distance over grapheme clusters instead of chars, so a
+    /// base letter with combining marks counts as a single character.
+    pub grapheme_mode: bool,
+    /// Normalize answers as code: per line, drop indentation and trailing
+    /// whitespace and collapse internal runs, but keep line breaks.
+    pub code_normalize: bool,
+    /// With `code_normalize`, also drop blank lines before comparing.
+    pub ignore_blank_lines: bool,
+    /// Typed answers with fewer non-whitespace characters are reported as
+    /// [`MatchResult::no_answer`] instead of being graded. An empty answer is
+    /// always no answer, even when this is `0`.
+    pub min_answer_chars: usize,
+    /// Ignore punctuation at the end of both answers, so "Paris" matches
+    /// "Paris.". Any Unicode punctuation counts, not just ASCII.
+    pub ignore_trailing_punctuation: bool,
+    /// Ignore punctuation anywhere in both answers.
+    pub ignore_punctuation: bool,
+    /// A deck's extra normalization, applied to both answers before they
+    /// are compared. See [`NormRule`].
+    pub normalization_rules: Vec<NormRule>,
+}
+
+/// Words dropped by [`NormRule::Stopwords`].
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "by", "for", "in", "is", "it", "of", "on", "or", "the",
+    "to", "with",
+];
+
+/// Minimum alphanumeric characters the contained answer needs for
+/// [`MatchingMode::Contains`], so a one-letter answer can't match any text
+/// containing that word. Shorter answers must match exactly (ignoring case).
+pub const CONTAINS_MIN_CHARS: usize = 4;
 
 /// Result of comparing a typed answer to the correct answer.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,6 +64,13 @@ pub struct MatchResult {
     pub typed_normalized: String,
     /// Normalized correct answer (for display).
     pub correct_normalized: String,
+    /// The typed answer was empty or too short to grade.
+    #[serde(default)]
+    pub no_answer: bool,
+    /// Required list items not found in the typed answer, see
+    /// [`compare_list_answer`]. Always empty for other comparisons.
+    #[serde(default)]
+    pub missing_items: Vec<String>,
 }
 
 /// Compare a typed answer to the correct answer.
@@ -25,35 +80,109 @@ pub fn compare_answers(
     mode: MatchingMode,
     fuzzy_threshold: f64,
 ) -> MatchResult {
-    let typed_normalized = normalize_whitespace(typed);
-    let correct_normalized = normalize_whitespace(correct);
+    compare_answers_with_options(
+        typed,
+        correct,
+        mode,
+        fuzzy_threshold,
+        &MatchOptions::default(),
+    )
+}
+
+/// Compare a typed answer to the correct answer using explicit match options.
+pub fn compare_answers_with_options(
+    typed: &str,
+    correct: &str,
+    mode: MatchingMode,
+    fuzzy_threshold: f64,
+    options: &MatchOptions,
+) -> MatchResult {
+    let (typed_normalized, correct_normalized) = if options.code_normalize {
+        (
+            normalize_code(typed, options.ignore_blank_lines),
+            normalize_code(correct, options.ignore_blank_lines),
+        )
+    } else {
+        (normalize_whitespace(typed), normalize_whitespace(correct))
+    };
+    // Punctuation is only dropped for the comparison; the normalized answers
+    // keep it for display.
+    let typed_cmp = apply_norm_rules(strip_punctuation(&typed_normalized, options), options);
+    let correct_cmp = apply_norm_rules(strip_punctuation(&correct_normalized, options), options);
+
+    let typed_chars = typed_cmp
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .count();
+    if typed_chars < options.min_answer_chars.max(1) {
+        return MatchResult {
+            is_correct: false,
+            similarity: 0.0,
+            matching_mode: mode,
+            typed_normalized,
+            correct_normalized,
+            no_answer: true,
+            missing_items: Vec::new(),
+        };
+    }
+
+    if let Some((is_correct, similarity)) = numeric_match(&typed_cmp, &correct_cmp, options) {
+        return MatchResult {
+            is_correct,
+            similarity,
+            matching_mode: mode,
+            typed_normalized,
+            correct_normalized,
+            no_answer: false,
+            missing_items: Vec::new(),
+        };
+    }
 
     match mode {
         MatchingMode::Exact => {
-            let is_correct = typed_normalized == correct_normalized;
+            let is_correct = typed_cmp == correct_cmp;
             MatchResult {
                 is_correct,
                 similarity: if is_correct { 1.0 } else { 0.0 },
                 matching_mode: mode,
                 typed_normalized,
                 correct_normalized,
+                no_answer: false,
+                missing_items: Vec::new(),
             }
         }
         MatchingMode::CaseInsensitive => {
-            let is_correct = typed_normalized.to_lowercase() == correct_normalized.to_lowercase();
+            let is_correct = typed_cmp.to_lowercase() == correct_cmp.to_lowercase();
             MatchResult {
                 is_correct,
                 similarity: if is_correct { 1.0 } else { 0.0 },
                 matching_mode: mode,
                 typed_normalized,
                 correct_normalized,
+                no_answer: false,
+                missing_items: Vec::new(),
+            }
+        }
+        MatchingMode::Contains => {
+            let (is_correct, similarity) = contains_match(&typed_cmp, &correct_cmp);
+            MatchResult {
+                is_correct,
+                similarity,
+                matching_mode: mode,
+                typed_normalized,
+                correct_normalized,
+                no_answer: false,
+                missing_items: Vec::new(),
             }
         }
         MatchingMode::Fuzzy => {
-            let similarity = normalized_similarity(
-                &typed_normalized.to_lowercase(),
-                &correct_normalized.to_lowercase(),
-            );
+            let typed_lower = typed_cmp.to_lowercase();
+            let correct_lower = correct_cmp.to_lowercase();
+            let similarity = if options.grapheme_mode {
+                normalized_similarity_graphemes(&typed_lower, &correct_lower)
+            } else {
+                normalized_similarity(&typed_lower, &correct_lower)
+            };
             let is_correct = similarity >= fuzzy_threshold;
             MatchResult {
                 is_correct,
@@ -61,23 +190,370 @@ pub fn compare_answers(
                 matching_mode: mode,
                 typed_normalized,
                 correct_normalized,
+                no_answer: false,
+                missing_items: Vec::new(),
+            }
+        }
+    }
+}
+
+/// Compare the typed segments of a multi-blank (cloze) answer to the
+/// correct ones, pairing them up by position.
+///
+/// Returns one result per position in the longer list. A blank left without
+/// a typed segment is graded as an empty answer ([`MatchResult::no_answer`]);
+/// a typed segment with no blank to fill is graded against an empty answer,
+/// so it is never correct. Use [`all_correct`] to grade the whole answer.
+pub fn compare_answers_multi(
+    typed: &[String],
+    correct: &[String],
+    mode: MatchingMode,
+    fuzzy_threshold: f64,
+) -> Vec<MatchResult> {
+    (0..typed.len().max(correct.len()))
+        .map(|i| {
+            let typed = typed.get(i).map_or("", String::as_str);
+            let correct = correct.get(i).map_or("", String::as_str);
+            compare_answers(typed, correct, mode, fuzzy_threshold)
+        })
+        .collect()
+}
+
+/// Whether every blank of a multi-blank answer is correct. False when there
+/// are no results at all.
+pub fn all_correct(results: &[MatchResult]) -> bool {
+    !results.is_empty() && results.iter().all(|r| r.is_correct)
+}
+
+/// Items of an answer written as a markdown list, one item per line.
+///
+/// Returns `None` unless the answer has at least two lines and every
+/// non-blank line is a bulleted (`-`, `*`, `+`) or numbered (`1.`, `1)`)
+/// item. Such answers can be graded with [`compare_list_answer`].
+pub fn answer_list_items(answer: &str) -> Option<Vec<&str>> {
+    let items: Vec<&str> = answer
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(list_item_text)
+        .collect::<Option<_>>()?;
+    (items.len() >= 2).then_some(items)
+}
+
+/// Text of a single list item line, without its marker.
+fn list_item_text(line: &str) -> Option<&str> {
+    let rest = match line.strip_prefix(['-', '*', '+']) {
+        Some(rest) => rest,
+        None => {
+            let number = line.trim_start_matches(|c: char| c.is_ascii_digit());
+            if number.len() == line.len() {
+                return None;
+            }
+            number.strip_prefix(['.', ')'])?
+        }
+    };
+    let text = rest.strip_prefix(char::is_whitespace)?.trim();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Grade a typed answer that must mention every item of a list, in any
+/// order.
+///
+/// Each item is looked for in the typed answer using `mode`: as a substring
+/// for the exact modes, as a run of whole words for
+/// [`MatchingMode::Contains`], and as a run of words at least
+/// `fuzzy_threshold` similar for [`MatchingMode::Fuzzy`]. The answer is
+/// correct when nothing is missing; similarity is the share of items found
+/// and [`MatchResult::missing_items`] lists the rest.
+pub fn compare_list_answer(
+    typed: &str,
+    items: &[&str],
+    mode: MatchingMode,
+    fuzzy_threshold: f64,
+) -> MatchResult {
+    let typed_normalized = normalize_whitespace(typed);
+    let items: Vec<String> = items.iter().map(|item| normalize_whitespace(item)).collect();
+    let correct_normalized = items.join(", ");
+
+    if typed_normalized.is_empty() || items.is_empty() {
+        return MatchResult {
+            is_correct: false,
+            similarity: 0.0,
+            matching_mode: mode,
+            typed_normalized,
+            correct_normalized,
+            no_answer: typed.trim().is_empty(),
+            missing_items: items,
+        };
+    }
+
+    let typed_words = match_words(&typed_normalized);
+    let missing_items: Vec<String> = items
+        .iter()
+        .filter(|item| {
+            !list_item_present(&typed_normalized, &typed_words, item, mode, fuzzy_threshold)
+        })
+        .cloned()
+        .collect();
+
+    MatchResult {
+        is_correct: missing_items.is_empty(),
+        similarity: (items.len() - missing_items.len()) as f64 / items.len() as f64,
+        matching_mode: mode,
+        typed_normalized,
+        correct_normalized,
+        no_answer: false,
+        missing_items,
+    }
+}
+
+/// Whether a required list item appears in the typed answer.
+fn list_item_present(
+    typed: &str,
+    typed_words: &[String],
+    item: &str,
+    mode: MatchingMode,
+    fuzzy_threshold: f64,
+) -> bool {
+    match mode {
+        MatchingMode::Exact => typed.contains(item),
+        MatchingMode::CaseInsensitive => typed.to_lowercase().contains(&item.to_lowercase()),
+        MatchingMode::Contains => {
+            let item_words = match_words(item);
+            !item_words.is_empty()
+                && typed_words
+                    .windows(item_words.len())
+                    .any(|window| window == item_words.as_slice())
+        }
+        MatchingMode::Fuzzy => {
+            let item_words = match_words(item);
+            if item_words.is_empty() || item_words.len() > typed_words.len() {
+                return false;
             }
+            let item_text = item_words.join(" ");
+            typed_words.windows(item_words.len()).any(|window| {
+                normalized_similarity(&window.join(" "), &item_text) >= fuzzy_threshold
+            })
         }
     }
 }
 
-/// Normalize whitespace in a string (trim and collapse multiple spaces).
-fn normalize_whitespace(s: &str) -> String {
+/// Whether `c` is in one of the Unicode punctuation categories (`P*`).
+fn is_punctuation(c: char) -> bool {
+    c.general_category_group() == GeneralCategoryGroup::Punctuation
+}
+
+/// Drop the punctuation `options` asks to ignore from a normalized answer,
+/// re-normalizing whitespace the removal leaves behind.
+fn strip_punctuation(normalized: &str, options: &MatchOptions) -> String {
+    let renormalize = |s: &str| {
+        if options.code_normalize {
+            normalize_code(s, options.ignore_blank_lines)
+        } else {
+            normalize_whitespace(s)
+        }
+    };
+    let ignore_punctuation = options.ignore_punctuation
+        || options.normalization_rules.contains(&NormRule::Punctuation);
+    if ignore_punctuation {
+        let stripped: String = normalized.chars().filter(|&c| !is_punctuation(c)).collect();
+        renormalize(&stripped)
+    } else if options.ignore_trailing_punctuation {
+        renormalize(normalized.trim_end_matches(is_punctuation))
+    } else {
+        normalized.to_string()
+    }
+}
+
+/// Apply the text rules in `options.normalization_rules` to an answer
+/// prepared for comparison. Punctuation is handled by [`strip_punctuation`]
+/// and numeric tolerance by [`numeric_match`].
+fn apply_norm_rules(answer: String, options: &MatchOptions) -> String {
+    let rules = &options.normalization_rules;
+    let mut answer = answer;
+    if rules.contains(&NormRule::Diacritics) {
+        answer = answer.nfkd().filter(|&c| !is_combining_mark(c)).collect();
+    }
+    if rules.contains(&NormRule::Stopwords) {
+        answer = answer
+            .split_whitespace()
+            .filter(|word| {
+                let word = word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+                !STOPWORDS.contains(&word.as_str())
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+    if rules.contains(&NormRule::Whitespace) {
+        answer.retain(|c| !c.is_whitespace());
+    }
+    answer
+}
+
+/// Grade two numeric answers under a [`NormRule::NumericTolerance`] rule,
+/// as whether the typed value is within the tolerance and its similarity.
+/// `None` without the rule or unless both answers are finite numbers.
+fn numeric_match(typed: &str, correct: &str, options: &MatchOptions) -> Option<(bool, f64)> {
+    let tolerance = options.normalization_rules.iter().find_map(|rule| match rule {
+        NormRule::NumericTolerance(tolerance) => Some(*tolerance),
+        _ => None,
+    })?;
+    let typed: f64 = typed.trim().parse().ok().filter(|v: &f64| v.is_finite())?;
+    let correct: f64 = correct.trim().parse().ok().filter(|v: &f64| v.is_finite())?;
+
+    let difference = (typed - correct).abs();
+    let is_correct = difference <= tolerance * correct.abs();
+    let similarity = if correct == 0.0 {
+        if typed == 0.0 {
+            1.0
+        } else {
+            0.0
+        }
+    } else {
+        (1.0 - difference / correct.abs()).max(0.0)
+    };
+    Some((is_correct, similarity))
+}
+
+/// Lowercased words with surrounding punctuation removed.
+fn match_words(s: &str) -> Vec<String> {
+    s.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Whether one answer's words appear as a contiguous run in the other's, and
+/// the share of the longer answer's words they cover.
+fn contains_match(typed: &str, correct: &str) -> (bool, f64) {
+    let typed = match_words(typed);
+    let correct = match_words(correct);
+    let (inner, outer) = if typed.len() <= correct.len() {
+        (&typed, &correct)
+    } else {
+        (&correct, &typed)
+    };
+    if inner.is_empty() {
+        return (false, 0.0);
+    }
+
+    let coverage = inner.len() as f64 / outer.len() as f64;
+    if inner == outer {
+        return (true, 1.0);
+    }
+
+    let inner_chars: usize = inner.iter().map(|word| word.chars().count()).sum();
+    let contained = inner_chars >= CONTAINS_MIN_CHARS
+        && outer
+            .windows(inner.len())
+            .any(|window| window == inner.as_slice());
+    (contained, if contained { coverage } else { 0.0 })
+}
+
+/// Normalize whitespace in a string: trim both ends and collapse every
+/// internal run of whitespace (spaces, tabs, newlines) to a single space.
+///
+/// This is the one definition used wherever cards are compared as text, so
+/// equality checks agree across the parser, matching, and stats.
+///
+/// ```
+/// use flashcard_core::normalize_whitespace;
+///
+/// assert_eq!(normalize_whitespace("  fn \t main()\n{ }  "), "fn main() { }");
+/// assert_eq!(normalize_whitespace(" \t\n "), "");
+/// ```
+pub fn normalize_whitespace(s: &str) -> String {
     s.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
-/// Calculate Levenshtein distance between two strings.
+/// Normalize code line by line: drop indentation and trailing whitespace and
+/// collapse internal runs to a single space, keeping line structure.
+fn normalize_code(s: &str, ignore_blank_lines: bool) -> String {
+    let lines = s.lines().map(normalize_whitespace);
+    let lines: Vec<String> = if ignore_blank_lines {
+        lines.filter(|line| !line.is_empty()).collect()
+    } else {
+        lines.collect()
+    };
+
+    // Leading/trailing blank lines never carry meaning
+    let start = lines
+        .iter()
+        .position(|l| !l.is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.is_empty())
+        .map_or(start, |i| i + 1);
+    lines[start..end].join("\n")
+}
+
+/// Whether `text` is a markdown table: a pipe-delimited header row, a
+/// `---` separator row, and any number of pipe-delimited body rows.
+pub fn is_table_block(text: &str) -> bool {
+    let rows: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    if rows.len() < 2 || !rows.iter().all(|row| is_table_row(row)) {
+        return false;
+    }
+
+    rows[1].trim_matches('|').split('|').all(|cell| {
+        let cell = cell.trim().trim_start_matches(':').trim_end_matches(':');
+        !cell.is_empty() && cell.chars().all(|c| c == '-')
+    })
+}
+
+fn is_table_row(line: &str) -> bool {
+    line.len() > 1 && line.starts_with('|') && line.ends_with('|')
+}
+
+/// Split an answer into `|`-delimited alternatives.
+///
+/// Only a top-level, single-line answer is split. Multi-line answers (tables,
+/// code, lists) and lines that look like a table row are returned whole so
+/// their pipes are never mistaken for delimiters.
+pub fn answer_alternatives(answer: &str) -> Vec<&str> {
+    let answer = answer.trim();
+    if answer.contains('\n') || is_table_row(answer) {
+        return vec![answer];
+    }
+
+    let alternatives: Vec<&str> = answer
+        .split('|')
+        .map(str::trim)
+        .filter(|alt| !alt.is_empty())
+        .collect();
+    if alternatives.is_empty() {
+        vec![answer]
+    } else {
+        alternatives
+    }
+}
+
+/// Calculate Levenshtein distance between two strings, counted in chars.
 pub fn levenshtein_distance(a: &str, b: &str) -> usize {
     let a_chars: Vec<char> = a.chars().collect();
     let b_chars: Vec<char> = b.chars().collect();
+    levenshtein(&a_chars, &b_chars)
+}
+
+/// Calculate Levenshtein distance between two strings, counted in
+/// extended grapheme clusters.
+pub fn levenshtein_distance_graphemes(a: &str, b: &str) -> usize {
+    let a_graphemes: Vec<&str> = a.graphemes(true).collect();
+    let b_graphemes: Vec<&str> = b.graphemes(true).collect();
+    levenshtein(&a_graphemes, &b_graphemes)
+}
 
-    let m = a_chars.len();
-    let n = b_chars.len();
+fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let m = a.len();
+    let n = b.len();
 
     if m == 0 {
         return n;
@@ -94,11 +570,7 @@ pub fn levenshtein_distance(a: &str, b: &str) -> usize {
         curr[0] = i;
 
         for j in 1..=n {
-            let cost = if a_chars[i - 1] == b_chars[j - 1] {
-                0
-            } else {
-                1
-            };
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
 
             curr[j] = (prev[j] + 1) // deletion
                 .min(curr[j - 1] + 1) // insertion
@@ -113,7 +585,7 @@ pub fn levenshtein_distance(a: &str, b: &str) -> usize {
 
 /// Calculate normalized similarity (0.0 to 1.0) based on Levenshtein distance.
 pub fn normalized_similarity(a: &str, b: &str) -> f64 {
-    let max_len = a.len().max(b.len());
+    let max_len = a.chars().count().max(b.chars().count());
     if max_len == 0 {
         return 1.0; // Both empty strings are identical
     }
@@ -122,6 +594,17 @@ pub fn normalized_similarity(a: &str, b: &str) -> f64 {
     1.0 - (distance as f64 / max_len as f64)
 }
 
+/// Calculate normalized similarity (0.0 to 1.0) over grapheme clusters.
+pub fn normalized_similarity_graphemes(a: &str, b: &str) -> f64 {
+    let max_len = a.graphemes(true).count().max(b.graphemes(true).count());
+    if max_len == 0 {
+        return 1.0;
+    }
+
+    let distance = levenshtein_distance_graphemes(a, b);
+    1.0 - (distance as f64 / max_len as f64)
+}
+
 /// Generate a diff between two strings for display.
 /// Returns a list of (text, diff_type) tuples.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -140,93 +623,105 @@ pub struct DiffSegment {
     pub diff_type: DiffType,
 }
 
-/// Simple word-level diff between typed and correct answers.
+/// Largest LCS table [`word_diff`] builds, in cells (changed typed words
+/// times changed correct words). About 4 MB; a pair of 1000-word answers
+/// that differ throughout just fits.
+pub const MAX_WORD_DIFF_CELLS: usize = 1_000_000;
+
+/// Word-level diff between typed and correct answers.
+///
+/// Aligns the two word sequences on their longest common subsequence
+/// (case-insensitive), so inserted, missing or edited passages anywhere in a
+/// long answer show up as a minimal set of added/removed words. Within a
+/// changed run, removed words come before added ones.
+///
+/// Matching leading and trailing words are never part of the alignment. If
+/// what is left between them would need more than [`MAX_WORD_DIFF_CELLS`],
+/// it is reported as a single removed segment followed by a single added
+/// segment, keeping time and memory bounded for pathological answers.
 pub fn word_diff(typed: &str, correct: &str) -> Vec<DiffSegment> {
     let typed_words: Vec<&str> = typed.split_whitespace().collect();
     let correct_words: Vec<&str> = correct.split_whitespace().collect();
+    let typed_lower: Vec<String> = typed_words.iter().map(|w| w.to_lowercase()).collect();
+    let correct_lower: Vec<String> = correct_words.iter().map(|w| w.to_lowercase()).collect();
+
+    let prefix = typed_lower
+        .iter()
+        .zip(&correct_lower)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = typed_lower[prefix..]
+        .iter()
+        .rev()
+        .zip(correct_lower[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    // The changed middle: typed[prefix..n] against correct[prefix..m]
+    let (n, m) = (typed_words.len() - suffix, correct_words.len() - suffix);
+
+    let segment = |text: &str, diff_type| DiffSegment {
+        text: text.to_string(),
+        diff_type,
+    };
+
+    let mut result = Vec::with_capacity(typed_words.len().max(correct_words.len()));
+    result.extend(
+        typed_words[..prefix]
+            .iter()
+            .map(|w| segment(w, DiffType::Same)),
+    );
+
+    if (n - prefix).saturating_mul(m - prefix) > MAX_WORD_DIFF_CELLS {
+        result.push(segment(&typed_words[prefix..n].join(" "), DiffType::Removed));
+        result.push(segment(&correct_words[prefix..m].join(" "), DiffType::Added));
+    } else {
+        let (rows, cols) = (n - prefix, m - prefix);
 
-    let mut result = Vec::new();
-    let mut i = 0;
-    let mut j = 0;
-
-    while i < typed_words.len() || j < correct_words.len() {
-        if i < typed_words.len() && j < correct_words.len() {
-            if typed_words[i].to_lowercase() == correct_words[j].to_lowercase() {
-                result.push(DiffSegment {
-                    text: typed_words[i].to_string(),
-                    diff_type: DiffType::Same,
-                });
+        // lcs[i][j] = length of the LCS of the middle's typed[i..] and correct[j..]
+        let mut lcs = vec![vec![0u32; cols + 1]; rows + 1];
+        for i in (0..rows).rev() {
+            for j in (0..cols).rev() {
+                lcs[i][j] = if typed_lower[prefix + i] == correct_lower[prefix + j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        while i < rows && j < cols {
+            if typed_lower[prefix + i] == correct_lower[prefix + j] {
+                result.push(segment(typed_words[prefix + i], DiffType::Same));
                 i += 1;
                 j += 1;
+            } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+                result.push(segment(typed_words[prefix + i], DiffType::Removed));
+                i += 1;
             } else {
-                // Look ahead to see if we can find a match
-                let mut found = false;
-
-                // Check if typed word appears later in correct
-                for k in j + 1..correct_words.len().min(j + 3) {
-                    if typed_words[i].to_lowercase() == correct_words[k].to_lowercase() {
-                        // Add missing words as added
-                        for l in j..k {
-                            result.push(DiffSegment {
-                                text: correct_words[l].to_string(),
-                                diff_type: DiffType::Added,
-                            });
-                        }
-                        j = k;
-                        found = true;
-                        break;
-                    }
-                }
-
-                if !found {
-                    // Check if correct word appears later in typed
-                    for k in i + 1..typed_words.len().min(i + 3) {
-                        if correct_words[j].to_lowercase() == typed_words[k].to_lowercase() {
-                            // Add extra words as removed
-                            for l in i..k {
-                                result.push(DiffSegment {
-                                    text: typed_words[l].to_string(),
-                                    diff_type: DiffType::Removed,
-                                });
-                            }
-                            i = k;
-                            found = true;
-                            break;
-                        }
-                    }
-                }
-
-                if !found {
-                    // No match found, mark typed as removed and correct as added
-                    result.push(DiffSegment {
-                        text: typed_words[i].to_string(),
-                        diff_type: DiffType::Removed,
-                    });
-                    result.push(DiffSegment {
-                        text: correct_words[j].to_string(),
-                        diff_type: DiffType::Added,
-                    });
-                    i += 1;
-                    j += 1;
-                }
+                result.push(segment(correct_words[prefix + j], DiffType::Added));
+                j += 1;
             }
-        } else if i < typed_words.len() {
-            // Extra words in typed
-            result.push(DiffSegment {
-                text: typed_words[i].to_string(),
-                diff_type: DiffType::Removed,
-            });
-            i += 1;
-        } else {
-            // Missing words from correct
-            result.push(DiffSegment {
-                text: correct_words[j].to_string(),
-                diff_type: DiffType::Added,
-            });
-            j += 1;
         }
+        result.extend(
+            typed_words[prefix + i..n]
+                .iter()
+                .map(|w| segment(w, DiffType::Removed)),
+        );
+        result.extend(
+            correct_words[prefix + j..m]
+                .iter()
+                .map(|w| segment(w, DiffType::Added)),
+        );
     }
 
+    result.extend(
+        typed_words[n..]
+            .iter()
+            .map(|w| segment(w, DiffType::Same)),
+    );
+
     result
 }
 
@@ -252,6 +747,186 @@ mod tests {
         assert!(normalized_similarity("abc", "xyz") < 0.5);
     }
 
+    #[test]
+    fn test_grapheme_distance_combining_marks() {
+        // "e" followed by two combining marks is one visible character.
+        let stacked = "e\u{301}\u{302}";
+        assert_eq!(levenshtein_distance(stacked, "e"), 2);
+        assert_eq!(levenshtein_distance_graphemes(stacked, "e"), 1);
+    }
+
+    #[test]
+    fn test_grapheme_similarity_decomposed() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        assert_eq!(decomposed.chars().count(), 5);
+        assert_eq!(decomposed.graphemes(true).count(), 4);
+
+        // Same visible length, one differing cluster.
+        assert_eq!(normalized_similarity_graphemes(decomposed, composed), 0.75);
+        assert_eq!(normalized_similarity(decomposed, composed), 0.6);
+        assert_eq!(normalized_similarity_graphemes(decomposed, decomposed), 1.0);
+    }
+
+    #[test]
+    fn test_compare_fuzzy_grapheme_mode() {
+        let options = MatchOptions {
+            grapheme_mode: true,
+            ..Default::default()
+        };
+        let result = compare_answers_with_options(
+            "cafe\u{301}s",
+            "cafe\u{300}s",
+            MatchingMode::Fuzzy,
+            0.8,
+            &options,
+        );
+        assert!(result.is_correct); // 4 of 5 graphemes match
+
+        let result = compare_answers("cafe\u{301}s", "cafe\u{300}s", MatchingMode::Fuzzy, 0.85);
+        assert!(!result.is_correct); // 5 of 6 chars match, below threshold
+    }
+
+    #[test]
+    fn test_code_normalize_ignores_indentation() {
+        let options = MatchOptions {
+            code_normalize: true,
+            ..Default::default()
+        };
+        let correct = "fn main() {\n    println!(\"hi\");\n}";
+        let typed = "fn main() {  \n\tprintln!(\"hi\");   \n}\n";
+
+        let result =
+            compare_answers_with_options(typed, correct, MatchingMode::Exact, 0.8, &options);
+        assert!(result.is_correct);
+        assert_eq!(result.typed_normalized, "fn main() {\nprintln!(\"hi\");\n}");
+    }
+
+    #[test]
+    fn test_code_normalize_detects_changed_identifier() {
+        let options = MatchOptions {
+            code_normalize: true,
+            ..Default::default()
+        };
+        let correct = "let total = a + b;\nreturn total;";
+        let typed = "  let sum = a + b;\n  return sum;";
+
+        let result =
+            compare_answers_with_options(typed, correct, MatchingMode::Exact, 0.8, &options);
+        assert!(!result.is_correct);
+    }
+
+    #[test]
+    fn test_code_normalize_blank_lines() {
+        let correct = "a = 1\nb = 2";
+        let typed = "a = 1\n\nb = 2";
+
+        let keep = MatchOptions {
+            code_normalize: true,
+            ..Default::default()
+        };
+        assert!(
+            !compare_answers_with_options(typed, correct, MatchingMode::Exact, 0.8, &keep)
+                .is_correct
+        );
+
+        let ignore = MatchOptions {
+            code_normalize: true,
+            ignore_blank_lines: true,
+            ..Default::default()
+        };
+        assert!(
+            compare_answers_with_options(typed, correct, MatchingMode::Exact, 0.8, &ignore)
+                .is_correct
+        );
+    }
+
+    #[test]
+    fn test_ignore_trailing_punctuation() {
+        let options = MatchOptions {
+            ignore_trailing_punctuation: true,
+            ..Default::default()
+        };
+        let compare =
+            |typed, correct, mode| compare_answers_with_options(typed, correct, mode, 0.8, &options);
+
+        let result = compare("Paris", "Paris!", MatchingMode::Exact);
+        assert!(result.is_correct);
+        assert_eq!(result.correct_normalized, "Paris!");
+        assert!(compare("color?", "color", MatchingMode::CaseInsensitive).is_correct);
+        // Unicode punctuation, not just ASCII
+        assert!(compare("Tokyo", "Tokyo\u{3002}", MatchingMode::Exact).is_correct);
+        assert!(compare("¿qué", "¿qué?", MatchingMode::Exact).is_correct);
+        assert!(!compare("qué", "¿qué?", MatchingMode::Exact).is_correct);
+    }
+
+    #[test]
+    fn test_ignore_all_punctuation() {
+        let options = MatchOptions {
+            ignore_punctuation: true,
+            ..Default::default()
+        };
+        let compare = |typed| {
+            let correct = "don't, \u{201c}panic\u{201d}";
+            compare_answers_with_options(typed, correct, MatchingMode::Exact, 0.8, &options)
+        };
+
+        let result = compare("dont panic");
+        assert!(result.is_correct);
+        assert_eq!(result.correct_normalized, "don't, \u{201c}panic\u{201d}");
+        assert!(!compare("do not panic").is_correct);
+        assert!(compare("!?").no_answer);
+    }
+
+    #[test]
+    fn test_deck_normalization_rules_change_outcome() {
+        let with_rules = |rules: Vec<NormRule>| MatchOptions {
+            normalization_rules: rules,
+            ..Default::default()
+        };
+        let compare = |typed, correct, options: &MatchOptions| {
+            compare_answers_with_options(typed, correct, MatchingMode::Exact, 0.8, options)
+        };
+        let plain = MatchOptions::default();
+
+        // French deck ignores accents
+        let french = with_rules(vec![NormRule::Diacritics]);
+        assert!(!compare("ecole", "\u{e9}cole", &plain).is_correct);
+        assert!(compare("ecole", "\u{e9}cole", &french).is_correct);
+        assert!(compare("ecole", "e\u{301}cole", &french).is_correct);
+        // Chemistry deck folds subscripts
+        assert!(compare("H2O", "H\u{2082}O", &french).is_correct);
+
+        // Code deck ignores whitespace
+        let code = with_rules(vec![NormRule::Whitespace]);
+        assert!(!compare("a+b", "a + b", &plain).is_correct);
+        assert!(compare("a+b", "a + b", &code).is_correct);
+
+        let punctuation = with_rules(vec![NormRule::Punctuation]);
+        assert!(compare("dont panic", "don't panic!", &punctuation).is_correct);
+
+        let stopwords = with_rules(vec![NormRule::Stopwords]);
+        assert!(compare("Battle Hastings", "The Battle of Hastings", &stopwords).is_correct);
+        assert!(compare("the", "The Battle of Hastings", &stopwords).no_answer);
+
+        let numeric = with_rules(vec![NormRule::NumericTolerance(0.01)]);
+        assert!(!compare("3.14", "3.14159", &plain).is_correct);
+        let close = compare("3.14", "3.14159", &numeric);
+        assert!(close.is_correct);
+        assert!(close.similarity > 0.99 && close.similarity < 1.0);
+        assert!(!compare("3.2", "3.14159", &numeric).is_correct);
+        // Non-numeric answers grade as usual
+        assert!(!compare("pi", "3.14159", &numeric).is_correct);
+    }
+
+    #[test]
+    fn test_code_punctuation_kept_by_default() {
+        let correct = "vec![1, 2];";
+        assert!(compare_answers(correct, correct, MatchingMode::Exact, 0.8).is_correct);
+        assert!(!compare_answers("vec![1, 2]", correct, MatchingMode::Exact, 0.8).is_correct);
+        assert!(!compare_answers("vec[1 2];", correct, MatchingMode::Exact, 0.8).is_correct);
+    }
+
     #[test]
     fn test_compare_exact() {
         let result = compare_answers("hello", "hello", MatchingMode::Exact, 0.8);
@@ -267,10 +942,81 @@ mod tests {
         let result = compare_answers("Hello", "hello", MatchingMode::CaseInsensitive, 0.8);
         assert!(result.is_correct);
 
-        let result = compare_answers("HELLO WORLD", "hello world", MatchingMode::CaseInsensitive, 0.8);
+        let result = compare_answers(
+            "HELLO WORLD",
+            "hello world",
+            MatchingMode::CaseInsensitive,
+            0.8,
+        );
         assert!(result.is_correct);
     }
 
+    #[test]
+    fn test_empty_answer_is_no_answer() {
+        for mode in [
+            MatchingMode::Exact,
+            MatchingMode::CaseInsensitive,
+            MatchingMode::Fuzzy,
+        ] {
+            for typed in ["", "   ", "\n\t"] {
+                let result = compare_answers(typed, "", mode, 0.0);
+                assert!(result.no_answer, "{:?} {:?}", mode, typed);
+                assert!(!result.is_correct, "{:?} {:?}", mode, typed);
+            }
+        }
+
+        let result = compare_answers("hello", "hello", MatchingMode::Exact, 0.8);
+        assert!(!result.no_answer);
+    }
+
+    #[test]
+    fn test_min_answer_chars() {
+        let options = MatchOptions {
+            min_answer_chars: 3,
+            ..MatchOptions::default()
+        };
+        let compare =
+            |typed| compare_answers_with_options(typed, "ox", MatchingMode::Fuzzy, 0.5, &options);
+
+        assert!(compare("o x").no_answer);
+        let result = compare("oxen");
+        assert!(!result.no_answer);
+        assert!(result.is_correct);
+    }
+
+    #[test]
+    fn test_compare_contains_verbose_answer() {
+        let correct = "A reference that doesn't take ownership";
+        let typed = "a borrow is a reference that doesn't take ownership, basically";
+        let result = compare_answers(typed, correct, MatchingMode::Contains, 0.8);
+        assert!(result.is_correct);
+        assert!((result.similarity - 6.0 / 10.0).abs() < 1e-9);
+
+        // The typed answer may also be a part of a longer correct answer
+        let result = compare_answers("take ownership", correct, MatchingMode::Contains, 0.8);
+        assert!(result.is_correct);
+
+        let result = compare_answers("a copy of the value", correct, MatchingMode::Contains, 0.8);
+        assert!(!result.is_correct);
+        assert_eq!(result.similarity, 0.0);
+    }
+
+    #[test]
+    fn test_compare_contains_short_answer_guard() {
+        // "Go" is too short to match by containment
+        let result = compare_answers("I would say go or rust", "Go", MatchingMode::Contains, 0.8);
+        assert!(!result.is_correct);
+
+        // ...but still matches on its own
+        let result = compare_answers("go!", "Go", MatchingMode::Contains, 0.8);
+        assert!(result.is_correct);
+        assert_eq!(result.similarity, 1.0);
+
+        // Containment is by whole words, not substrings
+        let result = compare_answers("concatenate", "cate", MatchingMode::Contains, 0.8);
+        assert!(!result.is_correct);
+    }
+
     #[test]
     fn test_compare_fuzzy() {
         let result = compare_answers("helo", "hello", MatchingMode::Fuzzy, 0.8);
@@ -280,9 +1026,269 @@ mod tests {
         assert!(!result.is_correct);
     }
 
+    const TABLE_ANSWER: &str = "| Type | Size |\n|------|:----:|\n| i32  | 4    |\n| i64  | 8    |";
+
+    #[test]
+    fn test_is_table_block() {
+        assert!(is_table_block(TABLE_ANSWER));
+        assert!(is_table_block("| a | b |\n| --- | --- |"));
+        assert!(!is_table_block("| a | b |"));
+        assert!(!is_table_block("| a | b |\n| c | d |"));
+        assert!(!is_table_block("red | crimson"));
+    }
+
+    #[test]
+    fn test_single_line_answer_splits_into_alternatives() {
+        assert_eq!(answer_alternatives("red | crimson"), vec!["red", "crimson"]);
+        assert_eq!(answer_alternatives("blue"), vec!["blue"]);
+    }
+
+    #[test]
+    fn test_multiline_table_answer_not_split() {
+        assert_eq!(answer_alternatives(TABLE_ANSWER), vec![TABLE_ANSWER]);
+
+        let cards = crate::parser::parse(&format!("Q: Integer sizes?\nA: {TABLE_ANSWER}")).unwrap();
+        assert_eq!(cards[0].answer, TABLE_ANSWER);
+        assert!(is_table_block(&cards[0].answer));
+        assert_eq!(answer_alternatives(&cards[0].answer).len(), 1);
+    }
+
+    #[test]
+    fn test_single_table_row_not_split() {
+        assert_eq!(answer_alternatives("| a | b |"), vec!["| a | b |"]);
+    }
+
     #[test]
     fn test_whitespace_normalization() {
         let result = compare_answers("  hello   world  ", "hello world", MatchingMode::Exact, 0.8);
         assert!(result.is_correct);
     }
+
+    /// The previous `word_diff`: greedy matching with a two-word lookahead.
+    fn naive_word_diff(typed: &str, correct: &str) -> Vec<DiffSegment> {
+        let typed: Vec<&str> = typed.split_whitespace().collect();
+        let correct: Vec<&str> = correct.split_whitespace().collect();
+        let same = |a: &str, b: &str| a.to_lowercase() == b.to_lowercase();
+        let segment = |text: &str, diff_type| DiffSegment {
+            text: text.to_string(),
+            diff_type,
+        };
+
+        let mut result = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < typed.len() && j < correct.len() {
+            if same(typed[i], correct[j]) {
+                result.push(segment(typed[i], DiffType::Same));
+                i += 1;
+                j += 1;
+            } else if let Some(k) =
+                (j + 1..correct.len().min(j + 3)).find(|&k| same(typed[i], correct[k]))
+            {
+                result.extend(correct[j..k].iter().map(|w| segment(w, DiffType::Added)));
+                j = k;
+            } else if let Some(k) =
+                (i + 1..typed.len().min(i + 3)).find(|&k| same(correct[j], typed[k]))
+            {
+                result.extend(typed[i..k].iter().map(|w| segment(w, DiffType::Removed)));
+                i = k;
+            } else {
+                result.push(segment(typed[i], DiffType::Removed));
+                result.push(segment(correct[j], DiffType::Added));
+                i += 1;
+                j += 1;
+            }
+        }
+        result.extend(typed[i..].iter().map(|w| segment(w, DiffType::Removed)));
+        result.extend(correct[j..].iter().map(|w| segment(w, DiffType::Added)));
+        result
+    }
+
+    fn texts(diff: &[DiffSegment], diff_type: DiffType) -> Vec<&str> {
+        diff.iter()
+            .filter(|s| s.diff_type == diff_type)
+            .map(|s| s.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_word_diff_inserted_clause() {
+        let correct = "the borrow checker which runs at compile time prevents data races";
+        let typed = "the borrow checker prevents data races";
+
+        // The lookahead gives up on the five-word gap and pairs words up
+        let naive = naive_word_diff(typed, correct);
+        assert!(!texts(&naive, DiffType::Removed).is_empty());
+
+        let diff = word_diff(typed, correct);
+        assert!(texts(&diff, DiffType::Removed).is_empty());
+        assert_eq!(
+            texts(&diff, DiffType::Added),
+            vec!["which", "runs", "at", "compile", "time"]
+        );
+        assert_eq!(
+            texts(&diff, DiffType::Same),
+            typed.split_whitespace().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_word_diff_edited_word() {
+        let diff = word_diff("Ownership Moves the value", "ownership copies the value");
+        let types: Vec<DiffType> = diff.iter().map(|s| s.diff_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                DiffType::Same,
+                DiffType::Removed,
+                DiffType::Added,
+                DiffType::Same,
+                DiffType::Same
+            ]
+        );
+        assert_eq!(diff[0].text, "Ownership");
+        assert_eq!(diff[2].text, "copies");
+    }
+
+    #[test]
+    fn test_word_diff_empty_sides() {
+        assert!(word_diff("", "").is_empty());
+        assert_eq!(
+            texts(&word_diff("", "a b"), DiffType::Added),
+            vec!["a", "b"]
+        );
+        assert_eq!(
+            texts(&word_diff("a b", ""), DiffType::Removed),
+            vec!["a", "b"]
+        );
+    }
+
+    #[test]
+    fn test_word_diff_long_answers_are_bounded() {
+        let correct: Vec<String> = (0..5000).map(|i| format!("word{i}")).collect();
+        let typed: Vec<String> = (0..5000).map(|i| format!("typo{i}")).collect();
+        let (correct, typed) = (correct.join(" "), typed.join(" "));
+
+        let started = std::time::Instant::now();
+        let diff = word_diff(&typed, &correct);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+        let types: Vec<DiffType> = diff.iter().map(|s| s.diff_type.clone()).collect();
+        assert_eq!(types, vec![DiffType::Removed, DiffType::Added]);
+        assert_eq!(diff[0].text, typed);
+        assert_eq!(diff[1].text, correct);
+
+        // A small edit in a long answer is still aligned word by word
+        let edited = correct.replacen("word2500", "changed", 1);
+        let diff = word_diff(&edited, &correct);
+        assert_eq!(texts(&diff, DiffType::Removed), vec!["changed"]);
+        assert_eq!(texts(&diff, DiffType::Added), vec!["word2500"]);
+        assert_eq!(texts(&diff, DiffType::Same).len(), 4999);
+    }
+
+    fn blanks(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_compare_answers_multi_two_blank_cloze() {
+        let correct = blanks(&["mitochondria", "ATP"]);
+        let results = compare_answers_multi(
+            &blanks(&["Mitochondria", "glucose"]),
+            &correct,
+            MatchingMode::CaseInsensitive,
+            0.8,
+        );
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_correct);
+        assert!(!results[1].is_correct);
+        assert!(!all_correct(&results));
+
+        let results = compare_answers_multi(
+            &blanks(&["mitochondria", "atp"]),
+            &correct,
+            MatchingMode::CaseInsensitive,
+            0.8,
+        );
+        assert!(all_correct(&results));
+    }
+
+    #[test]
+    fn test_compare_answers_multi_length_mismatch() {
+        let correct = blanks(&["mitochondria", "ATP"]);
+
+        // A blank left unfilled is no answer
+        let short =
+            compare_answers_multi(&blanks(&["mitochondria"]), &correct, MatchingMode::Exact, 0.8);
+        assert_eq!(short.len(), 2);
+        assert!(short[1].no_answer);
+        assert!(!all_correct(&short));
+
+        // A segment with no blank to fill is never correct
+        let long = compare_answers_multi(
+            &blanks(&["mitochondria", "ATP", "extra"]),
+            &correct,
+            MatchingMode::Exact,
+            0.8,
+        );
+        assert_eq!(long.len(), 3);
+        assert!(!long[2].is_correct);
+        assert!(!all_correct(&long));
+
+        assert!(!all_correct(&compare_answers_multi(&[], &[], MatchingMode::Exact, 0.8)));
+    }
+
+    #[test]
+    fn test_answer_list_items() {
+        let answer = "1. Legislative\n2) Executive\n\n- Judicial";
+        assert_eq!(
+            answer_list_items(answer),
+            Some(vec!["Legislative", "Executive", "Judicial"])
+        );
+
+        // A single item, prose, or a markdown rule aren't lists
+        assert_eq!(answer_list_items("- Legislative"), None);
+        assert_eq!(answer_list_items("- Legislative\nand more"), None);
+        assert_eq!(answer_list_items("---\n---"), None);
+        assert_eq!(answer_list_items("1984\n2001"), None);
+    }
+
+    #[test]
+    fn test_compare_list_answer_reports_missing_items() {
+        let items = ["legislative", "executive", "judicial"];
+        let typed = "Judicial and legislative branches";
+
+        let modes = [
+            MatchingMode::CaseInsensitive,
+            MatchingMode::Contains,
+            MatchingMode::Fuzzy,
+        ];
+        for mode in modes {
+            let result = compare_list_answer(typed, &items, mode, 0.8);
+            assert!(!result.is_correct, "{mode:?}");
+            assert_eq!(result.missing_items, vec!["executive"], "{mode:?}");
+            assert!((result.similarity - 2.0 / 3.0).abs() < 1e-9);
+        }
+
+        let complete = compare_list_answer(
+            "executive, judicial, legislative",
+            &items,
+            MatchingMode::Exact,
+            0.8,
+        );
+        assert!(complete.is_correct);
+        assert!(complete.missing_items.is_empty());
+
+        // Fuzzy tolerates a typo in one item
+        let typo = compare_list_answer(
+            "legislative, executve, judicial",
+            &items,
+            MatchingMode::Fuzzy,
+            0.8,
+        );
+        assert!(typo.is_correct);
+
+        let empty = compare_list_answer("  ", &items, MatchingMode::Fuzzy, 0.8);
+        assert!(empty.no_answer);
+        assert_eq!(empty.missing_items.len(), 3);
+    }
 }
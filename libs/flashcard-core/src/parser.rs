@@ -11,28 +11,244 @@
 //! A: Borrowing allows references without ownership.
 //! Multiple lines are supported.
 //! ```
+//!
+//! An optional `META:` line within a card sets scheduling hints as
+//! space-separated `key=value` pairs (`META: suspended=true ease=2.1`), and
+//! tags as a comma-separated list (`META: tags=exam,#chapter-3`), see
+//! [`CardMeta`]. A `META:` line set apart from an answer by a blank line
+//! starts the next card.
+//!
+//! Blockquote lines (starting with `>`) are kept verbatim, markers included,
+//! and are never read as `ID:`/`Q:`/`A:`/`META:` lines.
+//!
+//! A backslash before a field prefix (`\A: not an answer`) makes the line
+//! plain text in the current field; the backslash itself is dropped.
+//!
+//! With [`ParserConfig::card_separator`] set, a separator line (`---` or
+//! `===`) ends the current card, and inside an answer `ID:`/`Q:`/`A:`/`META:`
+//! lines are plain text:
+//! ```markdown
+//! Q: Where is ownership explained?
+//! A: In the book.
+//! ID: see chapter 4
+//! ---
+//! ```
+//!
+//! Media referenced with markdown image/link syntax (`![](diagram.png)`,
+//! `[listen](clip.mp3)`) or `<img>`/`<audio>`/`<video>` tags is listed in
+//! [`RawCard::media`].
 
 use crate::error::{ParseError, Result};
-use crate::types::RawCard;
-use std::collections::HashSet;
+use crate::hash::fnv1a;
+use crate::matching::normalize_whitespace;
+use crate::types::{CardMeta, IdStrategy, RawCard};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::io::{BufRead, Lines};
+
+/// Line that explicitly ends a card, see [`ParserConfig::card_separator`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CardSeparator {
+    /// A line of three or more `-`.
+    Dashes,
+    /// A line of three or more `=`.
+    Equals,
+}
+
+impl CardSeparator {
+    /// Whether a trimmed line is this separator.
+    fn matches(self, trimmed: &str) -> bool {
+        let marker = match self {
+            Self::Dashes => '-',
+            Self::Equals => '=',
+        };
+        trimmed.len() >= 3 && trimmed.chars().all(|c| c == marker)
+    }
+}
+
+/// Options that control how card text is extracted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ParserConfig {
+    /// Keep indentation and trailing whitespace of multi-line questions and
+    /// answers (e.g. code), dropping only surrounding blank lines. Single-line
+    /// fields and the space after `Q:`/`A:` are always trimmed.
+    pub preserve_whitespace: bool,
+    /// How cards without an `ID:` line get an ID.
+    pub id_strategy: IdStrategy,
+    /// End cards at an explicit separator line. An answer then runs until
+    /// the separator (or end of file), and field prefixes inside it are
+    /// literal text instead of starting a new card.
+    pub card_separator: Option<CardSeparator>,
+    /// Reject cards whose question and answer are the same after whitespace
+    /// normalization. Off by default so intentional mnemonic cards still
+    /// parse; [`parse_lenient`] reports them as warnings instead.
+    pub reject_identical_qa: bool,
+}
+
+/// Card that parsed but probably can't be studied as written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// Question and answer are identical after whitespace normalization.
+    /// `snippet` is the card's question line.
+    IdenticalQuestionAnswer { line: usize, snippet: String },
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IdenticalQuestionAnswer { line, snippet } => write!(
+                f,
+                "question and answer are identical at line {} ({:?})",
+                line, snippet
+            ),
+        }
+    }
+}
 
 /// Parse markdown content into raw cards.
 pub fn parse(content: &str) -> Result<Vec<RawCard>> {
-    if content.trim().is_empty() {
-        return Ok(vec![]);
+    parse_with_config(content, &ParserConfig::default())
+}
+
+/// Parse markdown content into raw cards using explicit parser options.
+pub fn parse_with_config(content: &str, config: &ParserConfig) -> Result<Vec<RawCard>> {
+    parse_collecting_warnings(content, config).map(|(cards, _)| cards)
+}
+
+/// Parse like [`parse_with_config`], reporting suspicious cards as warnings.
+///
+/// [`ParserConfig::reject_identical_qa`] is ignored here: identical cards are
+/// kept and listed as [`ParseWarning`]s.
+pub fn parse_lenient(
+    content: &str,
+    config: &ParserConfig,
+) -> Result<(Vec<RawCard>, Vec<ParseWarning>)> {
+    let config = ParserConfig {
+        reject_identical_qa: false,
+        ..*config
+    };
+    parse_collecting_warnings(content, &config)
+}
+
+/// Parse markdown from a reader, yielding each card as soon as it ends.
+///
+/// Only the card in progress is held in memory, so huge files can be
+/// imported without loading them whole. See [`parse_reader_with_config`].
+pub fn parse_reader<R: BufRead>(reader: R) -> CardReader<R> {
+    parse_reader_with_config(reader, &ParserConfig::default())
+}
+
+/// Parse markdown from a reader using explicit parser options.
+///
+/// Cards are yielded with the IDs written in the file:
+/// [`ParserConfig::id_strategy`] is not applied, because a content-hash ID
+/// can only be checked against explicit IDs once the whole file is read.
+/// Pass the collected cards to [`assign_content_ids`] instead. Iteration
+/// stops after the first error.
+pub fn parse_reader_with_config<R: BufRead>(reader: R, config: &ParserConfig) -> CardReader<R> {
+    CardReader {
+        lines: reader.lines(),
+        parser: Parser::new(*config),
+        line_num: 0,
+        done: false,
     }
+}
 
-    let mut cards = Vec::new();
-    let mut seen_ids = HashSet::new();
-    let mut parser = Parser::new();
+/// Streaming parser returned by [`parse_reader`].
+pub struct CardReader<R> {
+    lines: Lines<R>,
+    parser: Parser,
+    line_num: usize,
+    done: bool,
+}
 
-    for (idx, line) in content.lines().enumerate() {
-        let line_num = idx + 1;
-        parser.process_line(line, line_num)?;
+impl<R> CardReader<R> {
+    /// Warnings for the cards yielded so far.
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.parser.warnings
     }
+}
 
-    parser.finalize(&mut cards, &mut seen_ids)?;
-    Ok(cards)
+impl<R: BufRead> Iterator for CardReader<R> {
+    type Item = Result<RawCard>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(card) = self.parser.cards.pop_front() {
+                return Some(Ok(card));
+            }
+            if self.done {
+                return None;
+            }
+
+            let step = match self.lines.next() {
+                Some(Ok(line)) => {
+                    self.line_num += 1;
+                    self.parser.process_line(&line, self.line_num)
+                }
+                Some(Err(source)) => Err(ParseError::Read {
+                    line: self.line_num + 1,
+                    source,
+                }),
+                None => {
+                    self.done = true;
+                    self.parser.finalize()
+                }
+            };
+            if let Err(e) = step {
+                self.done = true;
+                return Some(Err(e));
+            }
+        }
+    }
+}
+
+fn parse_collecting_warnings(
+    content: &str,
+    config: &ParserConfig,
+) -> Result<(Vec<RawCard>, Vec<ParseWarning>)> {
+    let mut reader = parse_reader_with_config(content.as_bytes(), config);
+    let mut cards = reader.by_ref().collect::<Result<Vec<_>>>()?;
+    if config.id_strategy == IdStrategy::ContentHash {
+        assign_content_ids(&mut cards, |_, _| false);
+    }
+    Ok((cards, reader.parser.warnings))
+}
+
+/// Largest content-hash ID: the frontend holds card IDs as JS numbers,
+/// which are exact only up to `Number.MAX_SAFE_INTEGER` (2^53 - 1).
+pub const MAX_CONTENT_ID: i64 = (1 << 53) - 1;
+
+/// Stable ID derived from a card's question.
+///
+/// Uses 64-bit FNV-1a (stable across platforms and Rust versions) truncated
+/// to a positive ID no larger than [`MAX_CONTENT_ID`].
+pub fn content_hash_id(question: &str) -> i64 {
+    match (fnv1a(question.bytes()) & MAX_CONTENT_ID as u64) as i64 {
+        0 => 1,
+        id => id,
+    }
+}
+
+/// Give every card without an ID its content-hash ID.
+///
+/// `taken(id, card)` reports whether `id` already belongs to a different
+/// card (e.g. in a database). Taken IDs, and IDs already used in `cards`,
+/// are skipped by probing the following IDs.
+pub fn assign_content_ids<F>(cards: &mut [RawCard], mut taken: F)
+where
+    F: FnMut(i64, &RawCard) -> bool,
+{
+    let mut used: HashSet<i64> = cards.iter().filter_map(|c| c.id).collect();
+
+    for card in cards.iter_mut().filter(|c| c.id.is_none()) {
+        let mut id = content_hash_id(&card.question);
+        while used.contains(&id) || taken(id, card) {
+            id = if id >= MAX_CONTENT_ID { 1 } else { id + 1 };
+        }
+        used.insert(id);
+        card.id = Some(id);
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -45,71 +261,198 @@ struct CardBuilder {
     id: Option<i64>,
     question: Option<String>,
     answer: Option<String>,
+    meta: CardMeta,
     start_line: usize,
+    /// Snippets of the line that started the card and of its `Q:` line.
+    start_snippet: String,
+    question_snippet: Option<String>,
 }
 
 impl CardBuilder {
-    fn new(start_line: usize) -> Self {
+    fn new(start_line: usize, line: &str) -> Self {
         Self {
             id: None,
             question: None,
             answer: None,
+            meta: CardMeta::default(),
             start_line,
+            start_snippet: snippet(line),
+            question_snippet: None,
         }
     }
 
-    fn build(self) -> Result<RawCard> {
-        let question = self.question.ok_or(ParseError::MissingQuestion {
+    fn build(self, config: &ParserConfig) -> Result<RawCard> {
+        let question = self.question.ok_or_else(|| ParseError::MissingQuestion {
             line: self.start_line,
+            snippet: Some(self.start_snippet.clone()),
         })?;
-        let answer = self.answer.ok_or(ParseError::MissingAnswer {
+        let answer = self.answer.ok_or_else(|| ParseError::MissingAnswer {
             line: self.start_line,
+            snippet: Some(
+                self.question_snippet
+                    .clone()
+                    .unwrap_or_else(|| self.start_snippet.clone()),
+            ),
         })?;
 
+        let question = clean_field(&question, config);
+        let answer = clean_field(&answer, config);
+
+        let mut media = extract_media_refs(&question);
+        for media_ref in extract_media_refs(&answer) {
+            if !media.contains(&media_ref) {
+                media.push(media_ref);
+            }
+        }
+
         Ok(RawCard {
             id: self.id,
-            question: question.trim().to_string(),
-            answer: answer.trim().to_string(),
+            question,
+            answer,
             line_number: self.start_line,
+            media,
+            meta: self.meta,
         })
     }
 }
 
+/// Longest line snippet attached to a [`ParseError`], in characters.
+const SNIPPET_MAX_CHARS: usize = 80;
+
+/// Trimmed line text for error context, shortened to [`SNIPPET_MAX_CHARS`].
+fn snippet(line: &str) -> String {
+    let line = line.trim();
+    match line.char_indices().nth(SNIPPET_MAX_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+/// Parse the `key=value` pairs of a `META:` line into `meta`.
+fn parse_meta(text: &str, line: usize, line_text: &str, meta: &mut CardMeta) -> Result<()> {
+    let invalid = |message: String| ParseError::InvalidMeta {
+        line,
+        message,
+        snippet: Some(snippet(line_text)),
+    };
+
+    for pair in text.split_whitespace() {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| invalid(format!("expected key=value, got '{}'", pair)))?;
+        match key {
+            "suspended" => {
+                meta.suspended = value.parse().map_err(|_| {
+                    invalid(format!("suspended must be true or false, got '{}'", value))
+                })?;
+            }
+            "ease" => {
+                let ease: f64 = value
+                    .parse()
+                    .map_err(|_| invalid(format!("ease must be a number, got '{}'", value)))?;
+                if !CardMeta::EASE_RANGE.contains(&ease) {
+                    return Err(invalid(format!(
+                        "ease must be between {} and {}, got {}",
+                        CardMeta::EASE_RANGE.start(),
+                        CardMeta::EASE_RANGE.end(),
+                        ease
+                    )));
+                }
+                meta.ease = Some(ease);
+            }
+            "tags" => {
+                for tag in CardMeta::parse_tags(value) {
+                    if !meta.tags.contains(&tag) {
+                        meta.tags.push(tag);
+                    }
+                }
+            }
+            _ => meta.unknown_keys.push(key.to_string()),
+        }
+    }
+    Ok(())
+}
+
+/// Trim a field, or with `preserve_whitespace` only strip the blank lines
+/// around a multi-line field.
+fn clean_field(text: &str, config: &ParserConfig) -> String {
+    if !config.preserve_whitespace || !text.contains('\n') {
+        return text.trim().to_string();
+    }
+
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines
+        .iter()
+        .position(|l| !l.trim().is_empty())
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(start, |i| i + 1);
+    lines[start..end].join("\n")
+}
+
 struct Parser {
+    config: ParserConfig,
     current: Option<CardBuilder>,
     current_field: Option<Field>,
     buffer: Vec<String>,
+    /// Finished cards not yet handed out by [`CardReader`].
+    cards: VecDeque<RawCard>,
+    warnings: Vec<ParseWarning>,
+    seen_ids: HashSet<i64>,
 }
 
 impl Parser {
-    fn new() -> Self {
+    fn new(config: ParserConfig) -> Self {
         Self {
+            config,
             current: None,
             current_field: None,
             buffer: Vec::new(),
+            cards: VecDeque::new(),
+            warnings: Vec::new(),
+            seen_ids: HashSet::new(),
         }
     }
 
     fn process_line(&mut self, line: &str, line_num: usize) -> Result<()> {
-        match Self::parse_line(line) {
-            LineType::Id(id_str) => self.handle_id(id_str, line_num)?,
-            LineType::Question(text) => self.handle_question(text, line_num),
+        let separator = self.config.card_separator;
+        let in_answer = separator.is_some() && self.current_field == Some(Field::Answer);
+
+        match Self::parse_line(line, separator) {
+            LineType::Separator => self.handle_separator()?,
+            LineType::Empty => self.buffer.push(String::new()),
+            LineType::Escaped(indent, text) => self.buffer.push(format!("{indent}{text}")),
+            // Only a separator ends an answer
+            _ if in_answer => self.buffer.push(line.to_string()),
+            LineType::Id(id_str) => self.handle_id(id_str, line_num, line)?,
+            LineType::Question(text) => self.handle_question(text, line_num, line)?,
             LineType::Answer(text) => self.handle_answer(text),
+            LineType::Meta(text) => self.handle_meta(text, line_num, line)?,
             LineType::Text(text) => self.buffer.push(text.to_string()),
-            LineType::Empty => self.buffer.push(String::new()),
         }
         Ok(())
     }
 
-    fn parse_line(line: &str) -> LineType<'_> {
+    fn parse_line(line: &str, separator: Option<CardSeparator>) -> LineType<'_> {
         let trimmed = line.trim();
 
-        if let Some(rest) = trimmed.strip_prefix("ID:") {
+        // Blockquote lines are field text kept verbatim, even `> Q: ...`
+        if trimmed.starts_with('>') {
+            LineType::Text(line)
+        } else if let Some((indent, rest)) = escaped_field_prefix(line) {
+            LineType::Escaped(indent, rest)
+        } else if separator.is_some_and(|s| s.matches(trimmed)) {
+            LineType::Separator
+        } else if let Some(rest) = trimmed.strip_prefix("ID:") {
             LineType::Id(rest.trim())
         } else if let Some(rest) = trimmed.strip_prefix("Q:") {
             LineType::Question(rest.trim())
         } else if let Some(rest) = trimmed.strip_prefix("A:") {
             LineType::Answer(rest.trim())
+        } else if let Some(rest) = trimmed.strip_prefix("META:") {
+            LineType::Meta(rest.trim())
         } else if trimmed.is_empty() {
             LineType::Empty
         } else {
@@ -117,7 +460,7 @@ impl Parser {
         }
     }
 
-    fn handle_id(&mut self, id_str: &str, line_num: usize) -> Result<()> {
+    fn handle_id(&mut self, id_str: &str, line_num: usize, line: &str) -> Result<()> {
         self.flush_buffer();
 
         let id = id_str
@@ -125,29 +468,50 @@ impl Parser {
             .map_err(|_| ParseError::InvalidId {
                 line: line_num,
                 value: id_str.to_string(),
+                snippet: Some(snippet(line)),
             })?;
 
-        // Start new card with ID
-        if self.current.is_none() {
-            self.current = Some(CardBuilder::new(line_num));
-        }
+        // A META line written before the ID line belongs to this card
+        let leading_meta = match self.current.take() {
+            Some(card) if card.id.is_none() && card.question.is_none() => card.meta,
+            card => {
+                self.current = card;
+                CardMeta::default()
+            }
+        };
+
+        // An ID always starts a new card, closing any card in progress
+        self.finish_card()?;
+        self.current = Some(CardBuilder::new(line_num, line));
         if let Some(ref mut card) = self.current {
             card.id = Some(id);
+            card.meta = leading_meta;
         }
         self.current_field = None;
         Ok(())
     }
 
-    fn handle_question(&mut self, text: &str, line_num: usize) {
+    fn handle_question(&mut self, text: &str, line_num: usize, line: &str) -> Result<()> {
         self.flush_buffer();
 
-        // If no current card, start one (card without ID)
-        if self.current.is_none() {
-            self.current = Some(CardBuilder::new(line_num));
+        // A second Q: closes the previous card (cards without IDs)
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|card| card.question.is_some())
+        {
+            self.finish_card()?;
         }
 
+        // If no current card, start one (card without ID)
+        let card = self
+            .current
+            .get_or_insert_with(|| CardBuilder::new(line_num, line));
+        card.question_snippet = Some(snippet(line));
+
         self.current_field = Some(Field::Question);
         self.buffer.push(text.to_string());
+        Ok(())
     }
 
     fn handle_answer(&mut self, text: &str) {
@@ -156,6 +520,32 @@ impl Parser {
         self.buffer.push(text.to_string());
     }
 
+    fn handle_meta(&mut self, text: &str, line_num: usize, line: &str) -> Result<()> {
+        let after_blank = self.buffer.last().is_some_and(|l| l.trim().is_empty());
+        self.flush_buffer();
+
+        // Set apart from a finished answer, META belongs to the next card
+        if after_blank && self.current.as_ref().is_some_and(|card| card.answer.is_some()) {
+            self.finish_card()?;
+        }
+
+        // META before Q: starts the card, like a card without an ID
+        let card = self
+            .current
+            .get_or_insert_with(|| CardBuilder::new(line_num, line));
+        parse_meta(text, line_num, line, &mut card.meta)?;
+
+        // Text after META is not part of the previous field
+        self.current_field = None;
+        Ok(())
+    }
+
+    fn handle_separator(&mut self) -> Result<()> {
+        self.flush_buffer();
+        // Separators between cards (or before the first) are harmless
+        self.finish_card()
+    }
+
     fn flush_buffer(&mut self) {
         if self.buffer.is_empty() {
             return;
@@ -173,48 +563,185 @@ impl Parser {
         }
     }
 
-    fn finalize(mut self, cards: &mut Vec<RawCard>, seen_ids: &mut HashSet<i64>) -> Result<()> {
-        self.flush_buffer();
+    fn finish_card(&mut self) -> Result<()> {
+        self.current_field = None;
 
-        if let Some(card) = self.current {
-            let raw_card = card.build()?;
+        if let Some(card) = self.current.take() {
+            let start_snippet = card.start_snippet.clone();
+            let question_snippet = card.question_snippet.clone();
+            let raw_card = card.build(&self.config)?;
             if let Some(id) = raw_card.id {
-                if !seen_ids.insert(id) {
+                if !self.seen_ids.insert(id) {
                     return Err(ParseError::DuplicateId {
                         id,
                         line: raw_card.line_number,
+                        snippet: Some(start_snippet),
                     });
                 }
             }
-            cards.push(raw_card);
+            if normalize_whitespace(&raw_card.question) == normalize_whitespace(&raw_card.answer) {
+                let line = raw_card.line_number;
+                let snippet = question_snippet.unwrap_or(start_snippet);
+                if self.config.reject_identical_qa {
+                    return Err(ParseError::IdenticalQuestionAnswer {
+                        line,
+                        snippet: Some(snippet),
+                    });
+                }
+                self.warnings
+                    .push(ParseWarning::IdenticalQuestionAnswer { line, snippet });
+            }
+            self.cards.push_back(raw_card);
         }
 
         Ok(())
     }
+
+    fn finalize(&mut self) -> Result<()> {
+        self.flush_buffer();
+        self.finish_card()
+    }
 }
 
 enum LineType<'a> {
     Id(&'a str),
     Question(&'a str),
     Answer(&'a str),
+    Meta(&'a str),
     Text(&'a str),
+    /// A `\`-escaped field prefix line: its indentation and the text after
+    /// the backslash
+    Escaped(&'a str, &'a str),
+    Separator,
     Empty,
 }
 
+/// Line prefixes that start a field and can be escaped with a backslash.
+const FIELD_PREFIXES: &[&str] = &["ID:", "Q:", "A:", "META:"];
+
+/// Split a `\`-escaped field prefix line into its indentation and the text
+/// after the backslash.
+fn escaped_field_prefix(line: &str) -> Option<(&str, &str)> {
+    let trimmed = line.trim();
+    let rest = trimmed
+        .strip_prefix('\\')
+        .filter(|rest| FIELD_PREFIXES.iter().any(|p| rest.starts_with(p)))?;
+    let indent = &line[..line.len() - line.trim_start().len()];
+    Some((indent, rest))
+}
+
+/// The field text for a line that escapes a field prefix, such as
+/// `\A: note` for the literal text `A: note`. Other lines give `None`.
+pub fn unescape_field_prefix(line: &str) -> Option<String> {
+    escaped_field_prefix(line).map(|(indent, rest)| format!("{indent}{rest}"))
+}
+
+/// File extensions treated as media when referenced with a plain markdown link.
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "mp3", "wav", "ogg", "m4a", "flac", "mp4",
+    "webm", "mov",
+];
+
+/// HTML tags whose `src` attribute points at media.
+const MEDIA_TAGS: &[&str] = &["<img", "<audio", "<video", "<source"];
+
+/// Extract relative media paths referenced in card text.
+///
+/// Recognizes markdown images (`![alt](path)`), markdown links to files with a
+/// media extension (`[text](clip.mp3)`), and `src` attributes of
+/// `<img>`/`<audio>`/`<video>`/`<source>` tags. Absolute URLs (anything with a
+/// scheme such as `https:`) are ignored. Duplicates are removed, keeping the
+/// first occurrence.
+pub fn extract_media_refs(text: &str) -> Vec<String> {
+    let mut refs = Vec::new();
+    let mut push = |target: &str, is_image: bool| {
+        let path = target.trim();
+        // Drop an optional markdown title: (path "title")
+        let path = path.split_whitespace().next().unwrap_or("");
+        let path = path.trim_start_matches('<').trim_end_matches('>');
+        if path.is_empty() || path.contains("://") || path.starts_with("data:") {
+            return;
+        }
+        if !is_image && !has_media_extension(path) {
+            return;
+        }
+        if !refs.iter().any(|r: &String| r == path) {
+            refs.push(path.to_string());
+        }
+    };
+
+    // Markdown images and links
+    let mut rest = text;
+    while let Some(open) = rest.find('[') {
+        let is_image = rest[..open].ends_with('!');
+        let after_open = &rest[open + 1..];
+        let Some(close) = after_open.find(']') else {
+            break;
+        };
+        let after_close = &after_open[close + 1..];
+        if let Some(target) = after_close.strip_prefix('(') {
+            if let Some(end) = target.find(')') {
+                push(&target[..end], is_image);
+                rest = &target[end + 1..];
+                continue;
+            }
+        }
+        rest = after_open;
+    }
+
+    // HTML media tags
+    let lower = text.to_ascii_lowercase();
+    for tag in MEDIA_TAGS {
+        let mut offset = 0;
+        while let Some(start) = lower[offset..].find(tag) {
+            let tag_start = offset + start;
+            let tag_end = lower[tag_start..]
+                .find('>')
+                .map_or(lower.len(), |end| tag_start + end);
+            if let Some(src) = html_src(&text[tag_start..tag_end], &lower[tag_start..tag_end]) {
+                push(src, true);
+            }
+            offset = tag_end;
+        }
+    }
+
+    refs
+}
+
+fn has_media_extension(path: &str) -> bool {
+    path.rsplit_once('.')
+        .map(|(_, ext)| MEDIA_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+fn html_src<'a>(tag: &'a str, tag_lower: &str) -> Option<&'a str> {
+    let start = tag_lower.find("src=")? + "src=".len();
+    let value = &tag[start..];
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+    value.find(quote).map(|end| &value[..end])
+}
+
 /// Inject IDs into markdown content for cards that don't have them.
 /// Returns the updated content with IDs inserted.
+///
+/// Assignments for a card that already has an `ID:` line are skipped, so
+/// running this again on its own output changes nothing.
 pub fn inject_ids(content: &str, id_assignments: &[(usize, i64)]) -> String {
     if id_assignments.is_empty() {
         return content.to_string();
     }
 
     let mut assignments: std::collections::HashMap<usize, i64> = id_assignments.iter().copied().collect();
+    let lines: Vec<&str> = content.lines().collect();
     let mut result = Vec::new();
 
-    for (idx, line) in content.lines().enumerate() {
+    for (idx, line) in lines.iter().enumerate() {
         let line_num = idx + 1;
         if let Some(id) = assignments.remove(&line_num) {
-            result.push(format!("ID: {}", id));
+            if !has_adjacent_id(&lines, idx) {
+                result.push(format!("ID: {}", id));
+            }
         }
         result.push(line.to_string());
     }
@@ -222,6 +749,16 @@ pub fn inject_ids(content: &str, id_assignments: &[(usize, i64)]) -> String {
     result.join("\n")
 }
 
+/// Whether the card starting at `lines[idx]` already has an `ID:` line,
+/// looking past `META:` lines on either side.
+fn has_adjacent_id(lines: &[&str], idx: usize) -> bool {
+    let is_meta = |line: &&&str| line.trim().starts_with("META:");
+    let is_id = |line: &&str| line.trim().starts_with("ID:");
+    let before = lines[..idx].iter().rev().find(|line| !is_meta(line));
+    let after = lines[idx..].iter().find(|line| !is_meta(line));
+    before.is_some_and(is_id) || after.is_some_and(is_id)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -251,6 +788,124 @@ mod tests {
         assert_eq!(cards[0].answer, "Line 1\nLine 2\n\nLine 4");
     }
 
+    #[test]
+    fn preserve_whitespace_keeps_code_indentation() {
+        let input = "Q: Print?\nA:\n    println!(\"hi\");\n\nQ: Next\nA: Done";
+        let config = ParserConfig {
+            preserve_whitespace: true,
+            ..ParserConfig::default()
+        };
+        let cards = parse_with_config(input, &config).unwrap();
+        assert_eq!(cards[0].answer, "    println!(\"hi\");");
+        assert_eq!(cards[1].answer, "Done");
+    }
+
+    #[test]
+    fn preserve_whitespace_keeps_inner_lines() {
+        let input = "Q: Loop\nA: for x in xs {\n    body(x);\n}  \n";
+        let config = ParserConfig {
+            preserve_whitespace: true,
+            ..ParserConfig::default()
+        };
+        let cards = parse_with_config(input, &config).unwrap();
+        assert_eq!(cards[0].answer, "for x in xs {\n    body(x);\n}  ");
+    }
+
+    fn separator_config(separator: CardSeparator) -> ParserConfig {
+        ParserConfig {
+            card_separator: Some(separator),
+            ..ParserConfig::default()
+        }
+    }
+
+    #[test]
+    fn separator_keeps_field_prefixes_in_answer() {
+        let input = "ID: 1\nQ: Where is ownership?\nA: In the book.\nID: see chapter 3\nQ: and 4\n\
+                     ---\nID: 2\nQ: Next\nA: Done";
+        let cards = parse_with_config(input, &separator_config(CardSeparator::Dashes)).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(cards[0].answer, "In the book.\nID: see chapter 3\nQ: and 4");
+        assert_eq!(cards[1].id, Some(2));
+        assert_eq!(cards[1].answer, "Done");
+
+        // Without separators the same line starts a card
+        let input = "ID: 1\nQ: Where?\nA: In the book.\nID: see chapter 3";
+        assert!(matches!(
+            parse(input),
+            Err(ParseError::InvalidId { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn separator_lines_end_cards() {
+        let input = "===\nQ: One\nA: First\n\n===\nQ: Two\nA: Second\n---\n===\n";
+        let cards = parse_with_config(input, &separator_config(CardSeparator::Equals)).unwrap();
+        assert_eq!(cards.len(), 2);
+        // The other marker is plain answer text
+        assert_eq!(cards[1].answer, "Second\n---");
+
+        // A separator before the answer leaves the card incomplete
+        let input = "Q: One\n===\nA: First";
+        let result = parse_with_config(input, &separator_config(CardSeparator::Equals));
+        assert!(matches!(result, Err(ParseError::MissingAnswer { line: 1, .. })));
+    }
+
+    #[test]
+    fn default_config_trims_code_answer() {
+        let input = "Q: Print?\nA:\n    println!(\"hi\");\n";
+        let cards = parse(input).unwrap();
+        assert_eq!(cards[0].answer, "println!(\"hi\");");
+    }
+
+    #[test]
+    fn blockquote_answer_keeps_markers() {
+        let input = "Q: Who said it?\nA:\n> To be, or not to be,\n>   that is the question.\n>\n> Hamlet\n";
+        let cards = parse(input).unwrap();
+        assert_eq!(
+            cards[0].answer,
+            "> To be, or not to be,\n>   that is the question.\n>\n> Hamlet"
+        );
+    }
+
+    #[test]
+    fn blockquote_lines_are_not_field_prefixes() {
+        let input = "Q: Quote\nA: > Q: not a question\n> A: not an answer\n> ID: 5\n> META: suspended=true";
+        let cards = parse(input).unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].id, None);
+        assert_eq!(cards[0].question, "Quote");
+        assert_eq!(
+            cards[0].answer,
+            "> Q: not a question\n> A: not an answer\n> ID: 5\n> META: suspended=true"
+        );
+        assert!(!cards[0].meta.suspended);
+    }
+
+    #[test]
+    fn escaped_prefixes_are_field_text() {
+        let input = "Q: Which prefix?\n\\Q: literal\nA: The answer\n\\A: note\n  \\ID: 5\n\\META: suspended=true";
+        let cards = parse(input).unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].id, None);
+        assert_eq!(cards[0].question, "Which prefix?\nQ: literal");
+        assert_eq!(
+            cards[0].answer,
+            "The answer\nA: note\n  ID: 5\nMETA: suspended=true"
+        );
+        assert!(!cards[0].meta.suspended);
+
+        // Other backslashes are left alone
+        let cards = parse("Q: Path?\nA:\n\\n is a newline").unwrap();
+        assert_eq!(cards[0].answer, "\\n is a newline");
+    }
+
+    #[test]
+    fn unescape_field_prefix_strips_backslash() {
+        assert_eq!(unescape_field_prefix("  \\A: note").as_deref(), Some("  A: note"));
+        assert_eq!(unescape_field_prefix("\\n is a newline"), None);
+        assert_eq!(unescape_field_prefix("A: answer"), None);
+    }
+
     #[test]
     fn parse_multiple_cards() {
         let input = "ID: 1\nQ: Q1\nA: A1\n\nID: 2\nQ: Q2\nA: A2";
@@ -290,16 +945,277 @@ mod tests {
         assert!(matches!(result, Err(ParseError::MissingAnswer { .. })));
     }
 
+    #[test]
+    fn missing_answer_error_shows_question_line() {
+        let input = "ID: 7\nQ:   What is ownership?  \n\nID: 8\nQ: Next\nA: Done";
+        let err = parse(input).unwrap_err();
+        assert!(matches!(err, ParseError::MissingAnswer { line: 1, .. }));
+        assert_eq!(err.snippet(), Some("Q:   What is ownership?"));
+        assert_eq!(
+            err.to_string(),
+            "missing answer at line 1 (\"Q:   What is ownership?\")"
+        );
+
+        let err = parse("ID: 1\nA: Answer only").unwrap_err();
+        assert_eq!(err.snippet(), Some("ID: 1"));
+    }
+
+    #[test]
+    fn error_snippets_are_shortened() {
+        let long_id = format!("ID: {}", "x".repeat(200));
+        let err = parse(&long_id).unwrap_err();
+        let snippet = err.snippet().unwrap();
+        assert_eq!(snippet.chars().count(), SNIPPET_MAX_CHARS + 1);
+        assert!(snippet.starts_with("ID: xxx") && snippet.ends_with('…'));
+
+        let err = parse("ID: 1\nQ: Q1\nA: A1\n\nID: 1\nQ: Q2\nA: A2").unwrap_err();
+        assert_eq!(err.snippet(), Some("ID: 1"));
+    }
+
+    #[test]
+    fn parse_meta_hints() {
+        let input = "ID: 1\nQ: Q1\nA: A1\nMETA: suspended=true ease=2.1\n\nID: 2\nQ: Q2\nA: A2";
+        let cards = parse(input).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert!(cards[0].meta.suspended);
+        assert_eq!(cards[0].meta.ease, Some(2.1));
+        assert_eq!(cards[0].answer, "A1");
+        assert!(cards[1].meta.is_empty());
+    }
+
+    #[test]
+    fn meta_before_question_starts_card() {
+        let cards = parse("META: suspended=true\nQ: Q1\nA: A1").unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].line_number, 1);
+        assert!(cards[0].meta.suspended);
+
+        // An ID inserted between META and Q: keeps the hints
+        let cards = parse("META: ease=1.5\nID: 7\nQ: Q1\nA: A1").unwrap();
+        assert_eq!(cards.len(), 1);
+        assert_eq!(cards[0].id, Some(7));
+        assert_eq!(cards[0].meta.ease, Some(1.5));
+    }
+
+    #[test]
+    fn meta_between_cards_starts_next_card() {
+        let cards = parse("Q: Q1\nA: A1\n\nMETA: suspended=true\nQ: Q2\nA: A2").unwrap();
+        assert_eq!(cards.len(), 2);
+        assert!(cards[0].meta.is_empty());
+        assert_eq!(cards[0].answer, "A1");
+        assert!(cards[1].meta.suspended);
+        assert_eq!(cards[1].line_number, 4);
+    }
+
+    #[test]
+    fn parse_meta_tags() {
+        let cards = parse("Q: Q1\nA: A1\nMETA: tags=#Exam,oral,,exam suspended=true").unwrap();
+        assert_eq!(cards[0].meta.tags, vec!["exam", "oral"]);
+        assert!(cards[0].meta.suspended);
+        assert!(!cards[0].meta.is_empty());
+    }
+
+    #[test]
+    fn meta_unknown_keys_are_collected() {
+        let cards = parse("Q: Q1\nA: A1\nMETA: flag=red suspended=false").unwrap();
+        assert_eq!(cards[0].meta.unknown_keys, vec!["flag"]);
+        assert!(!cards[0].meta.suspended);
+    }
+
+    #[test]
+    fn reject_invalid_meta_values() {
+        for meta in ["suspended=yes", "ease=abc", "ease=0.5", "suspended"] {
+            let input = format!("Q: Q1\nA: A1\nMETA: {}", meta);
+            assert!(
+                matches!(parse(&input), Err(ParseError::InvalidMeta { line: 3, .. })),
+                "{meta}"
+            );
+        }
+    }
+
     #[test]
     fn parse_empty_content() {
         let cards = parse("").unwrap();
         assert!(cards.is_empty());
     }
 
+    #[test]
+    fn extract_image_references() {
+        let input = "Q: What does this show?\n![diagram](images/diagram.png)\nA: A <img src=\"cells.jpg\" alt=\"cells\">";
+        let cards = parse(input).unwrap();
+        assert_eq!(cards[0].media, vec!["images/diagram.png", "cells.jpg"]);
+    }
+
+    #[test]
+    fn extract_audio_references() {
+        let input = "Q: Name this bird call [listen](audio/robin.mp3)\nA: Robin <audio src='robin-full.ogg'></audio>";
+        let cards = parse(input).unwrap();
+        assert_eq!(cards[0].media, vec!["audio/robin.mp3", "robin-full.ogg"]);
+    }
+
+    #[test]
+    fn media_ignores_urls_and_plain_links() {
+        let refs = extract_media_refs(
+            "See [docs](notes.md), ![remote](https://example.com/a.png) and ![](a.png \"title\") twice ![](a.png)",
+        );
+        assert_eq!(refs, vec!["a.png"]);
+    }
+
+    #[test]
+    fn card_without_media_has_empty_list() {
+        let cards = parse("Q: Plain [brackets]\nA: (parens)").unwrap();
+        assert!(cards[0].media.is_empty());
+    }
+
+    fn content_hash_config() -> ParserConfig {
+        ParserConfig {
+            id_strategy: IdStrategy::ContentHash,
+            ..ParserConfig::default()
+        }
+    }
+
+    #[test]
+    fn content_hash_ids_are_stable() {
+        let input = "Q: What is Rust?\nA: A language\n\nQ: What is Go?\nA: Another";
+        let first = parse_with_config(input, &content_hash_config()).unwrap();
+        let second = parse_with_config(input, &content_hash_config()).unwrap();
+
+        assert!(first.iter().all(|c| c.id.is_some()));
+        assert_eq!(
+            first.iter().map(|c| c.id).collect::<Vec<_>>(),
+            second.iter().map(|c| c.id).collect::<Vec<_>>()
+        );
+        assert_ne!(first[0].id, first[1].id);
+        assert_eq!(first[0].id, Some(content_hash_id("What is Rust?")));
+        assert!(content_hash_id("What is Rust?") > 0);
+    }
+
+    #[test]
+    fn content_hash_ids_are_safe_js_integers() {
+        let input: String = (0..500).map(|i| format!("Q: Question {}\nA: A\n\n", i)).collect();
+        let cards = parse_with_config(&input, &content_hash_config()).unwrap();
+        assert!(cards.iter().all(|c| (1..=MAX_CONTENT_ID).contains(&c.id.unwrap())));
+    }
+
+    #[test]
+    fn content_hash_keeps_explicit_ids() {
+        let input = "ID: 5\nQ: Known\nA: A\n\nQ: New\nA: B";
+        let cards = parse_with_config(input, &content_hash_config()).unwrap();
+        assert_eq!(cards[0].id, Some(5));
+        assert_eq!(cards[1].id, Some(content_hash_id("New")));
+        assert!(parse(input).unwrap()[1].id.is_none());
+    }
+
+    #[test]
+    fn content_hash_probes_past_collisions() {
+        // Identical questions in one file are still distinct cards
+        let input = "Q: Same\nA: One\n\nQ: Same\nA: Two";
+        let cards = parse_with_config(input, &content_hash_config()).unwrap();
+        let base = content_hash_id("Same");
+        assert_eq!(cards[0].id, Some(base));
+        assert_eq!(cards[1].id, Some(base + 1));
+
+        // IDs owned by other cards elsewhere are skipped
+        let mut cards = parse("Q: Same\nA: One").unwrap();
+        assign_content_ids(&mut cards, |id, _| id == base);
+        assert_eq!(cards[0].id, Some(base + 1));
+    }
+
+    #[test]
+    fn identical_question_and_answer_warns() {
+        let input = "Q: Write   the word\nA: Write the word\n\nQ: Mnemonic\nA: Mnemonic device";
+        let (cards, warnings) = parse_lenient(input, &ParserConfig::default()).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert_eq!(
+            warnings,
+            vec![ParseWarning::IdenticalQuestionAnswer {
+                line: 1,
+                snippet: "Q: Write   the word".to_string(),
+            }]
+        );
+
+        // Strict parsing only rejects when asked to
+        assert!(parse(input).is_ok());
+        let strict = ParserConfig {
+            reject_identical_qa: true,
+            ..ParserConfig::default()
+        };
+        let err = parse_with_config(input, &strict).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::IdenticalQuestionAnswer { line: 1, .. }
+        ));
+        assert_eq!(parse_lenient(input, &strict).unwrap().1.len(), 1);
+    }
+
+    #[test]
+    fn near_identical_question_and_answer_does_not_warn() {
+        let input = "Q: Capital of France?\nA: Capital of France\n\nQ: colour\nA: Colour";
+        let (cards, warnings) = parse_lenient(input, &ParserConfig::default()).unwrap();
+        assert_eq!(cards.len(), 2);
+        assert!(warnings.is_empty());
+    }
+
     #[test]
     fn inject_ids_works() {
         let content = "Q: New card\nA: Answer";
         let result = inject_ids(content, &[(1, 42)]);
         assert!(result.starts_with("ID: 42\n"));
     }
+
+    #[test]
+    fn inject_ids_is_idempotent() {
+        let content = "Q: First\nA: One\n\nMETA: tags=rust\nQ: Second\nA: Two";
+        let assignments = [(1, 42), (4, 43)];
+        let once = inject_ids(content, &assignments);
+        assert_eq!(
+            once,
+            "ID: 42\nQ: First\nA: One\n\nID: 43\nMETA: tags=rust\nQ: Second\nA: Two"
+        );
+
+        // Stale assignments point at the ID line or the line after it
+        assert_eq!(inject_ids(&once, &[(1, 42), (5, 43)]), once);
+        assert_eq!(inject_ids(&once, &[(2, 42), (7, 43)]), once);
+        let ids: Vec<_> = parse(&once).unwrap().iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![Some(42), Some(43)]);
+    }
+
+    #[test]
+    fn streaming_parser_matches_parse_on_large_input() {
+        let mut input = String::new();
+        for i in 0..5_000 {
+            let card = match i % 3 {
+                0 => format!("ID: {}\nQ: Question {}\nA: Answer {}\n\n", i + 1, i, i),
+                1 => format!(
+                    "Q: Card {}?\nMETA: tags=bulk\nA: Line one\n  ![](img{}.png)\n\nLine three\n\n",
+                    i, i
+                ),
+                _ => format!("Q: Quote {}\n> Q: not a card\nA: Done\n", i),
+            };
+            input.push_str(&card);
+        }
+
+        let expected = parse(&input).unwrap();
+        // A tiny buffer splits lines across reads
+        let reader = std::io::BufReader::with_capacity(7, input.as_bytes());
+        let streamed = parse_reader(reader).collect::<Result<Vec<_>>>().unwrap();
+
+        assert_eq!(streamed.len(), 5_000);
+        assert_eq!(
+            serde_json::to_value(&streamed).unwrap(),
+            serde_json::to_value(&expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn streaming_parser_yields_cards_before_an_error() {
+        let input: &[u8] = b"Q: One\nA: First\n\nQ: Two\nA: caf\xe9\n";
+        let mut reader = parse_reader(input);
+        assert_eq!(reader.next().unwrap().unwrap().question, "One");
+        assert!(matches!(
+            reader.next().unwrap(),
+            Err(ParseError::Read { line: 5, .. })
+        ));
+        assert!(reader.next().is_none());
+    }
 }
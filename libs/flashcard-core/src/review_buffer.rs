@@ -0,0 +1,184 @@
+//! Offline buffer for reviews waiting to be pushed to the backend.
+//!
+//! Mirrors the desktop's `pending_reviews` table for clients without a local
+//! database: reviews are queued while offline, handed out in batches when a
+//! connection is available, and removed once the backend confirms them.
+
+use crate::types::ReviewSubmission;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// A queued review with its buffer-local ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BufferedReview {
+    pub id: u64,
+    pub review: ReviewSubmission,
+}
+
+/// Queue of reviews that have not been synced yet.
+///
+/// The buffer serializes to a plain list so it can be persisted anywhere
+/// (local storage, a file). Reviews handed out by [`ReviewBuffer::drain_for_sync`]
+/// are held back from later drains until they are confirmed with
+/// [`ReviewBuffer::mark_synced`] or returned with [`ReviewBuffer::release`].
+/// In-flight tracking is not persisted, so after a reload every unconfirmed
+/// review is pending again.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReviewBuffer {
+    next_id: u64,
+    pending: Vec<BufferedReview>,
+    #[serde(skip)]
+    in_flight: HashSet<u64>,
+}
+
+impl ReviewBuffer {
+    /// Create an empty buffer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue a review and return its buffer ID.
+    pub fn push(&mut self, review: ReviewSubmission) -> u64 {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.pending.push(BufferedReview { id, review });
+        id
+    }
+
+    /// Take every queued review that is not already being synced.
+    ///
+    /// Returned reviews stay in the buffer until [`ReviewBuffer::mark_synced`]
+    /// so a failed push loses nothing.
+    pub fn drain_for_sync(&mut self) -> Vec<BufferedReview> {
+        let batch: Vec<BufferedReview> = self
+            .pending
+            .iter()
+            .filter(|r| !self.in_flight.contains(&r.id))
+            .cloned()
+            .collect();
+        self.in_flight.extend(batch.iter().map(|r| r.id));
+        batch
+    }
+
+    /// Remove reviews the backend has accepted.
+    pub fn mark_synced(&mut self, ids: &[u64]) {
+        let ids: HashSet<u64> = ids.iter().copied().collect();
+        self.pending.retain(|r| !ids.contains(&r.id));
+        self.in_flight.retain(|id| !ids.contains(id));
+    }
+
+    /// Return in-flight reviews to the queue after a failed push.
+    pub fn release(&mut self, ids: &[u64]) {
+        for id in ids {
+            self.in_flight.remove(id);
+        }
+    }
+
+    /// Number of reviews not yet confirmed by the backend.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Whether every review has been synced.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn review(card_id: i64) -> ReviewSubmission {
+        ReviewSubmission {
+            card_id,
+            reviewed_at: Utc::now(),
+            rating: 3,
+            rating_scale: "4point".to_string(),
+            answer_mode: "flip".to_string(),
+            typed_answer: None,
+            was_correct: None,
+            time_taken_ms: Some(1500),
+            interval_before: 0.0,
+            interval_after: 1.0,
+            ease_before: 2.5,
+            ease_after: 2.5,
+            algorithm: "sm2".to_string(),
+            params_hash: None,
+            cram: false,
+        }
+    }
+
+    fn ids(batch: &[BufferedReview]) -> Vec<u64> {
+        batch.iter().map(|r| r.id).collect()
+    }
+
+    #[test]
+    fn test_offline_period_then_reconnect() {
+        let mut buffer = ReviewBuffer::new();
+
+        // Offline: reviews accumulate and survive a reload
+        buffer.push(review(1));
+        buffer.push(review(2));
+        let json = serde_json::to_string(&buffer).unwrap();
+        let mut buffer: ReviewBuffer = serde_json::from_str(&json).unwrap();
+        buffer.push(review(3));
+        assert_eq!(buffer.len(), 3);
+
+        // Reconnect: everything is drained once and confirmed
+        let batch = buffer.drain_for_sync();
+        let cards: Vec<i64> = batch.iter().map(|r| r.review.card_id).collect();
+        assert_eq!(cards, vec![1, 2, 3]);
+        assert!(buffer.drain_for_sync().is_empty());
+
+        buffer.mark_synced(&ids(&batch));
+        assert!(buffer.is_empty());
+        assert!(buffer.drain_for_sync().is_empty());
+    }
+
+    #[test]
+    fn test_reviews_pushed_during_sync_are_not_duplicated() {
+        let mut buffer = ReviewBuffer::new();
+        buffer.push(review(1));
+
+        let first = buffer.drain_for_sync();
+        buffer.push(review(2));
+        let second = buffer.drain_for_sync();
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].review.card_id, 2);
+
+        buffer.mark_synced(&ids(&first));
+        buffer.mark_synced(&ids(&second));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_failed_push_is_retried() {
+        let mut buffer = ReviewBuffer::new();
+        buffer.push(review(1));
+        buffer.push(review(2));
+
+        let batch = buffer.drain_for_sync();
+        buffer.release(&ids(&batch));
+
+        let retry = buffer.drain_for_sync();
+        assert_eq!(ids(&retry), ids(&batch));
+        buffer.mark_synced(&ids(&retry));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn test_ids_stay_unique_after_reload() {
+        let mut buffer = ReviewBuffer::new();
+        let first = buffer.push(review(1));
+        buffer.mark_synced(&[first]);
+
+        let json = serde_json::to_string(&buffer).unwrap();
+        let mut buffer: ReviewBuffer = serde_json::from_str(&json).unwrap();
+        let second = buffer.push(review(2));
+        assert_ne!(first, second);
+    }
+}
@@ -1,7 +1,8 @@
 //! Core types for flashcard application.
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
+use std::str::FromStr;
 
 /// Card learning status.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -19,6 +20,18 @@ impl Default for CardStatus {
     }
 }
 
+impl CardStatus {
+    /// Get the status name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::New => "new",
+            Self::Learning => "learning",
+            Self::Review => "review",
+            Self::Relearning => "relearning",
+        }
+    }
+}
+
 /// Rating for a review.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -72,6 +85,10 @@ pub struct CardState {
     pub reviews_count: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub due_date: Option<DateTime<Utc>>,
+    /// Consecutive passing ratings while New or Learning, counted towards
+    /// the graduation requirement. Reset once the card graduates.
+    #[serde(default)]
+    pub learning_reps: u32,
 }
 
 impl Default for CardState {
@@ -85,6 +102,30 @@ impl Default for CardState {
             lapses: 0,
             reviews_count: 0,
             due_date: None,
+            learning_reps: 0,
+        }
+    }
+}
+
+impl CardState {
+    /// Seed FSRS memory state for a card last scheduled by SM-2, so
+    /// switching algorithms keeps its history instead of restarting it.
+    ///
+    /// This is an approximation: the SM-2 interval is taken as the
+    /// stability (the days until recall drops to 90%), and the ease factor
+    /// maps linearly onto difficulty, with the default 2.5 at the middle of
+    /// the 1-10 scale and the SM-2 minimum of 1.3 at the hardest. Cards
+    /// that already have FSRS state, were never reviewed, or have no
+    /// interval yet are returned unchanged.
+    pub fn migrate_sm2_to_fsrs(&self) -> Self {
+        let has_fsrs_state = self.stability.is_some() && self.difficulty.is_some();
+        if has_fsrs_state || self.reviews_count == 0 || self.interval_days <= 0.0 {
+            return self.clone();
+        }
+        Self {
+            stability: Some(self.interval_days.max(0.1)),
+            difficulty: Some((5.0 + (2.5 - self.ease_factor) * 5.0 / 1.2).clamp(1.0, 10.0)),
+            ..self.clone()
         }
     }
 }
@@ -96,6 +137,64 @@ pub struct RawCard {
     pub question: String,
     pub answer: String,
     pub line_number: usize,
+    /// Relative paths of media (images, audio, video) referenced by the
+    /// question or answer, in order of first appearance.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub media: Vec<String>,
+    /// Scheduling hints from the card's `META:` line.
+    #[serde(default, skip_serializing_if = "CardMeta::is_empty")]
+    pub meta: CardMeta,
+}
+
+/// Optional per-card scheduling hints, e.g. `META: suspended=true ease=2.1`.
+///
+/// Hints only initialize a card's state the first time it is imported.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CardMeta {
+    /// Start the card suspended so it is left out of study queues.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub suspended: bool,
+    /// Initial ease factor instead of the algorithm default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ease: Option<f64>,
+    /// Tags for browsing and studying across decks, normalized with
+    /// [`CardMeta::normalize_tag`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// Keys that were not recognized; callers should warn about them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unknown_keys: Vec<String>,
+}
+
+impl CardMeta {
+    /// Lowest and highest ease accepted in a `META:` line.
+    pub const EASE_RANGE: std::ops::RangeInclusive<f64> = 1.3..=5.0;
+
+    /// Whether no hints were given.
+    pub fn is_empty(&self) -> bool {
+        !self.suspended
+            && self.ease.is_none()
+            && self.tags.is_empty()
+            && self.unknown_keys.is_empty()
+    }
+
+    /// Canonical form of a tag: trimmed, without a leading `#`, lowercase.
+    /// Returns `None` for an empty tag.
+    pub fn normalize_tag(tag: &str) -> Option<String> {
+        let tag = tag.trim().trim_start_matches('#').trim();
+        (!tag.is_empty()).then(|| tag.to_lowercase())
+    }
+
+    /// Parse a comma-separated `tags=` value, dropping empty and repeated tags.
+    pub fn parse_tags(value: &str) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in value.split(',').filter_map(Self::normalize_tag) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
 }
 
 /// Card with assigned ID and metadata.
@@ -108,6 +207,10 @@ pub struct Card {
     pub source_file: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deleted_at: Option<DateTime<Utc>>,
+    /// When the card's content last changed, used to keep the newer copy
+    /// when merging.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<DateTime<Utc>>,
 }
 
 /// Rating scale options.
@@ -126,6 +229,63 @@ impl Default for RatingScale {
     }
 }
 
+impl RatingScale {
+    /// Get the rating scale name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::FourPoint => "4point",
+            Self::TwoPoint => "2point",
+        }
+    }
+
+    /// Ratings to offer, in button order. The button at index `i`
+    /// submits the numeric rating `i + 1` on this scale.
+    pub fn ratings(&self) -> &'static [Rating] {
+        match self {
+            Self::FourPoint => &[Rating::Again, Rating::Hard, Rating::Good, Rating::Easy],
+            Self::TwoPoint => &[Rating::Again, Rating::Good],
+        }
+    }
+
+    /// Number of rating buttons to show.
+    pub fn button_count(&self) -> usize {
+        self.ratings().len()
+    }
+
+    /// Highest numeric rating accepted by this scale.
+    pub fn max_rating(&self) -> u8 {
+        match self {
+            Self::FourPoint => 4,
+            Self::TwoPoint => 2,
+        }
+    }
+
+    /// Convert a numeric rating on this scale, or `None` if out of range.
+    /// 2-point ratings map Wrong (1) -> Again and Correct (2) -> Good.
+    pub fn rating(&self, value: u8) -> Option<Rating> {
+        match self {
+            Self::FourPoint => Rating::from_value(value),
+            Self::TwoPoint => match value {
+                1 => Some(Rating::from_2point(false)),
+                2 => Some(Rating::from_2point(true)),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl FromStr for RatingScale {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4point" => Ok(Self::FourPoint),
+            "2point" => Ok(Self::TwoPoint),
+            _ => Err(format!("unknown rating scale '{}'", s)),
+        }
+    }
+}
+
 /// Answer mode options.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -140,6 +300,16 @@ impl Default for AnswerMode {
     }
 }
 
+impl AnswerMode {
+    /// Get the answer mode name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Flip => "flip",
+            Self::Typed => "typed",
+        }
+    }
+}
+
 /// Matching mode for typed answers.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -147,6 +317,8 @@ pub enum MatchingMode {
     Exact,
     CaseInsensitive,
     Fuzzy,
+    /// Correct when one answer contains the other as a run of whole words.
+    Contains,
 }
 
 impl Default for MatchingMode {
@@ -155,6 +327,394 @@ impl Default for MatchingMode {
     }
 }
 
+impl MatchingMode {
+    /// Get the matching mode name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Exact => "exact",
+            Self::CaseInsensitive => "case_insensitive",
+            Self::Fuzzy => "fuzzy",
+            Self::Contains => "contains",
+        }
+    }
+}
+
+/// Extra answer normalization a deck applies before typed answers are
+/// compared, on top of its matching mode.
+///
+/// A deck stores its rules as a comma-separated spec such as
+/// `diacritics,punctuation,numeric:0.01`; see [`NormRule::parse_spec`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum NormRule {
+    /// Drop accents and other combining marks, and fold compatibility forms
+    /// such as subscript digits, so "résumé" matches "resume" and "H₂O"
+    /// matches "H2O".
+    Diacritics,
+    /// Ignore punctuation anywhere in the answers.
+    Punctuation,
+    /// Ignore whitespace entirely, not just runs of it.
+    Whitespace,
+    /// When both answers are numbers, accept a typed value within this
+    /// relative tolerance of the correct one, e.g. `0.01` for 1%.
+    NumericTolerance(f64),
+    /// Ignore common English filler words like "the" and "of".
+    Stopwords,
+}
+
+impl NormRule {
+    /// Parse a comma-separated rule spec. An empty spec has no rules.
+    pub fn parse_spec(spec: &str) -> Result<Vec<Self>, String> {
+        spec.split(',')
+            .map(str::trim)
+            .filter(|rule| !rule.is_empty())
+            .map(str::parse)
+            .collect()
+    }
+
+    /// Write rules as a spec [`NormRule::parse_spec`] reads back.
+    pub fn format_spec(rules: &[Self]) -> String {
+        rules
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl std::fmt::Display for NormRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Diacritics => f.write_str("diacritics"),
+            Self::Punctuation => f.write_str("punctuation"),
+            Self::Whitespace => f.write_str("whitespace"),
+            Self::NumericTolerance(tolerance) => write!(f, "numeric:{}", tolerance),
+            Self::Stopwords => f.write_str("stopwords"),
+        }
+    }
+}
+
+/// Parses a single rule, as written in a spec.
+impl FromStr for NormRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rule = match s.trim() {
+            "diacritics" => Some(Self::Diacritics),
+            "punctuation" => Some(Self::Punctuation),
+            "whitespace" => Some(Self::Whitespace),
+            "stopwords" => Some(Self::Stopwords),
+            other => other
+                .strip_prefix("numeric:")
+                .and_then(|tolerance| tolerance.trim().parse::<f64>().ok())
+                .filter(|tolerance| tolerance.is_finite() && *tolerance >= 0.0)
+                .map(Self::NumericTolerance),
+        };
+        rule.ok_or_else(|| format!("unknown normalization rule '{}'", s.trim()))
+    }
+}
+
+impl TryFrom<String> for NormRule {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<NormRule> for String {
+    fn from(rule: NormRule) -> Self {
+        rule.to_string()
+    }
+}
+
+/// How cards without an `ID:` line are given an ID.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IdStrategy {
+    /// Leave the ID empty for the database or backend sequence to assign.
+    #[default]
+    Sequence,
+    /// Derive a stable ID from the question text, so the same card gets the
+    /// same ID on every machine without a central sequence.
+    ContentHash,
+}
+
+/// Order in which a study session shows new and due cards.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueOrder {
+    /// Due reviews first, then new cards.
+    DueFirst,
+    /// New cards first, then due reviews.
+    #[default]
+    NewFirst,
+    /// New cards spread evenly among due reviews, in proportion to their
+    /// counts.
+    Interleaved,
+}
+
+impl QueueOrder {
+    /// Get the order name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DueFirst => "due_first",
+            Self::NewFirst => "new_first",
+            Self::Interleaved => "interleaved",
+        }
+    }
+}
+
+impl FromStr for QueueOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "due_first" => Ok(Self::DueFirst),
+            "new_first" => Ok(Self::NewFirst),
+            "interleaved" => Ok(Self::Interleaved),
+            _ => Err(format!("unknown queue order '{}'", s)),
+        }
+    }
+}
+
+/// How a sync settles a card whose pulled state disagrees with unsynced
+/// local progress.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Keep whichever side changed last.
+    LastWriteWins,
+    /// Keep the side with more reviews.
+    MostProgress,
+    /// Pause the sync until the user picks a side for each card.
+    #[default]
+    Manual,
+}
+
+impl ConflictPolicy {
+    /// Get the policy name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LastWriteWins => "last_write_wins",
+            Self::MostProgress => "most_progress",
+            Self::Manual => "manual",
+        }
+    }
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "last_write_wins" => Ok(Self::LastWriteWins),
+            "most_progress" => Ok(Self::MostProgress),
+            "manual" => Ok(Self::Manual),
+            _ => Err(format!("unknown conflict policy '{}'", s)),
+        }
+    }
+}
+
+/// Which side of a card study sessions show as the prompt. Scheduling is
+/// per card whatever the direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StudyDirection {
+    /// Show the question, recall the answer.
+    #[default]
+    Forward,
+    /// Show the answer, recall the question.
+    Reverse,
+    /// Half the cards each way, swapping every study day.
+    Both,
+}
+
+impl StudyDirection {
+    /// Get the direction name as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Forward => "forward",
+            Self::Reverse => "reverse",
+            Self::Both => "both",
+        }
+    }
+
+    /// Whether `card_id` is studied answer first on study day `day`.
+    pub fn reverses(&self, card_id: i64, day: NaiveDate) -> bool {
+        match self {
+            Self::Forward => false,
+            Self::Reverse => true,
+            // Odd when exactly one of them is; adding them could overflow
+            Self::Both => (card_id ^ i64::from(day.num_days_from_ce())) & 1 == 1,
+        }
+    }
+}
+
+impl FromStr for StudyDirection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "forward" => Ok(Self::Forward),
+            "reverse" => Ok(Self::Reverse),
+            "both" => Ok(Self::Both),
+            _ => Err(format!("unknown study direction '{}'", s)),
+        }
+    }
+}
+
+/// Cap on a single study session.
+///
+/// Stored and sent as `none`, `cards:<n>` or `minutes:<n>`. A card cap trims
+/// the queue itself; a time cap is left to the client, which knows how long
+/// the session has run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SessionLimit {
+    /// At most this many cards.
+    Cards(u32),
+    /// Stop once this many minutes have passed.
+    Minutes(u32),
+    #[default]
+    None,
+}
+
+impl SessionLimit {
+    /// Most cards a session may hold, if capped by count.
+    pub fn max_cards(&self) -> Option<usize> {
+        match self {
+            Self::Cards(n) => Some(*n as usize),
+            Self::Minutes(_) | Self::None => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SessionLimit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cards(n) => write!(f, "cards:{}", n),
+            Self::Minutes(n) => write!(f, "minutes:{}", n),
+            Self::None => f.write_str("none"),
+        }
+    }
+}
+
+impl FromStr for SessionLimit {
+    type Err = String;
+
+    /// Parse a limit such as `cards:20`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let limit = match s.split_once(':') {
+            Some(("cards", n)) => n.parse().ok().map(Self::Cards),
+            Some(("minutes", n)) => n.parse().ok().map(Self::Minutes),
+            None if s == "none" => Some(Self::None),
+            _ => None,
+        };
+        limit.ok_or_else(|| format!("unknown session limit '{}'", s))
+    }
+}
+
+impl TryFrom<String> for SessionLimit {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<SessionLimit> for String {
+    fn from(limit: SessionLimit) -> Self {
+        limit.to_string()
+    }
+}
+
+/// Daily cap on new cards or reviews.
+///
+/// Sent as a number or `"unlimited"`, so `0` always means none. Integer
+/// columns store `Unlimited` as a negative number, see [`Limit::to_db`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "LimitRepr", into = "LimitRepr")]
+pub enum Limit {
+    Unlimited,
+    /// At most this many cards, `0` for none.
+    Count(u32),
+}
+
+impl Limit {
+    /// Most cards allowed, `None` when unlimited.
+    pub fn max_cards(&self) -> Option<usize> {
+        match self {
+            Self::Unlimited => None,
+            Self::Count(n) => Some(*n as usize),
+        }
+    }
+
+    /// What is left of the limit once `used` cards have been studied.
+    pub fn remaining(&self, used: usize) -> Self {
+        match self {
+            Self::Unlimited => Self::Unlimited,
+            Self::Count(n) => Self::Count((*n as usize).saturating_sub(used) as u32),
+        }
+    }
+
+    /// Allowance left over once `taken` cards were queued; none when unlimited,
+    /// as every available card was queued.
+    pub fn unused(&self, taken: usize) -> usize {
+        self.max_cards().map_or(0, |max| max.saturating_sub(taken))
+    }
+
+    /// `LIMIT` for a card query; SQL engines take counts up to `i64::MAX`,
+    /// which stands in for no limit.
+    pub fn query_limit(&self) -> i64 {
+        match self {
+            Self::Unlimited => i64::MAX,
+            Self::Count(n) => i64::from(*n),
+        }
+    }
+
+    /// Read a limit from an integer column; negative means unlimited.
+    pub fn from_db(value: i64) -> Self {
+        u32::try_from(value).map_or(Self::Unlimited, Self::Count)
+    }
+
+    /// Value for an integer column, `-1` when unlimited.
+    pub fn to_db(&self) -> i64 {
+        match self {
+            Self::Unlimited => -1,
+            Self::Count(n) => i64::from(*n),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum LimitRepr {
+    Count(u32),
+    Word(String),
+}
+
+impl TryFrom<LimitRepr> for Limit {
+    type Error = String;
+
+    fn try_from(repr: LimitRepr) -> Result<Self, Self::Error> {
+        match repr {
+            LimitRepr::Count(n) => Ok(Self::Count(n)),
+            LimitRepr::Word(w) if w == "unlimited" => Ok(Self::Unlimited),
+            LimitRepr::Word(w) => Err(format!("unknown limit '{}'", w)),
+        }
+    }
+}
+
+impl From<Limit> for LimitRepr {
+    fn from(limit: Limit) -> Self {
+        match limit {
+            Limit::Unlimited => Self::Word("unlimited".to_string()),
+            Limit::Count(n) => Self::Count(n),
+        }
+    }
+}
+
 /// Deck with card counts.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Deck {
@@ -165,6 +725,66 @@ pub struct Deck {
     pub due_count: usize,
 }
 
+/// Lightweight per-deck counts for overview screens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeckSummary {
+    pub path: String,
+    pub new_count: usize,
+    pub due_count: usize,
+    pub total: usize,
+}
+
+/// Deck summaries sorted by path, with grand totals.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeckSummaries {
+    pub decks: Vec<DeckSummary>,
+    pub total_new: usize,
+    pub total_due: usize,
+    pub total_cards: usize,
+}
+
+impl DeckSummaries {
+    /// Sort decks by path and compute grand totals.
+    pub fn from_decks(mut decks: Vec<DeckSummary>) -> Self {
+        decks.sort_by(|a, b| a.path.cmp(&b.path));
+        Self {
+            total_new: decks.iter().map(|d| d.new_count).sum(),
+            total_due: decks.iter().map(|d| d.due_count).sum(),
+            total_cards: decks.iter().map(|d| d.total).sum(),
+            decks,
+        }
+    }
+}
+
+/// A completed review as submitted to the backend's review sync endpoint.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReviewSubmission {
+    pub card_id: i64,
+    pub reviewed_at: DateTime<Utc>,
+    pub rating: i32,
+    pub rating_scale: String,
+    pub answer_mode: String,
+    pub typed_answer: Option<String>,
+    pub was_correct: Option<bool>,
+    pub time_taken_ms: Option<i32>,
+    pub interval_before: f64,
+    pub interval_after: f64,
+    pub ease_before: f64,
+    pub ease_after: f64,
+    pub algorithm: String,
+    /// Fingerprint of the algorithm parameters used, see
+    /// [`SpacedRepetitionAlgorithm::params_hash`](crate::SpacedRepetitionAlgorithm::params_hash).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub params_hash: Option<String>,
+    /// A cram review, which left the card's schedule alone; it doesn't count
+    /// against daily limits or toward retention.
+    #[serde(default)]
+    pub cram: bool,
+}
+
+/// Default and maximum number of cards in a difficult cards session.
+pub const DIFFICULT_CARDS_LIMIT: usize = 50;
+
 /// Study queue containing cards to study.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StudyQueue {
@@ -172,6 +792,59 @@ pub struct StudyQueue {
     pub review_cards: Vec<Card>,
     pub new_remaining: usize,
     pub review_remaining: usize,
+    /// How the session should combine `new_cards` and `review_cards`.
+    #[serde(default)]
+    pub queue_order: QueueOrder,
+    /// Cards to show answer first, per the study direction.
+    #[serde(default)]
+    pub reversed_card_ids: Vec<i64>,
+    /// Cap on this session. Card caps are already applied; the client stops
+    /// a timed session itself.
+    #[serde(default)]
+    pub session_limit: SessionLimit,
+}
+
+impl StudyQueue {
+    /// Keep only the first `max` cards the session would show, following
+    /// `queue_order`. Dropped cards count as remaining.
+    pub fn truncate(&mut self, max: usize) {
+        let (new, due) = (self.new_cards.len(), self.review_cards.len());
+        if new + due <= max {
+            return;
+        }
+        let keep_new = match self.queue_order {
+            QueueOrder::NewFirst => new.min(max),
+            QueueOrder::DueFirst => max.saturating_sub(due),
+            QueueOrder::Interleaved => {
+                // Same spread as the session builds: take a new card
+                // whenever new cards are behind their share
+                let (mut n, mut d) = (0, 0);
+                while n + d < max {
+                    if n < new && (d >= due || n * due <= d * new) {
+                        n += 1;
+                    } else {
+                        d += 1;
+                    }
+                }
+                n
+            }
+        };
+        let keep_due = max - keep_new;
+        self.new_remaining += new - keep_new;
+        self.review_remaining += due - keep_due;
+        self.new_cards.truncate(keep_new);
+        self.review_cards.truncate(keep_due);
+    }
+}
+
+/// Card together with its scheduling state, for detail views.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CardWithState {
+    pub card: Card,
+    /// `None` until the card is first reviewed.
+    pub state: Option<CardState>,
+    /// Current probability of recall, when the state carries FSRS stability.
+    pub retrievability: Option<f64>,
 }
 
 /// Algorithm options.
@@ -214,9 +887,83 @@ pub struct GlobalSettings {
     pub rating_scale: RatingScale,
     pub matching_mode: MatchingMode,
     pub fuzzy_threshold: f64,
-    pub new_cards_per_day: u32,
-    pub reviews_per_day: u32,
+    pub new_cards_per_day: Limit,
+    pub reviews_per_day: Limit,
     pub daily_reset_hour: u32,
+    /// Minutes before a learning card rated Again is shown again.
+    #[serde(default = "default_again_delay_minutes")]
+    pub again_delay_minutes: u32,
+    /// Hours past now within which due cards are shown, even if they fall
+    /// due after the current study day ends.
+    #[serde(default)]
+    pub due_grace_hours: u32,
+    /// Consecutive passing ratings a New or Learning card needs before it
+    /// graduates to Review.
+    #[serde(default = "default_graduation_requirement")]
+    pub graduation_requirement: u32,
+    /// Token that splits file names into deck paths (e.g. `::`), see
+    /// [`crate::deck_path`]. `None` derives decks from directories only.
+    #[serde(default)]
+    pub deck_path_separator: Option<String>,
+    /// Lowest similarity of a correct typed answer for which Easy is
+    /// suggested instead of Good.
+    #[serde(default = "default_suggest_easy_threshold")]
+    pub suggest_easy_threshold: f64,
+    /// Lowest similarity of an incorrect typed answer for which Hard is
+    /// suggested instead of Again.
+    #[serde(default = "default_suggest_hard_threshold")]
+    pub suggest_hard_threshold: f64,
+    /// Whether study sessions show new cards before, after, or mixed in
+    /// with due reviews.
+    #[serde(default)]
+    pub queue_order: QueueOrder,
+    /// Lowest ease an SM-2 card can fall to.
+    #[serde(default = "default_min_ease")]
+    pub min_ease: f64,
+    /// Highest ease an SM-2 card can grow to.
+    #[serde(default = "default_max_ease")]
+    pub max_ease: f64,
+    /// How syncs settle cards studied on more than one device.
+    #[serde(default)]
+    pub sync_conflict_policy: ConflictPolicy,
+    /// Offer no new cards in any deck, whatever its limit, while reviews
+    /// continue.
+    #[serde(default)]
+    pub new_cards_paused: bool,
+    /// Which side of each card study sessions show as the prompt.
+    #[serde(default)]
+    pub study_direction: StudyDirection,
+    /// Default cap on each study session.
+    #[serde(default)]
+    pub session_limit: SessionLimit,
+    /// Typed answers with fewer non-whitespace characters are not graded,
+    /// see [`crate::matching::MatchOptions::min_answer_chars`].
+    #[serde(default)]
+    pub min_answer_chars: u32,
+}
+
+fn default_min_ease() -> f64 {
+    crate::algorithm::DEFAULT_MIN_EASE
+}
+
+fn default_max_ease() -> f64 {
+    crate::algorithm::DEFAULT_MAX_EASE
+}
+
+fn default_again_delay_minutes() -> u32 {
+    crate::algorithm::DEFAULT_AGAIN_DELAY_MINUTES
+}
+
+fn default_suggest_easy_threshold() -> f64 {
+    crate::matching::DEFAULT_SUGGEST_EASY_THRESHOLD
+}
+
+fn default_suggest_hard_threshold() -> f64 {
+    crate::matching::DEFAULT_SUGGEST_HARD_THRESHOLD
+}
+
+fn default_graduation_requirement() -> u32 {
+    crate::algorithm::DEFAULT_GRADUATION_REQUIREMENT
 }
 
 impl Default for GlobalSettings {
@@ -226,10 +973,53 @@ impl Default for GlobalSettings {
             rating_scale: RatingScale::default(),
             matching_mode: MatchingMode::default(),
             fuzzy_threshold: 0.8,
-            new_cards_per_day: 20,
-            reviews_per_day: 200,
+            new_cards_per_day: Limit::Count(20),
+            reviews_per_day: Limit::Count(200),
             daily_reset_hour: 0,
+            again_delay_minutes: default_again_delay_minutes(),
+            due_grace_hours: 0,
+            graduation_requirement: default_graduation_requirement(),
+            deck_path_separator: None,
+            suggest_easy_threshold: default_suggest_easy_threshold(),
+            suggest_hard_threshold: default_suggest_hard_threshold(),
+            queue_order: QueueOrder::default(),
+            min_ease: default_min_ease(),
+            max_ease: default_max_ease(),
+            sync_conflict_policy: ConflictPolicy::default(),
+            new_cards_paused: false,
+            study_direction: StudyDirection::default(),
+            session_limit: SessionLimit::default(),
+            min_answer_chars: 0,
+        }
+    }
+}
+
+impl GlobalSettings {
+    /// Lowest ease floor accepted; below 1.0 a passing review would shrink
+    /// the interval.
+    pub const EASE_LOWER_LIMIT: f64 = 1.0;
+    /// Highest ease cap accepted.
+    pub const EASE_UPPER_LIMIT: f64 = 10.0;
+
+    /// Check an SM-2 ease floor and cap before they are stored.
+    pub fn validate_ease_bounds(min_ease: f64, max_ease: f64) -> Result<(), String> {
+        let limits = Self::EASE_LOWER_LIMIT..=Self::EASE_UPPER_LIMIT;
+        if !limits.contains(&min_ease) || !limits.contains(&max_ease) {
+            return Err(format!(
+                "ease bounds must be between {} and {}, got {} and {}",
+                Self::EASE_LOWER_LIMIT,
+                Self::EASE_UPPER_LIMIT,
+                min_ease,
+                max_ease
+            ));
+        }
+        if min_ease > max_ease {
+            return Err(format!(
+                "minimum ease {} is above maximum ease {}",
+                min_ease, max_ease
+            ));
         }
+        Ok(())
     }
 }
 
@@ -246,12 +1036,31 @@ pub struct DeckSettings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub fuzzy_threshold: Option<f64>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub new_cards_per_day: Option<u32>,
+    pub new_cards_per_day: Option<Limit>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub reviews_per_day: Option<u32>,
+    pub reviews_per_day: Option<Limit>,
+    /// FSRS probability of recall cards are scheduled to come due at.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_retention: Option<f64>,
+    /// Display color as `#rgb` or `#rrggbb`. Cosmetic, never merged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Display icon, typically an emoji. Cosmetic, never merged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub icon: Option<String>,
+    /// Hidden from deck lists and all-deck study queues; cards are kept.
+    /// Local to the device, never merged.
+    #[serde(default)]
+    pub archived: bool,
+    /// Extra normalization for typed answers in this deck.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub normalization_rules: Vec<NormRule>,
 }
 
 impl DeckSettings {
+    /// Longest accepted icon, in characters; an emoji can span several.
+    pub const MAX_ICON_CHARS: usize = 8;
+
     /// Create new deck settings with only the path set.
     pub fn new(deck_path: String) -> Self {
         Self {
@@ -262,8 +1071,97 @@ impl DeckSettings {
             fuzzy_threshold: None,
             new_cards_per_day: None,
             reviews_per_day: None,
+            request_retention: None,
+            color: None,
+            icon: None,
+            archived: false,
+            normalization_rules: Vec::new(),
+        }
+    }
+
+    /// Overrides for `deck_path` inherited down from its ancestors, each
+    /// deck's own overrides replacing those of the decks above it.
+    ///
+    /// `all` must be ordered shortest path first, so every ancestor is
+    /// applied before its descendants. Color, icon and archiving belong to
+    /// a deck alone and are not inherited.
+    pub fn cascade(all: &[DeckSettings], deck_path: &str) -> Option<DeckSettings> {
+        let mut merged = all
+            .iter()
+            .filter(|s| is_deck_or_ancestor(&s.deck_path, deck_path))
+            .cloned()
+            .reduce(|inherited, deck| deck.inherit(inherited))?;
+        if merged.deck_path != deck_path {
+            merged.deck_path = deck_path.to_string();
+            merged.color = None;
+            merged.icon = None;
+            merged.archived = false;
+        }
+        Some(merged)
+    }
+
+    /// Fill the overrides this deck leaves unset from its parent's.
+    fn inherit(self, parent: DeckSettings) -> Self {
+        let normalization_rules = if self.normalization_rules.is_empty() {
+            parent.normalization_rules
+        } else {
+            self.normalization_rules
+        };
+        Self {
+            algorithm: self.algorithm.or(parent.algorithm),
+            rating_scale: self.rating_scale.or(parent.rating_scale),
+            matching_mode: self.matching_mode.or(parent.matching_mode),
+            fuzzy_threshold: self.fuzzy_threshold.or(parent.fuzzy_threshold),
+            new_cards_per_day: self.new_cards_per_day.or(parent.new_cards_per_day),
+            reviews_per_day: self.reviews_per_day.or(parent.reviews_per_day),
+            request_retention: self.request_retention.or(parent.request_retention),
+            normalization_rules,
+            ..self
+        }
+    }
+
+    /// Check a retention target before it is stored or projected. FSRS
+    /// intervals are undefined at 0 and infinite at 1.
+    pub fn validate_request_retention(retention: f64) -> Result<(), String> {
+        if retention > 0.0 && retention < 1.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "retention must be between 0 and 1 (exclusive), got {}",
+                retention
+            ))
         }
     }
+
+    /// Check a deck color and icon before they are stored.
+    pub fn validate_appearance(color: Option<&str>, icon: Option<&str>) -> Result<(), String> {
+        if let Some(color) = color {
+            let hex = color.strip_prefix('#').unwrap_or("");
+            if !matches!(hex.len(), 3 | 6) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!(
+                    "color must be a hex color like #3a7 or #33aa77, got '{}'",
+                    color
+                ));
+            }
+        }
+        if let Some(icon) = icon {
+            let chars = icon.chars().count();
+            if icon.trim().is_empty() || chars > Self::MAX_ICON_CHARS {
+                return Err(format!(
+                    "icon must be 1-{} characters, got {}",
+                    Self::MAX_ICON_CHARS,
+                    chars
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `path` is `deck` itself or one of the decks containing it.
+fn is_deck_or_ancestor(path: &str, deck: &str) -> bool {
+    deck.strip_prefix(path)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
 }
 
 /// Effective settings (global merged with deck overrides).
@@ -273,9 +1171,22 @@ pub struct EffectiveSettings {
     pub rating_scale: RatingScale,
     pub matching_mode: MatchingMode,
     pub fuzzy_threshold: f64,
-    pub new_cards_per_day: u32,
-    pub reviews_per_day: u32,
+    pub new_cards_per_day: Limit,
+    pub reviews_per_day: Limit,
     pub daily_reset_hour: u32,
+    pub again_delay_minutes: u32,
+    pub due_grace_hours: u32,
+    pub graduation_requirement: u32,
+    pub min_ease: f64,
+    pub max_ease: f64,
+    /// FSRS retention target; only decks set one.
+    pub request_retention: f64,
+    /// Ratings to offer for `rating_scale`, in button order.
+    #[serde(default)]
+    pub rating_buttons: Vec<Rating>,
+    /// The deck's answer normalization rules; there are no global ones.
+    #[serde(default)]
+    pub normalization_rules: Vec<NormRule>,
 }
 
 impl EffectiveSettings {
@@ -290,6 +1201,20 @@ impl EffectiveSettings {
                 new_cards_per_day: d.new_cards_per_day.unwrap_or(global.new_cards_per_day),
                 reviews_per_day: d.reviews_per_day.unwrap_or(global.reviews_per_day),
                 daily_reset_hour: global.daily_reset_hour,
+                again_delay_minutes: global.again_delay_minutes,
+                due_grace_hours: global.due_grace_hours,
+                graduation_requirement: global.graduation_requirement,
+                min_ease: global.min_ease,
+                max_ease: global.max_ease,
+                request_retention: d
+                    .request_retention
+                    .unwrap_or(crate::algorithm::DEFAULT_REQUEST_RETENTION),
+                rating_buttons: d
+                    .rating_scale
+                    .unwrap_or(global.rating_scale)
+                    .ratings()
+                    .to_vec(),
+                normalization_rules: d.normalization_rules.clone(),
             },
             None => Self {
                 algorithm: global.algorithm,
@@ -299,7 +1224,306 @@ impl EffectiveSettings {
                 new_cards_per_day: global.new_cards_per_day,
                 reviews_per_day: global.reviews_per_day,
                 daily_reset_hour: global.daily_reset_hour,
+                again_delay_minutes: global.again_delay_minutes,
+                due_grace_hours: global.due_grace_hours,
+                graduation_requirement: global.graduation_requirement,
+                min_ease: global.min_ease,
+                max_ease: global.max_ease,
+                request_retention: crate::algorithm::DEFAULT_REQUEST_RETENTION,
+                rating_buttons: global.rating_scale.ratings().to_vec(),
+                normalization_rules: Vec::new(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rating_scale_buttons_match_accepted_ratings() {
+        for scale in [RatingScale::FourPoint, RatingScale::TwoPoint] {
+            let ratings = scale.ratings();
+            assert_eq!(scale.button_count(), ratings.len());
+            assert_eq!(scale.button_count(), scale.max_rating() as usize);
+            for (i, &rating) in ratings.iter().enumerate() {
+                assert_eq!(scale.rating(i as u8 + 1), Some(rating), "{}", scale.as_str());
+            }
+            assert_eq!(scale.as_str().parse(), Ok(scale));
+        }
+        assert_eq!(RatingScale::TwoPoint.ratings(), &[Rating::Again, Rating::Good]);
+        assert_eq!(RatingScale::FourPoint.button_count(), 4);
+        assert!("5point".parse::<RatingScale>().is_err());
+    }
+
+    #[test]
+    fn migrate_sm2_to_fsrs_seeds_memory_state() {
+        let mature = CardState {
+            status: CardStatus::Review,
+            interval_days: 60.0,
+            ease_factor: 2.5,
+            reviews_count: 8,
+            ..Default::default()
+        };
+        let migrated = mature.migrate_sm2_to_fsrs();
+        assert_eq!(migrated.stability, Some(60.0));
+        assert_eq!(migrated.difficulty, Some(5.0));
+        assert_eq!(migrated.interval_days, 60.0);
+
+        let hard = CardState {
+            ease_factor: 1.3,
+            ..mature.clone()
+        };
+        assert_eq!(hard.migrate_sm2_to_fsrs().difficulty, Some(10.0));
+
+        // Nothing to migrate from
+        let new = CardState::default().migrate_sm2_to_fsrs();
+        assert!(new.stability.is_none());
+        let fsrs = CardState {
+            stability: Some(3.0),
+            difficulty: Some(7.0),
+            ..mature
+        };
+        assert_eq!(fsrs.migrate_sm2_to_fsrs().stability, Some(3.0));
+    }
+
+    #[test]
+    fn effective_settings_follow_deck_rating_scale() {
+        let global = GlobalSettings::default();
+        let deck = DeckSettings {
+            rating_scale: Some(RatingScale::TwoPoint),
+            ..DeckSettings::new("rust".to_string())
+        };
+
+        let plain = EffectiveSettings::merge(&global, None);
+        assert_eq!(plain.rating_buttons, RatingScale::FourPoint.ratings());
+
+        let merged = EffectiveSettings::merge(&global, Some(&deck));
+        assert_eq!(merged.rating_buttons, vec![Rating::Again, Rating::Good]);
+    }
+
+    #[test]
+    fn ease_bounds_must_be_ordered_and_in_range() {
+        let defaults = GlobalSettings::default();
+        assert!(GlobalSettings::validate_ease_bounds(defaults.min_ease, defaults.max_ease).is_ok());
+        assert!(GlobalSettings::validate_ease_bounds(2.0, 2.0).is_ok());
+        assert!(GlobalSettings::validate_ease_bounds(3.0, 2.0).is_err());
+        assert!(GlobalSettings::validate_ease_bounds(0.5, 2.0).is_err());
+        assert!(GlobalSettings::validate_ease_bounds(1.3, 12.0).is_err());
+        assert!(GlobalSettings::validate_ease_bounds(f64::NAN, 2.0).is_err());
+    }
+
+    #[test]
+    fn normalization_rule_spec_round_trips() {
+        let rules = vec![
+            NormRule::Diacritics,
+            NormRule::Punctuation,
+            NormRule::Whitespace,
+            NormRule::NumericTolerance(0.05),
+            NormRule::Stopwords,
+        ];
+        let spec = NormRule::format_spec(&rules);
+        assert_eq!(spec, "diacritics,punctuation,whitespace,numeric:0.05,stopwords");
+        assert_eq!(NormRule::parse_spec(&spec).unwrap(), rules);
+
+        assert_eq!(
+            NormRule::parse_spec(" diacritics , numeric:1 ").unwrap(),
+            vec![NormRule::Diacritics, NormRule::NumericTolerance(1.0)]
+        );
+        assert!(NormRule::parse_spec("").unwrap().is_empty());
+        assert!(NormRule::parse_spec("diacritics,subscripts").is_err());
+        assert!(NormRule::parse_spec("numeric:-0.1").is_err());
+        assert!(NormRule::parse_spec("numeric:abc").is_err());
+        assert_eq!("numeric: 0.5".parse(), Ok(NormRule::NumericTolerance(0.5)));
+        assert_eq!(
+            "subscripts".parse::<NormRule>(),
+            Err("unknown normalization rule 'subscripts'".to_string())
+        );
+
+        // Serialized as spec tokens
+        let json = serde_json::to_string(&rules).unwrap();
+        assert_eq!(
+            json,
+            r#"["diacritics","punctuation","whitespace","numeric:0.05","stopwords"]"#
+        );
+        assert_eq!(serde_json::from_str::<Vec<NormRule>>(&json).unwrap(), rules);
+    }
+
+    #[test]
+    fn effective_settings_take_deck_normalization_rules() {
+        let global = GlobalSettings::default();
+        let deck = DeckSettings {
+            normalization_rules: vec![NormRule::Diacritics],
+            ..DeckSettings::new("french".to_string())
+        };
+
+        assert!(EffectiveSettings::merge(&global, None).normalization_rules.is_empty());
+        assert_eq!(
+            EffectiveSettings::merge(&global, Some(&deck)).normalization_rules,
+            vec![NormRule::Diacritics]
+        );
+    }
+
+    #[test]
+    fn effective_settings_take_deck_request_retention() {
+        let global = GlobalSettings::default();
+        let deck = DeckSettings {
+            request_retention: Some(0.85),
+            ..DeckSettings::new("french".to_string())
+        };
+
+        assert_eq!(EffectiveSettings::merge(&global, None).request_retention, 0.9);
+        assert_eq!(EffectiveSettings::merge(&global, Some(&deck)).request_retention, 0.85);
+        let unset = DeckSettings::new("german".to_string());
+        assert_eq!(EffectiveSettings::merge(&global, Some(&unset)).request_retention, 0.9);
+
+        assert!(DeckSettings::validate_request_retention(0.85).is_ok());
+        assert!(DeckSettings::validate_request_retention(0.0).is_err());
+        assert!(DeckSettings::validate_request_retention(1.0).is_err());
+    }
+
+    #[test]
+    fn deck_settings_cascade_from_ancestors() {
+        let deck = |path: &str, algorithm: Option<Algorithm>, new_cards: Option<u32>| DeckSettings {
+            algorithm,
+            new_cards_per_day: new_cards.map(Limit::Count),
+            color: Some(format!("{path}-color")),
+            archived: true,
+            ..DeckSettings::new(path.to_string())
+        };
+        // Shortest path first, as the settings queries return them
+        let all = vec![
+            DeckSettings {
+                normalization_rules: vec![NormRule::Diacritics],
+                request_retention: Some(0.85),
+                ..deck("lang", Some(Algorithm::Fsrs), Some(5))
             },
+            deck("langs", Some(Algorithm::Sm2), None),
+            deck("lang/es", None, Some(10)),
+            deck("lang/es/verbs", Some(Algorithm::Sm2), None),
+        ];
+
+        // The nearer deck wins over its ancestors
+        let verbs = DeckSettings::cascade(&all, "lang/es/verbs").unwrap();
+        assert_eq!(verbs.algorithm, Some(Algorithm::Sm2));
+        assert_eq!(verbs.new_cards_per_day, Some(Limit::Count(10)));
+        assert_eq!(verbs.normalization_rules, vec![NormRule::Diacritics]);
+        assert_eq!(verbs.request_retention, Some(0.85));
+        assert_eq!(verbs.color.as_deref(), Some("lang/es/verbs-color"));
+        assert!(verbs.archived);
+
+        // A deck without its own settings inherits, but not appearance
+        let nouns = DeckSettings::cascade(&all, "lang/es/nouns").unwrap();
+        assert_eq!(nouns.deck_path, "lang/es/nouns");
+        assert_eq!(nouns.algorithm, Some(Algorithm::Fsrs));
+        assert_eq!(nouns.new_cards_per_day, Some(Limit::Count(10)));
+        assert_eq!(nouns.color, None);
+        assert!(!nouns.archived);
+
+        // Only whole path segments are ancestors
+        assert!(DeckSettings::cascade(&all, "language").is_none());
+    }
+
+    #[test]
+    fn both_direction_splits_cards_and_swaps_daily() {
+        let day = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let next_day = day.succ_opt().unwrap();
+        let reversed = |d| (1..=10).filter(|&id| StudyDirection::Both.reverses(id, d)).count();
+
+        assert_eq!(reversed(day), 5);
+        for id in 1..=10 {
+            assert_ne!(
+                StudyDirection::Both.reverses(id, day),
+                StudyDirection::Both.reverses(id, next_day)
+            );
+        }
+        assert!(StudyDirection::Reverse.reverses(1, day));
+        assert!(!StudyDirection::Forward.reverses(1, day));
+
+        // IDs at the ends of the range alternate too
+        for id in [i64::MAX, i64::MIN, -1] {
+            assert_ne!(
+                StudyDirection::Both.reverses(id, day),
+                StudyDirection::Both.reverses(id, next_day)
+            );
+        }
+        assert_ne!(
+            StudyDirection::Both.reverses(i64::MAX, day),
+            StudyDirection::Both.reverses(i64::MAX - 1, day)
+        );
+    }
+
+    #[test]
+    fn session_limit_round_trips_as_spec() {
+        for limit in [SessionLimit::Cards(10), SessionLimit::Minutes(15), SessionLimit::None] {
+            assert_eq!(limit.to_string().parse(), Ok(limit));
         }
+        assert_eq!(
+            serde_json::to_string(&SessionLimit::Cards(10)).unwrap(),
+            r#""cards:10""#
+        );
+        assert!("cards:".parse::<SessionLimit>().is_err());
+        assert!("laps:3".parse::<SessionLimit>().is_err());
+    }
+
+    #[test]
+    fn limit_zero_is_none_and_unlimited_is_explicit() {
+        assert_eq!(Limit::Count(0).max_cards(), Some(0));
+        assert_eq!(Limit::Unlimited.max_cards(), None);
+        assert_eq!(Limit::Count(20).remaining(25), Limit::Count(0));
+        assert_eq!(Limit::Unlimited.remaining(25), Limit::Unlimited);
+        assert_eq!(Limit::Count(20).unused(15), 5);
+        assert_eq!(Limit::Count(20).unused(25), 0);
+        assert_eq!(Limit::Unlimited.unused(15), 0);
+        assert_eq!(Limit::Count(20).query_limit(), 20);
+        assert_eq!(Limit::Unlimited.query_limit(), i64::MAX);
+
+        assert_eq!(serde_json::to_string(&Limit::Count(0)).unwrap(), "0");
+        assert_eq!(
+            serde_json::to_string(&Limit::Unlimited).unwrap(),
+            r#""unlimited""#
+        );
+        assert_eq!(serde_json::from_str::<Limit>("20").unwrap(), Limit::Count(20));
+        assert!(serde_json::from_str::<Limit>(r#""lots""#).is_err());
+
+        for limit in [Limit::Unlimited, Limit::Count(0), Limit::Count(20)] {
+            assert_eq!(Limit::from_db(limit.to_db()), limit);
+        }
+    }
+
+    #[test]
+    fn truncate_keeps_first_cards_in_session_order() {
+        let card = |id| Card {
+            id,
+            deck_path: "deck".to_string(),
+            question: "Q".to_string(),
+            answer: "A".to_string(),
+            source_file: "deck.md".to_string(),
+            deleted_at: None,
+            updated_at: None,
+        };
+        let queue = |order| StudyQueue {
+            new_cards: (1..=4).map(card).collect(),
+            review_cards: (11..=18).map(card).collect(),
+            new_remaining: 0,
+            review_remaining: 0,
+            queue_order: order,
+            reversed_card_ids: Vec::new(),
+            session_limit: SessionLimit::Cards(6),
+        };
+        let counts = |order| {
+            let mut q = queue(order);
+            q.truncate(6);
+            (q.new_cards.len(), q.review_cards.len(), q.new_remaining, q.review_remaining)
+        };
+
+        assert_eq!(counts(QueueOrder::NewFirst), (4, 2, 0, 6));
+        assert_eq!(counts(QueueOrder::DueFirst), (0, 6, 4, 2));
+        assert_eq!(counts(QueueOrder::Interleaved), (2, 4, 2, 4));
+
+        let mut q = queue(QueueOrder::NewFirst);
+        q.truncate(20);
+        assert_eq!(q.new_cards.len() + q.review_cards.len(), 12);
     }
 }
@@ -10,11 +10,31 @@ export type RatingScale = '4point' | '2point';
 // Ratings (1-4 for 4-point scale)
 export type Rating = 1 | 2 | 3 | 4;
 
+// Rating names, as used for rating buttons
+export type RatingName = 'again' | 'hard' | 'good' | 'easy';
+
 // Answer modes
 export type AnswerMode = 'flip' | 'typed';
 
 // Matching modes
-export type MatchingMode = 'exact' | 'case_insensitive' | 'fuzzy';
+export type MatchingMode = 'exact' | 'case_insensitive' | 'contains' | 'fuzzy';
+
+export type IdStrategy = 'sequence' | 'content_hash';
+
+// Order of new and due cards within a study session
+export type QueueOrder = 'due_first' | 'new_first' | 'interleaved';
+
+// How a sync settles cards studied on more than one device
+export type ConflictPolicy = 'last_write_wins' | 'most_progress' | 'manual';
+
+// Which side of a card study sessions show as the prompt
+export type StudyDirection = 'forward' | 'reverse' | 'both';
+
+// Cap on a study session: "none", "cards:<n>" or "minutes:<n>"
+export type SessionLimit = 'none' | `cards:${number}` | `minutes:${number}`;
+
+// Daily cap on new cards or reviews; 0 means none
+export type Limit = number | 'unlimited';
 
 // Card (matches Rust Card struct - snake_case from serde)
 export interface Card {
@@ -36,6 +56,14 @@ export interface CardState {
   difficulty?: number;
   lapses: number;
   reviews_count: number;
+  learning_reps?: number;
+}
+
+// Card with its scheduling state (matches Rust CardWithState)
+export interface CardWithState {
+  card: Card;
+  state: CardState | null;
+  retrievability: number | null;
 }
 
 // Deck (matches Rust Deck)
@@ -53,6 +81,13 @@ export interface StudyQueue {
   review_cards: Card[];
   new_remaining: number;
   review_remaining: number;
+  queue_order?: QueueOrder;
+  // Cards to show answer first, per the study direction
+  reversed_card_ids?: number[];
+  // Card caps are already applied; a time cap is left to the session to stop
+  session_limit?: SessionLimit;
+  // Shorter typed answers count as no answer instead of being graded
+  min_answer_chars?: number;
 }
 
 // Review request (sent to Tauri)
@@ -63,18 +98,63 @@ export interface ReviewRequest {
   answer_mode: AnswerMode;
   typed_answer?: string;
   time_taken_ms?: number;
+  // Log the review without changing the card's schedule
+  cram?: boolean;
 }
 
 // Review response (from Tauri)
 export interface ReviewResponse {
   new_state: CardState;
   next_due: string;
+  /** Show the card again later in this session. */
+  requeue: boolean;
+}
+
+// Scheduling introspection (from Tauri)
+export type SchedulingPath = 'first_review' | 'recall' | 'lapse';
+
+export interface SchedulingDebug {
+  algorithm: Algorithm;
+  path: SchedulingPath;
+  elapsed_days: number | null;
+  retrievability: number | null;
+  stability_before: number | null;
+  stability_after: number | null;
+  difficulty_before: number | null;
+  difficulty_after: number | null;
+  ease_before: number;
+  ease_after: number;
+  interval_days: number;
+  short_term: boolean;
+}
+
+export interface ReviewDebugResponse {
+  new_state: CardState;
+  next_due: string;
+  debug: SchedulingDebug;
 }
 
 // Import result (from Tauri)
 export interface ImportResult {
   imported: number;
   deck_path: string;
+  // Imported questions that closely match an existing card elsewhere
+  warnings: string[];
+}
+
+// A markdown file that could not be read or parsed
+export interface FileError {
+  path: string;
+  error: string;
+}
+
+// What importing a directory would bring in (matches Rust DirectoryScan)
+export interface DirectoryScan {
+  files: number;
+  total_cards: number;
+  cards_without_ids: number;
+  parse_errors: FileError[];
+  decks: string[];
 }
 
 // Command error (from Tauri)
@@ -88,19 +168,50 @@ export interface GlobalSettings {
   rating_scale: RatingScale;
   matching_mode: MatchingMode;
   fuzzy_threshold: number;
-  new_cards_per_day: number;
-  reviews_per_day: number;
+  new_cards_per_day: Limit;
+  reviews_per_day: Limit;
   daily_reset_hour: number;
+  due_grace_hours?: number;
+  graduation_requirement?: number;
+  // Splits file names into deck paths (e.g. "::"); null derives decks from directories
+  deck_path_separator?: string | null;
+  // Similarity cut-offs for the rating suggested after a typed answer
+  suggest_easy_threshold?: number;
+  suggest_hard_threshold?: number;
+  queue_order?: QueueOrder;
+  // SM-2 ease floor and cap
+  min_ease?: number;
+  max_ease?: number;
+  sync_conflict_policy?: ConflictPolicy;
+  // No new cards in any deck while set; reviews continue
+  new_cards_paused?: boolean;
+  study_direction?: StudyDirection;
+  session_limit?: SessionLimit;
 }
 
+// Extra answer normalization a deck applies to typed answers;
+// `numeric:<tolerance>` accepts numbers within a relative tolerance
+export type NormRule =
+  | 'diacritics'
+  | 'punctuation'
+  | 'whitespace'
+  | 'stopwords'
+  | `numeric:${number}`;
+
 export interface DeckSettings {
   deck_path: string;
   algorithm?: Algorithm;
   rating_scale?: RatingScale;
   matching_mode?: MatchingMode;
   fuzzy_threshold?: number;
-  new_cards_per_day?: number;
-  reviews_per_day?: number;
+  new_cards_per_day?: Limit;
+  reviews_per_day?: Limit;
+  // FSRS probability of recall cards come due at
+  request_retention?: number;
+  color?: string;
+  icon?: string;
+  archived?: boolean;
+  normalization_rules?: NormRule[];
 }
 
 // Effective settings (global merged with deck overrides)
@@ -109,9 +220,21 @@ export interface EffectiveSettings {
   rating_scale: RatingScale;
   matching_mode: MatchingMode;
   fuzzy_threshold: number;
-  new_cards_per_day: number;
-  reviews_per_day: number;
+  new_cards_per_day: Limit;
+  reviews_per_day: Limit;
   daily_reset_hour: number;
+  // The device's offset from UTC, which daily_reset_hour is relative to
+  utc_offset_minutes?: number;
+  due_grace_hours?: number;
+  graduation_requirement?: number;
+  min_ease?: number;
+  max_ease?: number;
+  // FSRS retention target; only decks set one
+  request_retention?: number;
+  // Ratings to offer for rating_scale, in button order
+  rating_buttons: RatingName[];
+  // The deck's answer normalization rules
+  normalization_rules?: NormRule[];
 }
 
 // Diff types for answer comparison
@@ -129,7 +252,11 @@ export interface CompareAnswerResponse {
   matching_mode: MatchingMode;
   typed_normalized: string;
   correct_normalized: string;
+  no_answer: boolean;
   diff: DiffSegment[];
+  // Items of a list answer left out of the typed answer
+  missing_items: string[];
+  suggested_rating: RatingName;
 }
 
 // Statistics types
@@ -150,7 +277,37 @@ export interface StudyStats {
   total_reviews: number;
 }
 
+// Inclusive range of days as YYYY-MM-DD dates
+export interface DateRange {
+  from: string;
+  to: string;
+}
+
 export interface CalendarData {
   date: string;
   reviews: number;
 }
+
+export interface SlowCard {
+  card_id: number;
+  deck_path: string;
+  question: string;
+  average_time_ms: number;
+  review_count: number;
+}
+
+// Review counts as [again, hard, good, easy]
+export type RatingDistribution = [number, number, number, number];
+
+export interface WorkloadProjection {
+  cards: number;
+  current_retention: number;
+  new_retention: number;
+  current_daily_reviews: number;
+  projected_daily_reviews: number;
+}
+
+export interface ProgressReset {
+  cards_reset: number;
+  reviews_archived: number;
+}